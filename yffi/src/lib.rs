@@ -1,4 +1,5 @@
 use std::collections::{Bound, HashMap};
+use std::convert::TryFrom;
 use std::ffi::{c_char, c_void, CStr, CString};
 use std::mem::{forget, ManuallyDrop, MaybeUninit};
 use std::ops::{Deref, RangeBounds};
@@ -312,6 +313,10 @@ impl Into<Options> for YOptions {
             auto_load: if self.auto_load == 0 { false } else { true },
             should_load: if self.should_load == 0 { false } else { true },
             offset_kind: encoding,
+            encoding_version: yrs::EncodingVersion::V1,
+            update_log_capacity: 0,
+            gc_policy: yrs::GcPolicy::Always,
+            read_only: false,
         }
     }
 }
@@ -2808,6 +2813,20 @@ impl From<Any> for YOutput {
                     len: 1,
                     value: YOutputContent { integer: v },
                 },
+                // there's no dedicated tag for unsigned integers, values out of i64 range
+                // are passed as floating point numbers
+                Any::BigUInt(v) => match i64::try_from(v) {
+                    Ok(v) => YOutput {
+                        tag: Y_JSON_INT,
+                        len: 1,
+                        value: YOutputContent { integer: v },
+                    },
+                    Err(_) => YOutput {
+                        tag: Y_JSON_NUM,
+                        len: 1,
+                        value: YOutputContent { num: v as _ },
+                    },
+                },
                 Any::String(v) => YOutput {
                     tag: Y_JSON_STR,
                     len: v.len() as u32,
@@ -2,11 +2,49 @@ use criterion::*;
 use rand::distributions::Alphanumeric;
 use rand::prelude::StdRng;
 use rand::{Rng, RngCore, SeedableRng};
+use std::alloc::{GlobalAlloc, Layout, System};
 use std::cell::Cell;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use yrs::block::ClientID;
 use yrs::encoding::read::{Cursor, Read};
+use yrs::types::ToJson;
 use yrs::updates::decoder::Decode;
-use yrs::{Array, Doc, Map, MapRef, Text, TextRef, Transact, TransactionMut, Update};
+use yrs::{
+    Any, Array, Doc, GetString, Map, MapPrelim, MapRef, Out, ReadTxn, Text, TextRef, Transact,
+    TransactionMut, Update, Uuid,
+};
+
+/// Global allocator counting all allocations made by the benchmarks, see: [count_allocations].
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Returns a number of heap allocations (including reallocations) made while running `f`.
+fn count_allocations<F: FnOnce()>(f: F) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    f();
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
 
 const N: usize = 6000;
 const SQRT_N: usize = 77 * 20;
@@ -472,6 +510,214 @@ fn b4_2(c: &mut Criterion, name: &str) {
     );
 }
 
+fn b4_3(c: &mut Criterion, name: &str) {
+    let doc = Doc::new();
+    let txt = doc.get_or_insert_text("text");
+    let input = read_input("./assets/bench-input/b4-editing-trace.bin");
+    {
+        let mut txn = doc.transact_mut();
+        for i in input.iter() {
+            match i {
+                TextOp::Insert(idx, chunk) => txt.insert(&mut txn, *idx, chunk),
+                TextOp::Delete(idx, len) => txt.remove_range(&mut txn, *idx, *len),
+            }
+        }
+    }
+
+    let mut group = c.benchmark_group(name);
+    group.bench_function("uncached", |b| {
+        b.iter(|| {
+            let txn = doc.transact();
+            for _ in 0..10 {
+                black_box(txt.get_string(&txn));
+            }
+        });
+    });
+    group.bench_function("cached", |b| {
+        b.iter(|| {
+            let txn = doc.transact().with_read_cache();
+            for _ in 0..10 {
+                black_box(txt.get_string(&txn));
+            }
+        });
+    });
+    group.finish();
+}
+
+fn b4_4(c: &mut Criterion, name: &str) {
+    let doc = Doc::new();
+    let txt = doc.get_or_insert_text("text");
+    txt.insert(&mut doc.transact_mut(), 0, &"a".repeat(N));
+    {
+        // split text into single character blocks
+        let mut txn = doc.transact_mut();
+        for i in 0..N as u32 {
+            txt.format(&mut txn, i, 1, HashMap::new());
+        }
+    }
+
+    let mut group = c.benchmark_group(name);
+    group.bench_function("fragmented", |b| {
+        b.iter(|| {
+            let txn = doc.transact();
+            black_box(txt.get_string(&txn));
+        });
+    });
+    doc.compact(&mut doc.transact_mut());
+    group.bench_function("compacted", |b| {
+        b.iter(|| {
+            let txn = doc.transact();
+            black_box(txt.get_string(&txn));
+        });
+    });
+    group.finish();
+}
+
+fn b4_5(c: &mut Criterion, name: &str) {
+    let doc = Doc::new();
+    let array = doc.get_or_insert_array("array");
+    let map = doc.get_or_insert_map("map");
+    {
+        let mut txn = doc.transact_mut();
+        for i in 0..N {
+            array.push_back(&mut txn, MapPrelim::from([("value".to_owned(), i as i64)]));
+            map.insert(&mut txn, i.to_string(), i as i64);
+        }
+    }
+
+    let mut group = c.benchmark_group(name);
+    group.bench_function("array", |b| {
+        b.iter(|| {
+            let txn = doc.transact();
+            black_box(array.to_json(&txn));
+        });
+    });
+    group.bench_function("map", |b| {
+        b.iter(|| {
+            let txn = doc.transact();
+            black_box(map.to_json(&txn));
+        });
+    });
+    group.finish();
+}
+
+fn b4_6(c: &mut Criterion, name: &str) {
+    let mut group = c.benchmark_group(name);
+    group.bench_function("new", |b| {
+        b.iter(|| {
+            for _ in 0..N {
+                black_box(Doc::new());
+            }
+        });
+    });
+    group.bench_function("with_client_id", |b| {
+        b.iter(|| {
+            for i in 0..N {
+                black_box(Doc::with_client_id(i as ClientID));
+            }
+        });
+    });
+    group.bench_function("with_guid_and_client_id", |b| {
+        let guid: Uuid = "00000000-0000-4000-8000-000000000000".into();
+        b.iter(|| {
+            for i in 0..N {
+                black_box(Doc::with_guid_and_client_id(guid.clone(), i as ClientID));
+            }
+        });
+    });
+    group.finish();
+}
+
+fn b4_7(c: &mut Criterion, name: &str) {
+    let doc = Doc::new();
+    let array = doc.get_or_insert_array("array");
+    {
+        let mut txn = doc.transact_mut();
+        for i in 0..N {
+            array.push_back(&mut txn, i.to_string().repeat(1000));
+        }
+    }
+
+    let mut group = c.benchmark_group(name);
+    group.bench_function("get", |b| {
+        b.iter(|| {
+            let txn = doc.transact();
+            let mut len = 0;
+            for i in 0..N as u32 {
+                if let Some(Out::Any(Any::String(s))) = array.get(&txn, i) {
+                    len += s.len();
+                }
+            }
+            black_box(len);
+        });
+    });
+    group.bench_function("with_element", |b| {
+        b.iter(|| {
+            let txn = doc.transact();
+            let mut len = 0;
+            for i in 0..N as u32 {
+                len += array
+                    .with_element(&txn, i, |value| match value {
+                        Any::String(s) => s.len(),
+                        _ => 0,
+                    })
+                    .unwrap_or_default();
+            }
+            black_box(len);
+        });
+    });
+    group.finish();
+}
+
+fn b4_8(c: &mut Criterion, name: &str) {
+    let doc = Doc::new();
+    let txt = doc.get_or_insert_text("text");
+    {
+        let mut txn = doc.transact_mut();
+        for i in 0..N {
+            txt.push(&mut txn, &i.to_string());
+        }
+    }
+    let remote = doc.transact().state_vector();
+    let allocating = |txn: &yrs::Transaction| {
+        for _ in 0..100 {
+            black_box(txn.state_vector_bytes_v1().to_vec());
+            black_box(txn.encode_diff_v1(&remote));
+        }
+    };
+    let into_buffer = |txn: &yrs::Transaction, buf: &mut Vec<u8>| {
+        for _ in 0..100 {
+            txn.encode_state_vector_into(buf);
+            black_box(&buf);
+            txn.encode_diff_into(&remote, buf);
+            black_box(&buf);
+        }
+    };
+
+    // allocation counts are not measured by criterion, report them separately
+    {
+        let txn = doc.transact();
+        let mut buf = Vec::new();
+        into_buffer(&txn, &mut buf); // warm up the buffer
+        let before = count_allocations(|| allocating(&txn));
+        let after = count_allocations(|| into_buffer(&txn, &mut buf));
+        println!(
+            "{}: allocations per 100 calls: allocating {}, into_buffer {}",
+            name, before, after
+        );
+    }
+
+    let mut group = c.benchmark_group(name);
+    group.bench_function("allocating", |b| {
+        b.iter(|| allocating(&doc.transact()));
+    });
+    group.bench_function("into_buffer", |b| {
+        let mut buf = Vec::new();
+        b.iter(|| into_buffer(&doc.transact(), &mut buf));
+    });
+    group.finish();
+}
+
 fn read_input(fpath: &str) -> Vec<TextOp> {
     use std::fs::File;
     use yrs::updates::decoder::DecoderV1;
@@ -551,6 +797,12 @@ fn bench(c: &mut Criterion) {
     b3_4(c, "[B3.4] 20√N clients concurrently insert text in Array");
     b4_2(c, "[B4.2] Apply real-world document snapshot of size");
     b4_1(c, "[B4.1] Apply real-world editing dataset");
+    b4_3(c, "[B4.3] Repeatedly read text in a single transaction");
+    b4_4(c, "[B4.4] Read text before and after compaction");
+    b4_5(c, "[B4.5] Convert large collections to JSON");
+    b4_6(c, "[B4.6] Create N documents");
+    b4_7(c, "[B4.7] Read array of large strings");
+    b4_8(c, "[B4.8] Encode state vector and diff");
 }
 
 criterion_group! {
@@ -1,5 +1,6 @@
 use crate::encoding::read::{Error, Read};
 use crate::encoding::write::Write;
+use crate::types::PathSegment;
 use std::cmp::PartialEq;
 use std::collections::HashMap;
 use std::convert::TryFrom;
@@ -17,6 +18,10 @@ pub enum Any {
     Bool(bool),
     Number(f64),
     BigInt(i64),
+    /// Unsigned 64-bit integer, created only explicitly (see: [Any::big_uint]). Values exceeding
+    /// `i64::MAX` use an encoding extension, which is not recognized by Yjs peers. Smaller values
+    /// are encoded just like [Any::BigInt].
+    BigUInt(u64),
     String(Arc<str>),
     Buffer(Arc<[u8]>),
     Array(Arc<[Any]>),
@@ -34,6 +39,36 @@ impl Any {
         T::try_from(self)
     }
 
+    /// Returns an [Any::BigUInt] representing a given `value` without precision loss, including
+    /// values exceeding `i64::MAX`, which are rejected by `TryFrom<u64>` conversion.
+    ///
+    /// **Not compatible with Yjs:** values exceeding `i64::MAX` are encoded using a yrs-specific
+    /// tag, which Yjs peers are unable to decode. Use it only when all peers are using yrs.
+    pub fn big_uint(value: u64) -> Self {
+        Any::BigUInt(value)
+    }
+
+    /// Returns a builder of an [Any::Array] with preallocated space for at least `capacity`
+    /// elements. Useful when the final number of elements is known upfront.
+    pub fn array_with_capacity(capacity: usize) -> ArrayBuilder {
+        ArrayBuilder(Vec::with_capacity(capacity))
+    }
+
+    /// Returns a builder of an [Any::Map] with preallocated space for at least `capacity`
+    /// entries. Useful when the final number of entries is known upfront.
+    pub fn map_with_capacity(capacity: usize) -> MapBuilder {
+        MapBuilder(HashMap::with_capacity(capacity))
+    }
+
+    /// Returns a builder of an [OrderedMap] with preallocated space for at least `capacity`
+    /// entries. Entries of produced map will keep the order in which they were first inserted.
+    pub fn ordered_map_with_capacity(capacity: usize) -> OrderedMapBuilder {
+        OrderedMapBuilder {
+            entries: Vec::with_capacity(capacity),
+            index: HashMap::with_capacity(capacity),
+        }
+    }
+
     pub fn decode<R: Read>(decoder: &mut R) -> Result<Self, Error> {
         Ok(match decoder.read_u8()? {
             // CASE 127: undefined
@@ -48,6 +83,8 @@ impl Any {
             123 => Any::Number(decoder.read_f64()?),
             // CASE 122: bigint
             122 => Any::BigInt(decoder.read_i64()?),
+            // CASE 115: unsigned bigint (yrs extension)
+            115 => Any::BigUInt(decoder.read_var()?),
             // CASE 121: boolean (false)
             121 => Any::Bool(false),
             // CASE 120: boolean (true)
@@ -106,6 +143,7 @@ impl Any {
     // | object<string,any>  | 118      | custom             | Writes {length} then {length} key-value pairs |
     // | array<any>          | 117      | custom             | Writes {length} then {length} json values |
     // | Uint8Array          | 116      | writeVarUint8Array | We use Uint8Array for any kind of binary data |
+    // | biguint             | 115      | writeVarUint       | Yrs extension for u64 values exceeding i64 range, not supported by Yjs |
     //
     // Reasons for the decreasing prefix:
     // We need the first bit for extendability (later we may want to encode the
@@ -157,6 +195,18 @@ impl Any {
                 encoder.write_u8(122);
                 encoder.write_i64(*num)
             }
+            Any::BigUInt(num) => match i64::try_from(*num) {
+                Ok(num) => {
+                    // TYPE 122: BigInt
+                    encoder.write_u8(122);
+                    encoder.write_i64(num)
+                }
+                Err(_) => {
+                    // TYPE 115: unsigned BigInt
+                    encoder.write_u8(115);
+                    encoder.write_var(*num)
+                }
+            },
             Any::Array(arr) => {
                 // TYPE 117: Array
                 encoder.write_u8(117);
@@ -186,6 +236,21 @@ impl Any {
         Ok(serde_json::from_str(src)?)
     }
 
+    /// Parses a JSON string into [Any] just like [Any::from_json], except that all numbers without
+    /// fractional part, which fit into `i64` range, are parsed as [Any::BigInt]s. This way integers
+    /// keep their representation after being roundtripped through JSON.
+    ///
+    /// Keep in mind that [Any::BigInt] values are seen as JavaScript `BigInt`s by Yjs peers once
+    /// inserted into a document.
+    pub fn from_json_with_integers(src: &str) -> Result<Self, Error> {
+        use serde::de::DeserializeSeed;
+
+        let mut deserializer = serde_json::Deserializer::from_str(src);
+        let value = crate::encoding::serde::AnySeed::integers().deserialize(&mut deserializer)?;
+        deserializer.end()?;
+        Ok(value)
+    }
+
     pub fn to_json(&self, buf: &mut String) {
         use serde::Serialize;
         use serde_json::Serializer;
@@ -196,6 +261,211 @@ impl Any {
         let mut s = Serializer::new(cursor);
         self.serialize(&mut s).unwrap();
     }
+
+    /// Returns a value found under a given `path` of keys and indexes within nested [Any::Map]s
+    /// and [Any::Array]s, or `None` if there's no such value. An empty path points to current
+    /// value itself.
+    pub fn get_path(&self, path: &[PathSegment]) -> Option<&Any> {
+        let mut current = self;
+        for segment in path {
+            current = match (current, segment) {
+                (Any::Map(map), PathSegment::Key(key)) => map.get(key.as_ref())?,
+                (Any::Array(array), PathSegment::Index(index)) => array.get(*index as usize)?,
+                _ => return None,
+            };
+        }
+        Some(current)
+    }
+
+    /// Returns a copy of current value with a given `value` put under a specified `path`. Current
+    /// value is left untouched, while unchanged subtrees are shared between both of them.
+    ///
+    /// All segments of the `path` except the last one must point to existing values. The last one
+    /// may refer to a missing [Any::Map] entry, which will be inserted, but not to an index
+    /// outside of [Any::Array] bounds. If `path` could not be resolved, `None` is returned.
+    pub fn set_path(&self, path: &[PathSegment], value: Any) -> Option<Any> {
+        let (segment, rest) = match path.split_first() {
+            Some(split) => split,
+            None => return Some(value),
+        };
+        match (self, segment) {
+            (Any::Map(map), PathSegment::Key(key)) => {
+                let child = match map.get(key.as_ref()) {
+                    Some(child) => child.set_path(rest, value)?,
+                    None if rest.is_empty() => value,
+                    None => return None,
+                };
+                let mut map = map.as_ref().clone();
+                map.insert(key.to_string(), child);
+                Some(Any::Map(Arc::new(map)))
+            }
+            (Any::Array(array), PathSegment::Index(index)) => {
+                let index = *index as usize;
+                let child = array.get(index)?.set_path(rest, value)?;
+                let mut array = array.to_vec();
+                array[index] = child;
+                Some(Any::Array(array.into()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns an approximate number of bytes occupied in memory by current value, including
+    /// contents of strings, buffers and nested [Any::Array]s and [Any::Map]s. Useful for enforcing
+    /// size quotas before inserting large values into a document.
+    ///
+    /// This is only an estimate: it doesn't account for allocator overhead, and subtrees shared
+    /// between several values are counted every time they are referenced.
+    pub fn heap_size(&self) -> usize {
+        // every heap allocated variant is an Arc, which keeps strong and weak counters
+        const ARC_HEADER: usize = 2 * std::mem::size_of::<usize>();
+        let inner = match self {
+            Any::String(str) => ARC_HEADER + str.len(),
+            Any::Buffer(buf) => ARC_HEADER + buf.len(),
+            Any::Array(array) => ARC_HEADER + array.iter().map(Any::heap_size).sum::<usize>(),
+            Any::Map(map) => {
+                let entries = map.capacity() * std::mem::size_of::<(String, Any)>();
+                let contents: usize = map
+                    .iter()
+                    .map(|(key, value)| {
+                        key.capacity() + value.heap_size() - std::mem::size_of::<Any>()
+                    })
+                    .sum();
+                ARC_HEADER + std::mem::size_of::<HashMap<String, Any>>() + entries + contents
+            }
+            _ => 0,
+        };
+        std::mem::size_of::<Any>() + inner
+    }
+
+    /// Returns a list of [RFC 6902](https://datatracker.ietf.org/doc/html/rfc6902) JSON Patch
+    /// operations, which applied in order would transform current value into a `target` one.
+    ///
+    /// Maps are compared key by key, while arrays are compared index by index: elements past
+    /// the shorter array's length are added or removed at the tail. Values of different types are
+    /// always replaced as a whole.
+    pub fn json_patch_to(&self, target: &Any) -> Vec<PatchOp> {
+        let mut ops = Vec::new();
+        let mut path = String::new();
+        Self::diff(self, target, &mut path, &mut ops);
+        ops
+    }
+
+    fn diff(current: &Any, target: &Any, path: &mut String, ops: &mut Vec<PatchOp>) {
+        let len = path.len();
+        match (current, target) {
+            (Any::Map(current), Any::Map(target)) => {
+                // sort keys to make the produced patch deterministic
+                let mut keys: Vec<&String> = current.keys().chain(target.keys()).collect();
+                keys.sort();
+                keys.dedup();
+                for key in keys {
+                    PatchOp::push_key(path, key);
+                    match (current.get(key), target.get(key)) {
+                        (Some(current), Some(target)) => Self::diff(current, target, path, ops),
+                        (Some(_), None) => ops.push(PatchOp::Remove { path: path.clone() }),
+                        (None, Some(value)) => ops.push(PatchOp::Add {
+                            path: path.clone(),
+                            value: value.clone(),
+                        }),
+                        (None, None) => {}
+                    }
+                    path.truncate(len);
+                }
+            }
+            (Any::Array(current), Any::Array(target)) => {
+                for (i, (current, target)) in current.iter().zip(target.iter()).enumerate() {
+                    PatchOp::push_key(path, &i.to_string());
+                    Self::diff(current, target, path, ops);
+                    path.truncate(len);
+                }
+                for (i, value) in target.iter().enumerate().skip(current.len()) {
+                    PatchOp::push_key(path, &i.to_string());
+                    ops.push(PatchOp::Add {
+                        path: path.clone(),
+                        value: value.clone(),
+                    });
+                    path.truncate(len);
+                }
+                // remove from the back, so that indexes of preceding elements remain valid
+                for i in (target.len()..current.len()).rev() {
+                    PatchOp::push_key(path, &i.to_string());
+                    ops.push(PatchOp::Remove { path: path.clone() });
+                    path.truncate(len);
+                }
+            }
+            (current, target) if current != target => ops.push(PatchOp::Replace {
+                path: path.clone(),
+                value: target.clone(),
+            }),
+            _ => {}
+        }
+    }
+
+    /// Returns a new value being a result of merging `other` value into current one. Current value
+    /// is left untouched, while unchanged subtrees are shared between both of them.
+    ///
+    /// Entries of two maps are merged: values of keys present in `other` map override the ones
+    /// of current map, unless both of them can be merged themselves and [MapMerge::Deep] strategy
+    /// is used. Two arrays are merged according to [MergeStrategy::arrays]. In any other case
+    /// `other` value replaces current one.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{any, ArrayMerge, MergeStrategy};
+    ///
+    /// let a = any!({"tags": ["a"], "meta": {"x": 1}});
+    /// let b = any!({"tags": ["b"], "meta": {"y": 2}});
+    /// let strategy = MergeStrategy {
+    ///     arrays: ArrayMerge::Concat,
+    ///     ..MergeStrategy::default()
+    /// };
+    /// assert_eq!(
+    ///     a.deep_merge(&b, strategy),
+    ///     any!({"tags": ["a", "b"], "meta": {"x": 1, "y": 2}})
+    /// );
+    /// ```
+    pub fn deep_merge(&self, other: &Any, strategy: MergeStrategy) -> Any {
+        let merge_entry = |current: &Any, value: &Any| match strategy.maps {
+            MapMerge::Deep => current.deep_merge(value, strategy),
+            MapMerge::Shallow => value.clone(),
+        };
+        match (self, other) {
+            (Any::Map(current), Any::Map(entries)) => {
+                let mut map = current.as_ref().clone();
+                for (key, value) in entries.iter() {
+                    let merged = match map.get(key) {
+                        Some(current) => merge_entry(current, value),
+                        None => value.clone(),
+                    };
+                    map.insert(key.clone(), merged);
+                }
+                Any::Map(Arc::new(map))
+            }
+            (Any::Array(current), Any::Array(array)) => match strategy.arrays {
+                ArrayMerge::Replace => other.clone(),
+                ArrayMerge::Concat => {
+                    Any::Array(current.iter().chain(array.iter()).cloned().collect())
+                }
+                ArrayMerge::ElementWise => {
+                    let mut result: Vec<Any> = current
+                        .iter()
+                        .zip(array.iter())
+                        .map(|(current, value)| current.deep_merge(value, strategy))
+                        .collect();
+                    let tail = if current.len() > array.len() {
+                        current
+                    } else {
+                        array
+                    };
+                    result.extend(tail.iter().skip(result.len()).cloned());
+                    Any::Array(result.into())
+                }
+            },
+            _ => other.clone(),
+        }
+    }
 }
 
 impl std::fmt::Display for Any {
@@ -206,6 +476,7 @@ impl std::fmt::Display for Any {
             Any::Bool(value) => write!(f, "{}", value),
             Any::Number(value) => write!(f, "{}", value),
             Any::BigInt(value) => write!(f, "{}", value),
+            Any::BigUInt(value) => write!(f, "{}", value),
             Any::String(value) => f.write_str(value.as_ref()),
             Any::Array(values) => {
                 write!(f, "[")?;
@@ -257,6 +528,7 @@ macro_rules! impl_from_num {
                 match v {
                     Any::Number(num) => Ok(num as Self),
                     Any::BigInt(num) => Ok(num as Self),
+                    Any::BigUInt(num) => Ok(num as Self),
                     other => Err(other),
                 }
             }
@@ -283,6 +555,7 @@ macro_rules! impl_from_bigint {
                 match v {
                     Any::Number(num) => Ok(num as Self),
                     Any::BigInt(num) => Ok(num as Self),
+                    Any::BigUInt(num) => Ok(num as Self),
                     other => Err(other),
                 }
             }
@@ -323,6 +596,7 @@ impl TryFrom<Any> for u64 {
         match v {
             Any::Number(num) => Ok(num as Self),
             Any::BigInt(num) => Ok(num as Self),
+            Any::BigUInt(num) => Ok(num as Self),
             other => Err(other),
         }
     }
@@ -357,11 +631,54 @@ impl TryFrom<Any> for usize {
         match v {
             Any::Number(num) => Ok(num as Self),
             Any::BigInt(num) => Ok(num as Self),
+            Any::BigUInt(num) => Ok(num as Self),
             other => Err(other),
         }
     }
 }
 
+/// Key of a single-entry map, which represents a timestamp stored as a number of milliseconds
+/// since Unix epoch. See: `From<time::OffsetDateTime>` implementation of [Any].
+#[cfg(feature = "time")]
+pub const TIMESTAMP_TAG: &str = "$timestamp";
+
+/// Converts a timestamp into a map tagged with [TIMESTAMP_TAG] key, containing a number of
+/// milliseconds since Unix epoch. Sub-millisecond precision and UTC offset are not preserved.
+#[cfg(feature = "time")]
+impl From<time::OffsetDateTime> for Any {
+    fn from(value: time::OffsetDateTime) -> Self {
+        let millis = value.unix_timestamp_nanos().div_euclid(1_000_000) as i64;
+        let mut map = HashMap::with_capacity(1);
+        map.insert(TIMESTAMP_TAG.to_string(), Any::BigInt(millis));
+        Any::Map(Arc::new(map))
+    }
+}
+
+#[cfg(feature = "time")]
+impl TryFrom<Any> for time::OffsetDateTime {
+    type Error = Any;
+
+    fn try_from(v: Any) -> Result<Self, Self::Error> {
+        if let Any::Map(map) = &v {
+            let millis = match map.get(TIMESTAMP_TAG) {
+                Some(Any::BigInt(millis)) if map.len() == 1 => Some(*millis as i128),
+                // integers may be represented as floats eg. after JSON roundtrip
+                Some(Any::Number(millis)) if map.len() == 1 && millis.fract() == 0.0 => {
+                    Some(*millis as i128)
+                }
+                _ => None,
+            };
+            // huge numbers saturate when converted to i128, so they may overflow here
+            if let Some(nanos) = millis.and_then(|millis| millis.checked_mul(1_000_000)) {
+                if let Ok(timestamp) = Self::from_unix_timestamp_nanos(nanos) {
+                    return Ok(timestamp);
+                }
+            }
+        }
+        Err(v)
+    }
+}
+
 impl From<bool> for Any {
     #[inline]
     fn from(value: bool) -> Self {
@@ -504,6 +821,262 @@ where
     }
 }
 
+/// A single [RFC 6902](https://datatracker.ietf.org/doc/html/rfc6902) JSON Patch operation
+/// produced by [Any::json_patch_to]. Paths are JSON Pointers as defined by
+/// [RFC 6901](https://datatracker.ietf.org/doc/html/rfc6901). When serialized, it takes a form of
+/// a standard JSON Patch operation object, ie. `{"op":"add","path":"/a","value":1}`.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum PatchOp {
+    /// Inserts a `value` under a given `path`.
+    Add { path: String, value: Any },
+    /// Removes a value found under a given `path`.
+    Remove { path: String },
+    /// Replaces a value found under a given `path` with a new `value`.
+    Replace { path: String, value: Any },
+}
+
+impl PatchOp {
+    /// Returns a JSON Pointer this operation applies to.
+    pub fn path(&self) -> &str {
+        match self {
+            PatchOp::Add { path, .. } => path,
+            PatchOp::Remove { path } => path,
+            PatchOp::Replace { path, .. } => path,
+        }
+    }
+
+    fn push_key(path: &mut String, key: &str) {
+        path.push('/');
+        for c in key.chars() {
+            match c {
+                '~' => path.push_str("~0"),
+                '/' => path.push_str("~1"),
+                c => path.push(c),
+            }
+        }
+    }
+}
+
+/// Determines how [Any::deep_merge] combines nested values.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MergeStrategy {
+    /// Determines how two arrays are merged.
+    pub arrays: ArrayMerge,
+    /// Determines how entries present in both merged maps are combined.
+    pub maps: MapMerge,
+}
+
+/// Strategy of merging two [Any::Array]s used by [Any::deep_merge].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ArrayMerge {
+    /// Array being merged replaces the current one.
+    #[default]
+    Replace,
+    /// Elements of array being merged are appended at the end of the current one.
+    Concat,
+    /// Elements found at the same index in both arrays are merged with each other. If arrays
+    /// have different length, the remaining elements of a longer array are copied as they are.
+    ElementWise,
+}
+
+/// Strategy of merging two [Any::Map]s used by [Any::deep_merge].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MapMerge {
+    /// Values of entries present in both maps are replaced by the values of a map being merged.
+    Shallow,
+    /// Values of entries present in both maps are merged recursively.
+    #[default]
+    Deep,
+}
+
+/// Builder of an [Any::Array] value, created via [Any::array_with_capacity].
+#[derive(Debug, Default)]
+pub struct ArrayBuilder(Vec<Any>);
+
+impl ArrayBuilder {
+    /// Appends a new element at the end of the array.
+    #[inline]
+    pub fn push<V: Into<Any>>(&mut self, value: V) {
+        self.0.push(value.into())
+    }
+
+    /// Returns a number of elements pushed so far.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns true if no elements were pushed so far.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Consumes current builder, returning an [Any::Array] with all pushed elements.
+    pub fn build(self) -> Any {
+        Any::Array(Arc::from(self.0))
+    }
+}
+
+impl Extend<Any> for ArrayBuilder {
+    fn extend<T: IntoIterator<Item = Any>>(&mut self, iter: T) {
+        self.0.extend(iter)
+    }
+}
+
+/// Builder of an [Any::Map] value, created via [Any::map_with_capacity].
+#[derive(Debug, Default)]
+pub struct MapBuilder(HashMap<String, Any>);
+
+impl MapBuilder {
+    /// Inserts a new entry into the map, returning a value previously stored under the same `key`.
+    #[inline]
+    pub fn insert<K: Into<String>, V: Into<Any>>(&mut self, key: K, value: V) -> Option<Any> {
+        self.0.insert(key.into(), value.into())
+    }
+
+    /// Returns a number of entries inserted so far.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns true if no entries were inserted so far.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Consumes current builder, returning an [Any::Map] with all inserted entries.
+    pub fn build(self) -> Any {
+        Any::Map(Arc::new(self.0))
+    }
+}
+
+impl Extend<(String, Any)> for MapBuilder {
+    fn extend<T: IntoIterator<Item = (String, Any)>>(&mut self, iter: T) {
+        self.0.extend(iter)
+    }
+}
+
+/// Builder of an [OrderedMap] value, created via [Any::ordered_map_with_capacity].
+#[derive(Debug, Default)]
+pub struct OrderedMapBuilder {
+    entries: Vec<(String, Any)>,
+    /// Positions of entries within `entries`, indexed by their keys.
+    index: HashMap<String, usize>,
+}
+
+impl OrderedMapBuilder {
+    /// Inserts a new entry into the map, returning a value previously stored under the same `key`.
+    /// Entries inserted under an existing key keep their original position.
+    pub fn insert<K: Into<String>, V: Into<Any>>(&mut self, key: K, value: V) -> Option<Any> {
+        let key = key.into();
+        let value = value.into();
+        match self.index.get(&key) {
+            Some(&i) => Some(std::mem::replace(&mut self.entries[i].1, value)),
+            None => {
+                self.index.insert(key.clone(), self.entries.len());
+                self.entries.push((key, value));
+                None
+            }
+        }
+    }
+
+    /// Returns a number of entries inserted so far.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if no entries were inserted so far.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Consumes current builder, returning an [OrderedMap] with all inserted entries.
+    pub fn build(self) -> OrderedMap {
+        OrderedMap {
+            entries: self.entries.into(),
+            index: Arc::new(self.index),
+        }
+    }
+}
+
+impl Extend<(String, Any)> for OrderedMapBuilder {
+    fn extend<T: IntoIterator<Item = (String, Any)>>(&mut self, iter: T) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+/// Map of [Any] values, which preserves the order in which its entries were inserted, ie. when
+/// serialized to JSON. It's created via [Any::ordered_map_with_capacity] or
+/// [OrderedMap::from_json].
+///
+/// Only the order of its own entries is preserved: nested objects are still represented as
+/// unordered [Any::Map]s. Once converted into [Any], it becomes a regular [Any::Map] and its
+/// entries order is lost.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OrderedMap {
+    entries: Arc<[(String, Any)]>,
+    index: Arc<HashMap<String, usize>>,
+}
+
+impl OrderedMap {
+    /// Parses a JSON object into an [OrderedMap], preserving the order in which its keys were
+    /// defined. Values are parsed just like in case of [Any::from_json].
+    pub fn from_json(src: &str) -> Result<Self, Error> {
+        Ok(serde_json::from_str(src)?)
+    }
+
+    pub fn to_json(&self, buf: &mut String) {
+        use serde::Serialize;
+        use serde_json::Serializer;
+
+        let buf = unsafe { buf.as_mut_vec() };
+        let cursor = std::io::Cursor::new(buf);
+
+        let mut s = Serializer::new(cursor);
+        self.serialize(&mut s).unwrap();
+    }
+
+    /// Returns a value stored under a given `key`, if any.
+    pub fn get(&self, key: &str) -> Option<&Any> {
+        let i = *self.index.get(key)?;
+        Some(&self.entries[i].1)
+    }
+
+    /// Returns an iterator over entries of current map in their insertion order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Any)> {
+        self.entries
+            .iter()
+            .map(|(key, value)| (key.as_str(), value))
+    }
+
+    /// Returns a number of entries stored in current map.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if current map has no entries.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl From<OrderedMap> for Any {
+    fn from(value: OrderedMap) -> Self {
+        let map = value.entries.iter().cloned().collect();
+        Any::Map(Arc::new(map))
+    }
+}
+
 // This code is based on serde_json::json! macro (see: https://docs.rs/serde_json/latest/src/serde_json/macros.rs.html#53-58).
 // Kudos to the original authors.
 
@@ -749,3 +1322,465 @@ macro_rules! any_unexpected {
 macro_rules! any_expect_expr_comma {
     ($e:expr , $($tt:tt)*) => {};
 }
+
+#[cfg(test)]
+mod test {
+    use crate::any::{ArrayMerge, MapMerge, MergeStrategy, OrderedMap, PatchOp};
+    use crate::types::PathSegment;
+    use crate::types::ToJson;
+    use crate::updates::decoder::Decode;
+    use crate::{
+        any, Any, Array, ArrayPrelim, Doc, Map, MapPrelim, ReadTxn, StateVector, Transact, Update,
+    };
+    use std::convert::TryFrom;
+
+    #[test]
+    fn heap_size() {
+        let any_size = std::mem::size_of::<Any>();
+        assert_eq!(Any::Number(1.5).heap_size(), any_size);
+        assert_eq!(Any::Null.heap_size(), any_size);
+
+        let short = Any::from("a");
+        let long = Any::from("a".repeat(1000));
+        assert!(short.heap_size() > any_size);
+        assert_eq!(long.heap_size() - short.heap_size(), 999);
+
+        let nested = any!({
+            "name": "a".repeat(1000),
+            "tags": ["x", "y", "z"],
+            "data": { "count": 3 }
+        });
+        let tags = any!(["x", "y", "z"]);
+        // nested value covers all of its children
+        assert!(nested.heap_size() > long.heap_size() + tags.heap_size());
+        assert!(nested.heap_size() < 4096);
+    }
+
+    #[test]
+    fn deep_merge_arrays() {
+        let a = any!({"list": [{"a": 1}, 2, 3]});
+        let b = any!({"list": [{"b": 2}, 4]});
+
+        let replace = MergeStrategy {
+            arrays: ArrayMerge::Replace,
+            ..MergeStrategy::default()
+        };
+        assert_eq!(a.deep_merge(&b, replace), any!({"list": [{"b": 2}, 4]}));
+
+        let concat = MergeStrategy {
+            arrays: ArrayMerge::Concat,
+            ..MergeStrategy::default()
+        };
+        assert_eq!(
+            a.deep_merge(&b, concat),
+            any!({"list": [{"a": 1}, 2, 3, {"b": 2}, 4]})
+        );
+
+        let element_wise = MergeStrategy {
+            arrays: ArrayMerge::ElementWise,
+            ..MergeStrategy::default()
+        };
+        assert_eq!(
+            a.deep_merge(&b, element_wise),
+            any!({"list": [{"a": 1, "b": 2}, 4, 3]})
+        );
+        // longer array being merged contributes its tail
+        assert_eq!(
+            any!([1]).deep_merge(&any!([2, 3]), element_wise),
+            any!([2, 3])
+        );
+    }
+
+    #[test]
+    fn deep_merge_maps() {
+        let a = any!({"name": "a", "meta": {"x": 1, "nested": {"y": 2}}, "keep": true});
+        let b = any!({"name": "b", "meta": {"nested": {"z": 3}}, "new": null});
+
+        assert_eq!(
+            a.deep_merge(&b, MergeStrategy::default()),
+            any!({
+                "name": "b",
+                "meta": {"x": 1, "nested": {"y": 2, "z": 3}},
+                "keep": true,
+                "new": null
+            })
+        );
+
+        let shallow = MergeStrategy {
+            maps: MapMerge::Shallow,
+            ..MergeStrategy::default()
+        };
+        assert_eq!(
+            a.deep_merge(&b, shallow),
+            any!({"name": "b", "meta": {"nested": {"z": 3}}, "keep": true, "new": null})
+        );
+
+        // values of different types are replaced
+        assert_eq!(
+            a.deep_merge(&any!({"meta": [1]}), MergeStrategy::default()),
+            any!({"name": "a", "meta": [1], "keep": true})
+        );
+        assert_eq!(
+            a.deep_merge(&Any::Null, MergeStrategy::default()),
+            Any::Null
+        );
+    }
+
+    #[test]
+    fn json_patch_to() {
+        let current = any!({
+            "name": "John",
+            "age": 30,
+            "tags": ["a", "b", "c"],
+            "address": { "city": "Berlin", "zip": "10115" }
+        });
+
+        // added keys
+        let target = any!({
+            "name": "John",
+            "age": 30,
+            "tags": ["a", "b", "c", "d"],
+            "address": { "city": "Berlin", "zip": "10115", "street": "Main" },
+            "a/b~c": true
+        });
+        assert_eq!(
+            current.json_patch_to(&target),
+            vec![
+                PatchOp::Add {
+                    path: "/a~1b~0c".into(),
+                    value: any!(true)
+                },
+                PatchOp::Add {
+                    path: "/address/street".into(),
+                    value: any!("Main")
+                },
+                PatchOp::Add {
+                    path: "/tags/3".into(),
+                    value: any!("d")
+                },
+            ]
+        );
+
+        // removed keys
+        let target = any!({
+            "name": "John",
+            "tags": ["a"],
+            "address": { "city": "Berlin" }
+        });
+        assert_eq!(
+            current.json_patch_to(&target),
+            vec![
+                PatchOp::Remove {
+                    path: "/address/zip".into()
+                },
+                PatchOp::Remove {
+                    path: "/age".into()
+                },
+                PatchOp::Remove {
+                    path: "/tags/2".into()
+                },
+                PatchOp::Remove {
+                    path: "/tags/1".into()
+                },
+            ]
+        );
+
+        // changed scalar values
+        let target = any!({
+            "name": "Jane",
+            "age": 30,
+            "tags": ["a", 2, "c"],
+            "address": "unknown"
+        });
+        let patch = current.json_patch_to(&target);
+        assert_eq!(
+            patch,
+            vec![
+                PatchOp::Replace {
+                    path: "/address".into(),
+                    value: any!("unknown")
+                },
+                PatchOp::Replace {
+                    path: "/name".into(),
+                    value: any!("Jane")
+                },
+                PatchOp::Replace {
+                    path: "/tags/1".into(),
+                    value: any!(2)
+                },
+            ]
+        );
+        assert_eq!(
+            serde_json::to_string(&patch[1]).unwrap(),
+            r#"{"op":"replace","path":"/name","value":"Jane"}"#
+        );
+
+        assert!(current.json_patch_to(&current).is_empty());
+        assert_eq!(
+            Any::from(1).json_patch_to(&Any::from(2)),
+            vec![PatchOp::Replace {
+                path: "".into(),
+                value: any!(2)
+            }]
+        );
+    }
+
+    #[test]
+    fn ordered_map_preserves_key_order() {
+        let mut builder = Any::ordered_map_with_capacity(3);
+        builder.insert("zebra", 1);
+        builder.insert("apple", 2);
+        builder.insert("mango", 3);
+        assert_eq!(builder.insert("apple", 4), Some(Any::from(2)));
+        assert_eq!(builder.len(), 3);
+        let map = builder.build();
+
+        let keys: Vec<_> = map.iter().map(|(key, _)| key).collect();
+        assert_eq!(keys, vec!["zebra", "apple", "mango"]);
+        assert_eq!(map.get("apple"), Some(&Any::from(4)));
+        assert_eq!(map.get("pear"), None);
+
+        let mut json = String::new();
+        map.to_json(&mut json);
+        assert_eq!(json, r#"{"zebra":1,"apple":4,"mango":3}"#);
+
+        // top level keys keep their order through JSON parse
+        let src = r#"{"z":{"b":1},"c":"d","a":[true,null]}"#;
+        let parsed = OrderedMap::from_json(src).unwrap();
+        let mut json = String::new();
+        parsed.to_json(&mut json);
+        assert_eq!(json, src);
+        assert!(OrderedMap::from_json("[1,2]").is_err());
+
+        // once converted into Any it becomes a regular map
+        assert_eq!(Any::from(parsed), Any::from_json(src).unwrap());
+    }
+
+    #[test]
+    fn get_and_set_path() {
+        let value = any!({
+            "config": {
+                "children": [1, {"name": "a"}, [true, false]],
+                "title": "test"
+            }
+        });
+        let path = [
+            PathSegment::Key("config".into()),
+            PathSegment::Key("children".into()),
+            PathSegment::Index(1),
+            PathSegment::Key("name".into()),
+        ];
+        assert_eq!(value.get_path(&path), Some(&any!("a")));
+        assert_eq!(value.get_path(&[]), Some(&value));
+        assert_eq!(
+            value.get_path(&path[..3]),
+            Some(&any!({"name": "a"})),
+            "partial path"
+        );
+
+        let updated = value.set_path(&path, any!({"nested": [1, 2]})).unwrap();
+        assert_eq!(
+            updated,
+            any!({
+                "config": {
+                    "children": [1, {"name": {"nested": [1, 2]}}, [true, false]],
+                    "title": "test"
+                }
+            })
+        );
+        assert_eq!(
+            value.get_path(&path),
+            Some(&any!("a")),
+            "original is intact"
+        );
+
+        // new map entries can be inserted at the end of a path
+        let path = [
+            PathSegment::Key("config".into()),
+            PathSegment::Key("children".into()),
+            PathSegment::Index(2),
+            PathSegment::Index(0),
+        ];
+        let updated = updated.set_path(&path, any!(null)).unwrap();
+        assert_eq!(updated.get_path(&path), Some(&any!(null)));
+        let path = [
+            PathSegment::Key("config".into()),
+            PathSegment::Key("version".into()),
+        ];
+        let updated = updated.set_path(&path, any!(2)).unwrap();
+        assert_eq!(updated.get_path(&path), Some(&any!(2)));
+    }
+
+    #[test]
+    fn path_not_found() {
+        let value = any!({"a": [1, {"b": 2}]});
+        let missing = [
+            vec![PathSegment::Key("x".into())],
+            vec![PathSegment::Key("a".into()), PathSegment::Index(2)],
+            vec![PathSegment::Key("a".into()), PathSegment::Key("b".into())],
+            vec![
+                PathSegment::Key("a".into()),
+                PathSegment::Index(0),
+                PathSegment::Index(0),
+            ],
+            vec![PathSegment::Key("x".into()), PathSegment::Key("y".into())],
+        ];
+        for path in missing.iter() {
+            assert_eq!(value.get_path(path), None, "get {:?}", path);
+        }
+        // missing key at the end of a path is inserted by set_path
+        for path in missing[1..].iter() {
+            assert_eq!(value.set_path(path, any!(0)), None, "set {:?}", path);
+        }
+    }
+
+    #[test]
+    fn builders_with_capacity() {
+        let mut array = Any::array_with_capacity(3);
+        assert!(array.is_empty());
+        array.push(1);
+        array.push("a");
+        array.extend([Any::Null]);
+        assert_eq!(array.len(), 3);
+        assert_eq!(array.build(), any!([1, "a", null]));
+
+        let mut map = Any::map_with_capacity(2);
+        map.insert("a", 1);
+        assert_eq!(map.insert("a", 2), Some(any!(1)));
+        map.extend([("b".to_owned(), any!([true]))]);
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.build(), any!({"a": 2, "b": [true]}));
+
+        // shared collections output stays the same
+        let doc = Doc::with_client_id(1);
+        let root = doc.get_or_insert_map("map");
+        let expected = any!({
+            "array": [1, {"nested": [2, 3]}],
+            "value": "hello"
+        });
+        {
+            let mut txn = doc.transact_mut();
+            let array = root.insert(&mut txn, "array", ArrayPrelim::default());
+            array.push_back(&mut txn, 1);
+            let nested = array.push_back(&mut txn, MapPrelim::default());
+            nested.insert(&mut txn, "nested", vec![2, 3]);
+            root.insert(&mut txn, "removed", 1);
+            root.insert(&mut txn, "value", "hello");
+            root.remove(&mut txn, "removed");
+        }
+        assert_eq!(root.to_json(&doc.transact()), expected);
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn timestamp_conversion() {
+        use std::convert::TryFrom;
+        use time::OffsetDateTime;
+
+        let timestamp =
+            OffsetDateTime::from_unix_timestamp_nanos(1_700_000_000_123_000_000).unwrap();
+        let any = Any::from(timestamp);
+        assert_eq!(
+            any,
+            any!({crate::any::TIMESTAMP_TAG: Any::BigInt(1_700_000_000_123)})
+        );
+        assert_eq!(OffsetDateTime::try_from(any.clone()), Ok(timestamp));
+
+        // timestamps before Unix epoch are rounded down to the full millisecond
+        let before_epoch = OffsetDateTime::from_unix_timestamp_nanos(-1_500_000).unwrap();
+        let expected = OffsetDateTime::from_unix_timestamp_nanos(-2_000_000).unwrap();
+        assert_eq!(
+            OffsetDateTime::try_from(Any::from(before_epoch)),
+            Ok(expected)
+        );
+
+        // timestamps are also recognized after JSON roundtrip, which turns integers into floats
+        let mut json = String::new();
+        any.to_json(&mut json);
+        let parsed = Any::from_json(&json).unwrap();
+        assert_eq!(
+            parsed,
+            any!({crate::any::TIMESTAMP_TAG: Any::Number(1_700_000_000_123.0)})
+        );
+        assert_eq!(OffsetDateTime::try_from(parsed), Ok(timestamp));
+
+        let not_timestamp = any!({"at": 1_700_000_000_123i64});
+        assert_eq!(
+            OffsetDateTime::try_from(not_timestamp.clone()),
+            Err(not_timestamp)
+        );
+
+        // numbers out of the supported timestamp range
+        for millis in [1e300, -1e300, f64::INFINITY, f64::NAN] {
+            let out_of_range = any!({crate::any::TIMESTAMP_TAG: Any::Number(millis)});
+            assert!(OffsetDateTime::try_from(out_of_range).is_err());
+        }
+        let out_of_range = any!({crate::any::TIMESTAMP_TAG: Any::BigInt(i64::MAX)});
+        assert!(OffsetDateTime::try_from(out_of_range).is_err());
+
+        // encode/decode roundtrip through a document
+        let doc = Doc::with_client_id(1);
+        let map = doc.get_or_insert_map("map");
+        map.insert(&mut doc.transact_mut(), "created", timestamp);
+        let update = doc
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+        let remote = Doc::with_client_id(2);
+        let remote_map = remote.get_or_insert_map("map");
+        remote
+            .transact_mut()
+            .apply_update(Update::decode_v1(&update).unwrap());
+        let txn = remote.transact();
+        match remote_map.get(&txn, "created") {
+            Some(crate::Out::Any(any)) => {
+                assert_eq!(
+                    any,
+                    any!({crate::any::TIMESTAMP_TAG: Any::BigInt(1_700_000_000_123)})
+                );
+                assert_eq!(OffsetDateTime::try_from(any), Ok(timestamp));
+            }
+            other => panic!("unexpected value: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn u64_above_i64_range() {
+        let value = i64::MAX as u64 + 2;
+        // implicit conversion remains compatible with Yjs
+        assert_eq!(Any::try_from(value), Err(value));
+        assert_eq!(Any::try_from(i64::MAX as u64), Ok(Any::BigInt(i64::MAX)));
+
+        let any = Any::big_uint(value);
+        assert_eq!(any.to_string(), value.to_string());
+        let mut json = String::new();
+        any.to_json(&mut json);
+        assert_eq!(json, value.to_string());
+
+        let mut buf = Vec::new();
+        any.encode(&mut buf);
+        let decoded = Any::decode(&mut crate::encoding::read::Cursor::new(&buf)).unwrap();
+        assert_eq!(decoded, Any::BigUInt(value));
+        assert_eq!(decoded.cast::<u64>().unwrap(), value);
+
+        // values within i64 range are encoded in a way compatible with Yjs
+        let mut buf = Vec::new();
+        Any::big_uint(5).encode(&mut buf);
+        let decoded = Any::decode(&mut crate::encoding::read::Cursor::new(&buf)).unwrap();
+        assert_eq!(decoded, Any::BigInt(5));
+
+        let doc = Doc::with_client_id(1);
+        let map = doc.get_or_insert_map("map");
+        map.insert(&mut doc.transact_mut(), "id", Any::big_uint(value));
+        let txn = doc.transact();
+        let sv = StateVector::default();
+        let v1 = Update::decode_v1(&txn.encode_state_as_update_v1(&sv)).unwrap();
+        let v2 = Update::decode_v2(&txn.encode_state_as_update_v2(&sv)).unwrap();
+        for update in [v1, v2] {
+            let remote = Doc::with_client_id(2);
+            let remote_map = remote.get_or_insert_map("map");
+            remote.transact_mut().apply_update(update);
+            let actual: u64 = remote_map.get_as(&remote.transact(), "id").unwrap();
+            assert_eq!(actual, value);
+        }
+    }
+}
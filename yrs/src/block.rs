@@ -6,6 +6,7 @@ use crate::moving::Move;
 use crate::slice::{BlockSlice, GCSlice, ItemSlice};
 use crate::store::Store;
 use crate::transaction::TransactionMut;
+use crate::types::map::MapOp;
 use crate::types::text::update_current_attributes;
 use crate::types::{Attrs, TypePtr, TypeRef};
 use crate::undo::UndoStack;
@@ -98,6 +99,21 @@ impl ID {
     }
 }
 
+/// Information about a single block (struct) inserted by a given client, returned by
+/// [ReadTxn::client_blocks].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BlockInfo {
+    /// Identifier of the first element of this block.
+    pub id: ID,
+    /// Number of clock sequence numbers covered by this block.
+    pub len: u32,
+    /// Whether this block has been deleted.
+    pub deleted: bool,
+    /// Whether this block has been garbage collected, which means that its content is no longer
+    /// available. Garbage collected blocks are always deleted.
+    pub gc: bool,
+}
+
 pub(crate) enum BlockCell {
     GC(GC),
     Block(Box<Item>),
@@ -406,6 +422,7 @@ impl ItemPtr {
         block_ptr.integrate(txn, 0);
 
         txn.store_mut().blocks.push_block(redone_item);
+        txn.notify_local_change(block_ptr, true);
         Some(block_ptr)
     }
 
@@ -480,6 +497,7 @@ impl ItemPtr {
     /// Integrates current block into block store.
     /// If it returns true, it means that the block should be deleted after being added to a block store.
     pub(crate) fn integrate(&mut self, txn: &mut TransactionMut, offset: u32) -> bool {
+        txn.ensure_writable();
         let self_ptr = self.clone();
         let this = self.deref_mut();
         let store = txn.store_mut();
@@ -638,6 +656,22 @@ impl ItemPtr {
             } else if let Some(parent_sub) = &this.parent_sub {
                 // set as current parent value if right === null and this is parentSub
                 parent_ref.map.insert(parent_sub.clone(), self_ptr);
+                let old_value = match this.left {
+                    Some(left) if !left.is_deleted() => left.content.get_last(),
+                    _ => None,
+                };
+                txn.record_map_op(parent_ref, || {
+                    let key = parent_sub.clone();
+                    let value = this.content.get_last().unwrap_or_default();
+                    match old_value {
+                        Some(old_value) => MapOp::Updated {
+                            key,
+                            old_value,
+                            new_value: value,
+                        },
+                        None => MapOp::Inserted { key, value },
+                    }
+                });
                 if let Some(mut left) = this.left {
                     #[cfg(feature = "weak")]
                     {
@@ -645,7 +679,7 @@ impl ItemPtr {
                             // inherit links from the block we're overriding
                             left.info.clear_linked();
                             this.info.set_linked();
-                            let all_links = &mut txn.store.linked_by;
+                            let all_links = txn.linked_by_mut();
                             if let Some(linked_by) = all_links.remove(&left) {
                                 all_links.insert(self_ptr, linked_by);
                                 // since left is being deleted, it will remove
@@ -710,7 +744,7 @@ impl ItemPtr {
                 ItemContent::Doc(parent_doc, doc) => {
                     *parent_doc = Some(txn.doc().clone());
                     {
-                        let mut child_txn = doc.transact_mut();
+                        let mut child_txn = doc.transact_mut_unchecked(None);
                         child_txn.store.parent = Some(self_ptr);
                     }
                     let subdocs = txn.subdocs.get_or_init();
@@ -1044,6 +1078,11 @@ impl ItemFlags {
         self.set(ITEM_FLAG_DELETED)
     }
 
+    #[inline]
+    pub fn clear_deleted(&mut self) {
+        self.clear(ITEM_FLAG_DELETED)
+    }
+
     #[inline]
     pub fn is_deleted(&self) -> bool {
         self.check(ITEM_FLAG_DELETED)
@@ -1661,6 +1700,28 @@ impl ItemContent {
         }
     }
 
+    /// Lends a primitive value stored at a given `offset` to a function `f`. Unlike
+    /// [ItemContent::read], values stored as [Any] are lent by reference instead of being cloned.
+    /// Returns `None` if there's no primitive value at a given `offset`.
+    pub(crate) fn with_value<F, R>(&self, offset: usize, f: F) -> Option<R>
+    where
+        F: FnOnce(&Any) -> R,
+    {
+        match self {
+            ItemContent::Any(values) => values.get(offset).map(f),
+            other => {
+                let mut buf = [Out::default()];
+                if other.read(offset, &mut buf) == 0 {
+                    return None;
+                }
+                match &buf[0] {
+                    Out::Any(any) => Some(f(any)),
+                    _ => None,
+                }
+            }
+        }
+    }
+
     /// Reads all contents stored in this item and returns them. Use [ItemContent::read] if you need
     /// to read only slice of elements from the corresponding item.
     pub fn get_content(&self) -> Vec<Out> {
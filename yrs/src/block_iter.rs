@@ -3,7 +3,7 @@ use crate::branch::BranchPtr;
 use crate::moving::{Move, StickyIndex};
 use crate::transaction::{ReadTxn, TransactionMut};
 use crate::types::TypePtr;
-use crate::{Assoc, Out, ID};
+use crate::{Any, Assoc, Out, ID};
 
 /// Struct used for iterating over the sequence of item's values with respect to a potential
 /// [Move] markers that may change their order.
@@ -359,7 +359,40 @@ impl BlockIter {
     }
 
     pub(crate) fn slice<T: ReadTxn>(&mut self, txn: &T, buf: &mut [Out]) -> u32 {
-        let mut len = buf.len() as u32;
+        let len = buf.len() as u32;
+        self.read_with(txn, len, |content, offset, read| {
+            content.read(offset, &mut buf[read as usize..]) as u32
+        })
+    }
+
+    /// Lends a primitive value at the current iterator position to a given function `f`, advancing
+    /// the iterator by one element. Returns `None` if iterator has reached the end of the sequence
+    /// or the element is not a primitive value.
+    pub(crate) fn visit_value<T, F, R>(&mut self, txn: &T, f: F) -> Option<R>
+    where
+        T: ReadTxn,
+        F: FnOnce(&Any) -> R,
+    {
+        let mut f = Some(f);
+        let mut result = None;
+        self.read_with(txn, 1, |content, offset, _| {
+            if let Some(f) = f.take() {
+                result = content.with_value(offset, f);
+            }
+            1
+        });
+        result
+    }
+
+    /// Reads `len` elements starting from the current iterator position. For every visited item,
+    /// `read` function is called with the item content, offset of the first element to read
+    /// within that content and the number of elements read so far. It must return the number of
+    /// elements it has read.
+    fn read_with<T, F>(&mut self, txn: &T, mut len: u32, mut read_fn: F) -> u32
+    where
+        T: ReadTxn,
+        F: FnMut(&ItemContent, usize, u32) -> u32,
+    {
         if self.index + len > self.branch.content_len() {
             return 0;
         }
@@ -377,10 +410,7 @@ impl BlockIter {
                     {
                         if !item.is_deleted() && item.moved == self.curr_move {
                             // we're iterating inside of a block
-                            let r = item
-                                .content
-                                .read(self.rel as usize, &mut buf[read as usize..])
-                                as u32;
+                            let r = read_fn(&item.content, self.rel as usize, read);
                             read += r;
                             len -= r;
                             if self.rel + r == item.content_len(encoding) {
@@ -494,6 +524,7 @@ impl BlockIter {
         block_ptr.integrate(txn, 0);
 
         txn.store_mut().blocks.push_block(block);
+        txn.notify_local_change(block_ptr, true);
 
         if let Some(remainder) = remainder {
             remainder.integrate(txn, inner_ref.unwrap().into())
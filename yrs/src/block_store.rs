@@ -235,6 +235,49 @@ impl ClientBlockList {
     }
 
 
+    /// Squashes all adjacent blocks of this list, which can be merged together. Unlike calling
+    /// [Self::squash_left] for every index, this is done in a single pass: surviving blocks are
+    /// moved to the front of the list and the remaining ones are truncated at the end.
+    pub(crate) fn squash_all(&mut self) {
+        if self.list.len() < 2 {
+            return;
+        }
+        let mut last = 0;
+        for index in 1..self.list.len() {
+            let (l, r) = self.list.split_at_mut(index);
+            let squashed = match (&mut l[last], &mut r[0]) {
+                (BlockCell::GC(left), BlockCell::GC(right)) => {
+                    left.end = right.end;
+                    true
+                }
+                (BlockCell::Block(left), BlockCell::Block(right)) => {
+                    let mut left = ItemPtr::from(left);
+                    let right = ItemPtr::from(right);
+                    if left.try_squash(right) {
+                        if let Some(key) = right.parent_sub.as_deref() {
+                            if let TypePtr::Branch(mut parent) = right.parent {
+                                if let Some(e) = parent.map.get_mut(key) {
+                                    if right == *e {
+                                        *e = left;
+                                    }
+                                }
+                            }
+                        }
+                        true
+                    } else {
+                        false
+                    }
+                }
+                _ => false,
+            };
+            if !squashed {
+                last += 1;
+                self.list.swap(last, index);
+            }
+        }
+        self.list.truncate(last + 1);
+    }
+
     /// Attempts to squash block at a given `index` with a corresponding block on its left side.
     /// If this succeeds, block under a given `index` will be removed, and its contents will be
     /// squashed into its left neighbor. In such case a squash result will be returned in order to
@@ -366,6 +409,27 @@ impl BlockStore {
         StateVector::new(map)
     }
 
+    /// Merges all adjacent blocks of the same client, which can be squashed together without
+    /// changing the document content eg. consecutive text insertions split by former edits.
+    pub(crate) fn compact(&mut self) {
+        for blocks in self.clients.values_mut() {
+            blocks.squash_all();
+        }
+    }
+
+    /// Removes all blocks starting at or after clock values described by a given `state` vector.
+    /// Block lists of clients, which are left empty, are removed altogether.
+    pub(crate) fn truncate(&mut self, state: &StateVector) {
+        self.clients.retain(|client, blocks| {
+            let clock = state.get(client);
+            let index = blocks
+                .list
+                .partition_point(|cell| cell.clock_start() < clock);
+            blocks.list.truncate(index);
+            !blocks.list.is_empty()
+        });
+    }
+
     pub(crate) fn get_client(&self, client_id: &ClientID) -> Option<&ClientBlockList> {
         self.clients.get(client_id)
     }
@@ -1,6 +1,6 @@
 use crate::block::{BlockCell, Item, ItemContent, ItemPosition, ItemPtr, Prelim};
 use crate::types::array::ArrayEvent;
-use crate::types::map::MapEvent;
+use crate::types::map::{MapEvent, MapOp};
 use crate::types::text::TextEvent;
 use crate::types::xml::{XmlEvent, XmlTextEvent};
 use crate::types::{
@@ -36,13 +36,13 @@ impl BranchPtr {
         txn: &TransactionMut,
         subs: HashSet<Option<Arc<str>>>,
     ) -> Option<Event> {
-        let e = self.make_event(subs)?;
-        self.observers.trigger(|fun| fun(txn, &e));
+        let e = self.make_event(subs, txn.origin())?;
+        txn.trigger_observers(&self.observers, |fun| fun(txn, &e));
         Some(e)
     }
 
     pub(crate) fn trigger_deep(&self, txn: &TransactionMut, e: &Events) {
-        self.deep_observers.trigger(|fun| fun(txn, e));
+        txn.trigger_observers(&self.deep_observers, |fun| fun(txn, e));
     }
 }
 
@@ -212,6 +212,8 @@ pub struct Branch {
     pub(crate) observers: Observer<ObserveFn>,
 
     pub(crate) deep_observers: Observer<DeepObserveFn>,
+
+    pub(crate) op_observers: Observer<MapOpsFn>,
 }
 
 #[cfg(feature = "sync")]
@@ -224,6 +226,11 @@ type ObserveFn = Box<dyn Fn(&TransactionMut, &Event) + 'static>;
 #[cfg(not(feature = "sync"))]
 type DeepObserveFn = Box<dyn Fn(&TransactionMut, &Events) + 'static>;
 
+#[cfg(feature = "sync")]
+type MapOpsFn = Box<dyn Fn(&TransactionMut, &[MapOp]) + Send + Sync + 'static>;
+#[cfg(not(feature = "sync"))]
+type MapOpsFn = Box<dyn Fn(&TransactionMut, &[MapOp]) + 'static>;
+
 impl std::fmt::Debug for Branch {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         std::fmt::Display::fmt(self, f)
@@ -254,6 +261,7 @@ impl Branch {
             type_ref,
             observers: Observer::default(),
             deep_observers: Observer::default(),
+            op_observers: Observer::default(),
         })
     }
 
@@ -542,6 +550,14 @@ impl Branch {
         self.observers.subscribe(Box::new(f))
     }
 
+    #[cfg(feature = "sync")]
+    pub fn observe_once<F>(&mut self, f: F) -> Subscription
+    where
+        F: Fn(&TransactionMut, &Event) + Send + Sync + 'static,
+    {
+        self.observers.subscribe_once(Box::new(f))
+    }
+
     #[cfg(not(feature = "sync"))]
     pub fn observe<F>(&mut self, f: F) -> Subscription
     where
@@ -550,6 +566,14 @@ impl Branch {
         self.observers.subscribe(Box::new(f))
     }
 
+    #[cfg(not(feature = "sync"))]
+    pub fn observe_once<F>(&mut self, f: F) -> Subscription
+    where
+        F: Fn(&TransactionMut, &Event) + 'static,
+    {
+        self.observers.subscribe_once(Box::new(f))
+    }
+
     #[cfg(feature = "sync")]
 
     pub fn observe_with<F>(&mut self, key: Origin, f: F)
@@ -603,6 +627,22 @@ impl Branch {
         self.deep_observers.subscribe_with(key, Box::new(f))
     }
 
+    #[cfg(feature = "sync")]
+    pub(crate) fn observe_ops<F>(&self, f: F) -> Subscription
+    where
+        F: Fn(&TransactionMut, &[MapOp]) + Send + Sync + 'static,
+    {
+        self.op_observers.subscribe(Box::new(f))
+    }
+
+    #[cfg(not(feature = "sync"))]
+    pub(crate) fn observe_ops<F>(&self, f: F) -> Subscription
+    where
+        F: Fn(&TransactionMut, &[MapOp]) + 'static,
+    {
+        self.op_observers.subscribe(Box::new(f))
+    }
+
     pub(crate) fn is_parent_of(&self, mut ptr: Option<ItemPtr>) -> bool {
         while let Some(i) = ptr.as_deref() {
             if let Some(parent) = i.parent.as_branch() {
@@ -617,11 +657,15 @@ impl Branch {
         false
     }
 
-    pub(crate) fn make_event(&self, keys: HashSet<Option<Arc<str>>>) -> Option<Event> {
+    pub(crate) fn make_event(
+        &self,
+        keys: HashSet<Option<Arc<str>>>,
+        origin: Option<&Origin>,
+    ) -> Option<Event> {
         let self_ptr = BranchPtr::from(self);
         let event = match self.type_ref() {
-            TypeRef::Array => Event::Array(ArrayEvent::new(self_ptr)),
-            TypeRef::Map => Event::Map(MapEvent::new(self_ptr, keys)),
+            TypeRef::Array => Event::Array(ArrayEvent::new(self_ptr, origin.cloned())),
+            TypeRef::Map => Event::Map(MapEvent::new(self_ptr, keys, origin.cloned())),
             TypeRef::Text => Event::Text(TextEvent::new(self_ptr)),
             TypeRef::XmlElement(_) | TypeRef::XmlFragment => {
                 Event::XmlFragment(XmlEvent::new(self_ptr, keys))
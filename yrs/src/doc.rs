@@ -1,23 +1,27 @@
-use crate::block::{ClientID, ItemContent, ItemPtr, Prelim};
+use crate::block::{ClientID, ItemContent, ItemPosition, ItemPtr, Prelim, Unused};
 use crate::branch::BranchPtr;
 use crate::encoding::read::Error;
 use crate::event::{SubdocsEvent, TransactionCleanupEvent, UpdateEvent};
 use crate::store::{Store, StoreRef};
 use crate::transaction::{Origin, Transaction, TransactionMut};
-use crate::types::{RootRef, ToJson};
+use crate::types::{Path, PathSegment, RootRef, ToJson};
 use crate::updates::decoder::{Decode, Decoder};
 use crate::updates::encoder::{Encode, Encoder};
 use crate::utils::OptionExt;
 use crate::{
-    uuid_v4, uuid_v4_from, ArrayRef, BranchID, MapRef, Out, ReadTxn, TextRef, Uuid, WriteTxn,
-    XmlFragmentRef,
+    merge_updates_v1, uuid_parse, uuid_v4, uuid_v4_from, ArrayRef, BranchID, MapRef, Out, ReadTxn,
+    StateVector, TextRef, Update, Uuid, UuidError, WriteTxn, XmlFragmentRef,
 };
 use crate::{Any, Subscription};
 use atomic_refcell::{AtomicRefCell, BorrowError, BorrowMutError};
+use std::any::TypeId;
+use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::fmt::Formatter;
-use std::sync::Arc;
+use std::panic::AssertUnwindSafe;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
 /// A Yrs document type. Documents are the most important units of collaborative resources management.
@@ -99,12 +103,29 @@ impl Doc {
         ptr as *const Doc
     }
 
+    /// Creates a new document with a `client_id` and `guid` generated using a given random number
+    /// generator. Documents created from generators seeded with the same value are assigned the
+    /// same identifiers, which is useful for reproducible simulations.
+    pub fn new_with_rng(rng: &mut fastrand::Rng) -> Self {
+        Self::with_options(Options::with_rng(rng))
+    }
+
     /// Creates a new document with a specified `client_id`. It's up to a caller to guarantee that
     /// this identifier is unique across all communicating replicas of that document.
     pub fn with_client_id(client_id: ClientID) -> Self {
         Self::with_options(Options::with_client_id(client_id))
     }
 
+    /// Creates a new document with a specified `guid` and `client_id`. Unlike other constructors,
+    /// this one doesn't need to generate any random identifiers, which makes it the cheapest way
+    /// to create large numbers of documents, eg. in simulations.
+    ///
+    /// It's up to a caller to guarantee that `client_id` is unique across all communicating
+    /// replicas of that document.
+    pub fn with_guid_and_client_id(guid: Uuid, client_id: ClientID) -> Self {
+        Self::with_options(Options::with_guid_and_client_id(guid, client_id))
+    }
+
     /// Creates a new document with a configured set of [Options].
     pub fn with_options(options: Options) -> Self {
         Doc {
@@ -112,6 +133,50 @@ impl Doc {
         }
     }
 
+    /// Creates a new document with a configured set of [Options] and hydrates it with a batch of
+    /// `updates` encoded using a given encoding `version`. Updates are merged together and applied
+    /// within a single transaction, therefore their order doesn't matter. Changes whose
+    /// predecessors were not part of the batch are stashed as pending.
+    ///
+    /// Returns an error if any of the updates couldn't be decoded.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, EncodingVersion, GetString, Options, ReadTxn, StateVector, Text, Transact};
+    ///
+    /// let source = Doc::new();
+    /// source.get_or_insert_text("text").push(&mut source.transact_mut(), "hello");
+    /// let update = source.transact().encode_state_as_update_v1(&StateVector::default());
+    ///
+    /// let doc = Doc::from_updates(Options::default(), [update], EncodingVersion::V1).unwrap();
+    /// let text = doc.get_or_insert_text("text");
+    /// assert_eq!(text.get_string(&doc.transact()), "hello");
+    /// ```
+    pub fn from_updates<I, B>(
+        options: Options,
+        updates: I,
+        version: EncodingVersion,
+    ) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = B>,
+        B: AsRef<[u8]>,
+    {
+        let mut decoded = Vec::new();
+        for update in updates {
+            decoded.push(match version {
+                EncodingVersion::V1 => Update::decode_v1(update.as_ref())?,
+                EncodingVersion::V2 => Update::decode_v2(update.as_ref())?,
+            });
+        }
+        // merge the whole batch first, so that blocks and deletions referring to each other are
+        // integrated together regardless of the order in which updates were provided
+        let update = Update::merge_updates(decoded);
+        let doc = Doc::with_options(options);
+        doc.transact_mut_unchecked(None).apply_update(update);
+        Ok(doc)
+    }
+
     pub(crate) fn subdoc(parent: ItemPtr, options: Options) -> Self {
         let mut store = Store::new(options);
         store.parent = Some(parent);
@@ -137,6 +202,42 @@ impl Doc {
         self.store.options()
     }
 
+    /// Attaches an application-specific `value` (ie. database row ID) to current document,
+    /// replacing a value of the same type attached before. It can be retrieved later using
+    /// [Doc::meta].
+    ///
+    /// Attached values are local to a current document instance (and its clones): they are not
+    /// a part of the document state, so they are never encoded nor shared with other replicas or
+    /// other instances of a document with the same [Doc::guid].
+    ///
+    /// # Panics
+    ///
+    /// This method can be called while read-only transactions are active, but it will panic
+    /// whenever called while a read-write transaction (see: [Transact::transact_mut]) is active.
+    pub fn set_meta<T: Send + Sync + 'static>(&self, value: T) {
+        let store = self
+            .store
+            .try_borrow()
+            .expect("there's another active read-write transaction at the moment");
+        let mut meta = store.meta.lock().unwrap();
+        meta.insert(TypeId::of::<T>(), Arc::new(value));
+    }
+
+    /// Returns a value of type `T` attached to current document using [Doc::set_meta], if any.
+    ///
+    /// # Panics
+    ///
+    /// This method can be called while read-only transactions are active, but it will panic
+    /// whenever called while a read-write transaction (see: [Transact::transact_mut]) is active.
+    pub fn meta<T: Send + Sync + 'static>(&self) -> Option<Arc<T>> {
+        let store = self
+            .store
+            .try_borrow()
+            .expect("there's another active read-write transaction at the moment");
+        let value = store.meta.lock().unwrap().get(&TypeId::of::<T>())?.clone();
+        value.downcast::<T>().ok()
+    }
+
     /// Returns a [TextRef] data structure stored under a given `name`. Text structures are used for
     /// collaborative text editing: they expose operations to append and remove chunks of text,
     /// which are free to execute concurrently by multiple peers over remote boundaries.
@@ -154,7 +255,7 @@ impl Doc {
     /// is another transaction in process, it will panic. It's advised to define all root shared
     /// types during the document creation.
     pub fn get_or_insert_text<N: Into<Arc<str>>>(&self, name: N) -> TextRef {
-        TextRef::root(name).get_or_create(&mut self.transact_mut())
+        TextRef::root(name).get_or_create(&mut self.transact_mut_unchecked(None))
     }
 
     /// Returns a [MapRef] data structure stored under a given `name`. Maps are used to store key-value
@@ -175,7 +276,7 @@ impl Doc {
     /// is another transaction in process, it will panic. It's advised to define all root shared
     /// types during the document creation.
     pub fn get_or_insert_map<N: Into<Arc<str>>>(&self, name: N) -> MapRef {
-        MapRef::root(name).get_or_create(&mut self.transact_mut())
+        MapRef::root(name).get_or_create(&mut self.transact_mut_unchecked(None))
     }
 
     /// Returns an [ArrayRef] data structure stored under a given `name`. Array structures are used for
@@ -195,7 +296,7 @@ impl Doc {
     /// is another transaction in process, it will panic. It's advised to define all root shared
     /// types during the document creation.
     pub fn get_or_insert_array<N: Into<Arc<str>>>(&self, name: N) -> ArrayRef {
-        ArrayRef::root(name).get_or_create(&mut self.transact_mut())
+        ArrayRef::root(name).get_or_create(&mut self.transact_mut_unchecked(None))
     }
 
     /// Returns a [XmlFragmentRef] data structure stored under a given `name`. XML elements represent
@@ -217,7 +318,7 @@ impl Doc {
     /// is another transaction in process, it will panic. It's advised to define all root shared
     /// types during the document creation.
     pub fn get_or_insert_xml_fragment<N: Into<Arc<str>>>(&self, name: N) -> XmlFragmentRef {
-        XmlFragmentRef::root(name).get_or_create(&mut self.transact_mut())
+        XmlFragmentRef::root(name).get_or_create(&mut self.transact_mut_unchecked(None))
     }
 
     /// Subscribe callback function for any changes performed within transaction scope. These
@@ -225,6 +326,11 @@ impl Doc {
     /// necessary or passed to remote peers right away. This callback is triggered on function
     /// commit.
     ///
+    /// Callbacks are called synchronously, in the order of their subscription. Since the document
+    /// is still locked by a committed transaction at that time, trying to open another transaction
+    /// from within a callback will fail. Use [TransactionMut::defer] to schedule further changes
+    /// instead.
+    ///
     /// Returns a subscription, which will unsubscribe function when dropped.
     #[cfg(feature = "sync")]
     pub fn observe_update_v1<F>(&self, f: F) -> Result<Subscription, BorrowMutError>
@@ -241,6 +347,11 @@ impl Doc {
     /// necessary or passed to remote peers right away. This callback is triggered on function
     /// commit.
     ///
+    /// Callbacks are called synchronously, in the order of their subscription. Since the document
+    /// is still locked by a committed transaction at that time, trying to open another transaction
+    /// from within a callback will fail. Use [TransactionMut::defer] to schedule further changes
+    /// instead.
+    ///
     /// Returns a subscription, which will unsubscribe function when dropped.
     #[cfg(not(feature = "sync"))]
     pub fn observe_update_v1<F>(&self, f: F) -> Result<Subscription, BorrowMutError>
@@ -252,6 +363,141 @@ impl Doc {
         Ok(events.update_v1_events.subscribe(Box::new(f)))
     }
 
+    /// Subscribe callback function for changes performed within transaction scope of current
+    /// document and all of its sub-documents, recursively. Callback receives updates encoded using
+    /// lib0 v1 encoding - just like [Doc::observe_update_v1] - together with a guid of
+    /// the document they were made in. Sub-documents added later on are also included, while
+    /// removed ones are no longer observed.
+    ///
+    /// Returns a subscription, which will unsubscribe function when dropped.
+    #[cfg(feature = "sync")]
+    pub fn observe_update_v1_recursive<F>(&self, f: F) -> Result<Subscription, BorrowMutError>
+    where
+        F: Fn(&TransactionMut, &Uuid, &UpdateEvent) + Send + Sync + 'static,
+    {
+        self.observe_update_v1_tree(Arc::new(f))
+    }
+
+    /// Subscribe callback function for changes performed within transaction scope of current
+    /// document and all of its sub-documents, recursively. Callback receives updates encoded using
+    /// lib0 v1 encoding - just like [Doc::observe_update_v1] - together with a guid of
+    /// the document they were made in. Sub-documents added later on are also included, while
+    /// removed ones are no longer observed.
+    ///
+    /// Returns a subscription, which will unsubscribe function when dropped.
+    #[cfg(not(feature = "sync"))]
+    pub fn observe_update_v1_recursive<F>(&self, f: F) -> Result<Subscription, BorrowMutError>
+    where
+        F: Fn(&TransactionMut, &Uuid, &UpdateEvent) + 'static,
+    {
+        self.observe_update_v1_tree(Arc::new(f))
+    }
+
+    // without "sync" feature subscriptions are neither Send nor Sync, just like this one
+    #[cfg_attr(not(feature = "sync"), allow(clippy::arc_with_non_send_sync))]
+    fn observe_update_v1_tree(&self, f: RecursiveUpdateFn) -> Result<Subscription, BorrowMutError> {
+        let update = {
+            let f = f.clone();
+            let guid = self.guid().clone();
+            self.observe_update_v1(move |txn, e| f(txn, &guid, e))?
+        };
+        let children: Arc<Mutex<HashMap<DocAddr, Subscription>>> = Arc::default();
+        let subdocs = {
+            let f = f.clone();
+            let children = children.clone();
+            self.observe_subdocs(move |_, e| {
+                let mut children = children.lock().unwrap();
+                for subdoc in e.added().chain(e.loaded()) {
+                    Self::observe_subdoc_tree(&mut children, subdoc, &f);
+                }
+                for subdoc in e.removed() {
+                    children.remove(&DocAddr::new(subdoc));
+                }
+            })?
+        };
+        let existing: Vec<Doc> = self
+            .store
+            .try_borrow_mut()?
+            .subdocs
+            .values()
+            .cloned()
+            .collect();
+        {
+            let mut children = children.lock().unwrap();
+            for subdoc in existing.iter() {
+                Self::observe_subdoc_tree(&mut children, subdoc, &f);
+            }
+        }
+        Ok(Arc::new(TreeSubscription {
+            _update: update,
+            _subdocs: subdocs,
+            _children: children,
+        }))
+    }
+
+    fn observe_subdoc_tree(
+        children: &mut HashMap<DocAddr, Subscription>,
+        subdoc: &Doc,
+        f: &RecursiveUpdateFn,
+    ) {
+        if let Entry::Vacant(e) = children.entry(DocAddr::new(subdoc)) {
+            // sub-document may be locked by its own transaction, in which case it's skipped
+            if let Ok(sub) = subdoc.observe_update_v1_tree(f.clone()) {
+                e.insert(sub);
+            }
+        }
+    }
+
+    /// Subscribe callback function for changes performed within transaction scope, just like
+    /// [Doc::observe_update_v1]. If `skip_delete_only` flag is set, callback will not be called
+    /// for transactions which only deleted existing content without inserting any new blocks.
+    /// In such case the update is not even encoded unless other subscribers need it.
+    ///
+    /// Returns a subscription, which will unsubscribe function when dropped.
+    #[cfg(feature = "sync")]
+    pub fn observe_update_v1_ext<F>(
+        &self,
+        skip_delete_only: bool,
+        f: F,
+    ) -> Result<Subscription, BorrowMutError>
+    where
+        F: Fn(&TransactionMut, &UpdateEvent) + Send + Sync + 'static,
+    {
+        let mut r = self.store.try_borrow_mut()?;
+        let events = r.events.get_or_init();
+        let observer = if skip_delete_only {
+            &events.update_v1_content_events
+        } else {
+            &events.update_v1_events
+        };
+        Ok(observer.subscribe(Box::new(f)))
+    }
+
+    /// Subscribe callback function for changes performed within transaction scope, just like
+    /// [Doc::observe_update_v1]. If `skip_delete_only` flag is set, callback will not be called
+    /// for transactions which only deleted existing content without inserting any new blocks.
+    /// In such case the update is not even encoded unless other subscribers need it.
+    ///
+    /// Returns a subscription, which will unsubscribe function when dropped.
+    #[cfg(not(feature = "sync"))]
+    pub fn observe_update_v1_ext<F>(
+        &self,
+        skip_delete_only: bool,
+        f: F,
+    ) -> Result<Subscription, BorrowMutError>
+    where
+        F: Fn(&TransactionMut, &UpdateEvent) + 'static,
+    {
+        let mut r = self.store.try_borrow_mut()?;
+        let events = r.events.get_or_init();
+        let observer = if skip_delete_only {
+            &events.update_v1_content_events
+        } else {
+            &events.update_v1_events
+        };
+        Ok(observer.subscribe(Box::new(f)))
+    }
+
     /// Subscribe callback function for any changes performed within transaction scope. These
     /// changes are encoded using lib0 v1 encoding and can be decoded using [Update::decode_v1] if
     /// necessary or passed to remote peers right away. This callback is triggered on function
@@ -301,6 +547,82 @@ impl Doc {
         Ok(events.update_v1_events.unsubscribe(&key.into()))
     }
 
+    /// Subscribe callback function for changes performed within transaction scope, coalescing
+    /// updates of many consecutive transactions together. Updates are buffered until a given
+    /// `window` of time has passed since the first buffered update, then merged into a single
+    /// lib0 v1 encoded update (see: [merge_updates_v1]) and passed to a callback.
+    ///
+    /// No background thread or timer is used: buffered updates are only flushed by the commit
+    /// of a transaction, which happened after the `window` has elapsed. This means that the last
+    /// buffered updates will not be delivered until the next change is made.
+    ///
+    /// Returns a subscription, which will unsubscribe function when dropped.
+    #[cfg(feature = "sync")]
+    pub fn observe_update_v1_coalesced<F>(
+        &self,
+        window: Duration,
+        f: F,
+    ) -> Result<Subscription, BorrowMutError>
+    where
+        F: Fn(&TransactionMut, &UpdateEvent) + Send + Sync + 'static,
+    {
+        self.observe_update_v1(coalesce_updates(window, f))
+    }
+
+    /// Subscribe callback function for changes performed within transaction scope, coalescing
+    /// updates of many consecutive transactions together. Updates are buffered until a given
+    /// `window` of time has passed since the first buffered update, then merged into a single
+    /// lib0 v1 encoded update (see: [merge_updates_v1]) and passed to a callback.
+    ///
+    /// No background thread or timer is used: buffered updates are only flushed by the commit
+    /// of a transaction, which happened after the `window` has elapsed. This means that the last
+    /// buffered updates will not be delivered until the next change is made.
+    ///
+    /// Returns a subscription, which will unsubscribe function when dropped.
+    #[cfg(not(feature = "sync"))]
+    pub fn observe_update_v1_coalesced<F>(
+        &self,
+        window: Duration,
+        f: F,
+    ) -> Result<Subscription, BorrowMutError>
+    where
+        F: Fn(&TransactionMut, &UpdateEvent) + 'static,
+    {
+        self.observe_update_v1(coalesce_updates(window, f))
+    }
+
+    /// Subscribe callback function, that will be called on every commit which changed the document
+    /// with a byte length of its lib0 v1 encoded update. The update is encoded only once per
+    /// commit and shared with [Doc::observe_update_v1] subscribers, so this is a cheap way to
+    /// collect update size metrics without receiving (or cloning) the update itself.
+    ///
+    /// Returns a subscription, which will unsubscribe function when dropped.
+    #[cfg(feature = "sync")]
+    pub fn observe_update_metrics<F>(&self, f: F) -> Result<Subscription, BorrowMutError>
+    where
+        F: Fn(usize) + Send + Sync + 'static,
+    {
+        let mut r = self.store.try_borrow_mut()?;
+        let events = r.events.get_or_init();
+        Ok(events.update_metrics_events.subscribe(Box::new(f)))
+    }
+
+    /// Subscribe callback function, that will be called on every commit which changed the document
+    /// with a byte length of its lib0 v1 encoded update. The update is encoded only once per
+    /// commit and shared with [Doc::observe_update_v1] subscribers, so this is a cheap way to
+    /// collect update size metrics without receiving (or cloning) the update itself.
+    ///
+    /// Returns a subscription, which will unsubscribe function when dropped.
+    #[cfg(not(feature = "sync"))]
+    pub fn observe_update_metrics<F>(&self, f: F) -> Result<Subscription, BorrowMutError>
+    where
+        F: Fn(usize) + 'static,
+    {
+        let mut r = self.store.try_borrow_mut()?;
+        let events = r.events.get_or_init();
+        Ok(events.update_metrics_events.subscribe(Box::new(f)))
+    }
+
     /// Subscribe callback function for any changes performed within transaction scope. These
     /// changes are encoded using lib0 v2 encoding and can be decoded using [Update::decode_v2] if
     /// necessary or passed to remote peers right away. This callback is triggered on function
@@ -382,6 +704,167 @@ impl Doc {
         Ok(events.update_v2_events.unsubscribe(&key.into()))
     }
 
+    /// Subscribe callback function for any changes performed within transaction scope. These
+    /// changes are encoded using lib0 encoding version configured by [Options::encoding_version].
+    /// This callback is triggered on function commit.
+    ///
+    /// Returns a subscription, which will unsubscribe function when dropped.
+    #[cfg(feature = "sync")]
+    pub fn observe_update<F>(&self, f: F) -> Result<Subscription, BorrowMutError>
+    where
+        F: Fn(&TransactionMut, &UpdateEvent) + Send + Sync + 'static,
+    {
+        match self.options().encoding_version {
+            EncodingVersion::V1 => self.observe_update_v1(f),
+            EncodingVersion::V2 => self.observe_update_v2(f),
+        }
+    }
+
+    /// Subscribe callback function for any changes performed within transaction scope. These
+    /// changes are encoded using lib0 encoding version configured by [Options::encoding_version].
+    /// This callback is triggered on function commit.
+    ///
+    /// Returns a subscription, which will unsubscribe function when dropped.
+    #[cfg(not(feature = "sync"))]
+    pub fn observe_update<F>(&self, f: F) -> Result<Subscription, BorrowMutError>
+    where
+        F: Fn(&TransactionMut, &UpdateEvent) + 'static,
+    {
+        match self.options().encoding_version {
+            EncodingVersion::V1 => self.observe_update_v1(f),
+            EncodingVersion::V2 => self.observe_update_v2(f),
+        }
+    }
+
+    /// Subscribe callback function for any changes performed within transaction scope. These
+    /// changes are encoded using lib0 encoding version configured by [Options::encoding_version].
+    /// This callback is triggered on function commit.
+    ///
+    /// Provided `key` will be used to identify a subscription, which will be used to unsubscribe.
+    #[cfg(feature = "sync")]
+    pub fn observe_update_with<K, F>(&self, key: K, f: F) -> Result<(), BorrowMutError>
+    where
+        K: Into<Origin>,
+        F: Fn(&TransactionMut, &UpdateEvent) + Send + Sync + 'static,
+    {
+        match self.options().encoding_version {
+            EncodingVersion::V1 => self.observe_update_v1_with(key, f),
+            EncodingVersion::V2 => self.observe_update_v2_with(key, f),
+        }
+    }
+
+    /// Subscribe callback function for any changes performed within transaction scope. These
+    /// changes are encoded using lib0 encoding version configured by [Options::encoding_version].
+    /// This callback is triggered on function commit.
+    ///
+    /// Provided `key` will be used to identify a subscription, which will be used to unsubscribe.
+    #[cfg(not(feature = "sync"))]
+    pub fn observe_update_with<K, F>(&self, key: K, f: F) -> Result<(), BorrowMutError>
+    where
+        K: Into<Origin>,
+        F: Fn(&TransactionMut, &UpdateEvent) + 'static,
+    {
+        match self.options().encoding_version {
+            EncodingVersion::V1 => self.observe_update_v1_with(key, f),
+            EncodingVersion::V2 => self.observe_update_v2_with(key, f),
+        }
+    }
+
+    pub fn unobserve_update<K>(&self, key: K) -> Result<bool, BorrowMutError>
+    where
+        K: Into<Origin>,
+    {
+        match self.options().encoding_version {
+            EncodingVersion::V1 => self.unobserve_update_v1(key),
+            EncodingVersion::V2 => self.unobserve_update_v2(key),
+        }
+    }
+
+    /// Returns a sequence number of the last committed transaction, which changed the state of
+    /// current document. Sequence numbers are local to the document instance and grow
+    /// monotonically with every commit.
+    pub fn commit_seq(&self) -> u64 {
+        let txn = self.transact();
+        txn.store().commit_seq()
+    }
+
+    /// Returns a lib0 v1 encoded update containing all changes committed after the transaction
+    /// with a given `seq` number (see: [Doc::commit_seq]). This enables incremental persistence
+    /// without state vector exchange.
+    ///
+    /// Changes are recovered from the bounded update log configured via
+    /// [Options::update_log_capacity]. If the log is disabled or some of the requested changes
+    /// have already been evicted from it, `None` is returned and the caller should fall back to
+    /// full state encoding instead.
+    ///
+    /// This method opens a read-only transaction, therefore it will panic if there's another
+    /// read-write transaction active at the moment.
+    pub fn encode_diff_since_seq(&self, seq: u64) -> Option<Vec<u8>> {
+        let txn = self.transact();
+        txn.store().encode_diff_since_seq(seq)
+    }
+
+    /// Encodes the difference between remote peer state given its `state_vector` and the state
+    /// of a current document, using lib0 encoding version configured by
+    /// [Options::encoding_version].
+    ///
+    /// This method opens a read-only transaction, therefore it will panic if there's another
+    /// read-write transaction active at the moment.
+    pub fn encode_state_as_update(&self, state_vector: &StateVector) -> Vec<u8> {
+        let txn = self.transact();
+        match self.options().encoding_version {
+            EncodingVersion::V1 => txn.encode_state_as_update_v1(state_vector),
+            EncodingVersion::V2 => txn.encode_state_as_update_v2(state_vector),
+        }
+    }
+
+    /// Runs a given function `f` in the scope of a new read-write transaction. Changes made by `f`
+    /// are committed only if it returns `Ok`. If `f` returns `Err` or panics, all changes
+    /// integrated within that transaction - including remote updates applied through it - are
+    /// rolled back: the document is left unchanged and no observers are notified. Panics are
+    /// propagated to the caller once the rollback is complete.
+    ///
+    /// Shared types created within a rolled back transaction are no longer alive
+    /// (see: [ReadTxn::is_alive]) and must not be used afterwards.
+    ///
+    /// # Panics
+    ///
+    /// Just like [Transact::transact_mut], this method will panic if there's another transaction
+    /// active at the moment.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, GetString, Text, Transact};
+    ///
+    /// let doc = Doc::new();
+    /// let text = doc.get_or_insert_text("text");
+    /// let result: Result<(), &str> = doc.with_transaction_mut(|txn| {
+    ///     text.push(txn, "hello");
+    ///     Err("aborted")
+    /// });
+    /// assert_eq!(result, Err("aborted"));
+    /// assert_eq!(text.get_string(&doc.transact()), "");
+    /// ```
+    pub fn with_transaction_mut<F, R, E>(&self, f: F) -> Result<R, E>
+    where
+        F: FnOnce(&mut TransactionMut) -> Result<R, E>,
+    {
+        let mut txn = self.transact_mut();
+        match std::panic::catch_unwind(AssertUnwindSafe(|| f(&mut txn))) {
+            Ok(Ok(result)) => Ok(result),
+            Ok(Err(e)) => {
+                txn.revert();
+                Err(e)
+            }
+            Err(panic) => {
+                txn.revert();
+                drop(txn);
+                std::panic::resume_unwind(panic)
+            }
+        }
+    }
+
     /// Subscribe callback function to updates on the `Doc`. The callback will receive state updates and
     /// deletions when a document transaction is committed.
     #[cfg(feature = "sync")]
@@ -494,103 +977,247 @@ impl Doc {
         Ok(events.after_transaction_events.unsubscribe(&key.into()))
     }
 
-    /// Subscribe callback function, that will be called whenever a subdocuments inserted in this
-    /// [Doc] will request a load.
+    /// Subscribe callback function, which will be called at the very end of every transaction
+    /// commit - after all update events have been emitted. Unlike [Doc::observe_after_transaction],
+    /// the callback receives the lib0 v1 encoded update committed by the transaction, or `None`
+    /// if the transaction didn't change anything. If there are [Doc::observe_update_v1]
+    /// subscribers, the very same update bytes are shared with them instead of being encoded twice.
+    ///
+    /// Returns a subscription, which will unsubscribe function when dropped.
     #[cfg(feature = "sync")]
-    pub fn observe_subdocs<F>(&self, f: F) -> Result<Subscription, BorrowMutError>
+    pub fn observe_after_transaction_update<F>(&self, f: F) -> Result<Subscription, BorrowMutError>
     where
-        F: Fn(&TransactionMut, &SubdocsEvent) + Send + Sync + 'static,
+        F: Fn(&mut TransactionMut, Option<&UpdateEvent>) + Send + Sync + 'static,
     {
         let mut r = self.store.try_borrow_mut()?;
         let events = r.events.get_or_init();
-        Ok(events.subdocs_events.subscribe(Box::new(f)))
+        Ok(events
+            .after_transaction_update_events
+            .subscribe(Box::new(f)))
     }
 
-    /// Subscribe callback function, that will be called whenever a subdocuments inserted in this
-    /// [Doc] will request a load.
+    /// Subscribe callback function, which will be called at the very end of every transaction
+    /// commit - after all update events have been emitted. Unlike [Doc::observe_after_transaction],
+    /// the callback receives the lib0 v1 encoded update committed by the transaction, or `None`
+    /// if the transaction didn't change anything. If there are [Doc::observe_update_v1]
+    /// subscribers, the very same update bytes are shared with them instead of being encoded twice.
+    ///
+    /// Returns a subscription, which will unsubscribe function when dropped.
     #[cfg(not(feature = "sync"))]
-    pub fn observe_subdocs<F>(&self, f: F) -> Result<Subscription, BorrowMutError>
+    pub fn observe_after_transaction_update<F>(&self, f: F) -> Result<Subscription, BorrowMutError>
     where
-        F: Fn(&TransactionMut, &SubdocsEvent) + 'static,
+        F: Fn(&mut TransactionMut, Option<&UpdateEvent>) + 'static,
     {
         let mut r = self.store.try_borrow_mut()?;
         let events = r.events.get_or_init();
-        Ok(events.subdocs_events.subscribe(Box::new(f)))
+        Ok(events
+            .after_transaction_update_events
+            .subscribe(Box::new(f)))
     }
 
-    /// Subscribe callback function, that will be called whenever a subdocuments inserted in this
-    /// [Doc] will request a load.
+    /// Sets a handler receiving panics raised by transaction and update callbacks of current
+    /// document (such as [Doc::observe_update_v1] or [Doc::observe_after_transaction]), as well as
+    /// by callbacks of its shared types (subscribed via [Observable::observe](crate::Observable),
+    /// [DeepObservable::observe_deep](crate::DeepObservable) or [MapRef::observe_ops]). Panics are
+    /// caught for each callback separately, so that a single faulty callback doesn't prevent other
+    /// subscribers from being notified nor interrupts a transaction commit.
+    ///
+    /// If no handler has been set, the first caught panic is resumed once the transaction has been
+    /// committed and the document released.
     #[cfg(feature = "sync")]
-    pub fn observe_subdocs_with<K, F>(&self, key: K, f: F) -> Result<(), BorrowMutError>
+    pub fn set_observer_panic_handler<F>(&self, f: F) -> Result<(), BorrowMutError>
     where
-        K: Into<Origin>,
-        F: Fn(&TransactionMut, &SubdocsEvent) + Send + Sync + 'static,
+        F: Fn(Box<dyn std::any::Any + Send>) + Send + Sync + 'static,
     {
         let mut r = self.store.try_borrow_mut()?;
         let events = r.events.get_or_init();
-        events
-            .subdocs_events
-            .subscribe_with(key.into(), Box::new(f));
+        events.observer_panic_handler = Some(Box::new(f));
         Ok(())
     }
 
-    /// Subscribe callback function, that will be called whenever a subdocuments inserted in this
-    /// [Doc] will request a load.
+    /// Sets a handler receiving panics raised by transaction and update callbacks of current
+    /// document (such as [Doc::observe_update_v1] or [Doc::observe_after_transaction]), as well as
+    /// by callbacks of its shared types (subscribed via [Observable::observe](crate::Observable),
+    /// [DeepObservable::observe_deep](crate::DeepObservable) or [MapRef::observe_ops]). Panics are
+    /// caught for each callback separately, so that a single faulty callback doesn't prevent other
+    /// subscribers from being notified nor interrupts a transaction commit.
+    ///
+    /// If no handler has been set, the first caught panic is resumed once the transaction has been
+    /// committed and the document released.
     #[cfg(not(feature = "sync"))]
-    pub fn observe_subdocs_with<K, F>(&self, key: K, f: F) -> Result<(), BorrowMutError>
+    pub fn set_observer_panic_handler<F>(&self, f: F) -> Result<(), BorrowMutError>
     where
-        K: Into<Origin>,
-        F: Fn(&TransactionMut, &SubdocsEvent) + 'static,
+        F: Fn(Box<dyn std::any::Any + Send>) + 'static,
     {
         let mut r = self.store.try_borrow_mut()?;
         let events = r.events.get_or_init();
-        events
-            .subdocs_events
-            .subscribe_with(key.into(), Box::new(f));
+        events.observer_panic_handler = Some(Box::new(f));
         Ok(())
     }
 
-    pub fn unobserve_subdocs<K>(&self, key: K) -> Result<bool, BorrowMutError>
+    /// Subscribe callback function, that will be called when blocks which were received out of
+    /// order and stashed as pending (see: [TransactionMut::pending]) have finally been integrated,
+    /// because their missing dependencies arrived. Callback receives a state vector of
+    /// the document after the transaction integrating them.
+    ///
+    /// Returns a subscription, which will unsubscribe function when dropped.
+    #[cfg(feature = "sync")]
+    pub fn observe_pending_resolved<F>(&self, f: F) -> Result<Subscription, BorrowMutError>
     where
-        K: Into<Origin>,
+        F: Fn(&TransactionMut, &StateVector) + Send + Sync + 'static,
     {
         let mut r = self.store.try_borrow_mut()?;
         let events = r.events.get_or_init();
-        Ok(events.subdocs_events.unsubscribe(&key.into()))
+        Ok(events.pending_resolved_events.subscribe(Box::new(f)))
     }
 
-    /// Subscribe callback function, that will be called whenever a [DocRef::destroy] has been called.
-    #[cfg(feature = "sync")]
-    pub fn observe_destroy<F>(&self, f: F) -> Result<Subscription, BorrowMutError>
+    /// Subscribe callback function, that will be called when blocks which were received out of
+    /// order and stashed as pending (see: [TransactionMut::pending]) have finally been integrated,
+    /// because their missing dependencies arrived. Callback receives a state vector of
+    /// the document after the transaction integrating them.
+    ///
+    /// Returns a subscription, which will unsubscribe function when dropped.
+    #[cfg(not(feature = "sync"))]
+    pub fn observe_pending_resolved<F>(&self, f: F) -> Result<Subscription, BorrowMutError>
     where
-        F: Fn(&TransactionMut, &Doc) + Send + Sync + 'static,
+        F: Fn(&TransactionMut, &StateVector) + 'static,
     {
         let mut r = self.store.try_borrow_mut()?;
         let events = r.events.get_or_init();
-        Ok(events.destroy_events.subscribe(Box::new(f)))
+        Ok(events.pending_resolved_events.subscribe(Box::new(f)))
     }
 
-    /// Subscribe callback function, that will be called whenever a [DocRef::destroy] has been called.
-    #[cfg(not(feature = "sync"))]
-    pub fn observe_destroy<F>(&self, f: F) -> Result<Subscription, BorrowMutError>
+    /// Subscribe callback function, that will be called once a transaction, which applied
+    /// the first content to a previously empty document, has been committed. Unlike
+    /// [SubdocsEvent::loaded], which only reports that a sub-document has requested to be loaded,
+    /// this is emitted by the sub-document itself when the requested content has actually arrived.
+    /// Local changes made to an empty document don't trigger this event.
+    ///
+    /// Returns a subscription, which will unsubscribe function when dropped.
+    #[cfg(feature = "sync")]
+    pub fn observe_content_loaded<F>(&self, f: F) -> Result<Subscription, BorrowMutError>
     where
-        F: Fn(&TransactionMut, &Doc) + 'static,
+        F: Fn(&TransactionMut) + Send + Sync + 'static,
     {
         let mut r = self.store.try_borrow_mut()?;
         let events = r.events.get_or_init();
-        Ok(events.destroy_events.subscribe(Box::new(f)))
+        Ok(events.content_loaded_events.subscribe(Box::new(f)))
     }
 
-    /// Subscribe callback function, that will be called whenever a [DocRef::destroy] has been called.
-    #[cfg(feature = "sync")]
-    pub fn observe_destroy_with<K, F>(&self, key: K, f: F) -> Result<(), BorrowMutError>
+    /// Subscribe callback function, that will be called once a transaction, which applied
+    /// the first content to a previously empty document, has been committed. Unlike
+    /// [SubdocsEvent::loaded], which only reports that a sub-document has requested to be loaded,
+    /// this is emitted by the sub-document itself when the requested content has actually arrived.
+    /// Local changes made to an empty document don't trigger this event.
+    ///
+    /// Returns a subscription, which will unsubscribe function when dropped.
+    #[cfg(not(feature = "sync"))]
+    pub fn observe_content_loaded<F>(&self, f: F) -> Result<Subscription, BorrowMutError>
     where
-        K: Into<Origin>,
-        F: Fn(&TransactionMut, &Doc) + Send + Sync + 'static,
+        F: Fn(&TransactionMut) + 'static,
     {
         let mut r = self.store.try_borrow_mut()?;
         let events = r.events.get_or_init();
-        events
+        Ok(events.content_loaded_events.subscribe(Box::new(f)))
+    }
+
+    /// Subscribe callback function, that will be called whenever a subdocuments inserted in this
+    /// [Doc] will request a load.
+    #[cfg(feature = "sync")]
+    pub fn observe_subdocs<F>(&self, f: F) -> Result<Subscription, BorrowMutError>
+    where
+        F: Fn(&TransactionMut, &SubdocsEvent) + Send + Sync + 'static,
+    {
+        let mut r = self.store.try_borrow_mut()?;
+        let events = r.events.get_or_init();
+        Ok(events.subdocs_events.subscribe(Box::new(f)))
+    }
+
+    /// Subscribe callback function, that will be called whenever a subdocuments inserted in this
+    /// [Doc] will request a load.
+    #[cfg(not(feature = "sync"))]
+    pub fn observe_subdocs<F>(&self, f: F) -> Result<Subscription, BorrowMutError>
+    where
+        F: Fn(&TransactionMut, &SubdocsEvent) + 'static,
+    {
+        let mut r = self.store.try_borrow_mut()?;
+        let events = r.events.get_or_init();
+        Ok(events.subdocs_events.subscribe(Box::new(f)))
+    }
+
+    /// Subscribe callback function, that will be called whenever a subdocuments inserted in this
+    /// [Doc] will request a load.
+    #[cfg(feature = "sync")]
+    pub fn observe_subdocs_with<K, F>(&self, key: K, f: F) -> Result<(), BorrowMutError>
+    where
+        K: Into<Origin>,
+        F: Fn(&TransactionMut, &SubdocsEvent) + Send + Sync + 'static,
+    {
+        let mut r = self.store.try_borrow_mut()?;
+        let events = r.events.get_or_init();
+        events
+            .subdocs_events
+            .subscribe_with(key.into(), Box::new(f));
+        Ok(())
+    }
+
+    /// Subscribe callback function, that will be called whenever a subdocuments inserted in this
+    /// [Doc] will request a load.
+    #[cfg(not(feature = "sync"))]
+    pub fn observe_subdocs_with<K, F>(&self, key: K, f: F) -> Result<(), BorrowMutError>
+    where
+        K: Into<Origin>,
+        F: Fn(&TransactionMut, &SubdocsEvent) + 'static,
+    {
+        let mut r = self.store.try_borrow_mut()?;
+        let events = r.events.get_or_init();
+        events
+            .subdocs_events
+            .subscribe_with(key.into(), Box::new(f));
+        Ok(())
+    }
+
+    pub fn unobserve_subdocs<K>(&self, key: K) -> Result<bool, BorrowMutError>
+    where
+        K: Into<Origin>,
+    {
+        let mut r = self.store.try_borrow_mut()?;
+        let events = r.events.get_or_init();
+        Ok(events.subdocs_events.unsubscribe(&key.into()))
+    }
+
+    /// Subscribe callback function, that will be called whenever a [DocRef::destroy] has been called.
+    #[cfg(feature = "sync")]
+    pub fn observe_destroy<F>(&self, f: F) -> Result<Subscription, BorrowMutError>
+    where
+        F: Fn(&TransactionMut, &Doc) + Send + Sync + 'static,
+    {
+        let mut r = self.store.try_borrow_mut()?;
+        let events = r.events.get_or_init();
+        Ok(events.destroy_events.subscribe(Box::new(f)))
+    }
+
+    /// Subscribe callback function, that will be called whenever a [DocRef::destroy] has been called.
+    #[cfg(not(feature = "sync"))]
+    pub fn observe_destroy<F>(&self, f: F) -> Result<Subscription, BorrowMutError>
+    where
+        F: Fn(&TransactionMut, &Doc) + 'static,
+    {
+        let mut r = self.store.try_borrow_mut()?;
+        let events = r.events.get_or_init();
+        Ok(events.destroy_events.subscribe(Box::new(f)))
+    }
+
+    /// Subscribe callback function, that will be called whenever a [DocRef::destroy] has been called.
+    #[cfg(feature = "sync")]
+    pub fn observe_destroy_with<K, F>(&self, key: K, f: F) -> Result<(), BorrowMutError>
+    where
+        K: Into<Origin>,
+        F: Fn(&TransactionMut, &Doc) + Send + Sync + 'static,
+    {
+        let mut r = self.store.try_borrow_mut()?;
+        let events = r.events.get_or_init();
+        events
             .destroy_events
             .subscribe_with(key.into(), Box::new(f));
         Ok(())
@@ -620,13 +1247,44 @@ impl Doc {
         Ok(())
     }
 
+    /// Switches [Options::read_only] mode of current document on or off.
+    pub fn set_read_only(&self, read_only: bool) -> Result<(), BorrowMutError> {
+        let mut store = self.store.try_borrow_mut()?;
+        store.options.read_only = read_only;
+        Ok(())
+    }
+
+    /// Applies an `update` received from a remote peer. Unlike [Transact::try_transact_mut], this
+    /// works also when current document is in [Options::read_only] mode, so that read-only replicas
+    /// can still be kept in sync with others.
+    pub fn apply_remote_update(&self, update: Update) -> Result<(), TransactionAcqError> {
+        self.try_transact_mut_unchecked(None)?.apply_update(update);
+        Ok(())
+    }
+
+    /// Creates a read-write transaction regardless of [Options::read_only] mode. It's meant for
+    /// internal operations which are not local changes of the document content.
+    pub(crate) fn try_transact_mut_unchecked(
+        &self,
+        origin: Option<Origin>,
+    ) -> Result<TransactionMut<'_>, TransactionAcqError> {
+        let store = self.store.try_borrow_mut()?;
+        Ok(TransactionMut::new(self.clone(), store, origin))
+    }
+
+    /// Like [Doc::try_transact_mut_unchecked], but panics if there's another active transaction.
+    pub(crate) fn transact_mut_unchecked(&self, origin: Option<Origin>) -> TransactionMut<'_> {
+        self.try_transact_mut_unchecked(origin)
+            .expect("there's another active transaction at the moment")
+    }
+
     /// Sends a load request to a parent document. Works only if current document is a sub-document
     /// of an another document.
     pub fn load<T>(&self, parent_txn: &mut T)
     where
         T: WriteTxn,
     {
-        let mut txn = self.transact_mut();
+        let mut txn = self.transact_mut_unchecked(None);
         if txn.store.is_subdoc() {
             if !txn.store.options.should_load {
                 parent_txn
@@ -644,7 +1302,7 @@ impl Doc {
     where
         T: WriteTxn,
     {
-        let mut txn = self.transact_mut();
+        let mut txn = self.transact_mut_unchecked(None);
         let store = txn.store_mut();
         let subdocs: Vec<_> = store.subdocs.values().cloned().collect();
         for subdoc in subdocs {
@@ -677,6 +1335,91 @@ impl Doc {
         }
     }
 
+    /// Changes the [Options::auto_load] flag of current document.
+    ///
+    /// Sub-document options are encoded as part of a block, which references the sub-document
+    /// within its parent document. Since blocks are immutable once integrated, a reference is
+    /// re-inserted at the same position (the old one is being deleted) so that the new flag is
+    /// propagated to remote peers with the next update. Locally, current document instance remains
+    /// the same: it's neither removed nor reloaded.
+    ///
+    /// If current document is not a sub-document or its reference has already been deleted, only
+    /// the local flag is changed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `parent_txn` was not created by a parent document of current sub-document, or if
+    /// current sub-document has another transaction active at the moment.
+    pub fn set_auto_load(&self, parent_txn: &mut TransactionMut, value: bool) {
+        let parent = {
+            let mut txn = self.transact_mut_unchecked(None);
+            if txn.store.options.auto_load == value {
+                return;
+            }
+            txn.store.options.auto_load = value;
+            txn.store.parent
+        };
+        let item = match parent {
+            Some(item) if !item.is_deleted() => item,
+            _ => return,
+        };
+        let parent_doc = self.parent_doc();
+        assert!(
+            parent_doc.is_some_and(|doc| Doc::ptr_eq(&doc, parent_txn.doc())),
+            "transaction doesn't belong to a parent document"
+        );
+        let guid = self.guid().clone();
+        let (was_added, was_loaded) = {
+            let subdocs = parent_txn.subdocs_mut();
+            (
+                subdocs.added.values().any(|doc| doc.guid() == &guid),
+                subdocs.loaded.values().any(|doc| doc.guid() == &guid),
+            )
+        };
+        let pos = ItemPosition {
+            parent: item.parent.clone(),
+            left: Some(item),
+            right: item.right,
+            index: 0,
+            current_attrs: None,
+        };
+        parent_txn.create_item(&pos, SubdocRef(self.clone()), item.parent_sub.clone());
+        if item.parent_sub.is_none() {
+            // map entries are overridden automatically, sequence elements have to be deleted
+            parent_txn.delete(item);
+        }
+        // the same sub-document has been removed and added back, which is a no-op
+        // sub-documents are deduplicated by their GUID, so that a document referenced by
+        // different instances is not reported twice
+        let subdocs = parent_txn.subdocs_mut();
+        subdocs.removed.retain(|_, doc| doc.guid() != &guid);
+        if !was_added {
+            subdocs.added.retain(|_, doc| doc.guid() != &guid);
+        }
+        if !was_loaded {
+            subdocs.loaded.retain(|_, doc| doc.guid() != &guid);
+        }
+    }
+
+    /// Merges adjacent blocks of the same client, which became fragmented as a result of
+    /// incremental editing, into bigger ones. This doesn't change the document content nor its
+    /// state vector, and all [StickyIndex](crate::StickyIndex)es created over it remain valid.
+    /// Unlike garbage collection, no deleted content is being removed.
+    ///
+    /// Compaction is performed once a given transaction is committed, so that it doesn't interfere
+    /// with change events computed for the current transaction.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `txn` was not created by the current document.
+    pub fn compact(&self, txn: &mut TransactionMut) {
+        assert!(
+            Doc::ptr_eq(self, txn.doc()),
+            "transaction doesn't belong to a compacted document"
+        );
+        txn.compact = true;
+    }
+
     /// If current document has been inserted as a sub-document, returns a reference to a parent
     /// document, which contains it.
     pub fn parent_doc(&self) -> Option<Doc> {
@@ -690,6 +1433,13 @@ impl Doc {
         None
     }
 
+    /// If current document has been inserted as a sub-document, returns a path from the root of
+    /// a parent document to a place where it's mounted.
+    pub(crate) fn mount_path(&self) -> Option<Path> {
+        let store = unsafe { self.store.0.as_ptr().as_ref() }.unwrap();
+        store.parent.map(SubdocInfo::mount_path)
+    }
+
     pub fn branch_id(&self) -> Option<BranchID> {
         let store = unsafe { self.store.0.as_ptr().as_ref() }.unwrap();
         if let Some(item) = store.parent {
@@ -703,11 +1453,89 @@ impl Doc {
         Arc::ptr_eq(&a.store.0, &b.store.0)
     }
 
+    /// Creates a non-owning [WeakDoc] reference to current document. Unlike [Doc] clones, weak
+    /// references don't prevent the document from being dropped once all of its [Doc] references
+    /// are gone.
+    pub fn downgrade(&self) -> WeakDoc {
+        WeakDoc {
+            store: Arc::downgrade(&self.store.0),
+        }
+    }
+
     pub(crate) fn addr(&self) -> DocAddr {
         DocAddr::new(&self)
     }
 }
 
+/// Information about a sub document linked within the structures of a parent document, as returned
+/// by [ReadTxn::subdoc_infos].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubdocInfo {
+    /// A globally unique identifier of the sub document.
+    pub guid: Uuid,
+    /// If sub document is stored as an entry of a map-like type, this is the key of that entry.
+    pub key: Option<Arc<str>>,
+    /// Path from the document root to a place where sub document is mounted. First segment is
+    /// always a name of the root-level type containing it.
+    pub path: Path,
+    /// Whether the sub document should be synced by the provider. See: [Options::should_load].
+    pub should_load: bool,
+    /// Whether the sub document is loaded automatically by remote peers. See: [Options::auto_load].
+    pub auto_load: bool,
+    /// Whether the sub document store already contains any integrated content.
+    pub loaded: bool,
+}
+
+impl SubdocInfo {
+    pub(crate) fn new(doc: &Doc) -> Self {
+        let store = unsafe { doc.store.0.as_ptr().as_ref() }.unwrap();
+        let options = &store.options;
+        let key = store.parent.and_then(|item| item.parent_sub.clone());
+        SubdocInfo {
+            guid: options.guid.clone(),
+            key,
+            path: store.parent.map(Self::mount_path).unwrap_or_default(),
+            should_load: options.should_load,
+            auto_load: options.auto_load,
+            loaded: !store.blocks.is_empty(),
+        }
+    }
+
+    fn mount_path(item: ItemPtr) -> Path {
+        let mut path = Path::default();
+        let mut current = Some(item);
+        while let Some(item) = current {
+            let parent = match item.parent.as_branch() {
+                Some(branch) => *branch,
+                None => break,
+            };
+            if let Some(key) = &item.parent_sub {
+                path.push_front(PathSegment::Key(key.clone()));
+            } else {
+                let mut i = 0;
+                let mut c = parent.start;
+                while let Some(ptr) = c {
+                    if ptr.id() == item.id() {
+                        break;
+                    }
+                    if !ptr.is_deleted() && ptr.is_countable() {
+                        i += ptr.len();
+                    }
+                    c = ptr.right;
+                }
+                path.push_front(PathSegment::Index(i));
+            }
+            current = parent.item;
+            if current.is_none() {
+                if let Some(name) = &parent.name {
+                    path.push_front(PathSegment::Key(name.clone()));
+                }
+            }
+        }
+        path
+    }
+}
+
 impl PartialEq for Doc {
     fn eq(&self, other: &Self) -> bool {
         self.options().guid == other.options().guid
@@ -739,6 +1567,51 @@ impl Default for Doc {
     }
 }
 
+/// A non-owning reference to a [Doc], created via [Doc::downgrade]. It can be used i.e. by
+/// caches, which should not keep documents alive, to detect if referenced document has already
+/// been dropped.
+///
+/// # Example
+///
+/// ```rust
+/// use yrs::Doc;
+///
+/// let doc = Doc::new();
+/// let weak = doc.downgrade();
+/// assert!(weak.upgrade().is_some());
+///
+/// drop(doc);
+/// assert!(weak.upgrade().is_none());
+/// ```
+#[derive(Debug, Clone)]
+pub struct WeakDoc {
+    store: Weak<AtomicRefCell<Store>>,
+}
+
+unsafe impl Send for WeakDoc {}
+unsafe impl Sync for WeakDoc {}
+
+impl WeakDoc {
+    /// Attempts to obtain a [Doc] referenced by current weak reference. Returns `None` if
+    /// the document has already been dropped.
+    pub fn upgrade(&self) -> Option<Doc> {
+        let store = self.store.upgrade()?;
+        Some(Doc {
+            store: StoreRef(store),
+        })
+    }
+
+    /// Returns a number of [Doc] references keeping the referenced document alive.
+    pub fn strong_count(&self) -> usize {
+        self.store.strong_count()
+    }
+
+    /// Checks if both weak references point to the same document.
+    pub fn ptr_eq(a: &WeakDoc, b: &WeakDoc) -> bool {
+        Weak::ptr_eq(&a.store, &b.store)
+    }
+}
+
 impl ToJson for Doc {
     fn to_json<T: ReadTxn>(&self, txn: &T) -> Any {
         let mut m = HashMap::new();
@@ -749,6 +1622,43 @@ impl ToJson for Doc {
     }
 }
 
+/// Wraps a given update callback `f` so that the updates it receives are buffered and merged
+/// together until a given time `window` since the first buffered update has elapsed.
+fn coalesce_updates<F>(window: Duration, f: F) -> impl Fn(&TransactionMut, &UpdateEvent)
+where
+    F: Fn(&TransactionMut, &UpdateEvent),
+{
+    coalesce_updates_with_clock(window, Instant::now, f)
+}
+
+/// Same as [coalesce_updates], but reads current time using a given `now` function.
+fn coalesce_updates_with_clock<C, F>(
+    window: Duration,
+    now: C,
+    f: F,
+) -> impl Fn(&TransactionMut, &UpdateEvent)
+where
+    C: Fn() -> Instant,
+    F: Fn(&TransactionMut, &UpdateEvent),
+{
+    let buf: Mutex<Option<(Instant, Vec<Vec<u8>>)>> = Mutex::new(None);
+    move |txn, e| {
+        let mut guard = buf.lock().unwrap();
+        let (started, updates) = guard.get_or_insert_with(|| (now(), Vec::new()));
+        updates.push(e.update.clone());
+        if now().duration_since(*started) >= window {
+            let (_, updates) = guard.take().unwrap();
+            let update = if updates.len() == 1 {
+                updates.into_iter().next().unwrap()
+            } else {
+                merge_updates_v1(updates).expect("Failed to merge updates generated by a document")
+            };
+            drop(guard);
+            f(txn, &UpdateEvent { update });
+        }
+    }
+}
+
 /// Configuration options of [Doc] instance.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Options {
@@ -768,12 +1678,23 @@ pub struct Options {
     pub collection_id: Option<String>,
     /// How to we count offsets and lengths used in text operations.
     ///
+    /// This setting only affects how indexes passed to and returned from [Text]/[XmlText] methods
+    /// are interpreted by a local document. Internally block clocks are always measured in UTF-16
+    /// code units, so updates are independent of the offset kind and can be safely exchanged
+    /// between documents using different offset kinds. Sub-documents carry their offset kind as
+    /// part of encoded options, so their remote replicas use the same offset kind as the author.
+    ///
     /// Default value: [OffsetKind::Bytes].
     pub offset_kind: OffsetKind,
-    /// Determines if transactions commits should try to perform GC-ing of deleted items.
+    /// Determines if transactions commits should try to perform GC-ing of deleted items. When set
+    /// to `true`, it takes precedence over [Options::gc_policy] and works like [GcPolicy::Never].
     ///
     /// Default value: `false`.
     pub skip_gc: bool,
+    /// Determines when transaction commits should perform GC-ing of deleted items.
+    ///
+    /// Default value: [GcPolicy::Always].
+    pub gc_policy: GcPolicy,
     /// If a subdocument, automatically load document. If this is a subdocument, remote peers will
     /// load the document as well automatically.
     ///
@@ -784,6 +1705,26 @@ pub struct Options {
     ///
     /// Default value: `true`.
     pub should_load: bool,
+    /// Encoding version used by [Doc::observe_update] and [Doc::encode_state_as_update]. Methods
+    /// with explicit `_v1`/`_v2` suffix are not affected by this option.
+    ///
+    /// Default value: [EncodingVersion::V1].
+    pub encoding_version: EncodingVersion,
+    /// Number of the most recently committed updates retained by the document, which can be
+    /// retrieved using [Doc::encode_diff_since_seq]. Setting it to `0` disables the update log.
+    ///
+    /// Default value: `0`.
+    pub update_log_capacity: usize,
+    /// Makes [Transact::try_transact_mut] fail with [TransactionAcqError::ReadOnly], guarding
+    /// the document against accidental local changes. Remote updates can still be applied using
+    /// [Doc::apply_remote_update]. Callbacks receiving a read-write transaction of a read-only
+    /// document (ie. [Doc::observe_after_transaction_with]) will panic when trying to change it, while
+    /// callbacks deferred with [TransactionMut::defer] are discarded. This is a local setting,
+    /// which is not propagated to remote peers, and it can be changed at any time using
+    /// [Doc::set_read_only], so it's not a security boundary.
+    ///
+    /// Default value: `false`.
+    pub read_only: bool,
 }
 
 impl Options {
@@ -794,8 +1735,12 @@ impl Options {
             collection_id: None,
             offset_kind: OffsetKind::Bytes,
             skip_gc: false,
+            gc_policy: GcPolicy::Always,
             auto_load: false,
             should_load: true,
+            encoding_version: EncodingVersion::V1,
+            update_log_capacity: 0,
+            read_only: false,
         }
     }
 
@@ -806,14 +1751,57 @@ impl Options {
             collection_id: None,
             offset_kind: OffsetKind::Bytes,
             skip_gc: false,
+            gc_policy: GcPolicy::Always,
             auto_load: false,
             should_load: true,
+            encoding_version: EncodingVersion::V1,
+            update_log_capacity: 0,
+            read_only: false,
+        }
+    }
+
+    /// Creates a new set of options with a random `client_id` and a `guid` parsed from a given
+    /// string. Returns an error if `guid` is not a valid hyphenated UUID (see: [uuid_parse]).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::Options;
+    ///
+    /// let options = Options::with_parsed_guid("67E55044-10B1-426F-9247-BB680E5FE0C8").unwrap();
+    /// assert_eq!(options.guid.as_ref(), "67e55044-10b1-426f-9247-bb680e5fe0c8");
+    ///
+    /// assert!(Options::with_parsed_guid("not-a-guid").is_err());
+    /// ```
+    pub fn with_parsed_guid(guid: &str) -> Result<Self, UuidError> {
+        let guid = uuid_parse(guid)?;
+        Ok(Options {
+            guid,
+            ..Options::default()
+        })
+    }
+
+    /// Creates a new set of options with a `client_id` and `guid` generated using a given random
+    /// number generator.
+    pub fn with_rng(rng: &mut fastrand::Rng) -> Self {
+        let client_id: u32 = rng.u32(0..u32::MAX);
+        let uuid = uuid_v4_from(rng);
+        Self::with_guid_and_client_id(uuid, client_id as ClientID)
+    }
+
+    /// Returns a GC policy used by transaction commits, taking [Options::skip_gc] into account.
+    pub(crate) fn effective_gc_policy(&self) -> GcPolicy {
+        if self.skip_gc {
+            GcPolicy::Never
+        } else {
+            self.gc_policy
         }
     }
 
     fn as_any(&self) -> Any {
         let mut m = HashMap::new();
-        m.insert("gc".to_owned(), (!self.skip_gc).into());
+        let gc = self.effective_gc_policy() != GcPolicy::Never;
+        m.insert("gc".to_owned(), gc.into());
         if let Some(collection_id) = self.collection_id.as_ref() {
             m.insert("collectionId".to_owned(), collection_id.clone().into());
         }
@@ -830,10 +1818,7 @@ impl Options {
 
 impl Default for Options {
     fn default() -> Self {
-        let mut rng = fastrand::Rng::new();
-        let client_id: u32 = rng.u32(0..u32::MAX);
-        let uuid = uuid_v4_from(&mut rng);
-        Self::with_guid_and_client_id(uuid, client_id as ClientID)
+        Self::with_rng(&mut fastrand::Rng::new())
     }
 }
 
@@ -881,6 +1866,55 @@ pub enum OffsetKind {
     Utf16,
 }
 
+/// Determines when transaction commits perform garbage collection of deleted blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GcPolicy {
+    /// Deleted blocks are garbage collected on every transaction commit.
+    Always,
+    /// Deleted blocks are never garbage collected. This is required to keep the document history
+    /// intact, eg. for the purpose of using snapshots.
+    Never,
+    /// Deleted blocks are accumulated over many transaction commits and garbage collected all at
+    /// once, when their number (measured as a total length of deleted blocks) exceeds
+    /// `deleted_items` threshold.
+    Threshold { deleted_items: usize },
+}
+
+/// Version of lib0 encoding used to serialize document updates.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncodingVersion {
+    /// Encode updates using lib0 v1 encoding. See: [Update::encode_v1].
+    V1,
+    /// Encode updates using lib0 v2 encoding. See: [Update::encode_v2].
+    V2,
+}
+
+/// Options used to customize how a document state is encoded into an update by
+/// [ReadTxn::encode_state_as_update_v1_ext].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncodeOptions {
+    /// Whether references to sub-documents should be included in the encoded update. Keep in mind
+    /// that sub-document contents are never part of their parent's updates, only their
+    /// identifiers and options are.
+    ///
+    /// When set to `false`, blocks containing sub-document references are encoded as deleted ones.
+    /// Such updates will reconstruct the parent document without any sub-documents mounted and
+    /// are meant to be used for persistence only: once applied by a replica, sub-document
+    /// references are seen as removed, which would spread to other peers on synchronization.
+    ///
+    /// Default: `true`.
+    pub include_subdocs: bool,
+}
+
+impl Default for EncodeOptions {
+    fn default() -> Self {
+        EncodeOptions {
+            include_subdocs: true,
+        }
+    }
+}
+
 /// Trait implemented by [Doc] and shared types, used for carrying over the responsibilities of
 /// creating new transactions, used as a unit of work in Yrs.
 pub trait Transact {
@@ -958,9 +1992,10 @@ pub trait Transact {
     ///
     /// Only one read-write transaction can be active at the same time. If any other transaction -
     /// be it a read-write or read-only one - is active at the same time, this method will panic.
+    /// It will also panic if the document is in [Options::read_only] mode.
     fn transact_mut(&self) -> TransactionMut {
         self.try_transact_mut()
-            .expect("there's another active transaction at the moment")
+            .expect("there's another active transaction at the moment or the document is read-only")
     }
 }
 
@@ -971,6 +2006,9 @@ impl Transact for Doc {
 
     fn try_transact_mut(&self) -> Result<TransactionMut, TransactionAcqError> {
         let store = self.store.try_borrow_mut()?;
+        if store.options.read_only {
+            return Err(TransactionAcqError::ReadOnly);
+        }
         Ok(TransactionMut::new(self.clone(), store, None))
     }
 
@@ -979,6 +2017,9 @@ impl Transact for Doc {
         T: Into<Origin>,
     {
         let store = self.store.try_borrow_mut()?;
+        if store.options.read_only {
+            return Err(TransactionAcqError::ReadOnly);
+        }
         Ok(TransactionMut::new(
             self.clone(),
             store,
@@ -995,6 +2036,8 @@ pub enum TransactionAcqError {
     ExclusiveAcqFailed,
     #[error("All references to a parent document containing this structure has been dropped.")]
     DocumentDropped,
+    #[error("Failed to acquire read-write transaction. Document is in read-only mode.")]
+    ReadOnly,
 }
 
 impl From<BorrowError> for TransactionAcqError {
@@ -1022,6 +2065,39 @@ impl Prelim for Doc {
     fn integrate(self, _txn: &mut TransactionMut, _inner_ref: BranchPtr) {}
 }
 
+/// Prelim used to re-insert a reference to an already integrated sub-document, see: [Doc::set_auto_load].
+struct SubdocRef(Doc);
+
+impl Prelim for SubdocRef {
+    type Return = Unused;
+
+    fn into_content(self, _txn: &mut TransactionMut) -> (ItemContent, Option<Self>) {
+        (ItemContent::Doc(None, self.0), None)
+    }
+
+    fn integrate(self, _txn: &mut TransactionMut, _inner_ref: BranchPtr) {}
+}
+
+#[cfg(feature = "sync")]
+type RecursiveUpdateFn = Arc<dyn Fn(&TransactionMut, &Uuid, &UpdateEvent) + Send + Sync + 'static>;
+
+#[cfg(not(feature = "sync"))]
+type RecursiveUpdateFn = Arc<dyn Fn(&TransactionMut, &Uuid, &UpdateEvent) + 'static>;
+
+/// Subscription returned by [Doc::observe_update_v1_recursive], which keeps subscriptions of
+/// a document and all of its sub-documents alive.
+struct TreeSubscription {
+    _update: Subscription,
+    _subdocs: Subscription,
+    _children: Arc<Mutex<HashMap<DocAddr, Subscription>>>,
+}
+
+impl Drop for TreeSubscription {
+    fn drop(&mut self) {
+        // subscriptions are released together with the fields
+    }
+}
+
 /// For a Yjs compatibility reasons we expect subdocuments to be compared based on their reference
 /// equality. This concept however doesn't really exists in Rust. Therefore we use a store reference
 /// instead and specialize it for this single scenario.
@@ -1038,24 +2114,30 @@ impl DocAddr {
 
 #[cfg(test)]
 mod test {
-    use crate::block::ItemContent;
+    use crate::block::{BlockCell, ClientID, ItemContent};
+    use crate::branch::{Branch, BranchPtr};
+    use crate::doc::{coalesce_updates_with_clock, TransactionAcqError};
     use crate::test_utils::exchange_updates;
-    use crate::transaction::{ReadTxn, TransactionMut};
-    use crate::types::ToJson;
+    use crate::transaction::{LocalChange, ReadTxn, TransactionMut, TypeCounts, UpdateError};
+    use crate::types::{PathSegment, ToJson};
     use crate::update::Update;
     use crate::updates::decoder::Decode;
     use crate::updates::encoder::{Encode, Encoder, EncoderV1};
     use crate::{
-        any, Any, Array, ArrayPrelim, ArrayRef, Doc, GetString, Map, MapPrelim, MapRef, OffsetKind,
-        Options, StateVector, Subscription, Text, TextRef, Transact, Uuid, WriteTxn,
-        XmlElementPrelim, XmlFragment, XmlFragmentRef, XmlTextPrelim, XmlTextRef,
+        any, Any, Array, ArrayPrelim, ArrayRef, Assoc, BlockInfo, DeepObservable, Doc,
+        EncodeOptions, EncodingVersion, GcPolicy, GetString, In, IndexedSequence, Map, MapPrelim,
+        MapRef, Observable, OffsetKind, Options, PatchOp, PathError, StateVector, SubdocInfo,
+        Subscription, Text, TextPrelim, TextRef, Transact, Uuid, WeakDoc, WriteTxn,
+        XmlElementPrelim, XmlFragment, XmlFragmentRef, XmlTextPrelim, XmlTextRef, ID,
     };
-    use std::collections::BTreeSet;
+    use crate::{uuid_from_bytes, uuid_v4, uuid_v4_from, UuidError};
+    use std::collections::{BTreeSet, HashMap, HashSet};
 
     use arc_swap::ArcSwapOption;
     use assert_matches2::assert_matches;
     use std::sync::atomic::{AtomicU32, Ordering};
     use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
 
     #[test]
     fn apply_update_basic_v1() {
@@ -1203,9 +2285,97 @@ mod test {
     }
 
     #[test]
-    fn pending_update_integration() {
-        let doc = Doc::new();
-        let txt = doc.get_or_insert_text("source");
+    fn defer_mutation_from_update_observer() {
+        let doc = Doc::with_client_id(1);
+        let txt = doc.get_or_insert_text("test");
+        let updates = Arc::new(AtomicU32::new(0));
+        let _sub = {
+            let txt = txt.clone();
+            let updates = updates.clone();
+            doc.observe_update_v1(move |txn, _| {
+                updates.fetch_add(1, Ordering::SeqCst);
+                assert!(txn.doc().try_transact_mut().is_err());
+                if txt.get_string(txn).ends_with('!') {
+                    return;
+                }
+                let txt = txt.clone();
+                txn.defer(move |txn| txt.push(txn, "!"));
+            })
+            .unwrap()
+        };
+
+        txt.push(&mut doc.transact_mut_with("origin"), "hello");
+
+        assert_eq!(txt.get_string(&doc.transact()), "hello!");
+        // one update for the original transaction and one for a deferred one
+        assert_eq!(updates.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn defer_recursion_limit() {
+        let doc = Doc::with_client_id(1);
+        let txt = doc.get_or_insert_text("test");
+        let _sub = {
+            let txt = txt.clone();
+            doc.observe_update_v1(move |txn, _| {
+                let txt = txt.clone();
+                txn.defer(move |txn| txt.push(txn, "!"));
+            })
+            .unwrap()
+        };
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            txt.push(&mut doc.transact_mut(), "hello");
+        }));
+        assert!(result.is_err());
+        // every follow-up transaction but the last one managed to defer its changes
+        let expected = format!("hello{}", "!".repeat(16));
+        assert_eq!(txt.get_string(&doc.transact()), expected);
+    }
+
+    #[test]
+    fn defer_on_read_only_doc() {
+        let doc = Doc::with_options(Options {
+            client_id: 1,
+            read_only: true,
+            ..Options::default()
+        });
+        let txt = doc.get_or_insert_text("text");
+        let _sub = {
+            let txt = txt.clone();
+            doc.observe_update_v1(move |txn, _| {
+                let txt = txt.clone();
+                txn.defer(move |txn| txt.push(txn, " local!"));
+            })
+            .unwrap()
+        };
+
+        let remote = Doc::with_client_id(2);
+        let remote_txt = remote.get_or_insert_text("text");
+        remote_txt.push(&mut remote.transact_mut(), "hello");
+        let update = remote
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+        doc.apply_remote_update(Update::decode_v1(&update).unwrap())
+            .unwrap();
+
+        // deferred changes are discarded on read-only document
+        let txn = doc.transact();
+        assert_eq!(txt.get_string(&txn), "hello");
+        assert_eq!(txn.state_vector().get(&1), 0);
+    }
+
+    #[cfg(feature = "sync")]
+    #[test]
+    fn transaction_mut_is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<TransactionMut<'static>>();
+    }
+
+    #[test]
+    fn pending_update_integration() {
+        let doc = Doc::new();
+        let txt = doc.get_or_insert_text("source");
 
         let updates = [
             vec![
@@ -2089,303 +3259,2492 @@ mod test {
     }
 
     #[test]
-    fn subdoc_auto_load_edge_cases() {
-        let doc = Doc::with_client_id(1);
-        let array = doc.get_or_insert_array("test");
-        let subdoc_1 = Doc::with_options({
-            let mut o = Options::default();
-            o.auto_load = true;
-            o
-        });
+    fn encode_client_state() {
+        let d1 = Doc::with_client_id(1);
+        let d2 = Doc::with_client_id(2);
+        let d3 = Doc::with_client_id(3);
+        let t1 = d1.get_or_insert_text("text");
+        let t2 = d2.get_or_insert_text("text");
+        let m3 = d3.get_or_insert_map("map");
 
-        let event = Arc::new(ArcSwapOption::default());
-        let event_c = event.clone();
-        let _sub = doc.observe_subdocs(move |_, e| {
-            let added = e.added().map(|d| d.guid().clone()).collect();
-            let removed = e.removed().map(|d| d.guid().clone()).collect();
-            let loaded = e.loaded().map(|d| d.guid().clone()).collect();
+        t1.insert(&mut d1.transact_mut(), 0, "hello");
+        m3.insert(&mut d3.transact_mut(), "key", "value");
+        exchange_updates(&[&d1, &d2, &d3]);
+        {
+            // client 2 edits on top of client 1 changes
+            let mut txn = d2.transact_mut();
+            t2.insert(&mut txn, 5, " world");
+            t2.remove_range(&mut txn, 0, 1);
+        }
+        t1.insert(&mut d1.transact_mut(), 0, "> ");
+        exchange_updates(&[&d1, &d2, &d3]);
 
-            event_c.store(Some(Arc::new((added, removed, loaded))));
-        });
+        let update = d1.transact().encode_client_state(2);
 
-        let subdoc_1 = {
-            let mut txn = doc.transact_mut();
-            array.insert(&mut txn, 0, subdoc_1)
-        };
-        assert!(subdoc_1.options().should_load);
-        assert!(subdoc_1.options().auto_load);
+        // a fresh peer receives client 2 changes together with their dependencies
+        let d4 = Doc::with_client_id(4);
+        let t4 = d4.get_or_insert_text("text");
+        let m4 = d4.get_or_insert_map("map");
+        {
+            let mut txn = d4.transact_mut();
+            txn.apply_update(Update::decode_v1(&update).unwrap());
+            assert_eq!(txn.store().pending_update(), None);
+            assert_eq!(t4.get_string(&txn), "ello world");
+            assert_eq!(m4.len(&txn), 0);
+        }
 
-        let uuid_1 = subdoc_1.options().guid.clone();
-        let last_event = event.swap(None);
+        // the same update can be applied on top of partial state
+        let d5 = Doc::with_client_id(5);
+        let t5 = d5.get_or_insert_text("text");
+        let partial = d1.transact().encode_client_state(1);
+        d5.transact_mut()
+            .apply_update(Update::decode_v1(&partial).unwrap());
+        assert_eq!(t5.get_string(&d5.transact()), "> ello");
+        d5.transact_mut()
+            .apply_update(Update::decode_v1(&update).unwrap());
+        assert_eq!(t5.get_string(&d5.transact()), "> ello world");
+    }
+
+    /// Counts blocks of a given client, which content has been garbage collected.
+    fn gc_blocks(doc: &Doc, client_id: ClientID) -> usize {
+        let txn = doc.transact();
+        match txn.store().blocks.get_client(&client_id) {
+            Some(blocks) => blocks
+                .iter()
+                .filter(|block| match block {
+                    BlockCell::GC(_) => true,
+                    BlockCell::Block(item) => matches!(item.content, ItemContent::Deleted(_)),
+                })
+                .count(),
+            None => 0,
+        }
+    }
+
+    #[test]
+    fn gc_policy_threshold() {
+        let mut options = Options::with_client_id(1);
+        options.gc_policy = GcPolicy::Threshold { deleted_items: 5 };
+        let doc = Doc::with_options(options);
+        let text = doc.get_or_insert_text("text");
+        text.insert(&mut doc.transact_mut(), 0, "hello world");
+
+        text.remove_range(&mut doc.transact_mut(), 0, 3);
+        assert_eq!(gc_blocks(&doc, 1), 0);
+        text.remove_range(&mut doc.transact_mut(), 0, 2);
+        assert_eq!(gc_blocks(&doc, 1), 0);
+
+        // threshold has been crossed: all accumulated deletions are collected at once
+        text.remove_range(&mut doc.transact_mut(), 0, 1);
+        assert!(gc_blocks(&doc, 1) > 0);
+        assert!(doc.transact().store().gc_pending.is_empty());
+        assert_eq!(text.get_string(&doc.transact()), "world");
+
+        // counter starts over after collection
+        text.remove_range(&mut doc.transact_mut(), 0, 1);
+        let txn = doc.transact();
+        let block = txn.store().blocks.get_block(&ID::new(1, 6)).unwrap();
+        assert_matches!(block, BlockCell::Block(item));
+        assert!(item.is_deleted());
+        assert_matches!(&item.content, ItemContent::String(_));
+    }
+
+    #[test]
+    fn gc_policy_never_and_skip_gc() {
+        for (skip_gc, gc_policy) in [(false, GcPolicy::Never), (true, GcPolicy::Always)] {
+            let mut options = Options::with_client_id(1);
+            options.skip_gc = skip_gc;
+            options.gc_policy = gc_policy;
+            let doc = Doc::with_options(options);
+            let text = doc.get_or_insert_text("text");
+            text.insert(&mut doc.transact_mut(), 0, "hello world");
+            text.remove_range(&mut doc.transact_mut(), 0, 6);
+            assert_eq!(gc_blocks(&doc, 1), 0);
+        }
+
+        let doc = Doc::with_client_id(1);
+        let text = doc.get_or_insert_text("text");
+        text.insert(&mut doc.transact_mut(), 0, "hello world");
+        text.remove_range(&mut doc.transact_mut(), 0, 6);
+        assert!(gc_blocks(&doc, 1) > 0);
+    }
+
+    #[test]
+    fn updates_across_offset_kinds() {
+        let mut options = Options::with_client_id(1);
+        options.offset_kind = OffsetKind::Utf16;
+        let utf16 = Doc::with_options(options);
+        let t1 = utf16.get_or_insert_text("text");
+        let bytes = Doc::with_client_id(2);
+        let t2 = bytes.get_or_insert_text("text");
+
+        // "😀" is 2 UTF-16 code units and 4 UTF-8 bytes long
+        t1.insert(&mut utf16.transact_mut(), 0, "a😀b");
+        t1.insert(&mut utf16.transact_mut(), 3, "c");
+        exchange_updates(&[&utf16, &bytes]);
+        assert_eq!(t2.get_string(&bytes.transact()), "a😀cb");
+
+        t2.insert(&mut bytes.transact_mut(), 5, "d");
+        t2.remove_range(&mut bytes.transact_mut(), 1, 4);
+        exchange_updates(&[&utf16, &bytes]);
+        assert_eq!(t1.get_string(&utf16.transact()), "adcb");
+        assert_eq!(t2.get_string(&bytes.transact()), "adcb");
         assert_eq!(
-            last_event,
-            Some(Arc::new((
-                vec![uuid_1.clone()],
-                vec![],
-                vec![uuid_1.clone()]
-            )))
+            utf16.transact().state_vector(),
+            bytes.transact().state_vector()
         );
+    }
 
-        // destroy and check whether lastEvent adds it again to added (it shouldn't)
-        subdoc_1.destroy(&mut doc.transact_mut());
+    #[test]
+    fn subdoc_offset_kind_across_peers() {
+        let mut options = Options::with_client_id(1);
+        options.offset_kind = OffsetKind::Utf16;
+        let utf16 = Doc::with_options(options.clone());
+        let bytes = Doc::with_client_id(2);
+
+        let local = {
+            let docs = utf16.get_or_insert_map("docs");
+            let mut txn = utf16.transact_mut();
+            docs.insert(&mut txn, "sub", Doc::with_options(options))
+        };
+        let t1 = local.get_or_insert_text("text");
+        t1.insert(&mut local.transact_mut(), 0, "a😀b");
+        exchange_updates(&[&utf16, &bytes]);
+
+        // sub-document integrated by a Bytes document keeps offset kind of its author
+        let remote = {
+            let docs = bytes.get_or_insert_map("docs");
+            let txn = bytes.transact();
+            docs.get(&txn, "sub").unwrap().cast::<Doc>().unwrap()
+        };
+        assert_eq!(remote.options().offset_kind, OffsetKind::Utf16);
+
+        let t2 = remote.get_or_insert_text("text");
+        exchange_updates(&[&local, &remote]);
+        // "😀" is 2 UTF-16 code units long, so "c" lands right before "b" on both sides
+        t2.insert(&mut remote.transact_mut(), 3, "c");
+        exchange_updates(&[&local, &remote]);
+        assert_eq!(t1.get_string(&local.transact()), "a😀cb");
+        assert_eq!(t2.get_string(&remote.transact()), "a😀cb");
+    }
 
-        let subdoc_2 = array
-            .get(&doc.transact(), 0)
+    #[test]
+    fn observe_update_v1_recursive() {
+        let doc = Doc::with_client_id(1);
+        let docs = doc.get_or_insert_map("docs");
+        let updates = Arc::new(Mutex::new(Vec::new()));
+        let _sub = {
+            let updates = updates.clone();
+            doc.observe_update_v1_recursive(move |_, guid, e| {
+                updates
+                    .lock()
+                    .unwrap()
+                    .push((guid.clone(), e.update.clone()));
+            })
             .unwrap()
-            .cast::<Doc>()
-            .unwrap();
-        let uuid_2 = subdoc_2.options().guid.clone();
-        assert!(!Doc::ptr_eq(&subdoc_1, &subdoc_2));
+        };
 
-        let last_event = event.swap(None);
+        // sub-document added after subscription was made
+        let subdoc = docs.insert(&mut doc.transact_mut(), "sub", Doc::with_client_id(2));
+        let updates_of = |guid: &Uuid| -> Vec<Vec<u8>> {
+            std::mem::take(&mut *updates.lock().unwrap())
+                .into_iter()
+                .filter_map(|(g, u)| if &g == guid { Some(u) } else { None })
+                .collect()
+        };
+        assert_eq!(updates_of(doc.guid()).len(), 1);
+
+        let text = subdoc.get_or_insert_text("text");
+        text.insert(&mut subdoc.transact_mut(), 0, "hello");
+        let received = updates_of(subdoc.guid());
+        assert_eq!(received.len(), 1);
+
+        let remote = Doc::with_client_id(3);
+        let remote_text = remote.get_or_insert_text("text");
+        remote
+            .transact_mut()
+            .apply_update(Update::decode_v1(&received[0]).unwrap());
+        assert_eq!(remote_text.get_string(&remote.transact()), "hello");
+
+        // removed sub-documents are no longer observed
+        docs.remove(&mut doc.transact_mut(), "sub");
+        updates.lock().unwrap().clear();
+        text.insert(&mut subdoc.transact_mut(), 5, " world");
+        assert!(updates_of(subdoc.guid()).is_empty());
+    }
+
+    #[test]
+    fn insert_at_missing_path() {
+        let doc = Doc::with_client_id(1);
+        let root = doc.get_or_insert_map("root");
+        let mut txn = doc.transact_mut();
+        let path = [
+            PathSegment::Key("a".into()),
+            PathSegment::Key("b".into()),
+            PathSegment::Key("c".into()),
+        ];
+        txn.insert_at(&root, &path, 1).unwrap();
+        assert_eq!(root.to_json(&txn), any!({"a": {"b": {"c": 1}}}));
+
+        // existing maps are reused
+        let path = [PathSegment::Key("a".into()), PathSegment::Key("d".into())];
+        txn.insert_at(&root, &path, 2).unwrap();
+        assert_eq!(root.to_json(&txn), any!({"a": {"b": {"c": 1}, "d": 2}}));
+
+        // index segments never create arrays, nothing is inserted on failure
+        let path = [
+            PathSegment::Key("x".into()),
+            PathSegment::Index(0),
+            PathSegment::Key("y".into()),
+        ];
         assert_eq!(
-            last_event,
-            Some(Arc::new((
-                vec![uuid_2.clone()],
-                vec![uuid_2.clone()],
-                vec![]
-            )))
+            txn.insert_at(&root, &path, 3).err(),
+            Some(PathError::NotFound(1))
         );
-
-        subdoc_2.load(&mut doc.transact_mut());
-        let last_event = event.swap(None);
+        assert_eq!(txn.insert_at(&root, &[], 3).err(), Some(PathError::Empty));
+        let path = [PathSegment::Key("a".into()), PathSegment::Index(0)];
         assert_eq!(
-            last_event,
-            Some(Arc::new((vec![], vec![], vec![uuid_2.clone()])))
+            txn.insert_at(&root, &path, 3).err(),
+            Some(PathError::TypeMismatch(1))
         );
+        assert_eq!(root.to_json(&txn), any!({"a": {"b": {"c": 1}, "d": 2}}));
+    }
 
-        // apply from remote
-        let doc2 = Doc::with_client_id(2);
-        let event_c = event.clone();
-        let _sub = doc2.observe_subdocs(move |_, e| {
-            let added = e.added().map(|d| d.guid().clone()).collect();
-            let removed = e.removed().map(|d| d.guid().clone()).collect();
-            let loaded = e.loaded().map(|d| d.guid().clone()).collect();
+    #[test]
+    fn insert_at_array_index() {
+        let doc = Doc::with_client_id(1);
+        let root = doc.get_or_insert_map("root");
+        let mut txn = doc.transact_mut();
+        root.insert(
+            &mut txn,
+            "list",
+            ArrayPrelim::from([In::from(MapPrelim::default()), In::from(1)]),
+        );
 
-            event_c.store(Some(Arc::new((added, removed, loaded))));
-        });
-        let u = Update::decode_v1(
-            &doc.transact()
-                .encode_state_as_update_v1(&StateVector::default()),
+        let path = [
+            PathSegment::Key("list".into()),
+            PathSegment::Index(0),
+            PathSegment::Key("nested".into()),
+            PathSegment::Key("key".into()),
+        ];
+        txn.insert_at(&root, &path, "a").unwrap();
+        let path = [PathSegment::Key("list".into()), PathSegment::Index(1)];
+        txn.insert_at(&root, &path, "b").unwrap();
+        assert_eq!(
+            root.to_json(&txn),
+            any!({"list": [{"nested": {"key": "a"}}, "b", 1]})
         );
-        doc2.transact_mut().apply_update(u.unwrap());
-        let subdoc_3 = {
-            let array = doc2.get_or_insert_array("test");
-            array
-                .get(&doc2.transact(), 0)
-                .unwrap()
-                .cast::<Doc>()
-                .unwrap()
-        };
-        assert!(subdoc_1.options().should_load);
-        assert!(subdoc_1.options().auto_load);
-        let uuid_3 = subdoc_3.options().guid.clone();
-        let last_event = event.swap(None);
+
+        let path = [PathSegment::Key("list".into()), PathSegment::Index(4)];
         assert_eq!(
-            last_event,
-            Some(Arc::new((
-                vec![uuid_3.clone()],
-                vec![],
-                vec![uuid_3.clone()]
-            )))
+            txn.insert_at(&root, &path, "c").err(),
+            Some(PathError::NotFound(1))
+        );
+        let path = [
+            PathSegment::Key("list".into()),
+            PathSegment::Index(2),
+            PathSegment::Key("key".into()),
+        ];
+        assert_eq!(
+            txn.insert_at(&root, &path, "c").err(),
+            Some(PathError::TypeMismatch(2))
         );
     }
 
     #[test]
-    fn to_json() {
-        let doc = Doc::new();
-        let mut txn = doc.transact_mut();
-        let text = txn.get_or_insert_text("text");
-        let array = txn.get_or_insert_array("array");
-        let map = txn.get_or_insert_map("map");
-        let xml_fragment = txn.get_or_insert_xml_fragment("xml-fragment");
-        let xml_element = xml_fragment.insert(&mut txn, 0, XmlElementPrelim::empty("xml-element"));
-        let xml_text = xml_fragment.insert(&mut txn, 0, XmlTextPrelim::new(""));
+    fn json_patch_between_docs() {
+        let d1 = Doc::with_client_id(1);
+        let d2 = Doc::with_client_id(2);
+        let m1 = d1.get_or_insert_map("map");
+        let m2 = d2.get_or_insert_map("map");
+        m1.insert(&mut d1.transact_mut(), "a", 1);
+        m1.insert(&mut d1.transact_mut(), "b", 2);
+        m2.insert(&mut d2.transact_mut(), "b", 3);
+
+        let target = d2.to_json(&d2.transact());
+        let patch = d1.transact().json_patch_to(&target);
+        assert_eq!(
+            patch,
+            vec![
+                PatchOp::Remove {
+                    path: "/map/a".into()
+                },
+                PatchOp::Replace {
+                    path: "/map/b".into(),
+                    value: any!(3)
+                },
+            ]
+        );
+    }
 
-        text.push(&mut txn, "hello");
-        xml_text.push(&mut txn, "world");
-        xml_fragment.insert(&mut txn, 0, XmlElementPrelim::empty("div"));
-        xml_element.insert(&mut txn, 0, XmlElementPrelim::empty("body"));
-        array.insert_range(&mut txn, 0, [1, 2, 3]);
-        map.insert(&mut txn, "key1", "value1");
+    #[test]
+    fn client_blocks_in_range() {
+        let doc = Doc::with_client_id(1);
+        let text = doc.get_or_insert_text("text");
+        text.insert(&mut doc.transact_mut(), 0, "abc");
+        text.insert(&mut doc.transact_mut(), 0, "def");
+        text.remove_range(&mut doc.transact_mut(), 0, 1);
 
-        // sub documents cannot use their parent's transaction
-        let sub_doc = Doc::new();
-        let sub_text = sub_doc.get_or_insert_text("sub-text");
-        let sub_doc = map.insert(&mut txn, "sub-doc", sub_doc);
-        let mut sub_txn = sub_doc.transact_mut();
-        sub_text.push(&mut sub_txn, "sample");
+        let txn = doc.transact();
+        let blocks: Vec<_> = txn.client_blocks(1, 1..5).collect();
+        assert_eq!(
+            blocks,
+            vec![
+                BlockInfo {
+                    id: ID::new(1, 1),
+                    len: 2,
+                    deleted: false,
+                    gc: false,
+                },
+                BlockInfo {
+                    id: ID::new(1, 3),
+                    len: 1,
+                    deleted: true,
+                    gc: false,
+                },
+                BlockInfo {
+                    id: ID::new(1, 4),
+                    len: 1,
+                    deleted: false,
+                    gc: false,
+                },
+            ]
+        );
+        for block in blocks {
+            assert!(block.id.clock >= 1 && block.id.clock + block.len <= 5);
+        }
 
-        let actual = doc.to_json(&txn);
-        let expected = any!({
-            "text": "hello",
-            "array": [1,2,3],
-            "map": {
-                "key1": "value1",
-                "sub-doc": {
-                    "guid": sub_doc.guid().as_ref()
-                }
-            },
-            "xml-fragment": "<div></div>world<xml-element><body></body></xml-element>",
-        });
-        assert_eq!(actual, expected);
+        let all: u32 = txn.client_blocks(1, 0..u32::MAX).map(|b| b.len).sum();
+        assert_eq!(all, 6);
+        assert_eq!(txn.client_blocks(1, 6..10).count(), 0);
+        assert_eq!(txn.client_blocks(2, 0..10).count(), 0);
+        // empty and inverted ranges
+        assert_eq!(txn.client_blocks(1, 2..2).count(), 0);
+        #[allow(clippy::reversed_empty_ranges)]
+        let inverted = 4..2;
+        assert_eq!(txn.client_blocks(1, inverted).count(), 0);
     }
 
     #[test]
-    fn check_liveness() {
-        let d1 = Doc::new();
-        let r1 = d1.get_or_insert_map("root");
+    fn observe_update_metrics() {
+        let doc = Doc::with_client_id(1);
+        let text = doc.get_or_insert_text("text");
 
-        let d2 = Doc::new();
-        let r2 = d2.get_or_insert_map("root");
+        let sizes = Arc::new(Mutex::new(Vec::new()));
+        let expected = Arc::new(Mutex::new(Vec::new()));
+        let sizes_c = sizes.clone();
+        let _sub1 = doc
+            .observe_update_metrics(move |len| sizes_c.lock().unwrap().push(len))
+            .unwrap();
+        let expected_c = expected.clone();
+        let _sub2 = doc
+            .observe_update_v1(move |txn, _| {
+                expected_c
+                    .lock()
+                    .unwrap()
+                    .push(txn.encode_update_v1().len())
+            })
+            .unwrap();
 
-        let mut t1 = d1.transact_mut();
-        assert!(t1.is_alive(&r1), "root is always alive");
-        let a1 = r1.insert(&mut t1, "a", MapPrelim::default());
-        assert!(t1.is_alive(&a1), "1st level nesting");
-        let aa1 = a1.insert(&mut t1, "aa", MapPrelim::default());
-        assert!(t1.is_alive(&aa1), "2nd level nesting");
-        drop(t1);
+        text.insert(&mut doc.transact_mut(), 0, "hello");
+        text.insert(&mut doc.transact_mut(), 5, " world");
+        // transaction which doesn't change anything doesn't produce an update
+        drop(doc.transact_mut());
+        text.remove_range(&mut doc.transact_mut(), 0, 6);
 
-        exchange_updates(&[&d1, &d2]);
+        let sizes = sizes.lock().unwrap();
+        assert_eq!(sizes.len(), 3);
+        assert_eq!(*sizes, *expected.lock().unwrap());
+    }
 
-        let t2 = d2.transact();
-        let a2 = r2.get(&t2, "a").unwrap().cast::<MapRef>().unwrap();
-        let aa2 = a2.get(&t2, "aa").unwrap().cast::<MapRef>().unwrap();
-        assert!(t2.is_alive(&r2), "root is always alive (remote)");
-        assert!(t2.is_alive(&a2), "1st level nesting (remote)");
-        assert!(t2.is_alive(&aa2), "2nd level nesting (remote)");
-        drop(t2);
+    #[test]
+    fn observe_pending_resolved() {
+        let d1 = Doc::with_client_id(1);
+        let t1 = d1.get_or_insert_text("text");
+        t1.insert(&mut d1.transact_mut(), 0, "hello");
+        let u1 = d1.transact().encode_diff_v1(&StateVector::default());
+        let sv = d1.transact().state_vector();
+        t1.insert(&mut d1.transact_mut(), 5, " world");
+        let u2 = d1.transact().encode_diff_v1(&sv);
 
-        // delete nested
-        let mut t1 = d1.transact_mut();
-        r1.remove(&mut t1, "a");
-        assert!(t1.is_alive(&r1), "root is always alive");
-        assert!(!t1.is_alive(&a1), "child was removed");
-        assert!(!t1.is_alive(&aa1), "parent was removed");
-        drop(t1);
+        let d2 = Doc::with_client_id(2);
+        let t2 = d2.get_or_insert_text("text");
+        let resolved = Arc::new(Mutex::new(Vec::new()));
+        let _sub = {
+            let resolved = resolved.clone();
+            d2.observe_pending_resolved(move |_, sv| resolved.lock().unwrap().push(sv.clone()))
+                .unwrap()
+        };
 
-        exchange_updates(&[&d1, &d2]);
+        // out of order update is stashed
+        d2.transact_mut()
+            .apply_update(Update::decode_v1(&u2).unwrap());
+        assert!(d2.transact_mut().pending().is_some());
+        assert!(resolved.lock().unwrap().is_empty());
 
-        let t2 = d2.transact();
-        assert!(t2.is_alive(&r2), "root is always alive (remote)");
-        assert!(!t2.is_alive(&a2), "child was removed (remote)");
-        assert!(!t2.is_alive(&aa2), "parent was removed (remote)");
+        // missing update arrives
+        d2.transact_mut()
+            .apply_update(Update::decode_v1(&u1).unwrap());
+        assert_eq!(t2.get_string(&d2.transact()), "hello world");
+        assert_eq!(
+            std::mem::take(&mut *resolved.lock().unwrap()),
+            vec![d1.transact().state_vector()]
+        );
+
+        // in order updates don't fire the event
+        t1.insert(&mut d1.transact_mut(), 0, ">");
+        exchange_updates(&[&d1, &d2]);
+        assert!(resolved.lock().unwrap().is_empty());
     }
 
     #[test]
-    fn apply_snapshot_updates() {
-        let update = {
-            let doc = Doc::with_options(Options {
-                client_id: 1,
-                skip_gc: true,
-                offset_kind: OffsetKind::Utf16,
-                ..Options::default()
+    fn observe_update_v1_coalesced() {
+        let doc = Doc::with_client_id(1);
+        let text = doc.get_or_insert_text("text");
+        let updates = Arc::new(Mutex::new(Vec::new()));
+        let clock = Arc::new(Mutex::new(Instant::now()));
+        let _sub = {
+            let updates = updates.clone();
+            let clock = clock.clone();
+            let now = move || *clock.lock().unwrap();
+            let f = coalesce_updates_with_clock(Duration::from_millis(100), now, move |_, e| {
+                updates.lock().unwrap().push(e.update.clone());
             });
-            let txt = doc.get_or_insert_text("test");
-            let mut txn = doc.transact_mut();
-            txt.insert(&mut txn, 0, "hello");
+            doc.observe_update_v1(f).unwrap()
+        };
 
-            let snap = txn.snapshot();
+        // keystroke-per-transaction editing
+        for (i, c) in "hello".chars().enumerate() {
+            text.insert(&mut doc.transact_mut(), i as u32, &c.to_string());
+        }
+        assert!(updates.lock().unwrap().is_empty());
 
-            txt.insert(&mut txn, 5, " world");
+        *clock.lock().unwrap() += Duration::from_millis(150);
+        text.insert(&mut doc.transact_mut(), 5, "!");
 
-            let mut encoder = EncoderV1::new();
-            txn.encode_state_from_snapshot(&snap, &mut encoder).unwrap();
-            encoder.to_vec()
-        };
+        let updates = std::mem::take(&mut *updates.lock().unwrap());
+        assert_eq!(updates.len(), 1);
 
+        let remote = Doc::with_client_id(2);
+        let remote_text = remote.get_or_insert_text("text");
+        remote
+            .transact_mut()
+            .apply_update(Update::decode_v1(&updates[0]).unwrap());
+        assert_eq!(remote_text.get_string(&remote.transact()), "hello!");
+    }
+
+    #[test]
+    fn observe_update_v1_skip_delete_only() {
         let doc = Doc::with_client_id(1);
-        let txt = doc.get_or_insert_text("test");
-        let mut txn = doc.transact_mut();
-        txn.apply_update(Update::decode_v1(&update).unwrap());
-        let str = txt.get_string(&txn);
-        assert_eq!(&str, "hello");
+        let text = doc.get_or_insert_text("text");
+        let all = Arc::new(AtomicU32::new(0));
+        let content_only = Arc::new(AtomicU32::new(0));
+        let _s1 = {
+            let all = all.clone();
+            doc.observe_update_v1_ext(false, move |_, _| {
+                all.fetch_add(1, Ordering::SeqCst);
+            })
+            .unwrap()
+        };
+        let _s2 = {
+            let content_only = content_only.clone();
+            doc.observe_update_v1_ext(true, move |_, _| {
+                content_only.fetch_add(1, Ordering::SeqCst);
+            })
+            .unwrap()
+        };
+
+        text.insert(&mut doc.transact_mut(), 0, "hello");
+        assert_eq!(all.load(Ordering::SeqCst), 1);
+        assert_eq!(content_only.load(Ordering::SeqCst), 1);
+
+        // delete-only transaction
+        text.remove_range(&mut doc.transact_mut(), 0, 2);
+        assert_eq!(all.load(Ordering::SeqCst), 2);
+        assert_eq!(content_only.load(Ordering::SeqCst), 1);
+
+        // transaction with both deletions and insertions
+        {
+            let mut txn = doc.transact_mut();
+            text.remove_range(&mut txn, 0, 1);
+            text.insert(&mut txn, 0, "a");
+        }
+        assert_eq!(all.load(Ordering::SeqCst), 3);
+        assert_eq!(content_only.load(Ordering::SeqCst), 2);
     }
 
     #[test]
-    fn out_of_order_updates() {
-        let updates = Arc::new(Mutex::new(vec![]));
+    fn observe_update_default_encoding_version() {
+        let doc = Doc::with_options(Options {
+            encoding_version: EncodingVersion::V2,
+            ..Options::with_client_id(1)
+        });
+        let text = doc.get_or_insert_text("text");
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let expected = Arc::new(Mutex::new(Vec::new()));
+        let _s1 = {
+            let received = received.clone();
+            doc.observe_update(move |_, e| received.lock().unwrap().push(e.update.clone()))
+                .unwrap()
+        };
+        let _s2 = {
+            let expected = expected.clone();
+            doc.observe_update_v2(move |_, e| expected.lock().unwrap().push(e.update.clone()))
+                .unwrap()
+        };
 
-        let d1 = Doc::new();
+        text.push(&mut doc.transact_mut(), "hello");
+
+        let received = received.lock().unwrap().clone();
+        assert_eq!(received, *expected.lock().unwrap());
+        assert_eq!(received.len(), 1);
+
+        let remote = Doc::new();
+        let remote_text = remote.get_or_insert_text("text");
+        remote
+            .transact_mut()
+            .apply_update(Update::decode_v2(&received[0]).unwrap());
+        assert_eq!(remote_text.get_string(&remote.transact()), "hello");
+
+        let update = doc.encode_state_as_update(&StateVector::default());
+        assert!(Update::decode_v2(&update).is_ok());
+    }
+
+    #[test]
+    fn from_updates_shuffled() {
+        let source = Doc::with_client_id(1);
+        let text = source.get_or_insert_text("text");
+        let updates = Arc::new(Mutex::new(Vec::new()));
         let _sub = {
             let updates = updates.clone();
-            d1.observe_update_v1(move |_, e| {
-                let mut u = updates.lock().unwrap();
-                u.push(Update::decode_v1(&e.update).unwrap());
-            })
-            .unwrap()
+            source
+                .observe_update_v2(move |_, e| updates.lock().unwrap().push(e.update.clone()))
+                .unwrap()
         };
+        for i in 0..20 {
+            let mut txn = source.transact_mut();
+            text.push(&mut txn, &i.to_string());
+            if i % 3 == 0 {
+                text.remove_range(&mut txn, 0, 1);
+            }
+        }
 
-        let map = d1.get_or_insert_map("map");
-        map.insert(&mut d1.transact_mut(), "a", 1);
-        map.insert(&mut d1.transact_mut(), "a", 1.1);
-        map.insert(&mut d1.transact_mut(), "b", 2);
+        let mut updates = updates.lock().unwrap().clone();
+        fastrand::Rng::with_seed(7).shuffle(&mut updates);
 
-        assert_eq!(map.to_json(&d1.transact()), any!({"a": 1.1, "b": 2}));
+        let doc =
+            Doc::from_updates(Options::with_client_id(2), updates, EncodingVersion::V2).unwrap();
+        let restored = doc.get_or_insert_text("text");
+        let txn = doc.transact();
+        assert_eq!(
+            restored.get_string(&txn),
+            text.get_string(&source.transact())
+        );
+        assert!(txn.store().pending_update().is_none());
+        assert!(txn.store().pending_ds().is_none());
 
-        let d2 = Doc::new();
+        assert!(Doc::from_updates(Options::default(), [[0xff]], EncodingVersion::V1).is_err());
+    }
+
+    #[test]
+    fn observe_local_change() {
+        let remote = Doc::with_client_id(2);
+        remote
+            .get_or_insert_text("text")
+            .push(&mut remote.transact_mut(), "remote");
+        let update = remote
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
 
+        let doc = Doc::with_client_id(1);
+        let text = doc.get_or_insert_text("text");
+        let array = doc.get_or_insert_array("array");
+        let map = doc.get_or_insert_map("map");
+        let mut changes = Vec::new();
         {
-            let mut updates = updates.lock().unwrap();
-            let u3 = updates.pop().unwrap();
-            let u2 = updates.pop().unwrap();
-            let u1 = updates.pop().unwrap();
-            let mut txn = d2.transact_mut();
-            txn.apply_update(u1);
-            assert!(txn.store.pending.is_none()); // applied
-            txn.apply_update(u3);
-            assert!(txn.store.pending.is_some()); // pending update waiting for u2
-            txn.apply_update(u2);
-            assert!(txn.store.pending.is_none()); // applied after fixing the missing update
+            let mut txn = doc.transact_mut();
+            txn.observe_local_change(|change| changes.push(change.clone()));
+            text.insert(&mut txn, 0, "hello");
+            array.push_back(&mut txn, 1);
+            map.insert(&mut txn, "key", "value");
+            text.remove_range(&mut txn, 0, 1);
+            txn.apply_update(Update::decode_v1(&update).unwrap());
         }
 
-        let map = d2.get_or_insert_map("map");
-        assert_eq!(map.to_json(&d2.transact()), any!({"a": 1.1, "b": 2}));
+        let ptr = |branch: &Branch| BranchPtr::from(branch);
+        let text_ptr = ptr(text.as_ref());
+        assert_eq!(
+            changes,
+            vec![
+                LocalChange::Inserted {
+                    parent: text_ptr,
+                    key: None,
+                    id: ID::new(1, 0),
+                    len: 5,
+                },
+                LocalChange::Inserted {
+                    parent: ptr(array.as_ref()),
+                    key: None,
+                    id: ID::new(1, 5),
+                    len: 1,
+                },
+                LocalChange::Inserted {
+                    parent: ptr(map.as_ref()),
+                    key: Some("key".into()),
+                    id: ID::new(1, 6),
+                    len: 1,
+                },
+                LocalChange::Removed {
+                    parent: text_ptr,
+                    key: None,
+                    id: ID::new(1, 0),
+                    len: 1,
+                },
+            ]
+        );
     }
 
+    #[cfg(feature = "sync")]
     #[test]
-    fn encoding_buffer_overflow_errors() {
-        assert_matches!(
-            Update::decode_v1(&vec![
-                0xe4, 0x9c, 0x10, 0x00, 0x05, 0xff, 0xff, 0x05, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01,
-                0x01, 0x00, 0x00, 0x00, 0xed, 0x00, 0x00, 0x00, 0x01, 0x01, 0x00, 0xfe, 0xb8, 0xc2,
-                0xe9, 0xad, 0x87, 0xd9, 0x12, 0x00, 0x00, 0x01, 0x01, 0xff, 0xed, 0xf6,
-            ]),
-            Err(crate::encoding::read::Error::EndOfBuffer(_))
+    fn local_change_callback_is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        // callback is stored inside of a transaction, which must remain Send and Sync
+        assert_send_sync::<crate::transaction::LocalChangeFn<'static>>();
+    }
+
+    #[test]
+    fn encode_diff_since_seq() {
+        let doc = Doc::with_options(Options {
+            update_log_capacity: 2,
+            ..Options::with_client_id(1)
+        });
+        let text = doc.get_or_insert_text("text");
+        let map = doc.get_or_insert_map("map");
+
+        let mut persisted = Vec::new();
+        let mut last_seq = doc.commit_seq();
+        for i in 0..5 {
+            {
+                let mut txn = doc.transact_mut();
+                text.push(&mut txn, "abc");
+                map.insert(&mut txn, i.to_string(), i);
+                if i > 0 {
+                    text.remove_range(&mut txn, 0, 1);
+                }
+            }
+            persisted.push(doc.encode_diff_since_seq(last_seq).unwrap());
+            last_seq = doc.commit_seq();
+        }
+        assert_eq!(last_seq, 5);
+        // nothing changed since last persisted state
+        let empty = doc.encode_diff_since_seq(last_seq).unwrap();
+        assert_eq!(Update::decode_v1(&empty).unwrap(), Update::new());
+        // older changes have been evicted from the update log
+        assert_eq!(doc.encode_diff_since_seq(0), None);
+        assert!(doc.encode_diff_since_seq(3).is_some());
+
+        let restored = Doc::with_client_id(2);
+        restored.get_or_insert_text("text");
+        restored.get_or_insert_map("map");
+        {
+            let mut txn = restored.transact_mut();
+            for update in persisted {
+                txn.apply_update(Update::decode_v1(&update).unwrap());
+            }
+        }
+        assert_eq!(
+            restored.to_json(&restored.transact()),
+            doc.to_json(&doc.transact())
         );
+    }
 
-        assert_matches!(
-            Update::decode_v2(&vec![
-                0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x01, 0x02, 0x00, 0x00,
-                0x16, 0x02, 0x00, 0x00, 0x01, 0xfd, 0xff, 0xff, 0xff, 0xff, 0x7f, 0x00, 0x00,
-            ]),
-            Err(crate::encoding::read::Error::EndOfBuffer(_))
+    #[test]
+    fn undelete() {
+        let options = Options {
+            skip_gc: true,
+            ..Options::with_client_id(1)
+        };
+        let d1 = Doc::with_options(options);
+        let text1 = d1.get_or_insert_text("text");
+        let map1 = d1.get_or_insert_map("map");
+        let d2 = Doc::with_client_id(2);
+        let text2 = d2.get_or_insert_text("text");
+        let map2 = d2.get_or_insert_map("map");
+        {
+            let mut txn = d1.transact_mut();
+            text1.insert(&mut txn, 0, "hello world");
+            map1.insert(&mut txn, "key", MapPrelim::from([("a", 1)]));
+        }
+        exchange_updates(&[&d1, &d2]);
+
+        let deleted = {
+            let mut txn = d1.transact_mut();
+            text1.remove_range(&mut txn, 5, 6);
+            map1.remove(&mut txn, "key");
+            txn.delete_set().clone()
+        };
+        exchange_updates(&[&d1, &d2]);
+        assert_eq!(text2.get_string(&d2.transact()), "hello");
+        assert_eq!(map2.len(&d2.transact()), 0);
+
+        assert!(d1.transact_mut().undelete(&deleted));
+        // content has already been restored
+        assert!(!d1.transact_mut().undelete(&deleted));
+        assert_eq!(text1.get_string(&d1.transact()), "hello world");
+        assert_eq!(map1.to_json(&d1.transact()), any!({"key": {"a": 1}}));
+
+        exchange_updates(&[&d1, &d2]);
+        assert_eq!(text2.get_string(&d2.transact()), "hello world");
+        assert_eq!(map2.to_json(&d2.transact()), any!({"key": {"a": 1}}));
+    }
+
+    #[test]
+    fn encoded_size_hint() {
+        let doc = Doc::with_client_id(1);
+        assert_eq!(
+            doc.transact().encoded_size_hint(),
+            doc.transact()
+                .encode_state_as_update_v1(&StateVector::default())
+                .len()
         );
-        assert_matches!(
-            Update::decode_v2(&vec![
-                0xe4, 0x95, 0x00, 0x00, 0x01, 0x18, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x01,
-                0x00, 0x00, 0xed, 0x01, 0xbe, 0x82, 0xe3, 0xc3, 0x1c, 0x01, 0x02, 0xe4, 0x95, 0x00,
-                0x00, 0x01, 0x18, 0x00, 0x00, 0x01, 0x18, 0x00, 0x00, 0x01, 0x00, 0x01, 0xed, 0x00,
-            ]),
-            Err(crate::encoding::read::Error::InvalidVarInt)
+
+        let text = doc.get_or_insert_text("text");
+        let map = doc.get_or_insert_map("map");
+        let array = doc.get_or_insert_array("array");
+        {
+            let mut txn = doc.transact_mut();
+            text.insert(&mut txn, 0, "hello world ąę");
+            text.remove_range(&mut txn, 2, 3);
+            map.insert(&mut txn, "number", 1.5);
+            map.insert(
+                &mut txn,
+                "nested",
+                MapPrelim::from([("key", any!([1, "a", null]))]),
+            );
+            array.insert_range(&mut txn, 0, [1, 2, 3]);
+            array.remove(&mut txn, 1);
+        }
+        let remote = Doc::with_client_id(2);
+        let remote_text = remote.get_or_insert_text("text");
+        remote_text.insert(&mut remote.transact_mut(), 0, "remote");
+        exchange_updates(&[&doc, &remote]);
+
+        let txn = doc.transact();
+        let update = txn.encode_state_as_update_v1(&StateVector::default());
+        assert_eq!(txn.encoded_size_hint(), update.len());
+        drop(txn);
+
+        // pending updates are not accounted for
+        let d3 = Doc::with_client_id(3);
+        let t3 = d3.get_or_insert_text("text");
+        t3.insert(&mut d3.transact_mut(), 0, "a");
+        let sv = d3.transact().state_vector();
+        t3.insert(&mut d3.transact_mut(), 1, "b");
+        let missing_dependency = d3.transact().encode_state_as_update_v1(&sv);
+        doc.transact_mut()
+            .apply_update(Update::decode_v1(&missing_dependency).unwrap());
+
+        let txn = doc.transact();
+        assert!(txn.store().pending_update().is_some());
+        let update = txn.encode_state_as_update_v1(&StateVector::default());
+        let hint = txn.encoded_size_hint();
+        assert!(hint < update.len());
+        assert!(hint >= update.len() - missing_dependency.len());
+    }
+
+    #[test]
+    fn new_with_rng_is_deterministic() {
+        let mut rng1 = fastrand::Rng::with_seed(42);
+        let mut rng2 = fastrand::Rng::with_seed(42);
+        for _ in 0..10 {
+            let d1 = Doc::new_with_rng(&mut rng1);
+            let d2 = Doc::new_with_rng(&mut rng2);
+            assert_eq!(d1.client_id(), d2.client_id());
+            assert_eq!(d1.guid(), d2.guid());
+        }
+
+        // consecutive documents get different identifiers
+        let d1 = Doc::new_with_rng(&mut rng1);
+        let d2 = Doc::new_with_rng(&mut rng1);
+        assert_ne!(d1.client_id(), d2.client_id());
+        assert_ne!(d1.guid(), d2.guid());
+    }
+
+    #[test]
+    fn state_vector_bytes_cache() {
+        let doc = Doc::with_client_id(1);
+        let text = doc.get_or_insert_text("text");
+        text.push(&mut doc.transact_mut(), "abc");
+
+        let first = doc.transact().state_vector_bytes_v1();
+        assert_eq!(
+            &*first,
+            doc.transact().state_vector().encode_v1().as_slice()
         );
-        assert_matches!(
-            Update::decode_v2(&vec![
-                0x8f, 0x01, 0x80, 0x00, 0x00, 0x00, 0x01, 0xaa, 0x01, 0x00, 0x01, 0x02, 0x00, 0x00,
-                0x16, 0x02, 0x00, 0xe5, 0xc4, 0x43, 0x14, 0xe7, 0xa6, 0x8b, 0x93, 0xae, 0xb5, 0xfd,
-                0x5d, 0xe8, 0x26, 0x9a, 0x8a, 0x59, 0x00, 0x31, 0xd5, 0x0f, 0x12, 0x01, 0x30, 0x00,
-                0x00, 0x00,
-            ]),
-            Err(crate::encoding::read::Error::EndOfBuffer(_))
+        // no changes in between, cached bytes are reused
+        let second = doc.transact().state_vector_bytes_v1();
+        assert!(Arc::ptr_eq(&first, &second));
+
+        // read-write transaction which doesn't change the document keeps the cache
+        drop(doc.transact_mut());
+        assert!(Arc::ptr_eq(&first, &doc.transact().state_vector_bytes_v1()));
+
+        {
+            let mut txn = doc.transact_mut();
+            text.push(&mut txn, "def");
+            // uncommitted changes are visible within read-write transaction
+            assert_eq!(
+                &*txn.state_vector_bytes_v1(),
+                txn.state_vector().encode_v1().as_slice()
+            );
+        }
+
+        let third = doc.transact().state_vector_bytes_v1();
+        assert!(!Arc::ptr_eq(&first, &third));
+        let sv = StateVector::decode_v1(&third).unwrap();
+        assert_eq!(sv.get(&1), 6);
+    }
+
+    #[test]
+    fn encode_into_reused_buffer() {
+        let doc = Doc::with_client_id(1);
+        let text = doc.get_or_insert_text("text");
+        text.push(&mut doc.transact_mut(), "hello world");
+
+        let mut buf = Vec::with_capacity(1024);
+        let ptr = buf.as_ptr();
+        {
+            let txn = doc.transact();
+            txn.encode_state_vector_into(&mut buf);
+            assert_eq!(buf, txn.state_vector().encode_v1());
+
+            let remote = StateVector::decode_v1(&buf).unwrap();
+            txn.encode_diff_into(&remote, &mut buf);
+            assert_eq!(buf, txn.encode_diff_v1(&remote));
+
+            txn.encode_diff_into(&StateVector::default(), &mut buf);
+            assert_eq!(buf, txn.encode_diff_v1(&StateVector::default()));
+        }
+        {
+            let mut txn = doc.transact_mut();
+            text.push(&mut txn, "!");
+            txn.encode_state_vector_into(&mut buf);
+            assert_eq!(buf, txn.state_vector().encode_v1());
+        }
+        // buffer has been reused without reallocation
+        assert_eq!(buf.as_ptr(), ptr);
+    }
+
+    #[test]
+    fn encode_diff_concurrent_read_txns() {
+        let doc = Doc::with_client_id(1);
+        let text = doc.get_or_insert_text("text");
+        text.push(&mut doc.transact_mut(), "hello world");
+        let expected = doc.transact().encode_diff_v1(&StateVector::default());
+
+        // both read transactions are kept open at the same time while encoding
+        let barrier = std::sync::Barrier::new(2);
+        let encode = || {
+            let txn = doc.transact();
+            barrier.wait();
+            let diff = txn.encode_diff_v1(&StateVector::default());
+            barrier.wait();
+            diff
+        };
+        std::thread::scope(|s| {
+            let h1 = s.spawn(encode);
+            let h2 = s.spawn(encode);
+            assert_eq!(h1.join().unwrap(), expected);
+            assert_eq!(h2.join().unwrap(), expected);
+        });
+    }
+
+    #[test]
+    fn subdocs_event_mount_path() {
+        let doc = Doc::with_client_id(1);
+        let config = doc.get_or_insert_map("config");
+        let paths = Arc::new(Mutex::new(Vec::new()));
+        let _sub = {
+            let paths = paths.clone();
+            doc.observe_subdocs(move |_, e| {
+                let mut paths = paths.lock().unwrap();
+                for subdoc in e.added() {
+                    paths.push(e.path(subdoc).cloned());
+                }
+                for subdoc in e.removed() {
+                    paths.push(e.path(subdoc).cloned());
+                }
+            })
+            .unwrap()
+        };
+
+        let children = config.insert(
+            &mut doc.transact_mut(),
+            "children",
+            ArrayPrelim::from(["a", "b"]),
         );
-        assert_matches!(
-            Update::decode_v2(&vec![
-                0x00, 0x01, 0x23, 0x00, 0x00, 0x00, 0x01, 0x02, 0x81, 0x00, 0x00, 0x10, 0x00, 0xc7,
-                0xdc, 0x00, 0xc4, 0x7a, 0x80, 0x00, 0x41, 0xab, 0xea, 0xd6, 0x00, 0x01, 0x00, 0x00,
-                0x01, 0x00, 0x00, 0x84, 0x00, 0x00, 0x10, 0xff, 0xc7, 0xdc, 0xff, 0x00, 0x00, 0x00,
-            ]),
-            Err(crate::encoding::read::Error::EndOfBuffer(_))
+        let subdoc = children.insert(&mut doc.transact_mut(), 2, Doc::new());
+        assert_eq!(
+            paths.lock().unwrap().pop(),
+            Some(Some(
+                vec![
+                    PathSegment::Key("config".into()),
+                    PathSegment::Key("children".into()),
+                    PathSegment::Index(2),
+                ]
+                .into()
+            ))
+        );
+
+        {
+            let mut txn = doc.transact_mut();
+            children.remove(&mut txn, 0);
+            // mount path reflects current position of a sub-document
+            assert_eq!(
+                subdoc.mount_path(),
+                Some(
+                    vec![
+                        PathSegment::Key("config".into()),
+                        PathSegment::Key("children".into()),
+                        PathSegment::Index(1),
+                    ]
+                    .into()
+                )
+            );
+            children.remove(&mut txn, 1);
+        }
+        assert_eq!(paths.lock().unwrap().pop(), Some(None));
+    }
+
+    #[test]
+    fn encode_state_without_subdocs() {
+        let doc = Doc::with_client_id(1);
+        let map = doc.get_or_insert_map("docs");
+        let array = doc.get_or_insert_array("list");
+        let subdoc = Doc::new();
+        {
+            let mut txn = doc.transact_mut();
+            let subdoc = map.insert(&mut txn, "a", subdoc);
+            map.insert(&mut txn, "b", "x");
+            array.insert_range(&mut txn, 0, [1, 2]);
+            array.insert(&mut txn, 1, Doc::new());
+            subdoc
+                .get_or_insert_text("text")
+                .push(&mut subdoc.transact_mut(), "hello");
+        }
+
+        let txn = doc.transact();
+        let sv = StateVector::default();
+        let full = Doc::with_client_id(2);
+        full.transact_mut().apply_update(
+            Update::decode_v1(&txn.encode_state_as_update_v1_ext(&sv, &EncodeOptions::default()))
+                .unwrap(),
+        );
+        assert_eq!(full.transact().subdocs().count(), 2);
+
+        let update = txn.encode_state_as_update_v1_ext(
+            &sv,
+            &EncodeOptions {
+                include_subdocs: false,
+            },
+        );
+
+        let copy = Doc::with_client_id(2);
+        let copy_map = copy.get_or_insert_map("docs");
+        let copy_array = copy.get_or_insert_array("list");
+        copy.transact_mut()
+            .apply_update(Update::decode_v1(&update).unwrap());
+        let copy_txn = copy.transact();
+        assert_eq!(copy_map.to_json(&copy_txn), any!({"b": "x"}));
+        assert_eq!(copy_array.to_json(&copy_txn), any!([1, 2]));
+        assert_eq!(copy_txn.subdocs().count(), 0);
+        // block sequence remains complete
+        assert_eq!(copy_txn.state_vector(), txn.state_vector());
+    }
+
+    #[test]
+    fn subdoc_infos() {
+        let doc = Doc::with_client_id(1);
+        let map = doc.get_or_insert_map("docs");
+        let array = doc.get_or_insert_array("list");
+
+        let doc_a = Doc::with_options({
+            let mut o = Options::default();
+            o.guid = "A".into();
+            o
+        });
+        let doc_b = Doc::with_options({
+            let mut o = Options::default();
+            o.guid = "B".into();
+            o.should_load = false;
+            o.auto_load = true;
+            o
+        });
+        {
+            let mut txn = doc.transact_mut();
+            let doc_a = map.insert(&mut txn, "a", doc_a);
+            array.insert(&mut txn, 0, "x");
+            array.insert(&mut txn, 1, doc_b);
+            doc_a
+                .get_or_insert_text("text")
+                .push(&mut doc_a.transact_mut(), "hello");
+        }
+
+        let mut infos: Vec<_> = doc.transact().subdoc_infos().collect();
+        infos.sort_by(|a, b| a.guid.cmp(&b.guid));
+        assert_eq!(
+            infos,
+            vec![
+                SubdocInfo {
+                    guid: "A".into(),
+                    key: Some("a".into()),
+                    path: vec![
+                        PathSegment::Key("docs".into()),
+                        PathSegment::Key("a".into())
+                    ]
+                    .into(),
+                    should_load: true,
+                    auto_load: false,
+                    loaded: true,
+                },
+                SubdocInfo {
+                    guid: "B".into(),
+                    key: None,
+                    path: vec![PathSegment::Key("list".into()), PathSegment::Index(1)].into(),
+                    should_load: false,
+                    auto_load: true,
+                    loaded: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn subdoc_set_auto_load() {
+        let d1 = Doc::with_client_id(1);
+        let map = d1.get_or_insert_map("docs");
+        let array = d1.get_or_insert_array("list");
+        let (subdoc_a, subdoc_b) = {
+            let mut txn = d1.transact_mut();
+            array.insert_range(&mut txn, 0, ["x", "y"]);
+            let a = map.insert(&mut txn, "a", Doc::with_client_id(1));
+            let b = array.insert(&mut txn, 1, Doc::with_client_id(1));
+            (a, b)
+        };
+        subdoc_a
+            .get_or_insert_text("text")
+            .push(&mut subdoc_a.transact_mut(), "hello");
+
+        let d2 = Doc::with_client_id(2);
+        exchange_updates(&[&d1, &d2]);
+
+        let removed = Arc::new(AtomicU32::new(0));
+        let _sub = {
+            let removed = removed.clone();
+            d1.observe_subdocs(move |_, e| {
+                removed.fetch_add(e.removed().count() as u32, Ordering::SeqCst);
+            })
+            .unwrap()
+        };
+
+        {
+            let mut txn = d1.transact_mut();
+            subdoc_a.set_auto_load(&mut txn, true);
+            subdoc_b.set_auto_load(&mut txn, true);
+        }
+        assert!(subdoc_a.options().auto_load);
+        assert!(subdoc_b.options().auto_load);
+        assert_eq!(removed.load(Ordering::SeqCst), 0);
+
+        let changed = Arc::new(AtomicU32::new(0));
+        let _sub = {
+            let changed = changed.clone();
+            d1.observe_subdocs(move |_, e| {
+                let count = e.added().count() + e.removed().count() + e.loaded().count();
+                changed.fetch_add(count as u32, Ordering::SeqCst);
+            })
+            .unwrap()
+        };
+        {
+            // toggling the flag back and forth doesn't report sub-document more than once
+            let mut txn = d1.transact_mut();
+            subdoc_b.set_auto_load(&mut txn, false);
+            subdoc_b.set_auto_load(&mut txn, true);
+        }
+        assert_eq!(changed.load(Ordering::SeqCst), 0);
+        {
+            // local sub-documents stay the same
+            let txn = d1.transact();
+            let a = map.get(&txn, "a").unwrap().cast::<Doc>().unwrap();
+            assert!(Doc::ptr_eq(&a, &subdoc_a));
+            assert_eq!(array.len(&txn), 3);
+            let b = array.get(&txn, 1).unwrap().cast::<Doc>().unwrap();
+            assert!(Doc::ptr_eq(&b, &subdoc_b));
+            assert_eq!(txn.subdoc_guids().count(), 2);
+            let text = subdoc_a.get_or_insert_text("text");
+            assert_eq!(text.get_string(&subdoc_a.transact()), "hello");
+        }
+
+        exchange_updates(&[&d1, &d2]);
+
+        let txn = d2.transact();
+        let map = txn.get_map("docs").unwrap();
+        let array = txn.get_array("list").unwrap();
+        let a = map.get(&txn, "a").unwrap().cast::<Doc>().unwrap();
+        assert_eq!(a.guid(), subdoc_a.guid());
+        assert!(a.options().auto_load);
+        assert!(a.options().should_load);
+        assert_eq!(array.len(&txn), 3);
+        let b = array.get(&txn, 1).unwrap().cast::<Doc>().unwrap();
+        assert_eq!(b.guid(), subdoc_b.guid());
+        assert!(b.options().auto_load);
+        assert_eq!(txn.subdoc_guids().count(), 2);
+    }
+
+    #[test]
+    fn subdoc_auto_load_edge_cases() {
+        let doc = Doc::with_client_id(1);
+        let array = doc.get_or_insert_array("test");
+        let subdoc_1 = Doc::with_options({
+            let mut o = Options::default();
+            o.auto_load = true;
+            o
+        });
+
+        let event = Arc::new(ArcSwapOption::default());
+        let event_c = event.clone();
+        let _sub = doc.observe_subdocs(move |_, e| {
+            let added = e.added().map(|d| d.guid().clone()).collect();
+            let removed = e.removed().map(|d| d.guid().clone()).collect();
+            let loaded = e.loaded().map(|d| d.guid().clone()).collect();
+
+            event_c.store(Some(Arc::new((added, removed, loaded))));
+        });
+
+        let subdoc_1 = {
+            let mut txn = doc.transact_mut();
+            array.insert(&mut txn, 0, subdoc_1)
+        };
+        assert!(subdoc_1.options().should_load);
+        assert!(subdoc_1.options().auto_load);
+
+        let uuid_1 = subdoc_1.options().guid.clone();
+        let last_event = event.swap(None);
+        assert_eq!(
+            last_event,
+            Some(Arc::new((
+                vec![uuid_1.clone()],
+                vec![],
+                vec![uuid_1.clone()]
+            )))
+        );
+
+        // destroy and check whether lastEvent adds it again to added (it shouldn't)
+        subdoc_1.destroy(&mut doc.transact_mut());
+
+        let subdoc_2 = array
+            .get(&doc.transact(), 0)
+            .unwrap()
+            .cast::<Doc>()
+            .unwrap();
+        let uuid_2 = subdoc_2.options().guid.clone();
+        assert!(!Doc::ptr_eq(&subdoc_1, &subdoc_2));
+
+        let last_event = event.swap(None);
+        assert_eq!(
+            last_event,
+            Some(Arc::new((
+                vec![uuid_2.clone()],
+                vec![uuid_2.clone()],
+                vec![]
+            )))
+        );
+
+        subdoc_2.load(&mut doc.transact_mut());
+        let last_event = event.swap(None);
+        assert_eq!(
+            last_event,
+            Some(Arc::new((vec![], vec![], vec![uuid_2.clone()])))
+        );
+
+        // apply from remote
+        let doc2 = Doc::with_client_id(2);
+        let event_c = event.clone();
+        let _sub = doc2.observe_subdocs(move |_, e| {
+            let added = e.added().map(|d| d.guid().clone()).collect();
+            let removed = e.removed().map(|d| d.guid().clone()).collect();
+            let loaded = e.loaded().map(|d| d.guid().clone()).collect();
+
+            event_c.store(Some(Arc::new((added, removed, loaded))));
+        });
+        let u = Update::decode_v1(
+            &doc.transact()
+                .encode_state_as_update_v1(&StateVector::default()),
+        );
+        doc2.transact_mut().apply_update(u.unwrap());
+        let subdoc_3 = {
+            let array = doc2.get_or_insert_array("test");
+            array
+                .get(&doc2.transact(), 0)
+                .unwrap()
+                .cast::<Doc>()
+                .unwrap()
+        };
+        assert!(subdoc_1.options().should_load);
+        assert!(subdoc_1.options().auto_load);
+        let uuid_3 = subdoc_3.options().guid.clone();
+        let last_event = event.swap(None);
+        assert_eq!(
+            last_event,
+            Some(Arc::new((
+                vec![uuid_3.clone()],
+                vec![],
+                vec![uuid_3.clone()]
+            )))
+        );
+    }
+
+    #[test]
+    fn read_only_mode() {
+        let doc = Doc::with_options(Options {
+            client_id: 1,
+            read_only: true,
+            ..Options::default()
+        });
+        // root types can still be obtained
+        let text = doc.get_or_insert_text("text");
+
+        assert!(matches!(
+            doc.try_transact_mut(),
+            Err(TransactionAcqError::ReadOnly)
+        ));
+        assert!(matches!(
+            doc.try_transact_mut_with("origin"),
+            Err(TransactionAcqError::ReadOnly)
+        ));
+        assert_eq!(text.get_string(&doc.try_transact().unwrap()), "");
+
+        // remote updates are allowed
+        let remote = Doc::with_client_id(2);
+        let remote_text = remote.get_or_insert_text("text");
+        remote_text.push(&mut remote.transact_mut(), "hello");
+        let update = remote
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+        doc.apply_remote_update(Update::decode_v1(&update).unwrap())
+            .unwrap();
+        assert_eq!(text.get_string(&doc.transact()), "hello");
+
+        // read-only mode can be switched off
+        doc.set_read_only(false).unwrap();
+        text.push(&mut doc.transact_mut(), " world");
+        assert_eq!(text.get_string(&doc.transact()), "hello world");
+    }
+
+    #[test]
+    fn read_only_subdoc() {
+        let read_only = || {
+            Doc::with_options(Options {
+                read_only: true,
+                ..Options::default()
+            })
+        };
+        let doc = Doc::with_client_id(1);
+        let subdocs = doc.get_or_insert_map("subdocs");
+
+        // parent transaction commit integrates read-only subdocs
+        let subdoc = subdocs.insert(&mut doc.transact_mut(), "a", read_only());
+        assert_eq!(subdoc.client_id(), 1);
+        assert_eq!(doc.transact().subdoc_guids().count(), 1);
+        assert!(matches!(
+            subdoc.try_transact_mut(),
+            Err(TransactionAcqError::ReadOnly)
+        ));
+
+        // reverting parent transaction detaches read-only subdocs
+        let result: Result<(), ()> = doc.with_transaction_mut(|txn| {
+            subdocs.insert(txn, "b", read_only());
+            Err(())
+        });
+        assert!(result.is_err());
+        assert_eq!(subdocs.len(&doc.transact()), 1);
+    }
+
+    #[test]
+    fn read_only_observer_changes() {
+        let doc = Doc::with_options(Options {
+            client_id: 1,
+            read_only: true,
+            ..Options::default()
+        });
+        let text = doc.get_or_insert_text("text");
+        let panics = Arc::new(AtomicU32::new(0));
+        {
+            let panics = panics.clone();
+            doc.set_observer_panic_handler(move |_| {
+                panics.fetch_add(1, Ordering::SeqCst);
+            })
+            .unwrap();
+        }
+        {
+            let text = text.clone();
+            doc.observe_after_transaction_with("insert", move |txn| text.push(txn, " local"))
+                .unwrap();
+        }
+        {
+            let text = text.clone();
+            doc.observe_after_transaction_with("remove", move |txn| text.remove_range(txn, 0, 1))
+                .unwrap();
+        }
+
+        let remote = Doc::with_client_id(2);
+        let remote_text = remote.get_or_insert_text("text");
+        remote_text.push(&mut remote.transact_mut(), "hello");
+        let update = remote
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+        doc.apply_remote_update(Update::decode_v1(&update).unwrap())
+            .unwrap();
+
+        // callbacks failed to change the document
+        assert_eq!(panics.load(Ordering::SeqCst), 2);
+        let txn = doc.transact();
+        assert_eq!(text.get_string(&txn), "hello");
+        assert_eq!(txn.state_vector().get(&1), 0);
+        assert!(txn.snapshot().delete_set.is_empty());
+    }
+
+    #[test]
+    fn observe_content_loaded() {
+        let doc = Doc::with_client_id(1);
+        let subdocs = doc.get_or_insert_map("subdocs");
+        let requested = Arc::new(Mutex::new(Vec::new()));
+        let requested_c = requested.clone();
+        let _sub = doc
+            .observe_subdocs(move |_, e| {
+                let mut requested = requested_c.lock().unwrap();
+                requested.extend(e.loaded().map(|d| d.guid().clone()));
+            })
+            .unwrap();
+
+        let subdoc = subdocs.insert(
+            &mut doc.transact_mut(),
+            "a",
+            Doc::with_options(Options {
+                guid: "A".into(),
+                should_load: false,
+                ..Options::default()
+            }),
+        );
+        let loaded = Arc::new(AtomicU32::new(0));
+        let loaded_c = loaded.clone();
+        let _sub = subdoc
+            .observe_content_loaded(move |_| {
+                loaded_c.fetch_add(1, Ordering::SeqCst);
+            })
+            .unwrap();
+
+        // requesting a load doesn't mean that content is there yet
+        subdoc.load(&mut doc.transact_mut());
+        assert_eq!(requested.lock().unwrap().as_slice(), &["A".into()]);
+        assert_eq!(loaded.load(Ordering::SeqCst), 0);
+
+        // content fetched from storage is applied
+        let stored = Doc::with_client_id(2);
+        stored
+            .get_or_insert_text("text")
+            .push(&mut stored.transact_mut(), "hello");
+        let update = stored
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+        subdoc
+            .transact_mut()
+            .apply_update(Update::decode_v1(&update).unwrap());
+        assert_eq!(loaded.load(Ordering::SeqCst), 1);
+
+        // subsequent changes are not reported
+        let text = subdoc.get_or_insert_text("text");
+        text.push(&mut subdoc.transact_mut(), " world");
+        assert_eq!(loaded.load(Ordering::SeqCst), 1);
+
+        // local changes made to an empty document are not reported either
+        let empty = Doc::with_client_id(3);
+        let empty_loaded = Arc::new(AtomicU32::new(0));
+        let empty_loaded_c = empty_loaded.clone();
+        let _sub = empty
+            .observe_content_loaded(move |_| {
+                empty_loaded_c.fetch_add(1, Ordering::SeqCst);
+            })
+            .unwrap();
+        let text = empty.get_or_insert_text("text");
+        text.push(&mut empty.transact_mut(), "local");
+        assert_eq!(empty_loaded.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn to_json() {
+        let doc = Doc::new();
+        let mut txn = doc.transact_mut();
+        let text = txn.get_or_insert_text("text");
+        let array = txn.get_or_insert_array("array");
+        let map = txn.get_or_insert_map("map");
+        let xml_fragment = txn.get_or_insert_xml_fragment("xml-fragment");
+        let xml_element = xml_fragment.insert(&mut txn, 0, XmlElementPrelim::empty("xml-element"));
+        let xml_text = xml_fragment.insert(&mut txn, 0, XmlTextPrelim::new(""));
+
+        text.push(&mut txn, "hello");
+        xml_text.push(&mut txn, "world");
+        xml_fragment.insert(&mut txn, 0, XmlElementPrelim::empty("div"));
+        xml_element.insert(&mut txn, 0, XmlElementPrelim::empty("body"));
+        array.insert_range(&mut txn, 0, [1, 2, 3]);
+        map.insert(&mut txn, "key1", "value1");
+
+        // sub documents cannot use their parent's transaction
+        let sub_doc = Doc::new();
+        let sub_text = sub_doc.get_or_insert_text("sub-text");
+        let sub_doc = map.insert(&mut txn, "sub-doc", sub_doc);
+        let mut sub_txn = sub_doc.transact_mut();
+        sub_text.push(&mut sub_txn, "sample");
+
+        let actual = doc.to_json(&txn);
+        let expected = any!({
+            "text": "hello",
+            "array": [1,2,3],
+            "map": {
+                "key1": "value1",
+                "sub-doc": {
+                    "guid": sub_doc.guid().as_ref()
+                }
+            },
+            "xml-fragment": "<div></div>world<xml-element><body></body></xml-element>",
+        });
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn check_liveness() {
+        let d1 = Doc::new();
+        let r1 = d1.get_or_insert_map("root");
+
+        let d2 = Doc::new();
+        let r2 = d2.get_or_insert_map("root");
+
+        let mut t1 = d1.transact_mut();
+        assert!(t1.is_alive(&r1), "root is always alive");
+        let a1 = r1.insert(&mut t1, "a", MapPrelim::default());
+        assert!(t1.is_alive(&a1), "1st level nesting");
+        let aa1 = a1.insert(&mut t1, "aa", MapPrelim::default());
+        assert!(t1.is_alive(&aa1), "2nd level nesting");
+        drop(t1);
+
+        exchange_updates(&[&d1, &d2]);
+
+        let t2 = d2.transact();
+        let a2 = r2.get(&t2, "a").unwrap().cast::<MapRef>().unwrap();
+        let aa2 = a2.get(&t2, "aa").unwrap().cast::<MapRef>().unwrap();
+        assert!(t2.is_alive(&r2), "root is always alive (remote)");
+        assert!(t2.is_alive(&a2), "1st level nesting (remote)");
+        assert!(t2.is_alive(&aa2), "2nd level nesting (remote)");
+        drop(t2);
+
+        // delete nested
+        let mut t1 = d1.transact_mut();
+        r1.remove(&mut t1, "a");
+        assert!(t1.is_alive(&r1), "root is always alive");
+        assert!(!t1.is_alive(&a1), "child was removed");
+        assert!(!t1.is_alive(&aa1), "parent was removed");
+        drop(t1);
+
+        exchange_updates(&[&d1, &d2]);
+
+        let t2 = d2.transact();
+        assert!(t2.is_alive(&r2), "root is always alive (remote)");
+        assert!(!t2.is_alive(&a2), "child was removed (remote)");
+        assert!(!t2.is_alive(&aa2), "parent was removed (remote)");
+    }
+
+    #[test]
+    fn apply_snapshot_updates() {
+        let update = {
+            let doc = Doc::with_options(Options {
+                client_id: 1,
+                skip_gc: true,
+                offset_kind: OffsetKind::Utf16,
+                ..Options::default()
+            });
+            let txt = doc.get_or_insert_text("test");
+            let mut txn = doc.transact_mut();
+            txt.insert(&mut txn, 0, "hello");
+
+            let snap = txn.snapshot();
+
+            txt.insert(&mut txn, 5, " world");
+
+            let mut encoder = EncoderV1::new();
+            txn.encode_state_from_snapshot(&snap, &mut encoder).unwrap();
+            encoder.to_vec()
+        };
+
+        let doc = Doc::with_client_id(1);
+        let txt = doc.get_or_insert_text("test");
+        let mut txn = doc.transact_mut();
+        txn.apply_update(Update::decode_v1(&update).unwrap());
+        let str = txt.get_string(&txn);
+        assert_eq!(&str, "hello");
+    }
+
+    #[test]
+    fn out_of_order_updates() {
+        let updates = Arc::new(Mutex::new(vec![]));
+
+        let d1 = Doc::new();
+        let _sub = {
+            let updates = updates.clone();
+            d1.observe_update_v1(move |_, e| {
+                let mut u = updates.lock().unwrap();
+                u.push(Update::decode_v1(&e.update).unwrap());
+            })
+            .unwrap()
+        };
+
+        let map = d1.get_or_insert_map("map");
+        map.insert(&mut d1.transact_mut(), "a", 1);
+        map.insert(&mut d1.transact_mut(), "a", 1.1);
+        map.insert(&mut d1.transact_mut(), "b", 2);
+
+        assert_eq!(map.to_json(&d1.transact()), any!({"a": 1.1, "b": 2}));
+
+        let d2 = Doc::new();
+
+        {
+            let mut updates = updates.lock().unwrap();
+            let u3 = updates.pop().unwrap();
+            let u2 = updates.pop().unwrap();
+            let u1 = updates.pop().unwrap();
+            let mut txn = d2.transact_mut();
+            txn.apply_update(u1);
+            assert!(txn.store.pending.is_none()); // applied
+            txn.apply_update(u3);
+            assert!(txn.store.pending.is_some()); // pending update waiting for u2
+            txn.apply_update(u2);
+            assert!(txn.store.pending.is_none()); // applied after fixing the missing update
+        }
+
+        let map = d2.get_or_insert_map("map");
+        assert_eq!(map.to_json(&d2.transact()), any!({"a": 1.1, "b": 2}));
+    }
+
+    #[test]
+    fn encoding_buffer_overflow_errors() {
+        assert_matches!(
+            Update::decode_v1(&vec![
+                0xe4, 0x9c, 0x10, 0x00, 0x05, 0xff, 0xff, 0x05, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01,
+                0x01, 0x00, 0x00, 0x00, 0xed, 0x00, 0x00, 0x00, 0x01, 0x01, 0x00, 0xfe, 0xb8, 0xc2,
+                0xe9, 0xad, 0x87, 0xd9, 0x12, 0x00, 0x00, 0x01, 0x01, 0xff, 0xed, 0xf6,
+            ]),
+            Err(crate::encoding::read::Error::EndOfBuffer(_))
+        );
+
+        assert_matches!(
+            Update::decode_v2(&vec![
+                0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x01, 0x02, 0x00, 0x00,
+                0x16, 0x02, 0x00, 0x00, 0x01, 0xfd, 0xff, 0xff, 0xff, 0xff, 0x7f, 0x00, 0x00,
+            ]),
+            Err(crate::encoding::read::Error::EndOfBuffer(_))
+        );
+        assert_matches!(
+            Update::decode_v2(&vec![
+                0xe4, 0x95, 0x00, 0x00, 0x01, 0x18, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x01,
+                0x00, 0x00, 0xed, 0x01, 0xbe, 0x82, 0xe3, 0xc3, 0x1c, 0x01, 0x02, 0xe4, 0x95, 0x00,
+                0x00, 0x01, 0x18, 0x00, 0x00, 0x01, 0x18, 0x00, 0x00, 0x01, 0x00, 0x01, 0xed, 0x00,
+            ]),
+            Err(crate::encoding::read::Error::InvalidVarInt)
+        );
+        assert_matches!(
+            Update::decode_v2(&vec![
+                0x8f, 0x01, 0x80, 0x00, 0x00, 0x00, 0x01, 0xaa, 0x01, 0x00, 0x01, 0x02, 0x00, 0x00,
+                0x16, 0x02, 0x00, 0xe5, 0xc4, 0x43, 0x14, 0xe7, 0xa6, 0x8b, 0x93, 0xae, 0xb5, 0xfd,
+                0x5d, 0xe8, 0x26, 0x9a, 0x8a, 0x59, 0x00, 0x31, 0xd5, 0x0f, 0x12, 0x01, 0x30, 0x00,
+                0x00, 0x00,
+            ]),
+            Err(crate::encoding::read::Error::EndOfBuffer(_))
+        );
+        assert_matches!(
+            Update::decode_v2(&vec![
+                0x00, 0x01, 0x23, 0x00, 0x00, 0x00, 0x01, 0x02, 0x81, 0x00, 0x00, 0x10, 0x00, 0xc7,
+                0xdc, 0x00, 0xc4, 0x7a, 0x80, 0x00, 0x41, 0xab, 0xea, 0xd6, 0x00, 0x01, 0x00, 0x00,
+                0x01, 0x00, 0x00, 0x84, 0x00, 0x00, 0x10, 0xff, 0xc7, 0xdc, 0xff, 0x00, 0x00, 0x00,
+            ]),
+            Err(crate::encoding::read::Error::EndOfBuffer(_))
+        );
+    }
+
+    #[test]
+    fn with_transaction_mut_rollback() {
+        use crate::{DeepObservable, Observable};
+
+        let doc = Doc::with_client_id(1);
+        let text = doc.get_or_insert_text("text");
+        let map = doc.get_or_insert_map("map");
+        let array = doc.get_or_insert_array("array");
+        {
+            let mut txn = doc.transact_mut();
+            text.push(&mut txn, "hello world");
+            map.insert(&mut txn, "a", 1);
+            map.insert(&mut txn, "b", MapPrelim::from([("c", 2)]));
+            array.insert_range(&mut txn, 0, [1, 2, 3]);
+        }
+        let remote = Doc::with_client_id(2);
+        remote
+            .get_or_insert_text("text")
+            .push(&mut remote.transact_mut(), "remote");
+        let remote_update = remote
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+
+        let (before_sv, before_json) = {
+            let txn = doc.transact();
+            (txn.state_vector(), doc.to_json(&txn))
+        };
+        let events = Arc::new(AtomicU32::new(0));
+        let _subs = {
+            let e1 = events.clone();
+            let e2 = events.clone();
+            let e3 = events.clone();
+            (
+                doc.observe_update_v1(move |_, _| {
+                    e1.fetch_add(1, Ordering::SeqCst);
+                })
+                .unwrap(),
+                text.observe(move |_, _| {
+                    e2.fetch_add(1, Ordering::SeqCst);
+                }),
+                map.observe_deep(move |_, _| {
+                    e3.fetch_add(1, Ordering::SeqCst);
+                }),
+            )
+        };
+
+        let nested = Arc::new(Mutex::new(None));
+        let result: Result<(), &str> = doc.with_transaction_mut(|txn| {
+            text.remove_range(txn, 2, 5);
+            text.insert(txn, 3, "XYZ");
+            map.insert(txn, "a", "overridden");
+            map.remove(txn, "b");
+            let inner = map.insert(txn, "d", ArrayPrelim::from([true]));
+            *nested.lock().unwrap() = Some(inner);
+            array.remove(txn, 1);
+            array.push_back(txn, 4);
+            txn.apply_update(Update::decode_v1(&remote_update).unwrap());
+            Err("aborted")
+        });
+        assert_eq!(result, Err("aborted"));
+        assert_eq!(
+            events.load(Ordering::SeqCst),
+            0,
+            "observers should not fire"
+        );
+
+        {
+            let txn = doc.transact();
+            assert_eq!(txn.state_vector(), before_sv);
+            assert_eq!(doc.to_json(&txn), before_json);
+            assert_eq!(text.len(&txn), 11);
+            assert_eq!(array.len(&txn), 3);
+            let inner = nested.lock().unwrap().take().unwrap();
+            assert!(
+                !txn.is_alive(&inner),
+                "types created in rolled back txn are dead"
+            );
+        }
+
+        // document remains fully functional and in sync with its peers
+        text.insert(&mut doc.transact_mut(), 5, ",");
+        map.insert(&mut doc.transact_mut(), "a", 3);
+        assert_eq!(events.load(Ordering::SeqCst), 4);
+        let copy = Doc::with_client_id(3);
+        copy.get_or_insert_text("text");
+        copy.get_or_insert_map("map");
+        copy.get_or_insert_array("array");
+        copy.transact_mut().apply_update(
+            Update::decode_v1(
+                &doc.transact()
+                    .encode_state_as_update_v1(&StateVector::default()),
+            )
+            .unwrap(),
+        );
+        let txn = copy.transact();
+        assert_eq!(
+            copy.to_json(&txn),
+            any!({
+                "text": "hello, world",
+                "map": {"a": 3, "b": {"c": 2}},
+                "array": [1, 2, 3],
+            })
+        );
+    }
+
+    #[test]
+    fn transaction_mut_rollback() {
+        let doc = Doc::with_client_id(1);
+        let text = doc.get_or_insert_text("text");
+        let map = doc.get_or_insert_map("map");
+        text.push(&mut doc.transact_mut(), "hello");
+        map.insert(&mut doc.transact_mut(), "a", 1);
+
+        // out of order update from a remote peer, which stays pending
+        let remote = Doc::with_client_id(2);
+        let remote_text = remote.get_or_insert_text("text");
+        remote_text.push(&mut remote.transact_mut(), "a");
+        let sv = remote.transact().state_vector();
+        remote_text.push(&mut remote.transact_mut(), "b");
+        let out_of_order = remote.transact().encode_diff_v1(&sv);
+
+        let (before_sv, before_json) = {
+            let txn = doc.transact();
+            (txn.state_vector(), doc.to_json(&txn))
+        };
+        let events = Arc::new(AtomicU32::new(0));
+        let _subs = {
+            let e1 = events.clone();
+            let e2 = events.clone();
+            let e3 = events.clone();
+            (
+                doc.observe_update_v1(move |_, _| {
+                    e1.fetch_add(1, Ordering::SeqCst);
+                })
+                .unwrap(),
+                doc.observe_transaction_cleanup(move |_, _| {
+                    e2.fetch_add(1, Ordering::SeqCst);
+                })
+                .unwrap(),
+                doc.observe_after_transaction_update(move |_, _| {
+                    e3.fetch_add(1, Ordering::SeqCst);
+                })
+                .unwrap(),
+            )
+        };
+
+        let mut txn = doc.transact_mut();
+        text.insert(&mut txn, 0, "say ");
+        text.remove_range(&mut txn, 5, 2);
+        map.insert(&mut txn, "a", "overridden");
+        map.insert(&mut txn, "b", MapPrelim::default());
+        txn.apply_update(Update::decode_v1(&out_of_order).unwrap());
+        assert!(txn.pending().is_some());
+        txn.rollback();
+
+        assert_eq!(
+            events.load(Ordering::SeqCst),
+            0,
+            "observers should not fire"
+        );
+        let mut txn = doc.transact_mut();
+        assert_eq!(txn.state_vector(), before_sv);
+        assert_eq!(doc.to_json(&txn), before_json);
+        assert!(txn.pending().is_none());
+        assert!(txn.store().pending_ds().is_none());
+        text.push(&mut txn, "!");
+        drop(txn);
+        assert_eq!(events.load(Ordering::SeqCst), 3);
+        assert_eq!(text.get_string(&doc.transact()), "hello!");
+    }
+
+    #[test]
+    fn with_transaction_mut_rollback_on_panic() {
+        let doc = Doc::with_client_id(1);
+        let array = doc.get_or_insert_array("array");
+        array.insert_range(&mut doc.transact_mut(), 0, [1, 2, 3]);
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            doc.with_transaction_mut(|txn| -> Result<(), ()> {
+                array.move_to(txn, 0, 3);
+                array.insert(txn, 1, "new");
+                panic!("failure in the middle of transaction")
+            })
+        }));
+        assert!(result.is_err());
+        assert_eq!(array.to_json(&doc.transact()), any!([1, 2, 3]));
+
+        // previously moved element can be moved again
+        let result: Result<u32, ()> = doc.with_transaction_mut(|txn| {
+            array.move_to(txn, 0, 3);
+            Ok(array.len(txn))
+        });
+        assert_eq!(result, Ok(3));
+        assert_eq!(array.to_json(&doc.transact()), any!([2, 3, 1]));
+    }
+
+    #[test]
+    fn observer_panic_handler() {
+        let doc = Doc::with_client_id(1);
+        let txt = doc.get_or_insert_text("text");
+        let panics = Arc::new(Mutex::new(Vec::new()));
+        {
+            let panics = panics.clone();
+            doc.set_observer_panic_handler(move |panic| {
+                let msg = panic.downcast_ref::<&str>().unwrap().to_string();
+                panics.lock().unwrap().push(msg);
+            })
+            .unwrap();
+        }
+        let calls = Arc::new(AtomicU32::new(0));
+        let _s1 = doc
+            .observe_update_v1(|_, _| panic!("faulty observer"))
+            .unwrap();
+        let _s2 = {
+            let calls = calls.clone();
+            doc.observe_update_v1(move |_, _| {
+                calls.fetch_add(1, Ordering::SeqCst);
+            })
+            .unwrap()
+        };
+        let _s3 = {
+            let calls = calls.clone();
+            doc.observe_update_v2(move |_, _| {
+                calls.fetch_add(1, Ordering::SeqCst);
+            })
+            .unwrap()
+        };
+
+        txt.push(&mut doc.transact_mut(), "hello");
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(*panics.lock().unwrap(), vec!["faulty observer"]);
+
+        // document is still usable
+        txt.push(&mut doc.transact_mut(), " world");
+        assert_eq!(txt.get_string(&doc.transact()), "hello world");
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+        assert_eq!(panics.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn observer_panic_handler_shared_types() {
+        let doc = Doc::with_client_id(1);
+        let root = doc.get_or_insert_map("root");
+        let array = root.insert(&mut doc.transact_mut(), "array", ArrayPrelim::default());
+        let panics = Arc::new(Mutex::new(Vec::new()));
+        {
+            let panics = panics.clone();
+            doc.set_observer_panic_handler(move |panic| {
+                let msg = panic.downcast_ref::<&str>().unwrap().to_string();
+                panics.lock().unwrap().push(msg);
+            })
+            .unwrap();
+        }
+        let calls = Arc::new(AtomicU32::new(0));
+        let _s1 = array.observe(|_, _| panic!("faulty observer"));
+        let _s2 = root.observe_deep(|_, _| panic!("faulty deep observer"));
+        let _s3 = {
+            let calls = calls.clone();
+            array.observe(move |_, _| {
+                calls.fetch_add(1, Ordering::SeqCst);
+            })
+        };
+        let _s4 = {
+            let calls = calls.clone();
+            root.observe_deep(move |_, _| {
+                calls.fetch_add(1, Ordering::SeqCst);
+            })
+        };
+
+        array.push_back(&mut doc.transact_mut(), 1);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(
+            *panics.lock().unwrap(),
+            vec!["faulty observer", "faulty deep observer"]
+        );
+
+        // document is still usable
+        array.push_back(&mut doc.transact_mut(), 2);
+        assert_eq!(array.len(&doc.transact()), 2);
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+    }
+
+    #[test]
+    fn observer_panic_without_handler() {
+        let doc = Doc::with_client_id(1);
+        let txt = doc.get_or_insert_text("text");
+        let calls = Arc::new(AtomicU32::new(0));
+        let s1 = doc
+            .observe_update_v1(|_, _| panic!("faulty observer"))
+            .unwrap();
+        let _s2 = {
+            let calls = calls.clone();
+            doc.observe_update_v1(move |_, _| {
+                calls.fetch_add(1, Ordering::SeqCst);
+            })
+            .unwrap()
+        };
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            txt.push(&mut doc.transact_mut(), "hello");
+        }));
+        assert!(result.is_err());
+        // other observers were called before the panic has been resumed
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        // document has been released
+        drop(s1);
+        txt.push(&mut doc.transact_mut(), " world");
+        assert_eq!(txt.get_string(&doc.transact()), "hello world");
+    }
+
+    #[test]
+    fn weak_doc() {
+        let doc = Doc::with_client_id(1);
+        let weak = doc.downgrade();
+        let txt = doc.get_or_insert_text("text");
+        txt.push(&mut doc.transact_mut(), "hello");
+
+        let other = doc.clone();
+        assert_eq!(weak.strong_count(), 2);
+        assert!(WeakDoc::ptr_eq(&weak, &other.downgrade()));
+        let upgraded = weak.upgrade().unwrap();
+        assert!(Doc::ptr_eq(&upgraded, &doc));
+        assert_eq!(txt.get_string(&upgraded.transact()), "hello");
+        drop(upgraded);
+
+        drop(doc);
+        assert!(weak.upgrade().is_some());
+        drop(other);
+        assert_eq!(weak.strong_count(), 0);
+        assert!(weak.upgrade().is_none());
+    }
+
+    #[test]
+    fn compact() {
+        let doc = Doc::with_client_id(1);
+        let txt = doc.get_or_insert_text("text");
+        txt.insert(&mut doc.transact_mut(), 0, "hello world");
+        {
+            // formatting with no attributes splits blocks without inserting anything in between
+            let mut txn = doc.transact_mut();
+            for i in 0..txt.len(&txn) {
+                txt.format(&mut txn, i, 1, HashMap::new());
+            }
+        }
+        let blocks_count = |doc: &Doc| doc.transact().store().blocks.get_client(&1).unwrap().len();
+        assert_eq!(blocks_count(&doc), 11);
+
+        let sticky = txt
+            .sticky_index(&mut doc.transact_mut(), 6, Assoc::After)
+            .unwrap();
+        let sv = doc.transact().state_vector();
+
+        doc.compact(&mut doc.transact_mut());
+        assert_eq!(blocks_count(&doc), 1);
+
+        let txn = doc.transact();
+        assert_eq!(txt.get_string(&txn), "hello world");
+        assert_eq!(txn.state_vector(), sv);
+        assert_eq!(sticky.get_offset(&txn).unwrap().index, 6);
+
+        let remote = Doc::with_client_id(2);
+        let remote_txt = remote.get_or_insert_text("text");
+        let update = txn.encode_state_as_update_v1(&StateVector::default());
+        remote
+            .transact_mut()
+            .apply_update(Update::decode_v1(&update).unwrap());
+        assert_eq!(remote_txt.get_string(&remote.transact()), "hello world");
+    }
+
+    #[test]
+    fn compact_keeps_unmergeable_blocks() {
+        let doc = Doc::with_client_id(1);
+        let txt = doc.get_or_insert_text("text");
+        txt.insert(&mut doc.transact_mut(), 0, "hello world");
+        {
+            let mut txn = doc.transact_mut();
+            for i in 0..txt.len(&txn) {
+                txt.format(&mut txn, i, 1, HashMap::new());
+            }
+        }
+        // deleted blocks cannot be squashed with alive ones
+        txt.remove_range(&mut doc.transact_mut(), 5, 1);
+        let blocks_count = |doc: &Doc| doc.transact().store().blocks.get_client(&1).unwrap().len();
+        assert_eq!(blocks_count(&doc), 11);
+
+        doc.compact(&mut doc.transact_mut());
+        assert_eq!(blocks_count(&doc), 3);
+        assert_eq!(txt.get_string(&doc.transact()), "helloworld");
+    }
+
+    #[test]
+    fn uuid_parse() {
+        let uuid = crate::uuid_parse("67E55044-10b1-426F-9247-bb680e5fe0c8").unwrap();
+        assert_eq!(uuid.as_ref(), "67e55044-10b1-426f-9247-bb680e5fe0c8");
+
+        assert_eq!(
+            crate::uuid_parse("67e55044-10b1-426f-9247-bb680e5fe0c"),
+            Err(UuidError::InvalidLength(35))
+        );
+        assert_eq!(
+            crate::uuid_parse("67e55044-10b1-426f-9247-bb680e5fe0cg"),
+            Err(UuidError::InvalidCharacter('g', 35))
+        );
+        assert_eq!(
+            crate::uuid_parse("67e5504-410b1-426f-9247-bb680e5fe0c8"),
+            Err(UuidError::InvalidCharacter('-', 7))
+        );
+        assert_eq!(
+            crate::uuid_parse("67e55044-10b1-426f-9247-bb680e5fe0\u{e9}"),
+            Err(UuidError::InvalidCharacter('\u{e9}', 34))
+        );
+        assert!(Options::with_parsed_guid("").is_err());
+    }
+
+    #[test]
+    fn uuid_roundtrip() {
+        let bytes = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+        let uuid = uuid_from_bytes(bytes);
+        assert_eq!(uuid.as_ref(), "00010203-0405-0607-0809-0a0b0c0d0e0f");
+        assert_eq!(crate::uuid_parse(&uuid.to_string()), Ok(uuid));
+        let uuid = uuid_from_bytes([0xa5; 16]);
+        assert_eq!(uuid.as_ref(), "a5a5a5a5-a5a5-a5a5-a5a5-a5a5a5a5a5a5");
+
+        let mut rng = fastrand::Rng::with_seed(1);
+        for _ in 0..100 {
+            let uuid = uuid_v4_from(&mut rng);
+            assert_eq!(crate::uuid_parse(&uuid.to_string()), Ok(uuid));
+        }
+
+        let doc = Doc::with_options(Options::with_parsed_guid(&uuid_v4()).unwrap());
+        assert_eq!(crate::uuid_parse(doc.guid()).as_ref(), Ok(doc.guid()));
+    }
+
+    #[test]
+    fn with_guid_and_client_id() {
+        let guid: Uuid = "67e55044-10b1-426f-9247-bb680e5fe0c8".into();
+        let seed = fastrand::get_seed();
+        let d1 = Doc::with_guid_and_client_id(guid.clone(), 1);
+        let d2 = Doc::with_guid_and_client_id(guid.clone(), 1);
+        // thread-local random number generator has not been used
+        assert_eq!(fastrand::get_seed(), seed);
+
+        assert_eq!(d1.guid(), &guid);
+        assert_eq!(d1.client_id(), 1);
+        assert_eq!(d1.options(), d2.options());
+        let _ = Doc::with_client_id(1);
+        assert_ne!(fastrand::get_seed(), seed);
+    }
+
+    #[test]
+    fn merge_doc_with_moves_and_links() {
+        let src = Doc::with_client_id(1);
+        let values = src.get_or_insert_array("values");
+        let quotes = src.get_or_insert_array("quotes");
+        {
+            let mut txn = src.transact_mut();
+            values.insert_range(&mut txn, 0, [1, 2, 3]);
+            values.move_to(&mut txn, 0, 3);
+            // move overriding the previous one
+            values.move_to(&mut txn, 2, 0);
+            #[cfg(feature = "weak")]
+            {
+                use crate::Quotable;
+                let quote = values.quote(&txn, 0..=1).unwrap();
+                quotes.push_back(&mut txn, quote);
+            }
+        }
+
+        let local = Doc::with_client_id(2);
+        local.transact_mut().merge_doc(&src);
+        // copied blocks must not refer to the source document anymore
+        drop((values, quotes, src));
+
+        let values = local.get_or_insert_array("values");
+        assert_eq!(values.to_json(&local.transact()), any!([1, 2, 3]));
+        #[cfg(feature = "weak")]
+        {
+            let quotes = local.get_or_insert_array("quotes");
+            let txn = local.transact();
+            let quote: crate::WeakRef<ArrayRef> = quotes.get(&txn, 0).unwrap().cast().unwrap();
+            let quoted: Vec<_> = quote.unquote(&txn).collect();
+            assert_eq!(quoted, vec![1.into(), 2.into()]);
+        }
+
+        // removing moved element makes moves re-integrate the elements they override
+        values.remove(&mut local.transact_mut(), 0);
+        values.move_to(&mut local.transact_mut(), 1, 0);
+        assert_eq!(values.to_json(&local.transact()), any!([3, 2]));
+    }
+
+    #[test]
+    fn merge_doc() {
+        let src = Doc::with_client_id(1);
+        let txt = src.get_or_insert_text("text");
+        let map = src.get_or_insert_map("map");
+        txt.insert(&mut src.transact_mut(), 0, "hello");
+
+        let local = Doc::with_client_id(2);
+        exchange_updates(&[&src, &local]);
+        local
+            .get_or_insert_array("array")
+            .insert(&mut local.transact_mut(), 0, "local");
+        let copy = Doc::with_client_id(2);
+        copy.transact_mut().apply_update(
+            Update::decode_v1(
+                &local
+                    .transact()
+                    .encode_state_as_update_v1(&StateVector::default()),
+            )
+            .unwrap(),
+        );
+
+        {
+            let mut txn = src.transact_mut();
+            // this is squashed into the same block as "hello", which local doc has already seen
+            txt.insert(&mut txn, 5, " world");
+        }
+        {
+            let mut txn = src.transact_mut();
+            txt.remove_range(&mut txn, 0, 1);
+            map.insert(&mut txn, "nested", ArrayPrelim::from([1, 2, 3]));
+            map.insert(&mut txn, "subdoc", Doc::new());
+            map.insert(&mut txn, "removed", "value");
+            map.remove(&mut txn, "removed");
+        }
+
+        local.transact_mut().merge_doc(&src);
+        {
+            let mut txn = copy.transact_mut();
+            let sv = txn.state_vector();
+            let update = src.transact().encode_diff_v1(&sv);
+            txn.apply_update(Update::decode_v1(&update).unwrap());
+        }
+
+        let txn = local.transact();
+        assert_eq!(txn.get_text("text").unwrap().get_string(&txn), "ello world");
+        let copy_txn = copy.transact();
+        assert_eq!(txn.snapshot(), copy_txn.snapshot());
+        assert_eq!(
+            txn.get_map("map").unwrap().to_json(&txn),
+            copy_txn.get_map("map").unwrap().to_json(&copy_txn)
+        );
+        assert_eq!(
+            txn.get_array("array").unwrap().to_json(&txn),
+            copy_txn.get_array("array").unwrap().to_json(&copy_txn)
+        );
+        drop((txn, copy_txn));
+
+        // merging again or merging with itself changes nothing
+        local.transact_mut().merge_doc(&src);
+        local.transact_mut().merge_doc(&local);
+        assert_eq!(
+            local.transact().state_vector(),
+            copy.transact().state_vector()
+        );
+    }
+
+    #[test]
+    fn apply_update_and() {
+        let d1 = Doc::with_client_id(1);
+        let txt1 = d1.get_or_insert_text("text");
+        let mut updates = Vec::new();
+        for chunk in ["a", "b", "c"] {
+            let sv = d1.transact().state_vector();
+            txt1.push(&mut d1.transact_mut(), chunk);
+            updates.push(d1.transact().encode_diff_v1(&sv));
+        }
+
+        let d2 = Doc::with_client_id(2);
+        let txt2 = d2.get_or_insert_text("text");
+        let commits = Arc::new(AtomicU32::new(0));
+        let _sub = {
+            let commits = commits.clone();
+            d2.observe_update_v1(move |_, _| {
+                commits.fetch_add(1, Ordering::SeqCst);
+            })
+            .unwrap()
+        };
+        {
+            let mut txn = d2.transact_mut();
+            let mut seen = Vec::new();
+            for update in updates.iter() {
+                let update = Update::decode_v1(update).unwrap();
+                seen.push(txn.apply_update_and(update, |txn| txt2.get_string(txn)));
+            }
+            assert_eq!(seen, vec!["a", "ab", "abc"]);
+            assert_eq!(commits.load(Ordering::SeqCst), 0);
+        }
+        // all updates have been committed together
+        assert_eq!(commits.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn type_counts() {
+        let doc = Doc::with_client_id(1);
+        assert_eq!(doc.transact().type_counts(), TypeCounts::default());
+
+        let root = doc.get_or_insert_map("root");
+        let notes = doc.get_or_insert_text("notes");
+        let xml = doc.get_or_insert_xml_fragment("xml");
+        {
+            let mut txn = doc.transact_mut();
+            notes.push(&mut txn, "hello");
+            root.insert(&mut txn, "text", TextPrelim::new("world"));
+            let list = root.insert(&mut txn, "list", ArrayPrelim::default());
+            let nested = list.push_back(&mut txn, MapPrelim::default());
+            nested.insert(&mut txn, "array", ArrayPrelim::default());
+            // deleted types are not counted
+            let removed = list.push_back(&mut txn, MapPrelim::default());
+            removed.insert(&mut txn, "text", TextPrelim::new("removed"));
+            list.remove(&mut txn, 1);
+
+            let p = xml.insert(&mut txn, 0, XmlElementPrelim::empty("p"));
+            p.insert(&mut txn, 0, XmlTextPrelim::new("paragraph"));
+        }
+
+        let counts = doc.transact().type_counts();
+        assert_eq!(
+            counts,
+            TypeCounts {
+                text: 2,
+                array: 2,
+                map: 2,
+                xml_fragment: 1,
+                xml_element: 1,
+                xml_text: 1,
+                other: 0,
+                max_depth: 4,
+            }
+        );
+    }
+
+    #[test]
+    fn doc_meta() {
+        #[derive(Debug, PartialEq)]
+        struct RowId(u64);
+
+        let guid = uuid_v4();
+        let d1 = Doc::with_guid_and_client_id(guid.clone(), 1);
+        let d2 = Doc::with_guid_and_client_id(guid, 2);
+        assert_eq!(d1.meta::<RowId>(), None);
+
+        d1.set_meta(RowId(123));
+        d1.set_meta("tenant-a".to_string());
+        assert_eq!(d1.meta::<RowId>(), Some(Arc::new(RowId(123))));
+        assert_eq!(d1.meta::<String>(), Some(Arc::new("tenant-a".to_string())));
+        assert_eq!(d1.meta::<u64>(), None);
+
+        // metadata is shared with clones of the same instance, but not with other instances
+        assert_eq!(d1.clone().meta::<RowId>(), Some(Arc::new(RowId(123))));
+        assert_eq!(d2.meta::<RowId>(), None);
+        d2.set_meta(RowId(456));
+        assert_eq!(d1.meta::<RowId>(), Some(Arc::new(RowId(123))));
+
+        // it's available while read-only transaction is active and replaced by a value of the same type
+        {
+            let _txn = d1.transact();
+            d1.set_meta(RowId(789));
+            assert_eq!(d1.meta::<RowId>(), Some(Arc::new(RowId(789))));
+        }
+
+        // it's not available while read-write transaction is active
+        let txn = d1.transact_mut();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| d1.meta::<RowId>()));
+        assert!(result.is_err());
+        drop(txn);
+        assert_eq!(d1.meta::<RowId>(), Some(Arc::new(RowId(789))));
+    }
+
+    #[test]
+    fn apply_update_filtered() {
+        let d1 = Doc::with_client_id(1);
+        let txt1 = d1.get_or_insert_text("text");
+        let d2 = Doc::with_client_id(2);
+        let txt2 = d2.get_or_insert_text("text");
+        let d3 = Doc::with_client_id(3);
+        let txt3 = d3.get_or_insert_text("text");
+
+        txt1.push(&mut d1.transact_mut(), "hello");
+        exchange_updates(&[&d1, &d2]);
+        txt2.push(&mut d2.transact_mut(), " world");
+        exchange_updates(&[&d2, &d3]);
+        // client 3 changes depend on client 2 changes
+        txt3.push(&mut d3.transact_mut(), "?");
+        txt1.push(&mut d1.transact_mut(), "!");
+        exchange_updates(&[&d1, &d2, &d3]);
+        assert_eq!(txt1.get_string(&d1.transact()), "hello! world?");
+
+        let update = d1
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+        let doc = Doc::with_client_id(4);
+        let txt = doc.get_or_insert_text("text");
+        let mut txn = doc.transact_mut();
+        // reject blocks of client 2
+        txn.apply_update_filtered(Update::decode_v1(&update).unwrap(), |block| {
+            block.id.client != 2
+        });
+        assert_eq!(txt.get_string(&txn), "hello!");
+        let pending = txn.pending().unwrap();
+        assert_eq!(pending.update.author_clients(), HashSet::from([3]));
+        assert_eq!(pending.missing.get(&2), 0);
+    }
+
+    #[test]
+    fn try_apply_update_client_id_collision() {
+        let d1 = Doc::with_client_id(1);
+        let txt1 = d1.get_or_insert_text("text");
+        txt1.push(&mut d1.transact_mut(), "hello");
+
+        // another peer was (carelessly) assigned the same client ID
+        let d2 = Doc::with_client_id(1);
+        let txt2 = d2.get_or_insert_text("text");
+        txt2.push(&mut d2.transact_mut(), "world!");
+        let update = d2
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+
+        {
+            let mut txn = d1.transact_mut();
+            let result = txn.try_apply_update(Update::decode_v1(&update).unwrap());
+            assert_eq!(result, Err(UpdateError::ClientIdCollision(1)));
+            assert_eq!(txt1.get_string(&txn), "hello");
+            assert_eq!(txn.state_vector().get(&1), 5);
+        }
+
+        // colliding peer produced less content than we did, so its clocks overlap with ours
+        let d2 = Doc::with_client_id(1);
+        let txt2 = d2.get_or_insert_text("text");
+        txt2.push(&mut d2.transact_mut(), "hey");
+        let update = d2
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+        {
+            let mut txn = d1.transact_mut();
+            let result = txn.try_apply_update(Update::decode_v1(&update).unwrap());
+            assert_eq!(result, Err(UpdateError::ClientIdCollision(1)));
+            assert_eq!(txt1.get_string(&txn), "hello");
+        }
+
+        // updates echoing back our own changes are fine
+        let update = d1
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+        let d3 = Doc::with_client_id(3);
+        let txt3 = d3.get_or_insert_text("text");
+        d3.transact_mut()
+            .apply_update(Update::decode_v1(&update).unwrap());
+        txt3.push(&mut d3.transact_mut(), "!");
+        let update = d3
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+        let mut txn = d1.transact_mut();
+        txn.try_apply_update(Update::decode_v1(&update).unwrap())
+            .unwrap();
+        assert_eq!(txt1.get_string(&txn), "hello!");
+
+        // including the ones covering local blocks, which have been split in the meantime
+        txt1.insert(&mut txn, 2, "_");
+        txn.try_apply_update(Update::decode_v1(&update).unwrap())
+            .unwrap();
+        assert_eq!(txt1.get_string(&txn), "he_llo!");
+    }
+
+    #[test]
+    fn diff_to_peer() {
+        let local = Doc::with_client_id(1);
+        let txt = local.get_or_insert_text("text");
+        txt.push(&mut local.transact_mut(), "hello");
+
+        let remote = Doc::with_client_id(2);
+        let remote_txt = remote.get_or_insert_text("text");
+        remote_txt.push(&mut remote.transact_mut(), "world");
+
+        let sv = remote.transact().state_vector();
+        let diff = local.transact().diff_to_peer(&sv.encode_v1()).unwrap();
+        assert_eq!(diff, local.transact().encode_diff_v1(&sv));
+        let diff_v2 = local.transact().diff_to_peer_v2(&sv.encode_v2()).unwrap();
+        assert_eq!(diff_v2, local.transact().encode_diff_v2(&sv));
+        remote
+            .transact_mut()
+            .apply_update(Update::decode_v1(&diff).unwrap());
+        assert_eq!(
+            remote
+                .transact()
+                .diff_status(&local.transact().state_vector()),
+            crate::SyncStatus::Ahead
+        );
+
+        // malformed state vectors
+        let mut truncated = sv.encode_v1();
+        truncated.pop();
+        assert!(local.transact().diff_to_peer(&truncated).is_err());
+        assert!(local.transact().diff_to_peer(&[0xff]).is_err());
+        assert!(local.transact().diff_to_peer_v2(&[0xff]).is_err());
+    }
+
+    #[test]
+    fn try_resolve_pending() {
+        let d1 = Doc::with_client_id(1);
+        let txt1 = d1.get_or_insert_text("text");
+        let mut updates = Vec::new();
+        for chunk in ["a", "b", "c"] {
+            let sv = d1.transact().state_vector();
+            txt1.push(&mut d1.transact_mut(), chunk);
+            updates.push(d1.transact().encode_diff_v1(&sv));
+        }
+
+        let d2 = Doc::with_client_id(2);
+        let txt2 = d2.get_or_insert_text("text");
+        let mut txn = d2.transact_mut();
+        assert!(txn.pending().is_none());
+        assert!(txn.try_resolve_pending());
+
+        txn.apply_update(Update::decode_v1(&updates[2]).unwrap());
+        let pending = txn.pending().unwrap();
+        assert!(pending.missing.contains_client(&1));
+        assert_eq!(pending.update.state_vector().get(&1), 3);
+        // missing blocks have not been supplied yet
+        assert!(!txn.try_resolve_pending());
+        assert!(txn.pending().is_some());
+        assert_eq!(txt2.get_string(&txn), "");
+
+        txn.apply_update(Update::decode_v1(&updates[0]).unwrap());
+        txn.apply_update(Update::decode_v1(&updates[1]).unwrap());
+        assert!(txn.try_resolve_pending());
+        assert!(txn.pending().is_none());
+        assert_eq!(txt2.get_string(&txn), "abc");
+    }
+
+    #[test]
+    fn observe_after_transaction_update() {
+        let doc = Doc::with_client_id(1);
+        let txt = doc.get_or_insert_text("text");
+
+        let v1_updates = Arc::new(Mutex::new(Vec::new()));
+        let after_updates = Arc::new(Mutex::new(Vec::new()));
+        let _sub_v1 = {
+            let v1_updates = v1_updates.clone();
+            doc.observe_update_v1(move |_, e| v1_updates.lock().unwrap().push(e.update.clone()))
+                .unwrap()
+        };
+        let _sub_after = {
+            let after_updates = after_updates.clone();
+            doc.observe_after_transaction_update(move |_, e| {
+                after_updates
+                    .lock()
+                    .unwrap()
+                    .push(e.map(|e| e.update.clone()))
+            })
+            .unwrap()
+        };
+
+        txt.push(&mut doc.transact_mut(), "hello");
+        txt.remove_range(&mut doc.transact_mut(), 0, 1);
+        // transaction without any changes
+        doc.transact_mut();
+
+        let v1_updates = v1_updates.lock().unwrap();
+        let after_updates = after_updates.lock().unwrap();
+        assert_eq!(v1_updates.len(), 2);
+        assert_eq!(
+            *after_updates,
+            vec![
+                Some(v1_updates[0].clone()),
+                Some(v1_updates[1].clone()),
+                None
+            ]
         );
     }
 }
@@ -1,12 +1,13 @@
-use crate::any::Any;
+use crate::any::{Any, OrderedMap};
 use crate::encoding::read::Error;
 use serde::de::value::{MapAccessDeserializer, MapDeserializer, SeqDeserializer};
-use serde::de::{IntoDeserializer, MapAccess, SeqAccess, Visitor};
+use serde::de::{DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess, Visitor};
 use serde::{Deserialize, Deserializer};
 use std::any::type_name;
 use std::collections::HashMap;
 use std::convert::{TryFrom, TryInto};
 use std::fmt::{Display, Formatter};
+use std::marker::PhantomData;
 use std::sync::Arc;
 
 pub fn from_any<'de, T: Deserialize<'de>>(any: &'de Any) -> Result<T, Error> {
@@ -19,7 +20,32 @@ impl<'de> Deserialize<'de> for Any {
     where
         D: Deserializer<'de>,
     {
-        struct AnyVisitor;
+        AnySeed::default().deserialize(deserializer)
+    }
+}
+
+/// Deserializes [Any] values, optionally keeping all 64-bit integers as [Any::BigInt]s.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct AnySeed {
+    integers: bool,
+}
+
+impl AnySeed {
+    /// Returns a seed, which deserializes 64-bit integers into [Any::BigInt]s, even if they could
+    /// be safely represented as [Any::Number]s.
+    pub fn integers() -> Self {
+        AnySeed { integers: true }
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for AnySeed {
+    type Value = Any;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct AnyVisitor(AnySeed);
         impl<'de> Visitor<'de> for AnyVisitor {
             type Value = Any;
 
@@ -59,7 +85,11 @@ impl<'de> Deserialize<'de> for Any {
             where
                 E: serde::de::Error,
             {
-                Ok(Any::from(v))
+                if self.0.integers {
+                    Ok(Any::BigInt(v))
+                } else {
+                    Ok(Any::from(v))
+                }
             }
 
             fn visit_u8<E>(self, v: u8) -> Result<Self::Value, E>
@@ -87,7 +117,12 @@ impl<'de> Deserialize<'de> for Any {
             where
                 E: serde::de::Error,
             {
-                match Any::try_from(v) {
+                let value = if self.0.integers {
+                    i64::try_from(v).map(Any::BigInt).map_err(|_| v)
+                } else {
+                    Any::try_from(v)
+                };
+                match value {
                     Ok(any) => Ok(any),
                     Err(v) => Err(serde::de::Error::custom(format!(
                         "Value {} out of range for i64",
@@ -170,7 +205,7 @@ impl<'de> Deserialize<'de> for Any {
             where
                 D: Deserializer<'de>,
             {
-                Any::deserialize(deserializer)
+                self.0.deserialize(deserializer)
             }
 
             fn visit_unit<E>(self) -> Result<Self::Value, E>
@@ -186,7 +221,7 @@ impl<'de> Deserialize<'de> for Any {
             {
                 let mut vec = Vec::new();
 
-                while let Some(value) = seq.next_element()? {
+                while let Some(value) = seq.next_element_seed(self.0)? {
                     vec.push(value);
                 }
 
@@ -198,7 +233,7 @@ impl<'de> Deserialize<'de> for Any {
                 A: MapAccess<'de>,
             {
                 let mut any_map = HashMap::new();
-                while let Some((key, value)) = map.next_entry()? {
+                while let Some((key, value)) = map.next_entry_seed(PhantomData::<String>, self.0)? {
                     any_map.insert(key, value);
                 }
 
@@ -206,7 +241,37 @@ impl<'de> Deserialize<'de> for Any {
             }
         }
 
-        deserializer.deserialize_any(AnyVisitor)
+        deserializer.deserialize_any(AnyVisitor(self))
+    }
+}
+
+impl<'de> Deserialize<'de> for OrderedMap {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct OrderedMapVisitor;
+        impl<'de> Visitor<'de> for OrderedMapVisitor {
+            type Value = OrderedMap;
+
+            fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+                formatter.write_str("map")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut builder = Any::ordered_map_with_capacity(map.size_hint().unwrap_or(0));
+                while let Some((key, value)) = map.next_entry::<String, Any>()? {
+                    builder.insert(key, value);
+                }
+
+                Ok(builder.build())
+            }
+        }
+
+        deserializer.deserialize_map(OrderedMapVisitor)
     }
 }
 
@@ -250,6 +315,7 @@ impl<'de> Deserializer<'de> for AnyDeserializer<'de> {
             Any::Bool(_) => self.deserialize_bool(visitor),
             Any::Number(_) => self.deserialize_f64(visitor),
             Any::BigInt(_) => self.deserialize_i64(visitor),
+            Any::BigUInt(_) => self.deserialize_u64(visitor),
             Any::String(_) => self.deserialize_string(visitor),
             Any::Buffer(_) => self.deserialize_byte_buf(visitor),
             Any::Array(_) => self.deserialize_seq(visitor),
@@ -280,6 +346,7 @@ impl<'de> Deserializer<'de> for AnyDeserializer<'de> {
                 }
             }
             Any::BigInt(i) => *i,
+            Any::BigUInt(i) => i64::try_from(*i).map_err(|_| Error::UnexpectedValue)?,
             _ => return Err(Error::type_mismatch::<i8>()),
         }
         .try_into()
@@ -300,6 +367,7 @@ impl<'de> Deserializer<'de> for AnyDeserializer<'de> {
                 }
             }
             Any::BigInt(i) => *i,
+            Any::BigUInt(i) => i64::try_from(*i).map_err(|_| Error::UnexpectedValue)?,
             _ => return Err(Error::type_mismatch::<i16>()),
         }
         .try_into()
@@ -320,6 +388,7 @@ impl<'de> Deserializer<'de> for AnyDeserializer<'de> {
                 }
             }
             Any::BigInt(i) => *i,
+            Any::BigUInt(i) => i64::try_from(*i).map_err(|_| Error::UnexpectedValue)?,
             _ => return Err(Error::type_mismatch::<i32>()),
         }
         .try_into()
@@ -340,6 +409,7 @@ impl<'de> Deserializer<'de> for AnyDeserializer<'de> {
                 }
             }
             Any::BigInt(i) => *i,
+            Any::BigUInt(i) => i64::try_from(*i).map_err(|_| Error::UnexpectedValue)?,
             _ => return Err(Error::type_mismatch::<i64>()),
         };
         visitor.visit_i64(value)
@@ -358,6 +428,7 @@ impl<'de> Deserializer<'de> for AnyDeserializer<'de> {
                 }
             }
             Any::BigInt(i) => *i,
+            Any::BigUInt(i) => i64::try_from(*i).map_err(|_| Error::UnexpectedValue)?,
             _ => return Err(Error::type_mismatch::<u8>()),
         }
         .try_into()
@@ -378,6 +449,7 @@ impl<'de> Deserializer<'de> for AnyDeserializer<'de> {
                 }
             }
             Any::BigInt(i) => *i,
+            Any::BigUInt(i) => i64::try_from(*i).map_err(|_| Error::UnexpectedValue)?,
             _ => return Err(Error::type_mismatch::<u16>()),
         }
         .try_into()
@@ -398,6 +470,7 @@ impl<'de> Deserializer<'de> for AnyDeserializer<'de> {
                 }
             }
             Any::BigInt(i) => *i,
+            Any::BigUInt(i) => i64::try_from(*i).map_err(|_| Error::UnexpectedValue)?,
             _ => return Err(Error::type_mismatch::<u32>()),
         }
         .try_into()
@@ -418,6 +491,7 @@ impl<'de> Deserializer<'de> for AnyDeserializer<'de> {
                 }
             }
             Any::BigInt(i) => *i,
+            Any::BigUInt(i) => return visitor.visit_u64(*i),
             _ => return Err(Error::type_mismatch::<u64>()),
         }
         .try_into()
@@ -432,6 +506,7 @@ impl<'de> Deserializer<'de> for AnyDeserializer<'de> {
         match self.value {
             Any::Number(f) => visitor.visit_f32(*f as f32),
             Any::BigInt(f) => visitor.visit_f32(*f as f32),
+            Any::BigUInt(f) => visitor.visit_f32(*f as f32),
             _ => Err(Error::type_mismatch::<f32>()),
         }
     }
@@ -443,6 +518,7 @@ impl<'de> Deserializer<'de> for AnyDeserializer<'de> {
         match self.value {
             Any::Number(f) => visitor.visit_f64(*f),
             Any::BigInt(f) => visitor.visit_f64(*f as f64),
+            Any::BigUInt(f) => visitor.visit_f64(*f as f64),
             _ => Err(Error::type_mismatch::<f64>()),
         }
     }
@@ -665,6 +741,12 @@ mod test {
         assert!(serde_json::from_str::<Any>(&u64::MAX.to_string()).is_err());
     }
 
+    #[test]
+    fn test_deserialize_u64_from_big_uint() {
+        let value: u64 = from_any(&Any::big_uint(u64::MAX)).unwrap();
+        assert_eq!(value, u64::MAX);
+    }
+
     #[test]
     fn test_deserialize_any_from_string() {
         assert_eq!(
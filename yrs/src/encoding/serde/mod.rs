@@ -2,6 +2,7 @@ mod de;
 mod ser;
 
 pub use de::from_any;
+pub(crate) use de::AnySeed;
 pub use ser::to_any;
 
 #[cfg(test)]
@@ -46,6 +47,24 @@ mod test {
         }
     }
 
+    #[test]
+    fn json_any_bigint() {
+        for v in [5, -10, 0, i64::MAX, i64::MIN] {
+            let mut buf = String::new();
+            Any::BigInt(v).to_json(&mut buf);
+            assert_eq!(buf, v.to_string());
+            let actual = Any::from_json_with_integers(&buf).unwrap();
+            assert_eq!(actual, Any::BigInt(v));
+        }
+
+        let actual = Any::from_json_with_integers("[5.5, {\"a\": 5}]").unwrap();
+        assert_eq!(actual, any!([5.5, {"a": Any::BigInt(5)}]));
+
+        // by default integers within safe range are parsed as numbers
+        assert_eq!(roundtrip(&Any::BigInt(5)), Any::Number(5.0));
+        assert_eq!(roundtrip(&Any::Number(5.0)), Any::Number(5.0));
+    }
+
     #[test]
     fn json_any_string() {
         for v in ["", "hello ", "hello \nworld", "hello \"world\"", "hello 😀"] {
@@ -1,4 +1,4 @@
-use crate::any::Any;
+use crate::any::{Any, OrderedMap};
 use serde::ser::{
     SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant, SerializeTuple,
     SerializeTupleStruct, SerializeTupleVariant,
@@ -33,6 +33,7 @@ impl Serialize for Any {
                 }
             }
             Any::BigInt(value) => serializer.serialize_i64(*value),
+            Any::BigUInt(value) => serializer.serialize_u64(*value),
             Any::String(value) => serializer.serialize_str(value.as_ref()),
             Any::Array(values) => {
                 let mut seq = serializer.serialize_seq(Some(values.len()))?;
@@ -53,6 +54,19 @@ impl Serialize for Any {
     }
 }
 
+impl Serialize for OrderedMap {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(self.len()))?;
+        for (key, value) in self.iter() {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+}
+
 struct AnySerializer;
 
 #[derive(Debug, Error)]
@@ -1,5 +1,6 @@
 use crate::doc::DocAddr;
 use crate::transaction::Subdocs;
+use crate::types::Path;
 use crate::{DeleteSet, Doc, StateVector, TransactionMut};
 use std::collections::HashMap;
 
@@ -47,14 +48,25 @@ pub struct SubdocsEvent {
     pub(crate) added: HashMap<DocAddr, Doc>,
     pub(crate) removed: HashMap<DocAddr, Doc>,
     pub(crate) loaded: HashMap<DocAddr, Doc>,
+    pub(crate) paths: HashMap<DocAddr, Path>,
 }
 
 impl SubdocsEvent {
     pub(crate) fn new(inner: Box<Subdocs>) -> Self {
+        let mut paths = HashMap::new();
+        for (addr, doc) in inner.added.iter().chain(inner.loaded.iter()) {
+            // removed sub-documents are no longer mounted
+            if !inner.removed.contains_key(addr) {
+                if let Some(path) = doc.mount_path() {
+                    paths.insert(*addr, path);
+                }
+            }
+        }
         SubdocsEvent {
             added: inner.added,
             removed: inner.removed,
             loaded: inner.loaded,
+            paths,
         }
     }
 
@@ -71,10 +83,19 @@ impl SubdocsEvent {
     }
 
     /// Returns an iterator over all sub-documents living in a parent document, that have requested
-    /// to be loaded within a scope of committed transaction.
+    /// to be loaded within a scope of committed transaction. Use [Doc::observe_content_loaded]
+    /// on a sub-document to get notified once its content has actually been applied.
     pub fn loaded(&self) -> SubdocsEventIter {
         SubdocsEventIter(self.loaded.values())
     }
+
+    /// Returns a path from the document root to a place where a given `doc` is mounted, if it's one
+    /// of the [SubdocsEvent::added] or [SubdocsEvent::loaded] sub-documents. First segment of
+    /// the path is always a name of the root-level type containing it. Paths are captured at
+    /// the moment when the event is emitted. No path is reported for removed sub-documents.
+    pub fn path(&self, doc: &Doc) -> Option<&Path> {
+        self.paths.get(&doc.addr())
+    }
 }
 
 #[repr(transparent)]
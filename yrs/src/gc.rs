@@ -1,5 +1,6 @@
 use crate::block::{BlockCell, ClientID, GC};
-use crate::{TransactionMut, ID};
+use crate::block_store::BlockStore;
+use crate::{DeleteSet, TransactionMut, ID};
 use std::collections::HashMap;
 
 #[derive(Default)]
@@ -10,13 +11,21 @@ pub(crate) struct GCCollector {
 impl GCCollector {
     pub fn collect(txn: &mut TransactionMut) {
         let mut gc = Self::default();
-        gc.mark_all(txn);
+        gc.mark_all(&txn.delete_set, &mut txn.store.blocks);
         gc.collect_all_marked(txn);
     }
 
-    fn mark_all(&mut self, txn: &mut TransactionMut) {
-        for (client, range) in txn.delete_set.iter() {
-            if let Some(blocks) = txn.store.blocks.get_client_mut(client) {
+    /// Garbage collects deleted blocks described by a given `delete_set`, which may span over
+    /// blocks deleted by many past transactions.
+    pub fn collect_delete_set(txn: &mut TransactionMut, delete_set: &DeleteSet) {
+        let mut gc = Self::default();
+        gc.mark_all(delete_set, &mut txn.store.blocks);
+        gc.collect_all_marked(txn);
+    }
+
+    fn mark_all(&mut self, delete_set: &DeleteSet, blocks: &mut BlockStore) {
+        for (client, range) in delete_set.iter() {
+            if let Some(blocks) = blocks.get_client_mut(client) {
                 for delete_item in range.iter().rev() {
                     let mut start = delete_item.start;
                     if let Some(mut i) = blocks.find_pivot(start) {
@@ -398,6 +398,21 @@ impl IdSet {
     }
 }
 
+impl IdSet {
+    /// Encodes current ID set the same way as [Encode::encode] does, but with clients written in
+    /// a descending order, so that equal ID sets always produce the same binary output.
+    pub(crate) fn encode_sorted<E: Encoder>(&self, encoder: &mut E) {
+        let mut clients: Vec<_> = self.0.iter().collect();
+        clients.sort_by(|(a, _), (b, _)| b.cmp(a));
+        encoder.write_var(clients.len() as u32);
+        for (&client_id, block) in clients {
+            encoder.reset_ds_cur_val();
+            encoder.write_var(client_id);
+            block.encode(encoder);
+        }
+    }
+}
+
 impl Encode for IdSet {
     fn encode<E: Encoder>(&self, encoder: &mut E) {
         encoder.write_var(self.0.len() as u32);
@@ -551,6 +566,33 @@ impl DeleteSet {
         self.0.iter()
     }
 
+    /// Checks if all clock ranges deleted according to current delete set are also deleted
+    /// according to `other` delete set.
+    pub fn is_subset_of(&self, other: &DeleteSet) -> bool {
+        for (client, range) in self.iter() {
+            let mut other_ranges: Vec<_> = match other.range(client) {
+                Some(range) => range.iter().filter(|r| r.start < r.end).collect(),
+                None => Vec::new(),
+            };
+            other_ranges.sort_by_key(|r| r.start);
+            for r in range.iter().filter(|r| r.start < r.end) {
+                // other delete set may be not squashed, so ranges may overlap or be adjacent
+                let mut covered = r.start;
+                for o in other_ranges.iter() {
+                    if o.start > covered {
+                        break;
+                    } else if o.end > covered {
+                        covered = o.end;
+                    }
+                }
+                if covered < r.end {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
     /// Merges another delete set into a current one, combining their information about deleted
     /// clock ranges.
     pub fn merge(&mut self, other: Self) {
@@ -568,6 +610,11 @@ impl DeleteSet {
         self.0.get(client_id)
     }
 
+    /// Encodes current delete set with clients written in a deterministic order.
+    pub(crate) fn encode_sorted<E: Encoder>(&self, encoder: &mut E) {
+        self.0.encode_sorted(encoder)
+    }
+
     pub(crate) fn try_squash_with(&mut self, store: &mut Store) {
         // try to merge deleted / gc'd items
         for (&client, range) in self.iter() {
@@ -818,6 +865,26 @@ mod test {
         assert_eq!(range, IdRange::Fragmented(vec![0..6, 7..9]));
     }
 
+    #[test]
+    fn delete_set_is_subset_of() {
+        let mut a = DeleteSet::new();
+        a.insert(ID::new(1, 2), 6);
+        let mut b = DeleteSet::new();
+        // not squashed, overlapping and adjacent ranges
+        b.insert(ID::new(1, 5), 3);
+        b.insert(ID::new(1, 0), 4);
+        b.insert(ID::new(1, 3), 2);
+        assert!(a.is_subset_of(&b));
+        assert!(!b.is_subset_of(&a));
+        assert!(DeleteSet::new().is_subset_of(&a));
+
+        a.insert(ID::new(2, 0), 1);
+        assert!(!a.is_subset_of(&b));
+        b.insert(ID::new(1, 9), 1);
+        b.insert(ID::new(2, 0), 1);
+        assert!(a.is_subset_of(&b));
+    }
+
     #[test]
     fn id_range_encode_decode() {
         roundtrip(&IdRange::Continuous(0..4));
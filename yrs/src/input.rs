@@ -72,6 +72,26 @@ impl From<Any> for In {
     }
 }
 
+impl From<Doc> for In {
+    #[inline]
+    fn from(value: Doc) -> Self {
+        In::Doc(value)
+    }
+}
+
+impl<T> From<Option<T>> for In
+where
+    T: Into<In>,
+{
+    #[inline]
+    fn from(value: Option<T>) -> Self {
+        match value {
+            Some(value) => value.into(),
+            None => In::Any(Any::Null),
+        }
+    }
+}
+
 macro_rules! impl_from_any {
     ($t:ty) => {
         impl From<$t> for In {
@@ -608,16 +608,25 @@ pub use crate::alt::{
     diff_updates_v1, diff_updates_v2, encode_state_vector_from_update_v1,
     encode_state_vector_from_update_v2, merge_updates_v1, merge_updates_v2,
 };
-pub use crate::any::Any;
+pub use crate::any::{
+    Any, ArrayBuilder, ArrayMerge, MapBuilder, MapMerge, MergeStrategy, OrderedMap,
+    OrderedMapBuilder, PatchOp,
+};
+pub use crate::block::BlockInfo;
 pub use crate::block::ID;
 pub use crate::branch::BranchID;
 pub use crate::branch::Hook;
 pub use crate::branch::Nested;
 pub use crate::branch::Root;
 pub use crate::doc::Doc;
+pub use crate::doc::EncodeOptions;
+pub use crate::doc::EncodingVersion;
+pub use crate::doc::GcPolicy;
 pub use crate::doc::OffsetKind;
 pub use crate::doc::Options;
+pub use crate::doc::SubdocInfo;
 pub use crate::doc::Transact;
+pub use crate::doc::WeakDoc;
 pub use crate::event::{SubdocsEvent, SubdocsEventIter, TransactionCleanupEvent, UpdateEvent};
 pub use crate::id_set::DeleteSet;
 pub use crate::input::In;
@@ -628,23 +637,37 @@ pub use crate::moving::Offset;
 pub use crate::moving::StickyIndex;
 pub use crate::observer::{Observer, Subscription};
 pub use crate::out::Out;
+pub use crate::out::OutKind;
 pub use crate::state_vector::Snapshot;
 pub use crate::state_vector::StateVector;
+pub use crate::state_vector::SyncStatus;
 pub use crate::store::Store;
+pub use crate::transaction::ClientBlocks;
+pub use crate::transaction::LocalChange;
 pub use crate::transaction::Origin;
+pub use crate::transaction::PathError;
+pub use crate::transaction::ReadCache;
 pub use crate::transaction::ReadTxn;
 pub use crate::transaction::RootRefs;
 pub use crate::transaction::Transaction;
 pub use crate::transaction::TransactionMut;
+pub use crate::transaction::TypeCounts;
+pub use crate::transaction::UpdateError;
 pub use crate::transaction::WriteTxn;
 pub use crate::types::array::Array;
+pub use crate::types::array::ArrayChunks;
 pub use crate::types::array::ArrayPrelim;
 pub use crate::types::array::ArrayRef;
+pub use crate::types::array::BranchCursor;
+pub use crate::types::array::ConversionError;
 pub use crate::types::map::Map;
+pub use crate::types::map::MapOp;
 pub use crate::types::map::MapPrelim;
 pub use crate::types::map::MapRef;
+pub use crate::types::text::OtOp;
 pub use crate::types::text::Text;
 pub use crate::types::text::TextPrelim;
+pub use crate::types::text::TextRange;
 pub use crate::types::text::TextRef;
 #[cfg(feature = "weak")]
 pub use crate::types::weak::{Quotable, WeakPrelim, WeakRef};
@@ -662,6 +685,7 @@ pub use crate::types::GetString;
 pub use crate::types::Observable;
 pub use crate::types::RootRef;
 pub use crate::types::SharedRef;
+pub use crate::update::PendingUpdate;
 pub use crate::update::Update;
 
 #[deprecated(since = "0.19.0", note = "Use `yrs::Out` instead")]
@@ -686,24 +710,62 @@ pub fn uuid_v4_from(rng: &mut fastrand::Rng) -> Uuid {
     b[6] = b[6] & 0x0f | 0x40; // time_hi_and_version (bits 4-7 of 7th octet)
     b[8] = b[8] & 0x3f | 0x80; // clock_seq_hi_and_reserved (bit 6 & 7 of 9th octet)
 
-    let uuid = format!(
-        "{:x}{:x}{:x}{:x}-{:x}{:x}-{:x}{:x}-{:x}{:x}-{:x}{:x}{:x}{:x}{:x}{:x}",
-        b[0],
-        b[1],
-        b[2],
-        b[3],
-        b[4],
-        b[5],
-        b[6],
-        b[7],
-        b[8],
-        b[9],
-        b[10],
-        b[11],
-        b[12],
-        b[13],
-        b[14],
-        b[15]
-    );
-    uuid.into()
+    uuid_from_bytes(b)
+}
+
+/// Creates a UUID from its 16 byte binary representation. Returned UUID uses a canonical,
+/// lower-case hyphenated string form eg. `"67e55044-10b1-426f-9247-bb680e5fe0c8"`.
+pub fn uuid_from_bytes(b: [u8; 16]) -> Uuid {
+    const HEX: &[u8; 16] = b"0123456789abcdef";
+    let mut buf = [b'-'; 36];
+    let mut i = 0;
+    for (n, byte) in b.iter().enumerate() {
+        if n == 4 || n == 6 || n == 8 || n == 10 {
+            i += 1; // skip hyphen
+        }
+        buf[i] = HEX[(byte >> 4) as usize];
+        buf[i + 1] = HEX[(byte & 0x0f) as usize];
+        i += 2;
+    }
+    // buffer consists of ASCII characters only
+    std::str::from_utf8(&buf).unwrap().into()
+}
+
+/// Parses a UUID from its hyphenated string form eg. `"67E55044-10B1-426F-9247-BB680E5FE0C8"`.
+/// Hex digits are case-insensitive, but the returned UUID is always normalized to lower-case,
+/// so that for any UUID returned by this function or [uuid_from_bytes], formatting it with
+/// [std::fmt::Display] and parsing it back produces the same value.
+///
+/// This function can be used to validate document guids (see: [Options::guid]) coming from
+/// untrusted sources.
+pub fn uuid_parse(s: &str) -> Result<Uuid, UuidError> {
+    const HYPHENS: [usize; 4] = [8, 13, 18, 23];
+    if s.len() != 36 {
+        return Err(UuidError::InvalidLength(s.len()));
+    }
+    let mut b = [0u8; 16];
+    let mut i = 0;
+    for (index, c) in s.char_indices() {
+        if HYPHENS.contains(&index) {
+            if c != '-' {
+                return Err(UuidError::InvalidCharacter(c, index));
+            }
+        } else {
+            let digit = c
+                .to_digit(16)
+                .ok_or(UuidError::InvalidCharacter(c, index))?;
+            b[i / 2] = b[i / 2] << 4 | digit as u8;
+            i += 1;
+        }
+    }
+    Ok(uuid_from_bytes(b))
+}
+
+/// Error returned by [uuid_parse] when a string is not a valid hyphenated UUID.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum UuidError {
+    #[error("UUID must be 36 characters long, but it was {0}")]
+    InvalidLength(usize),
+    #[error("invalid character '{0}' found in UUID at position {1}")]
+    InvalidCharacter(char, usize),
 }
@@ -146,6 +146,29 @@ impl Move {
         e.insert(ptr);
     }
 
+    /// Reverts effects of integrating a move `item`, which has been created within the scope of
+    /// a transaction that is being rolled back, so that its block can be safely dropped.
+    pub(crate) fn rollback(&self, txn: &mut TransactionMut, item: ItemPtr) {
+        let (mut start, end) = self.get_moved_coords(txn);
+        while start != end {
+            if let Some(ptr) = start {
+                if let Some(mut moved) = ptr.moved {
+                    if let ItemContent::Move(m) = &mut moved.content {
+                        if let Some(overrides) = &mut m.overrides {
+                            overrides.remove(&item);
+                        }
+                    }
+                }
+                start = ptr.right;
+            } else {
+                break;
+            }
+        }
+        if !item.is_deleted() {
+            self.delete(txn, item);
+        }
+    }
+
     pub(crate) fn integrate_block(&mut self, txn: &mut TransactionMut, item: ItemPtr) {
         let (init, end) = self.get_moved_coords_mut(txn);
         let mut max_priority = 0i32;
@@ -1,3 +1,4 @@
+use std::panic::{catch_unwind, AssertUnwindSafe};
 use std::sync::{Arc, Weak};
 
 use arc_swap::{ArcSwapOption, AsRaw, Guard};
@@ -85,18 +86,42 @@ where
         if let Some(inner) = &*self.inner.load() {
             let mut next = inner.head.load();
             while let Some(node) = &*next {
-                each(&node.callback);
+                // one-shot callbacks are removed before being called, so that they are never
+                // called more than once, even if observer is triggered again in the meantime
+                if !node.once || inner.remove(&node.uid) {
+                    each(&node.callback);
+                }
                 next = node.next.load();
             }
         }
     }
 
+    /// Works like [Observer::trigger], but panics raised by individual callbacks are caught, so
+    /// that a single faulty callback doesn't prevent the remaining ones from being called. Payload
+    /// of every caught panic is passed over to `on_panic`.
+    pub fn trigger_guarded<E, P>(&self, mut each: E, mut on_panic: P)
+    where
+        E: FnMut(&F),
+        P: FnMut(Box<dyn std::any::Any + Send>),
+    {
+        self.trigger(|callback| {
+            if let Err(panic) = catch_unwind(AssertUnwindSafe(|| each(callback))) {
+                on_panic(panic);
+            }
+        })
+    }
+
     /// Subscribes a callback parameter to a current [Observer].
     /// Returns a subscription object which - when dropped - will unsubscribe current callback.
     /// If the `id` was already present in the observer, current callback will be ignored.
     pub fn subscribe_with(&self, id: Origin, callback: F) {
+        self.insert(Node::new(id, callback, false))
+    }
+
+    fn insert(&self, node: Node<F>) {
+        let id = node.uid.clone();
         let inner = self.inner();
-        let mut node = Arc::new(Node::new(id.clone(), callback));
+        let mut node = Arc::new(node);
         let cur = inner.head.load();
         let head = loop {
             {
@@ -134,6 +159,21 @@ where
             inner: Arc::downgrade(&self.inner()),
         })
     }
+
+    /// Subscribes a callback parameter to a current [Observer], which will be automatically
+    /// unsubscribed after being triggered for the first time.
+    /// Returns a subscription object which - when dropped - will unsubscribe current callback
+    /// if it has not been triggered yet.
+    pub fn subscribe_once(&self, callback: F) -> Subscription {
+        let mut rng = fastrand::Rng::new();
+        let id = rng.usize(0..usize::MAX);
+        let origin = Origin::from(id);
+        self.insert(Node::new(origin.clone(), callback, true));
+        Arc::new(Cancel {
+            id: origin,
+            inner: Arc::downgrade(&self.inner()),
+        })
+    }
 }
 
 #[cfg(not(feature = "sync"))]
@@ -151,6 +191,21 @@ where
             inner: Arc::downgrade(&self.inner()),
         })
     }
+
+    /// Subscribes a callback parameter to a current [Observer], which will be automatically
+    /// unsubscribed after being triggered for the first time.
+    /// Returns a subscription object which - when dropped - will unsubscribe current callback
+    /// if it has not been triggered yet.
+    pub fn subscribe_once(&self, callback: F) -> Subscription {
+        let mut rng = fastrand::Rng::new();
+        let id = rng.usize(0..usize::MAX);
+        let origin = Origin::from(id);
+        self.insert(Node::new(origin.clone(), callback, true));
+        Arc::new(Cancel {
+            id: origin,
+            inner: Arc::downgrade(&self.inner()),
+        })
+    }
 }
 
 #[cfg(feature = "sync")]
@@ -208,14 +263,17 @@ where
 struct Node<T> {
     uid: Origin,
     callback: T,
+    /// If true, node is removed from the observer right before its callback is triggered.
+    once: bool,
     next: ArcSwapOption<Node<T>>,
 }
 
 impl<F> Node<F> {
-    fn new(uid: Origin, callback: F) -> Self {
+    fn new(uid: Origin, callback: F, once: bool) -> Self {
         Node {
             uid,
             callback,
+            once,
             next: Default::default(),
         }
     }
@@ -379,6 +437,22 @@ mod test {
         assert_eq!(rx.try_recv().unwrap(), "b-2");
     }
 
+    #[test]
+    fn subscribe_once() {
+        let o: Observer<Box<dyn Fn(u32) + Send + Sync + 'static>> = Observer::new();
+        let state = Arc::new(AtomicU32::new(0));
+        let s = state.clone();
+        let _sub = o.subscribe_once(Box::new(move |v| {
+            s.fetch_add(v, Ordering::SeqCst);
+        }));
+        assert!(o.has_subscribers());
+
+        o.trigger(|fun| fun(1));
+        o.trigger(|fun| fun(2));
+        assert_eq!(state.load(Ordering::SeqCst), 1);
+        assert!(!o.has_subscribers());
+    }
+
     struct DropCounter(Arc<AtomicI32>);
 
     impl DropCounter {
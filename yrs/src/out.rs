@@ -2,8 +2,8 @@ use crate::block::{ItemContent, ItemPtr};
 use crate::branch::{Branch, BranchPtr};
 use crate::types::{AsPrelim, ToJson};
 use crate::{
-    any, Any, ArrayRef, Doc, GetString, In, MapPrelim, MapRef, ReadTxn, TextRef, XmlElementRef,
-    XmlFragmentRef, XmlTextRef,
+    any, Any, Array, ArrayRef, Doc, GetString, In, Map, MapPrelim, MapRef, ReadTxn, TextRef,
+    XmlElementRef, XmlFragmentRef, XmlTextRef,
 };
 use std::convert::TryFrom;
 use std::fmt::Formatter;
@@ -38,6 +38,32 @@ pub enum Out {
     UndefinedRef(BranchPtr),
 }
 
+/// Discriminant of an [Out] value, which describes a kind of value stored in it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OutKind {
+    /// [Out::Any] value.
+    Any,
+    /// [Out::YText] value.
+    Text,
+    /// [Out::YArray] value.
+    Array,
+    /// [Out::YMap] value.
+    Map,
+    /// [Out::YXmlElement] value.
+    XmlElement,
+    /// [Out::YXmlFragment] value.
+    XmlFragment,
+    /// [Out::YXmlText] value.
+    XmlText,
+    /// [Out::YDoc] value.
+    Doc,
+    /// [Out::YWeakLink] value.
+    #[cfg(feature = "weak")]
+    WeakLink,
+    /// [Out::UndefinedRef] value.
+    Undefined,
+}
+
 impl Default for Out {
     fn default() -> Self {
         Out::Any(Any::Undefined)
@@ -75,6 +101,81 @@ impl Out {
         }
     }
 
+    /// Returns a kind of value stored in current [Out] without trying to cast it.
+    pub fn kind(&self) -> OutKind {
+        match self {
+            Out::Any(_) => OutKind::Any,
+            Out::YText(_) => OutKind::Text,
+            Out::YArray(_) => OutKind::Array,
+            Out::YMap(_) => OutKind::Map,
+            Out::YXmlElement(_) => OutKind::XmlElement,
+            Out::YXmlFragment(_) => OutKind::XmlFragment,
+            Out::YXmlText(_) => OutKind::XmlText,
+            Out::YDoc(_) => OutKind::Doc,
+            #[cfg(feature = "weak")]
+            Out::YWeakLink(_) => OutKind::WeakLink,
+            Out::UndefinedRef(_) => OutKind::Undefined,
+        }
+    }
+
+    /// Structurally compares current value, read within a `txn`, with an `other` value read within
+    /// `other_txn`. Both values may come from different documents.
+    ///
+    /// Unlike comparing results of [ToJson::to_json], this method doesn't materialize nested
+    /// collections and returns as soon as the first difference is found. Values of different
+    /// kinds are never equal, even if their JSON representations would be. Sub-documents are
+    /// compared by their GUIDs.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Array, Doc, Out, Transact};
+    ///
+    /// let d1 = Doc::new();
+    /// let a1 = d1.get_or_insert_array("array");
+    /// a1.insert_range(&mut d1.transact_mut(), 0, [1, 2, 3]);
+    ///
+    /// let d2 = Doc::new();
+    /// let a2 = d2.get_or_insert_array("array");
+    /// a2.insert_range(&mut d2.transact_mut(), 0, [1, 2]);
+    ///
+    /// let (v1, v2) = (Out::YArray(a1.clone()), Out::YArray(a2.clone()));
+    /// assert!(!v1.value_eq(&d1.transact(), &v2, &d2.transact()));
+    ///
+    /// a2.push_back(&mut d2.transact_mut(), 3);
+    /// assert!(v1.value_eq(&d1.transact(), &v2, &d2.transact()));
+    /// ```
+    pub fn value_eq<T1: ReadTxn, T2: ReadTxn>(
+        &self,
+        txn: &T1,
+        other: &Out,
+        other_txn: &T2,
+    ) -> bool {
+        match (self, other) {
+            (Out::Any(a), Out::Any(b)) => a == b,
+            (Out::YText(a), Out::YText(b)) => a.get_string(txn) == b.get_string(other_txn),
+            (Out::YArray(a), Out::YArray(b)) => {
+                a.len(txn) == b.len(other_txn)
+                    && a.iter(txn)
+                        .zip(b.iter(other_txn))
+                        .all(|(a, b)| a.value_eq(txn, &b, other_txn))
+            }
+            (Out::YMap(a), Out::YMap(b)) => {
+                a.len(txn) == b.len(other_txn)
+                    && a.iter(txn).all(|(key, a)| match b.get(other_txn, key) {
+                        Some(b) => a.value_eq(txn, &b, other_txn),
+                        None => false,
+                    })
+            }
+            (Out::YDoc(a), Out::YDoc(b)) => a.guid() == b.guid(),
+            (a, b) if a.kind() == b.kind() => {
+                // XML nodes and other types are compared by their string representation
+                a.clone().to_string(txn) == b.clone().to_string(other_txn)
+            }
+            _ => false,
+        }
+    }
+
     pub fn try_branch(&self) -> Option<&Branch> {
         match self {
             Out::YText(b) => Some(b.as_ref()),
@@ -234,3 +335,94 @@ impl std::fmt::Display for Out {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::out::OutKind;
+    use crate::test_utils::exchange_updates;
+    use crate::{
+        Array, ArrayPrelim, Doc, Map, MapPrelim, MapRef, Out, ReadTxn, Text, TextPrelim, TextRef,
+        Transact, XmlElementPrelim, XmlFragmentPrelim, XmlTextPrelim,
+    };
+
+    #[test]
+    fn out_kind() {
+        let doc = Doc::with_client_id(1);
+        let array = doc.get_or_insert_array("array");
+        {
+            let mut txn = doc.transact_mut();
+            array.push_back(&mut txn, 1);
+            array.push_back(&mut txn, TextPrelim::new("text"));
+            array.push_back(&mut txn, ArrayPrelim::default());
+            array.push_back(&mut txn, MapPrelim::default());
+            array.push_back(&mut txn, XmlElementPrelim::empty("p"));
+            array.push_back(&mut txn, XmlFragmentPrelim::default());
+            array.push_back(&mut txn, XmlTextPrelim::new("xml"));
+            array.push_back(&mut txn, Doc::new());
+        }
+
+        let kinds: Vec<_> = array.iter(&doc.transact()).map(|v| v.kind()).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                OutKind::Any,
+                OutKind::Text,
+                OutKind::Array,
+                OutKind::Map,
+                OutKind::XmlElement,
+                OutKind::XmlFragment,
+                OutKind::XmlText,
+                OutKind::Doc,
+            ]
+        );
+
+        // root type which has not been defined by the remote peer
+        let remote = Doc::with_client_id(2);
+        exchange_updates(&[&doc, &remote]);
+        let txn = remote.transact();
+        let (_, root) = txn.root_refs().next().unwrap();
+        assert_eq!(root.kind(), OutKind::Undefined);
+    }
+
+    #[test]
+    fn value_eq() {
+        let d1 = Doc::with_client_id(1);
+        let a1 = d1.get_or_insert_array("array");
+        let d2 = Doc::with_client_id(2);
+        let a2 = d2.get_or_insert_array("array");
+        {
+            let mut txn = d1.transact_mut();
+            a1.push_back(&mut txn, 1);
+            a1.push_back(&mut txn, TextPrelim::new("text"));
+            let map = a1.push_back(&mut txn, MapPrelim::from([("key", "value")]));
+            map.insert(&mut txn, "nested", ArrayPrelim::from([true, false]));
+        }
+        exchange_updates(&[&d1, &d2]);
+
+        let (v1, v2) = (Out::YArray(a1.clone()), Out::YArray(a2.clone()));
+        assert!(v1.value_eq(&d1.transact(), &v2, &d2.transact()));
+
+        // diverged nested text
+        let text: TextRef = a2.get(&d2.transact(), 1).unwrap().cast().unwrap();
+        text.push(&mut d2.transact_mut(), "!");
+        assert!(!v1.value_eq(&d1.transact(), &v2, &d2.transact()));
+        exchange_updates(&[&d1, &d2]);
+        assert!(v1.value_eq(&d1.transact(), &v2, &d2.transact()));
+
+        // diverged nested map
+        let map: MapRef = a1.get(&d1.transact(), 2).unwrap().cast().unwrap();
+        map.insert(&mut d1.transact_mut(), "key", "other");
+        assert!(!v1.value_eq(&d1.transact(), &v2, &d2.transact()));
+        exchange_updates(&[&d1, &d2]);
+        assert!(v1.value_eq(&d1.transact(), &v2, &d2.transact()));
+
+        // diverged length
+        a2.push_back(&mut d2.transact_mut(), 2);
+        assert!(!v1.value_eq(&d1.transact(), &v2, &d2.transact()));
+
+        // different kinds of values are never equal
+        let m1 = Out::YMap(d1.get_or_insert_map("map"));
+        let m2 = Out::YArray(d2.get_or_insert_array("map2"));
+        assert!(!m1.value_eq(&d1.transact(), &m2, &d2.transact()));
+    }
+}
@@ -1,4 +1,6 @@
-use crate::block::{ItemPtr, BLOCK_GC_REF_NUMBER, GC, HAS_ORIGIN, HAS_RIGHT_ORIGIN};
+use crate::block::{
+    ItemPtr, BLOCK_GC_REF_NUMBER, BLOCK_ITEM_DELETED_REF_NUMBER, GC, HAS_ORIGIN, HAS_RIGHT_ORIGIN,
+};
 use crate::types::TypePtr;
 use crate::updates::encoder::Encoder;
 use crate::ID;
@@ -197,8 +199,21 @@ impl ItemSlice {
     }
 
     pub fn encode<E: Encoder>(&self, encoder: &mut E) {
+        self.encode_with(encoder, false)
+    }
+
+    /// Encodes current slice just like [ItemSlice::encode], except its content is replaced with
+    /// a tombstone of the same length.
+    pub fn encode_as_deleted<E: Encoder>(&self, encoder: &mut E) {
+        self.encode_with(encoder, true)
+    }
+
+    fn encode_with<E: Encoder>(&self, encoder: &mut E, as_deleted: bool) {
         let item = self.ptr.deref();
         let mut info = item.info();
+        if as_deleted {
+            info = (info & !0b11111) | BLOCK_ITEM_DELETED_REF_NUMBER;
+        }
         let origin = if self.adjacent_left() {
             item.origin
         } else {
@@ -247,7 +262,11 @@ impl ItemSlice {
                 encoder.write_string(parent_sub.as_ref());
             }
         }
-        item.content.encode_slice(encoder, self.start, self.end);
+        if as_deleted {
+            encoder.write_len(self.len());
+        } else {
+            item.content.encode_slice(encoder, self.start, self.end);
+        }
     }
 
     /// Returns a [ItemSlice] wrapper for a [Item] identified as a right neighbor of this slice.
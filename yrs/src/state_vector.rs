@@ -1,11 +1,14 @@
 use crate::block::ClientID;
-use crate::encoding::read::Error;
+use crate::encoding::read::{Cursor, Error, Read};
+use crate::encoding::write::Write;
 use crate::updates::decoder::{Decode, Decoder};
 use crate::updates::encoder::{Encode, Encoder};
 use crate::utils::client_hasher::ClientHasher;
 use crate::{DeleteSet, ID};
+use std::cmp::Ordering;
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
+use std::convert::{TryFrom, TryInto};
 use std::hash::BuildHasherDefault;
 
 /// State vector is a compact representation of all known blocks inserted and integrated into
@@ -19,6 +22,9 @@ use std::hash::BuildHasherDefault;
 pub struct StateVector(HashMap<ClientID, u32, BuildHasherDefault<ClientHasher>>);
 
 impl StateVector {
+    /// Current version of a binary format used by [StateVector::to_portable_bytes].
+    pub const PORTABLE_VERSION: u8 = 1;
+
     /// Checks if current state vector contains any data.
     pub fn is_empty(&self) -> bool {
         self.0.is_empty()
@@ -102,6 +108,189 @@ impl StateVector {
             *e = (*e).max(clock);
         }
     }
+
+    /// Compares current state vector against the `other` one, telling if replicas described by them
+    /// are in sync. Missing client entries are treated as having a clock equal to 0.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{StateVector, SyncStatus};
+    ///
+    /// let mut a = StateVector::default();
+    /// a.set_max(1, 10);
+    /// let mut b = a.clone();
+    /// assert_eq!(a.compare(&b), SyncStatus::Equal);
+    ///
+    /// b.set_max(2, 5);
+    /// assert_eq!(a.compare(&b), SyncStatus::Behind);
+    ///
+    /// a.set_max(1, 11);
+    /// assert_eq!(a.compare(&b), SyncStatus::Diverged);
+    /// ```
+    pub fn compare(&self, other: &StateVector) -> SyncStatus {
+        let mut ahead = false;
+        let mut behind = false;
+        for (client, &clock) in self.iter() {
+            let other_clock = other.get(client);
+            ahead |= clock > other_clock;
+            behind |= clock < other_clock;
+        }
+        for (client, &clock) in other.iter() {
+            behind |= clock > self.get(client);
+        }
+        match (ahead, behind) {
+            (false, false) => SyncStatus::Equal,
+            (true, false) => SyncStatus::Ahead,
+            (false, true) => SyncStatus::Behind,
+            (true, true) => SyncStatus::Diverged,
+        }
+    }
+
+    /// Encodes only the client entries of a current state vector, which differ from a given `base`
+    /// state vector. This is useful when exchanging big state vectors, which usually change only
+    /// slightly between the subsequent exchanges, given that both sides agreed on the same `base`
+    /// vector already. Encoded delta can be turned back into a state vector using
+    /// [StateVector::decode_delta] with the same `base`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::StateVector;
+    ///
+    /// let mut base = StateVector::default();
+    /// base.set_max(1, 10);
+    /// base.set_max(2, 5);
+    ///
+    /// let mut sv = base.clone();
+    /// sv.set_max(2, 7);
+    ///
+    /// let delta = sv.encode_delta(&base);
+    /// assert_eq!(StateVector::decode_delta(&base, &delta).unwrap(), sv);
+    /// ```
+    pub fn encode_delta(&self, base: &StateVector) -> Vec<u8> {
+        let mut changed: Vec<_> = self
+            .iter()
+            .filter(|&(client, &clock)| base.0.get(client) != Some(&clock))
+            .collect();
+        changed.sort_unstable();
+        let mut removed: Vec<_> = base.0.keys().filter(|c| !self.contains_client(c)).collect();
+        removed.sort_unstable();
+
+        let mut buf = Vec::new();
+        buf.write_var(changed.len());
+        for (&client, &clock) in changed {
+            buf.write_var(client);
+            // state vectors usually grow, so clock difference is smaller than the clock itself
+            buf.write_var(clock as i64 - base.get(&client) as i64);
+        }
+        buf.write_var(removed.len());
+        for &client in removed {
+            buf.write_var(client);
+        }
+        buf
+    }
+
+    /// Reconstructs a state vector from a `delta` produced by [StateVector::encode_delta] against
+    /// the same `base` state vector.
+    pub fn decode_delta(base: &StateVector, delta: &[u8]) -> Result<Self, Error> {
+        let mut cursor = Cursor::new(delta);
+        let mut sv = base.clone();
+        let changed: usize = cursor.read_var()?;
+        for _ in 0..changed {
+            let client: ClientID = cursor.read_var()?;
+            let diff: i64 = cursor.read_var()?;
+            let clock = (base.get(&client) as i64)
+                .checked_add(diff)
+                .ok_or(Error::UnexpectedValue)?;
+            let clock = u32::try_from(clock).map_err(|_| Error::UnexpectedValue)?;
+            sv.0.insert(client, clock);
+        }
+        let removed: usize = cursor.read_var()?;
+        for _ in 0..removed {
+            let client: ClientID = cursor.read_var()?;
+            sv.0.remove(&client);
+        }
+        Ok(sv)
+    }
+
+    /// Serializes current state vector into a self-describing binary format meant for long-term
+    /// storage. Unlike [Encode::encode_v1], this format doesn't depend on lib0 encoding: it starts
+    /// with a [StateVector::PORTABLE_VERSION] byte, followed by a number of entries (as 4-byte
+    /// little-endian integer) and the entries themselves, ordered by client ID, each written as
+    /// an 8-byte little-endian client ID and a 4-byte little-endian clock.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::StateVector;
+    ///
+    /// let mut sv = StateVector::default();
+    /// sv.set_max(1, 10);
+    /// sv.set_max(2, 5);
+    ///
+    /// let bytes = sv.to_portable_bytes();
+    /// assert_eq!(bytes[0], StateVector::PORTABLE_VERSION);
+    /// assert_eq!(StateVector::from_portable_bytes(&bytes).unwrap(), sv);
+    /// ```
+    pub fn to_portable_bytes(&self) -> Vec<u8> {
+        let mut entries: Vec<_> = self.iter().collect();
+        entries.sort_unstable();
+        let mut buf = Vec::with_capacity(5 + entries.len() * 12);
+        buf.push(Self::PORTABLE_VERSION);
+        buf.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+        for (&client, &clock) in entries {
+            buf.extend_from_slice(&client.to_le_bytes());
+            buf.extend_from_slice(&clock.to_le_bytes());
+        }
+        buf
+    }
+
+    /// Deserializes state vector from a binary produced by [StateVector::to_portable_bytes].
+    /// Returns [Error::Custom] if `bytes` were written using a different version of the format
+    /// and [Error::UnexpectedValue] if they contain the same client ID more than once.
+    pub fn from_portable_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let (&version, bytes) = bytes.split_first().ok_or(Error::EndOfBuffer(1))?;
+        if version != Self::PORTABLE_VERSION {
+            return Err(Error::Custom(format!(
+                "unsupported state vector format version: {}",
+                version
+            )));
+        }
+        if bytes.len() < 4 {
+            return Err(Error::EndOfBuffer(4));
+        }
+        let (len, mut bytes) = bytes.split_at(4);
+        let len = u32::from_le_bytes(len.try_into().unwrap()) as usize;
+        if len.checked_mul(12) != Some(bytes.len()) {
+            return Err(Error::UnexpectedValue);
+        }
+        let mut sv = HashMap::with_capacity_and_hasher(len, BuildHasherDefault::default());
+        while !bytes.is_empty() {
+            let (client, rest) = bytes.split_at(8);
+            let (clock, rest) = rest.split_at(4);
+            let client = ClientID::from_le_bytes(client.try_into().unwrap());
+            let clock = u32::from_le_bytes(clock.try_into().unwrap());
+            if sv.insert(client, clock).is_some() {
+                return Err(Error::UnexpectedValue);
+            }
+            bytes = rest;
+        }
+        Ok(StateVector(sv))
+    }
+}
+
+/// Result of [StateVector::compare], describing a relation between states of two replicas.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum SyncStatus {
+    /// Both replicas have seen exactly the same updates.
+    Equal,
+    /// Current replica has seen all updates of the other one, and some more.
+    Ahead,
+    /// Other replica has seen all updates of the current one, and some more.
+    Behind,
+    /// Each replica has seen updates, which the other one is missing.
+    Diverged,
 }
 
 impl Decode for StateVector {
@@ -129,6 +318,26 @@ impl Encode for StateVector {
     }
 }
 
+impl IntoIterator for StateVector {
+    type Item = (ClientID, u32);
+    type IntoIter = std::collections::hash_map::IntoIter<ClientID, u32>;
+
+    /// Consumes current state vector, returning an iterator over all clients and their known
+    /// clock values. Order of iteration is not specified.
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a StateVector {
+    type Item = (&'a ClientID, &'a u32);
+    type IntoIter = std::collections::hash_map::Iter<'a, ClientID, u32>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
 /// Snapshot describes a state of a document store at a given point in (logical) time. In practice
 /// it's a combination of [StateVector] (a summary of all observed insert/update operations)
 /// and a [DeleteSet] (a summary of all observed deletions).
@@ -151,6 +360,31 @@ impl Snapshot {
     pub(crate) fn is_visible(&self, id: &ID) -> bool {
         self.state_map.get(&id.client) > id.clock && !self.delete_set.is_deleted(id)
     }
+
+    /// Checks if current snapshot causally precedes `other` snapshot, meaning that `other` has
+    /// observed all insertions and deletions observed by the current one and at least one more.
+    /// Equal and concurrent snapshots are not ancestors of each other. See: [Snapshot::causal_cmp].
+    pub fn is_ancestor_of(&self, other: &Snapshot) -> bool {
+        self.causal_cmp(other) == Some(Ordering::Less)
+    }
+
+    /// Compares snapshots causally: a snapshot is less than another one if its state vector and
+    /// delete set are both contained by the other snapshot. Concurrent snapshots, which have
+    /// observed changes unknown to each other, are incomparable.
+    ///
+    /// Unlike `==`, which compares snapshots structurally, this method returns
+    /// [Ordering::Equal] for snapshots which observed the same changes, even if their delete sets
+    /// are represented differently.
+    pub fn causal_cmp(&self, other: &Snapshot) -> Option<Ordering> {
+        let before = self.delete_set.is_subset_of(&other.delete_set);
+        let after = other.delete_set.is_subset_of(&self.delete_set);
+        match self.state_map.compare(&other.state_map) {
+            SyncStatus::Equal if before && after => Some(Ordering::Equal),
+            SyncStatus::Equal | SyncStatus::Behind if before => Some(Ordering::Less),
+            SyncStatus::Equal | SyncStatus::Ahead if after => Some(Ordering::Greater),
+            _ => None,
+        }
+    }
 }
 
 impl Encode for Snapshot {
@@ -167,3 +401,225 @@ impl Decode for Snapshot {
         Ok(Snapshot::new(sm, ds))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::encoding::read::Error;
+    use crate::test_utils::exchange_updates;
+    use crate::updates::encoder::Encode;
+    use crate::{ReadTxn, StateVector, SyncStatus, Text, Transact};
+    use std::cmp::Ordering;
+
+    #[test]
+    fn state_vector_iteration() {
+        let d1 = crate::Doc::with_client_id(1);
+        let d2 = crate::Doc::with_client_id(2);
+        d1.get_or_insert_text("text")
+            .push(&mut d1.transact_mut(), "abc");
+        d2.get_or_insert_text("text")
+            .push(&mut d2.transact_mut(), "de");
+
+        let mut sv: StateVector = d1.transact().state_vector();
+        sv.merge(d2.transact().state_vector());
+
+        let mut by_ref: Vec<_> = (&sv).into_iter().map(|(&c, &v)| (c, v)).collect();
+        by_ref.sort();
+        assert_eq!(by_ref, vec![(1, 3), (2, 2)]);
+
+        assert_eq!(sv.get(&1), 3);
+        assert_eq!(sv.get(&3), 0, "unknown client should report clock 0");
+
+        let mut owned: Vec<(u64, u32)> = sv.into_iter().collect();
+        owned.sort();
+        assert_eq!(owned, vec![(1, 3), (2, 2)]);
+    }
+
+    #[test]
+    fn state_vector_compare() {
+        let d1 = crate::Doc::with_client_id(1);
+        let d2 = crate::Doc::with_client_id(2);
+        let t1 = d1.get_or_insert_text("text");
+        let t2 = d2.get_or_insert_text("text");
+        assert_eq!(
+            d1.transact().diff_status(&d2.transact().state_vector()),
+            SyncStatus::Equal
+        );
+
+        t1.push(&mut d1.transact_mut(), "abc");
+        let sv1 = d1.transact().state_vector();
+        let sv2 = d2.transact().state_vector();
+        assert_eq!(sv1.compare(&sv2), SyncStatus::Ahead);
+        assert_eq!(sv2.compare(&sv1), SyncStatus::Behind);
+
+        exchange_updates(&[&d1, &d2]);
+        let sv1 = d1.transact().state_vector();
+        let sv2 = d2.transact().state_vector();
+        assert_eq!(sv1.compare(&sv2), SyncStatus::Equal);
+
+        t1.push(&mut d1.transact_mut(), "d");
+        t2.push(&mut d2.transact_mut(), "e");
+        let sv1 = d1.transact().state_vector();
+        let sv2 = d2.transact().state_vector();
+        assert_eq!(sv1.compare(&sv2), SyncStatus::Diverged);
+        assert_eq!(sv2.compare(&sv1), SyncStatus::Diverged);
+
+        // zero clocks are equivalent to missing entries
+        let mut sv = StateVector::default();
+        sv.set_max(3, 0);
+        assert_eq!(sv.compare(&StateVector::default()), SyncStatus::Equal);
+    }
+
+    #[test]
+    fn snapshot_ordering() {
+        let d1 = crate::Doc::with_client_id(1);
+        let d2 = crate::Doc::with_client_id(2);
+        let t1 = d1.get_or_insert_text("text");
+        let t2 = d2.get_or_insert_text("text");
+
+        t1.push(&mut d1.transact_mut(), "abc");
+        let s1 = d1.transact().snapshot();
+        t1.push(&mut d1.transact_mut(), "def");
+        let s2 = d1.transact().snapshot();
+        // deletions don't change the state vector
+        t1.remove_range(&mut d1.transact_mut(), 0, 2);
+        let s3 = d1.transact().snapshot();
+
+        // ancestor
+        assert_eq!(s1.causal_cmp(&s2), Some(Ordering::Less));
+        assert!(s1.is_ancestor_of(&s2));
+        assert!(s2.is_ancestor_of(&s3));
+        assert!(s1.is_ancestor_of(&s3));
+
+        // descendant
+        assert_eq!(s3.causal_cmp(&s2), Some(Ordering::Greater));
+        assert!(!s3.is_ancestor_of(&s2));
+
+        // equal
+        exchange_updates(&[&d1, &d2]);
+        let s4 = d2.transact().snapshot();
+        assert_eq!(s3.causal_cmp(&s4), Some(Ordering::Equal));
+        assert!(!s3.is_ancestor_of(&s4));
+
+        // concurrent
+        t1.remove_range(&mut d1.transact_mut(), 0, 1);
+        t2.push(&mut d2.transact_mut(), "g");
+        let s5 = d1.transact().snapshot();
+        let s6 = d2.transact().snapshot();
+        assert_eq!(s5.causal_cmp(&s6), None);
+        assert_eq!(s6.causal_cmp(&s5), None);
+        assert!(!s5.is_ancestor_of(&s6));
+        assert!(!s6.is_ancestor_of(&s5));
+        assert!(s4.is_ancestor_of(&s5));
+        assert!(s4.is_ancestor_of(&s6));
+    }
+
+    #[test]
+    fn state_vector_delta() {
+        let mut base = StateVector::default();
+        for client in 0..1000 {
+            base.set_max(client, client as u32 * 100 + 1);
+        }
+        let mut sv = base.clone();
+        sv.inc_by(10, 5);
+        sv.inc_by(500, 1);
+        sv.set_max(2000, 7);
+
+        let delta = sv.encode_delta(&base);
+        let full = sv.encode_v1();
+        assert!(delta.len() < 16, "delta size: {}", delta.len());
+        assert!(full.len() > 1000);
+        assert_eq!(StateVector::decode_delta(&base, &delta).unwrap(), sv);
+
+        // identical state vectors produce minimal delta
+        let delta = base.encode_delta(&base);
+        assert_eq!(delta, vec![0, 0]);
+        assert_eq!(StateVector::decode_delta(&base, &delta).unwrap(), base);
+
+        // delta against a more recent base vector
+        let delta = base.encode_delta(&sv);
+        assert_eq!(StateVector::decode_delta(&sv, &delta).unwrap(), base);
+
+        assert!(StateVector::decode_delta(&base, &[1]).is_err());
+    }
+
+    #[test]
+    fn state_vector_delta_malformed() {
+        use crate::encoding::write::Write;
+
+        let mut base = StateVector::default();
+        base.set_max(1, 5);
+        let delta = |diff: i64| {
+            let mut buf = Vec::new();
+            buf.write_var(1usize);
+            buf.write_var(1u64);
+            buf.write_var(diff);
+            buf.write_var(0usize);
+            buf
+        };
+        assert!(StateVector::decode_delta(&base, &delta(1)).is_ok());
+        // clock difference overflowing i64
+        assert!(StateVector::decode_delta(&base, &delta(i64::MAX)).is_err());
+        // resulting clock out of u32 range
+        assert!(StateVector::decode_delta(&base, &delta(-6)).is_err());
+        assert!(StateVector::decode_delta(&base, &delta(u32::MAX as i64)).is_err());
+    }
+
+    #[test]
+    fn portable_bytes_roundtrip() {
+        let mut sv = StateVector::default();
+        sv.set_max(2, 7);
+        sv.set_max(1, 3);
+        sv.set_max(u64::MAX, u32::MAX);
+
+        let bytes = sv.to_portable_bytes();
+        assert_eq!(bytes.len(), 1 + 4 + 3 * 12);
+        assert_eq!(&bytes[..5], &[StateVector::PORTABLE_VERSION, 3, 0, 0, 0]);
+        // entries are ordered by client ID
+        assert_eq!(&bytes[5..13], &1u64.to_le_bytes());
+        assert_eq!(StateVector::from_portable_bytes(&bytes).unwrap(), sv);
+
+        let empty = StateVector::default().to_portable_bytes();
+        assert_eq!(empty, vec![StateVector::PORTABLE_VERSION, 0, 0, 0, 0]);
+        assert_eq!(
+            StateVector::from_portable_bytes(&empty).unwrap(),
+            StateVector::default()
+        );
+
+        // truncated input
+        assert!(StateVector::from_portable_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(StateVector::from_portable_bytes(&[]).is_err());
+    }
+
+    #[test]
+    fn portable_bytes_unsupported_version() {
+        let mut sv = StateVector::default();
+        sv.set_max(1, 3);
+        let mut bytes = sv.to_portable_bytes();
+        bytes[0] = StateVector::PORTABLE_VERSION + 1;
+
+        let err = StateVector::from_portable_bytes(&bytes).unwrap_err();
+        assert!(matches!(err, Error::Custom(_)));
+        assert_eq!(
+            err.to_string(),
+            "unsupported state vector format version: 2"
+        );
+    }
+
+    #[test]
+    fn portable_bytes_malformed() {
+        let mut sv = StateVector::default();
+        sv.set_max(1, 3);
+        sv.set_max(2, 5);
+        let mut bytes = sv.to_portable_bytes();
+
+        // duplicate client entries
+        bytes[17..25].copy_from_slice(&1u64.to_le_bytes());
+        let err = StateVector::from_portable_bytes(&bytes).unwrap_err();
+        assert!(matches!(err, Error::UnexpectedValue));
+
+        // entry count overflowing when multiplied by the entry size
+        let mut bytes = vec![StateVector::PORTABLE_VERSION];
+        bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+        assert!(StateVector::from_portable_bytes(&bytes).is_err());
+    }
+}
@@ -1,25 +1,26 @@
 use crate::block::{BlockCell, ClientID, ItemContent, ItemPtr};
 use crate::block_store::BlockStore;
 use crate::branch::{Branch, BranchPtr};
-use crate::doc::{DocAddr, Options};
+use crate::doc::{DocAddr, EncodeOptions, GcPolicy, Options, SubdocInfo};
 use crate::error::Error;
 use crate::event::SubdocsEvent;
 use crate::id_set::DeleteSet;
-use crate::slice::ItemSlice;
-use crate::types::{Path, PathSegment, TypeRef};
-use crate::update::PendingUpdate;
+use crate::slice::{BlockSlice, ItemSlice};
+use crate::types::{Path, PathSegment, TypePtr, TypeRef};
+use crate::update::{BlockCarrier, PendingUpdate, Update};
 use crate::updates::encoder::{Encode, Encoder};
 use crate::StateVector;
 use crate::{
-    Doc, Observer, OffsetKind, Snapshot, TransactionCleanupEvent, TransactionMut, UpdateEvent,
-    Uuid, ID,
+    merge_updates_v1, Doc, Observer, OffsetKind, Snapshot, TransactionCleanupEvent, TransactionMut,
+    UpdateEvent, Uuid, ID,
 };
 use atomic_refcell::{AtomicRef, AtomicRefCell, AtomicRefMut, BorrowError, BorrowMutError};
+use std::any::TypeId;
 use std::borrow::Borrow;
 use std::collections::hash_map::Entry;
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::ops::Deref;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 /// Store is a core element of a document. It contains all of the information, like block store
 /// map of root types, pending updates waiting to be applied once a missing update information
@@ -59,8 +60,30 @@ pub struct Store {
 
     /// Dependencies between items and weak links pointing to these items.
     pub(crate) linked_by: HashMap<ItemPtr, HashSet<BranchPtr>>,
+
+    /// Sequence number of the last committed transaction, which changed the state of the document.
+    pub(crate) commit_seq: u64,
+
+    /// Bounded log of lib0 v1 encoded updates of the most recent commits, ordered by their commit
+    /// sequence numbers. Its capacity is configured via [Options::update_log_capacity].
+    pub(crate) update_log: VecDeque<(u64, Vec<u8>)>,
+
+    /// Deleted blocks accumulated over many transactions, which are waiting to be garbage
+    /// collected once their number exceeds a threshold of [GcPolicy::Threshold].
+    pub(crate) gc_pending: DeleteSet,
+
+    /// Lib0 v1 encoded state vector of the document, cached between commits changing the
+    /// document state. See: [Store::state_vector_bytes_v1].
+    state_vector_v1: Mutex<Option<Arc<[u8]>>>,
+
+    /// Application data attached to a document via [Doc::set_meta], one value per type. It's
+    /// local to a current document instance and never becomes a part of the document state.
+    /// It's guarded by its own lock, so it can be updated through a shared store borrow.
+    pub(crate) meta: Mutex<MetaMap>,
 }
 
+pub(crate) type MetaMap = HashMap<TypeId, Arc<dyn std::any::Any + Send + Sync>>;
+
 impl Store {
     /// Create a new empty store in context of a given `client_id`.
     pub(crate) fn new(options: Options) -> Self {
@@ -75,9 +98,69 @@ impl Store {
             pending: None,
             pending_ds: None,
             parent: None,
+            commit_seq: 0,
+            update_log: VecDeque::default(),
+            gc_pending: DeleteSet::default(),
+            state_vector_v1: Mutex::default(),
+            meta: Mutex::default(),
         }
     }
 
+    /// Returns a sequence number of the last committed transaction, which changed the state of
+    /// the document.
+    pub fn commit_seq(&self) -> u64 {
+        self.commit_seq
+    }
+
+    /// Bumps the commit sequence number and - if update log is enabled - appends a given `update`
+    /// to it, evicting the oldest entries over the configured capacity.
+    pub(crate) fn record_commit(&mut self, update: Option<Vec<u8>>) {
+        self.commit_seq += 1;
+        *self.state_vector_v1.get_mut().unwrap() = None;
+        if let Some(update) = update {
+            self.update_log.push_back((self.commit_seq, update));
+            while self.update_log.len() > self.options.update_log_capacity {
+                self.update_log.pop_front();
+            }
+        }
+    }
+
+    /// Returns a lib0 v1 encoded state vector of the document. Encoded bytes are cached and reused
+    /// by subsequent calls until the next commit changing the document state.
+    pub(crate) fn state_vector_bytes_v1(&self) -> Arc<[u8]> {
+        let mut cached = self.state_vector_v1.lock().unwrap();
+        match &*cached {
+            Some(bytes) => bytes.clone(),
+            None => {
+                let bytes: Arc<[u8]> = self.blocks.get_state_vector().encode_v1().into();
+                *cached = Some(bytes.clone());
+                bytes
+            }
+        }
+    }
+
+    /// Returns a lib0 v1 encoded update containing all changes committed after a given `seq`
+    /// number. Returns `None` if update log doesn't contain all of these changes.
+    pub fn encode_diff_since_seq(&self, seq: u64) -> Option<Vec<u8>> {
+        if seq > self.commit_seq {
+            return None;
+        }
+        if seq == self.commit_seq {
+            return Some(Update::new().encode_v1());
+        }
+        let (first_seq, _) = self.update_log.front()?;
+        if *first_seq > seq + 1 {
+            // update log has been truncated
+            return None;
+        }
+        let updates = self
+            .update_log
+            .iter()
+            .filter(|(s, _)| *s > seq)
+            .map(|(_, update)| update.as_slice());
+        merge_updates_v1(updates).ok()
+    }
+
     /// If there are any missing updates, this method will return a pending update which contains
     /// updates waiting for their predecessors to arrive in order to be integrated.
     pub fn pending_update(&self) -> Option<&PendingUpdate> {
@@ -141,7 +224,7 @@ impl Store {
         snapshot: &Snapshot,
         encoder: &mut E,
     ) -> Result<(), Error> {
-        if !self.options.skip_gc {
+        if self.options.effective_gc_policy() != GcPolicy::Never {
             return Err(Error::Gc);
         }
         self.write_blocks_to(&snapshot.state_map, encoder);
@@ -201,7 +284,90 @@ impl Store {
         delete_set.encode(encoder);
     }
 
+    /// Encodes all blocks created by a given `client_id` together with all blocks (possibly created
+    /// by other clients), which they transitively depend on in order to be integrated. Deletions
+    /// are limited to the encoded blocks.
+    pub fn encode_client_state<E: Encoder>(&self, client_id: ClientID, encoder: &mut E) {
+        let deps = self.client_dependencies(client_id);
+        self.write_blocks_to(&deps, encoder);
+        let mut delete_set = DeleteSet::new();
+        for (client, range) in DeleteSet::from(&self.blocks).iter() {
+            let end = deps.get(client);
+            for r in range.iter() {
+                if r.start < end {
+                    let id = ID::new(*client, r.start);
+                    delete_set.insert(id, r.end.min(end) - r.start);
+                }
+            }
+        }
+        delete_set.encode(encoder);
+    }
+
+    /// Computes a state vector describing all blocks created by a given `client_id` and
+    /// the blocks they transitively depend on (via origins and parents).
+    fn client_dependencies(&self, client_id: ClientID) -> StateVector {
+        let mut required = StateVector::default();
+        let mut scanned = StateVector::default();
+        let clock = self.blocks.get_clock(&client_id);
+        if clock > 0 {
+            required.set_max(client_id, clock);
+        }
+        let mut pending = vec![client_id];
+        while let Some(client) = pending.pop() {
+            let from = scanned.get(&client);
+            let to = required.get(&client);
+            if from >= to {
+                continue;
+            }
+            scanned.set_max(client, to);
+            let blocks = match self.blocks.get_client(&client) {
+                Some(blocks) => blocks,
+                None => continue,
+            };
+            for block in blocks.iter() {
+                if block.clock_end() < from || block.clock_start() >= to {
+                    continue;
+                }
+                if let Some(item) = block.as_item() {
+                    let parent = match &item.parent {
+                        TypePtr::Branch(branch) => branch.item.map(|item| item.id),
+                        TypePtr::ID(id) => Some(*id),
+                        _ => None,
+                    };
+                    let deps = [item.origin, item.right_origin, parent];
+                    for id in deps.iter().flatten() {
+                        if required.get(&id.client) <= id.clock {
+                            required.set_max(id.client, id.clock + 1);
+                            pending.push(id.client);
+                        }
+                    }
+                }
+            }
+        }
+        required
+    }
+
     pub(crate) fn write_blocks_from<E: Encoder>(&self, sv: &StateVector, encoder: &mut E) {
+        self.write_blocks_from_ext(sv, encoder, &EncodeOptions::default())
+    }
+
+    /// Writes all blocks not observed by a given state vector `sv`, using provided encoding
+    /// `options`. If sub-documents are excluded, their blocks are written as tombstones of the
+    /// same length, so that the encoded block sequences stay contiguous.
+    pub(crate) fn write_blocks_from_ext<E: Encoder>(
+        &self,
+        sv: &StateVector,
+        encoder: &mut E,
+        options: &EncodeOptions,
+    ) {
+        let encode = |slice: BlockSlice, encoder: &mut E| match slice {
+            BlockSlice::Item(item)
+                if !options.include_subdocs && matches!(item.ptr.content, ItemContent::Doc(..)) =>
+            {
+                item.encode_as_deleted(encoder)
+            }
+            slice => slice.encode(encoder),
+        };
         let local_sv = self.blocks.get_state_vector();
         let mut diff = Self::diff_state_vectors(&local_sv, sv);
 
@@ -223,12 +389,36 @@ impl Store {
             let offset = clock - first_block.clock_start();
             let mut slice = first_block.as_slice();
             slice.trim_start(offset);
-            slice.encode(encoder);
+            encode(slice, encoder);
             for i in (start + 1)..blocks.len() {
                 let block = &blocks[i];
-                block.as_slice().encode(encoder);
+                encode(block.as_slice(), encoder);
+            }
+        }
+    }
+
+    /// Computes an [Update] containing all blocks not observed by a given state vector `sv`
+    /// together with a delete set of this store. It's an in-memory equivalent of decoding
+    /// a result of [Store::encode_diff], which doesn't need to serialize the blocks.
+    pub(crate) fn diff_update(&self, sv: &StateVector) -> Update {
+        let local_sv = self.blocks.get_state_vector();
+        let mut update = Update::new();
+        for (client, clock) in Self::diff_state_vectors(&local_sv, sv) {
+            let blocks = self.blocks.get_client(&client).unwrap();
+            let clock = clock.max(blocks.get(0).map(|i| i.clock_start()).unwrap_or_default()); // make sure the first id exists
+            let start = blocks.find_pivot(clock).unwrap();
+            for i in start..blocks.len() {
+                let block = &blocks[i];
+                let mut copy = BlockCarrier::copy_of(client, block);
+                let offset = clock.saturating_sub(block.clock_start());
+                if offset > 0 {
+                    copy = copy.splice(offset).unwrap();
+                }
+                update.blocks.add_block(copy);
             }
         }
+        update.delete_set = DeleteSet::from(&self.blocks);
+        update
     }
 
     fn diff_state_vectors(local_sv: &StateVector, remote_sv: &StateVector) -> Vec<(ClientID, u32)> {
@@ -341,6 +531,12 @@ impl Store {
         SubdocGuids(self.subdocs.values())
     }
 
+    /// Returns a collection of information about sub documents linked within the structures of
+    /// this document store, including their mount point and load state.
+    pub fn subdoc_infos(&self) -> SubdocInfos<'_> {
+        SubdocInfos(self.subdocs.values())
+    }
+
     pub(crate) fn follow_redone(&self, id: &ID) -> Option<ItemSlice> {
         let mut next_id = Some(*id);
         let mut slice = None;
@@ -465,28 +661,62 @@ impl<'doc> Iterator for SubdocGuids<'doc> {
     }
 }
 
+#[repr(transparent)]
+pub struct SubdocInfos<'doc>(std::collections::hash_map::Values<'doc, DocAddr, Doc>);
+
+impl<'doc> Iterator for SubdocInfos<'doc> {
+    type Item = SubdocInfo;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let d = self.0.next()?;
+        Some(SubdocInfo::new(d))
+    }
+}
+
 #[cfg(feature = "sync")]
 pub type TransactionCleanupFn =
     Box<dyn Fn(&TransactionMut, &TransactionCleanupEvent) + Send + Sync + 'static>;
 #[cfg(feature = "sync")]
 pub type AfterTransactionFn = Box<dyn Fn(&mut TransactionMut) + Send + Sync + 'static>;
 #[cfg(feature = "sync")]
+pub type AfterTransactionUpdateFn =
+    Box<dyn Fn(&mut TransactionMut, Option<&UpdateEvent>) + Send + Sync + 'static>;
+#[cfg(feature = "sync")]
 pub type UpdateFn = Box<dyn Fn(&TransactionMut, &UpdateEvent) + Send + Sync + 'static>;
 #[cfg(feature = "sync")]
+pub type ObserverPanicFn = Box<dyn Fn(Box<dyn std::any::Any + Send>) + Send + Sync + 'static>;
+#[cfg(feature = "sync")]
 pub type SubdocsFn = Box<dyn Fn(&TransactionMut, &SubdocsEvent) + Send + Sync + 'static>;
 #[cfg(feature = "sync")]
 pub type DestroyFn = Box<dyn Fn(&TransactionMut, &Doc) + Send + Sync + 'static>;
+#[cfg(feature = "sync")]
+pub type PendingResolvedFn = Box<dyn Fn(&TransactionMut, &StateVector) + Send + Sync + 'static>;
+#[cfg(feature = "sync")]
+pub type ContentLoadedFn = Box<dyn Fn(&TransactionMut) + Send + Sync + 'static>;
+#[cfg(feature = "sync")]
+pub type UpdateMetricsFn = Box<dyn Fn(usize) + Send + Sync + 'static>;
 
 #[cfg(not(feature = "sync"))]
 pub type TransactionCleanupFn = Box<dyn Fn(&TransactionMut, &TransactionCleanupEvent) + 'static>;
 #[cfg(not(feature = "sync"))]
 pub type AfterTransactionFn = Box<dyn Fn(&mut TransactionMut) + 'static>;
 #[cfg(not(feature = "sync"))]
+pub type AfterTransactionUpdateFn =
+    Box<dyn Fn(&mut TransactionMut, Option<&UpdateEvent>) + 'static>;
+#[cfg(not(feature = "sync"))]
 pub type UpdateFn = Box<dyn Fn(&TransactionMut, &UpdateEvent) + 'static>;
 #[cfg(not(feature = "sync"))]
+pub type ObserverPanicFn = Box<dyn Fn(Box<dyn std::any::Any + Send>) + 'static>;
+#[cfg(not(feature = "sync"))]
 pub type SubdocsFn = Box<dyn Fn(&TransactionMut, &SubdocsEvent) + 'static>;
 #[cfg(not(feature = "sync"))]
 pub type DestroyFn = Box<dyn Fn(&TransactionMut, &Doc) + 'static>;
+#[cfg(not(feature = "sync"))]
+pub type PendingResolvedFn = Box<dyn Fn(&TransactionMut, &StateVector) + 'static>;
+#[cfg(not(feature = "sync"))]
+pub type ContentLoadedFn = Box<dyn Fn(&TransactionMut) + 'static>;
+#[cfg(not(feature = "sync"))]
+pub type UpdateMetricsFn = Box<dyn Fn(usize) + 'static>;
 
 #[derive(Default)]
 pub struct StoreEvents {
@@ -498,29 +728,76 @@ pub struct StoreEvents {
     /// newest updates once they are committed and compacted.
     pub after_transaction_events: Observer<AfterTransactionFn>,
 
+    /// Handles subscriptions for the final stage of a transaction commit. Events are called
+    /// after all update events, together with the lib0 v1 encoded update committed by the
+    /// transaction (if it changed anything).
+    pub after_transaction_update_events: Observer<AfterTransactionUpdateFn>,
+
     /// A subscription handler. It contains all callbacks with registered by user functions that
     /// are supposed to be called, once a new update arrives.
     pub update_v1_events: Observer<UpdateFn>,
 
+    /// Just like [StoreEvents::update_v1_events], but its callbacks are not called for updates
+    /// which contain only deletions and no new blocks.
+    pub update_v1_content_events: Observer<UpdateFn>,
+
     /// A subscription handler. It contains all callbacks with registered by user functions that
     /// are supposed to be called, once a new update arrives.
     pub update_v2_events: Observer<UpdateFn>,
 
+    /// Handles subscriptions receiving only a byte length of lib0 v1 encoded updates.
+    pub update_metrics_events: Observer<UpdateMetricsFn>,
+
     /// Handles subscriptions for subdocs events.
     pub subdocs_events: Observer<SubdocsFn>,
 
     pub destroy_events: Observer<DestroyFn>,
+
+    /// Handles subscriptions for an event emitted when blocks, which were waiting for their
+    /// missing dependencies, have been integrated into the document.
+    pub pending_resolved_events: Observer<PendingResolvedFn>,
+
+    /// Handles subscriptions for an event emitted when an empty document receives its first
+    /// content from an applied update.
+    pub content_loaded_events: Observer<ContentLoadedFn>,
+
+    /// Handler receiving panics caught while calling transaction and update callbacks. If not set,
+    /// the first caught panic is resumed once the transaction has been committed.
+    pub observer_panic_handler: Option<ObserverPanicFn>,
+
+    /// The first panic caught while calling callbacks, which was not passed to
+    /// [StoreEvents::observer_panic_handler].
+    unhandled_panic: Mutex<Option<Box<dyn std::any::Any + Send>>>,
 }
 
 impl StoreEvents {
-    pub fn emit_update_v1(&self, txn: &TransactionMut) {
-        if self.update_v1_events.has_subscribers() {
-            if !txn.delete_set.is_empty() || txn.after_state != txn.before_state {
-                // produce update only if anything changed
-                let update = UpdateEvent::new_v1(txn);
-                self.update_v1_events
-                    .trigger(|callback| callback(txn, &update));
+    /// Emits lib0 v1 encoded update to all update subscribers. Returns the encoded update if it
+    /// was produced, so that it can be reused by [StoreEvents::emit_after_transaction_update].
+    pub fn emit_update_v1(&self, txn: &TransactionMut) -> Option<UpdateEvent> {
+        let has_content = txn.after_state != txn.before_state;
+        let changed = has_content || !txn.delete_set.is_empty();
+        let notify_all = changed && self.update_v1_events.has_subscribers();
+        let notify_content = has_content && self.update_v1_content_events.has_subscribers();
+        let notify_after = changed && self.after_transaction_update_events.has_subscribers();
+        let notify_metrics = changed && self.update_metrics_events.has_subscribers();
+        if notify_all || notify_content || notify_after || notify_metrics {
+            // produce update only if anything changed
+            let update = UpdateEvent::new_v1(txn);
+            if notify_all {
+                self.trigger(&self.update_v1_events, |callback| callback(txn, &update));
+            }
+            if notify_content {
+                self.trigger(&self.update_v1_content_events, |callback| {
+                    callback(txn, &update)
+                });
             }
+            if notify_metrics {
+                let len = update.update.len();
+                self.trigger(&self.update_metrics_events, |callback| callback(len));
+            }
+            Some(update)
+        } else {
+            None
         }
     }
 
@@ -529,20 +806,62 @@ impl StoreEvents {
             if !txn.delete_set.is_empty() || txn.after_state != txn.before_state {
                 // produce update only if anything changed
                 let update = UpdateEvent::new_v2(txn);
-                self.update_v2_events.trigger(|fun| fun(txn, &update));
+                self.trigger(&self.update_v2_events, |fun| fun(txn, &update));
             }
         }
     }
 
     pub fn emit_after_transaction(&self, txn: &mut TransactionMut) {
-        self.after_transaction_events.trigger(|fun| fun(txn));
+        self.trigger(&self.after_transaction_events, |fun| fun(txn));
+    }
+
+    pub fn emit_after_transaction_update(
+        &self,
+        txn: &mut TransactionMut,
+        update: Option<&UpdateEvent>,
+    ) {
+        self.trigger(&self.after_transaction_update_events, |fun| {
+            fun(txn, update)
+        });
+    }
+
+    pub fn emit_pending_resolved(&self, txn: &TransactionMut) {
+        self.trigger(&self.pending_resolved_events, |fun| {
+            fun(txn, &txn.after_state)
+        });
+    }
+
+    pub fn emit_content_loaded(&self, txn: &TransactionMut) {
+        self.trigger(&self.content_loaded_events, |fun| fun(txn));
     }
 
     pub fn emit_transaction_cleanup(&self, txn: &TransactionMut) {
         if self.transaction_cleanup_events.has_subscribers() {
             let event = TransactionCleanupEvent::new(txn);
-            self.transaction_cleanup_events
-                .trigger(|fun| fun(txn, &event));
+            self.trigger(&self.transaction_cleanup_events, |fun| fun(txn, &event));
         }
     }
+
+    /// Calls all callbacks of a given `observer`. Panics raised by callbacks are passed over to
+    /// [StoreEvents::observer_panic_handler], so that they don't prevent remaining callbacks from
+    /// being called nor interrupt a transaction commit.
+    pub(crate) fn trigger<F, E>(&self, observer: &Observer<F>, each: E)
+    where
+        F: 'static,
+        E: FnMut(&F),
+    {
+        observer.trigger_guarded(each, |panic| match &self.observer_panic_handler {
+            Some(handler) => handler(panic),
+            None => {
+                let mut unhandled = self.unhandled_panic.lock().unwrap();
+                unhandled.get_or_insert(panic);
+            }
+        });
+    }
+
+    /// Returns the first panic caught while calling callbacks, which was not handled by
+    /// [StoreEvents::observer_panic_handler].
+    pub(crate) fn take_unhandled_panic(&self) -> Option<Box<dyn std::any::Any + Send>> {
+        self.unhandled_panic.lock().unwrap().take()
+    }
 }
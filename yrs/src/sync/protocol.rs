@@ -75,8 +75,10 @@ pub trait Protocol {
         awareness: &mut Awareness,
         update: Update,
     ) -> Result<Option<Message>, Error> {
-        let mut txn = awareness.doc().transact_mut();
-        txn.apply_update(update);
+        awareness
+            .doc()
+            .apply_remote_update(update)
+            .map_err(|e| Error::Other(Box::new(e)))?;
         Ok(None)
     }
 
@@ -1,25 +1,30 @@
-use crate::block::{Item, ItemContent, ItemPtr, Prelim, ID};
+use crate::block::{BlockCell, BlockInfo, ClientID, Item, ItemContent, ItemPtr, Prelim, ID};
+use crate::block_store::ClientBlockList;
 use crate::branch::{Branch, BranchPtr};
-use crate::doc::DocAddr;
+use crate::doc::{DocAddr, EncodeOptions, GcPolicy};
 use crate::error::Error;
 use crate::event::SubdocsEvent;
 use crate::gc::GCCollector;
 use crate::id_set::DeleteSet;
 use crate::iter::TxnIterator;
 use crate::slice::BlockSlice;
-use crate::store::{Store, StoreEvents, SubdocGuids, SubdocsIter};
-use crate::types::{Event, Events, RootRef, SharedRef, TypePtr};
-use crate::update::Update;
+use crate::store::{Store, StoreEvents, SubdocGuids, SubdocInfos, SubdocsIter};
+use crate::types::map::MapOp;
+use crate::types::{Event, Events, PathSegment, RootRef, SharedRef, ToJson, TypePtr, TypeRef};
+use crate::undo::UndoStack;
+use crate::update::{BlockCarrier, PendingUpdate, Update};
+use crate::updates::decoder::Decode;
 use crate::utils::OptionExt;
 use crate::*;
-use atomic_refcell::{AtomicRef, AtomicRefMut};
+use atomic_refcell::{AtomicRef, AtomicRefCell, AtomicRefMut};
 use smallvec::SmallVec;
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::fmt::Formatter;
 use std::hash::Hash;
-use std::ops::{Deref, DerefMut};
+use std::mem::ManuallyDrop;
+use std::ops::{Deref, DerefMut, Range};
 use std::pin::Pin;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use updates::encoder::*;
 
 /// Trait defining read capabilities present in a transaction. Implemented by both lightweight
@@ -27,11 +32,42 @@ use updates::encoder::*;
 pub trait ReadTxn: Sized {
     fn store(&self) -> &Store;
 
+    /// Returns a per-transaction read cache, if one was enabled for current transaction.
+    /// Read-write transactions never expose a cache, as their contents may change at any time.
+    /// See: [Transaction::with_read_cache].
+    #[inline]
+    fn read_cache(&self) -> Option<&ReadCache> {
+        None
+    }
+
     /// Returns state vector describing current state of the updates.
     fn state_vector(&self) -> StateVector {
         self.store().blocks.get_state_vector()
     }
 
+    /// Compares current document state against the `remote` state vector, telling if both
+    /// replicas are in sync. See: [StateVector::compare].
+    fn diff_status(&self, remote: &StateVector) -> SyncStatus {
+        self.state_vector().compare(remote)
+    }
+
+    /// Returns a lib0 v1 encoded [state vector](ReadTxn::state_vector) of the document.
+    ///
+    /// Read-only transactions reuse bytes cached by the document store, which are invalidated only
+    /// once a transaction changing the document state is committed. This makes it cheap to answer
+    /// repeated sync requests for a document that didn't change in the meantime.
+    fn state_vector_bytes_v1(&self) -> Arc<[u8]> {
+        self.store().state_vector_bytes_v1()
+    }
+
+    /// Clears provided `buf` and writes a lib0 v1 encoded state vector of a current document
+    /// into it. The output is the same as the one of [ReadTxn::state_vector_bytes_v1], but it
+    /// can be written into a reusable buffer.
+    fn encode_state_vector_into(&self, buf: &mut Vec<u8>) {
+        buf.clear();
+        buf.extend_from_slice(&self.state_vector_bytes_v1());
+    }
+
     /// Returns a snapshot which describes a current state of updates and removals made within
     /// the corresponding document.
     fn snapshot(&self) -> Snapshot {
@@ -53,23 +89,91 @@ pub trait ReadTxn: Sized {
     }
 
     /// Encodes the difference between remove peer state given its `state_vector` and the state
-    /// of a current local peer
+    /// of a current local peer.
+    ///
+    /// Encoding only reads the document store, so it can be done using a read-only [Transaction].
+    /// This way multiple sync responses can be computed concurrently.
     fn encode_diff<E: Encoder>(&self, state_vector: &StateVector, encoder: &mut E) {
         self.store().encode_diff(state_vector, encoder)
     }
 
+    /// Encodes the difference between remove peer state given its `state_vector` and the state
+    /// of a current local peer using lib0 v1 encoding.
     fn encode_diff_v1(&self, state_vector: &StateVector) -> Vec<u8> {
         let mut encoder = EncoderV1::new();
         self.encode_diff(state_vector, &mut encoder);
         encoder.to_vec()
     }
 
+    /// Works like [ReadTxn::encode_diff_v1], but instead of allocating a new vector, it clears
+    /// provided `buf` and writes the encoded update into it. This way the same buffer can be
+    /// reused across many calls.
+    fn encode_diff_into(&self, state_vector: &StateVector, buf: &mut Vec<u8>) {
+        buf.clear();
+        let mut encoder = EncoderV1::with_buffer(std::mem::take(buf));
+        self.encode_diff(state_vector, &mut encoder);
+        *buf = encoder.to_vec();
+    }
+
+    /// Encodes the difference between remove peer state given its `state_vector` and the state
+    /// of a current local peer using lib0 v2 encoding.
     fn encode_diff_v2(&self, state_vector: &StateVector) -> Vec<u8> {
         let mut encoder = EncoderV2::new();
         self.encode_diff(state_vector, &mut encoder);
         encoder.to_vec()
     }
 
+    /// Decodes a lib0 v1 encoded `state_vector` received from a remote peer and returns the
+    /// difference between its state and the state of a current local peer, encoded using lib0 v1
+    /// encoding. Returns an error if `state_vector` is malformed.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, ReadTxn, Text, Transact, Update};
+    /// use yrs::updates::decoder::Decode;
+    ///
+    /// let local = Doc::new();
+    /// let txt = local.get_or_insert_text("text");
+    /// txt.push(&mut local.transact_mut(), "hello");
+    ///
+    /// let remote = Doc::new();
+    /// let sv = remote.transact().state_vector_bytes_v1();
+    /// let diff = local.transact().diff_to_peer(&sv).unwrap();
+    /// remote.transact_mut().apply_update(Update::decode_v1(&diff).unwrap());
+    ///
+    /// assert!(local.transact().diff_to_peer(&[0xff]).is_err());
+    /// ```
+    fn diff_to_peer(&self, state_vector: &[u8]) -> Result<Vec<u8>, crate::encoding::read::Error> {
+        let sv = StateVector::decode_v1(state_vector)?;
+        Ok(self.encode_diff_v1(&sv))
+    }
+
+    /// Decodes a lib0 v2 encoded `state_vector` received from a remote peer and returns the
+    /// difference between its state and the state of a current local peer, encoded using lib0 v2
+    /// encoding. Returns an error if `state_vector` is malformed.
+    fn diff_to_peer_v2(
+        &self,
+        state_vector: &[u8],
+    ) -> Result<Vec<u8>, crate::encoding::read::Error> {
+        let sv = StateVector::decode_v2(state_vector)?;
+        Ok(self.encode_diff_v2(&sv))
+    }
+
+    /// Returns a size (in bytes) of the lib0 v1 encoded update containing the whole document
+    /// state, as produced by [ReadTxn::encode_state_as_update_v1] called with an empty state
+    /// vector. The size is computed by walking over the blocks and delete set, without allocating
+    /// the update buffer itself.
+    ///
+    /// Returned value is exact, unless the document contains pending updates waiting for their
+    /// missing dependencies. These are not accounted for, so in such case returned value is a
+    /// lower bound of the actual update size.
+    fn encoded_size_hint(&self) -> usize {
+        let mut counter = EncodedLenV1::default();
+        self.encode_state_as_update(&StateVector::default(), &mut counter);
+        counter.len()
+    }
+
     fn encode_state_as_update<E: Encoder>(&self, sv: &StateVector, encoder: &mut E) {
         let store = self.store();
         store.write_blocks_from(sv, encoder);
@@ -77,6 +181,16 @@ pub trait ReadTxn: Sized {
         ds.encode(encoder);
     }
 
+    /// Encodes all changes made by a given `client_id` together with their causal dependencies
+    /// (changes made by other clients, which are necessary to integrate them) using lib0 v1
+    /// encoding. This can be used to let a peer recover its own contributions without having
+    /// to send the whole document state.
+    fn encode_client_state(&self, client_id: ClientID) -> Vec<u8> {
+        let mut encoder = EncoderV1::new();
+        self.store().encode_client_state(client_id, &mut encoder);
+        encoder.to_vec()
+    }
+
     fn encode_state_as_update_v1(&self, sv: &StateVector) -> Vec<u8> {
         let mut encoder = EncoderV1::new();
         self.encode_state_as_update(sv, &mut encoder);
@@ -84,6 +198,42 @@ pub trait ReadTxn: Sized {
         merge_pending_v1(encoder.to_vec(), self.store())
     }
 
+    /// Encodes the difference between remote peer state given its `sv` state vector and the state
+    /// of a current document using lib0 v1 encoding, customized with provided `options`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::updates::decoder::Decode;
+    /// use yrs::{Doc, EncodeOptions, Map, ReadTxn, StateVector, Transact, Update};
+    ///
+    /// let doc = Doc::new();
+    /// let map = doc.get_or_insert_map("map");
+    /// map.insert(&mut doc.transact_mut(), "subdoc", Doc::new());
+    ///
+    /// let options = EncodeOptions {
+    ///     include_subdocs: false,
+    /// };
+    /// let update = doc
+    ///     .transact()
+    ///     .encode_state_as_update_v1_ext(&StateVector::default(), &options);
+    ///
+    /// let copy = Doc::new();
+    /// let copy_map = copy.get_or_insert_map("map");
+    /// copy.transact_mut()
+    ///     .apply_update(Update::decode_v1(&update).unwrap());
+    /// assert_eq!(copy_map.get(&copy.transact(), "subdoc"), None);
+    /// ```
+    fn encode_state_as_update_v1_ext(&self, sv: &StateVector, options: &EncodeOptions) -> Vec<u8> {
+        let mut encoder = EncoderV1::new();
+        let store = self.store();
+        store.write_blocks_from_ext(sv, &mut encoder, options);
+        let ds = DeleteSet::from(&store.blocks);
+        ds.encode(&mut encoder);
+        // check for pending data
+        merge_pending_v1(encoder.to_vec(), store)
+    }
+
     fn encode_state_as_update_v2(&self, sv: &StateVector) -> Vec<u8> {
         let mut encoder = EncoderV2::new();
         self.encode_state_as_update(sv, &mut encoder);
@@ -107,6 +257,67 @@ pub trait ReadTxn: Sized {
         RootRefs(store.types.iter())
     }
 
+    /// Walks over all shared types of current document - both root-level and nested ones - and
+    /// returns a number of their instances grouped by their kind, together with the maximum
+    /// nesting depth. Only alive (non-deleted) nested types are taken into account.
+    ///
+    /// This is meant for diagnostic purposes, ie. to get an insight into shape of deeply nested
+    /// documents.
+    fn type_counts(&self) -> TypeCounts {
+        let mut counts = TypeCounts::default();
+        let mut stack: Vec<(BranchPtr, usize)> = self
+            .store()
+            .types
+            .values()
+            .map(|branch| (BranchPtr::from(branch), 1))
+            .collect();
+        while let Some((branch, depth)) = stack.pop() {
+            counts.add(branch.type_ref(), depth);
+            let mut children: Vec<ItemPtr> = branch.map.values().copied().collect();
+            let mut current = branch.start;
+            while let Some(item) = current {
+                children.push(item);
+                current = item.right;
+            }
+            for item in children {
+                if let ItemContent::Type(child) = &item.content {
+                    if !item.is_deleted() {
+                        stack.push((BranchPtr::from(child), depth + 1));
+                    }
+                }
+            }
+        }
+        counts
+    }
+
+    /// Returns a list of JSON Patch operations, which would transform a JSON representation of
+    /// all root types of current document (just like the one returned by `Doc::to_json`) into
+    /// a `target` value. See: [Any::json_patch_to].
+    fn json_patch_to(&self, target: &Any) -> Vec<PatchOp> {
+        let mut current = HashMap::new();
+        for (key, value) in self.root_refs() {
+            current.insert(key.to_string(), value.to_json(self));
+        }
+        Any::from(current).json_patch_to(target)
+    }
+
+    /// Returns an iterator over the blocks inserted by a given `client_id`, which fall into
+    /// a given `range` of its clock sequence numbers. Blocks crossing the range boundaries are
+    /// trimmed to fit in it, so that all returned blocks are within requested bounds.
+    fn client_blocks(&self, client_id: ClientID, range: Range<u32>) -> ClientBlocks<'_> {
+        let store = self.store();
+        let blocks = store.blocks.get_client(&client_id);
+        let index = blocks
+            .and_then(|blocks| blocks.find_pivot(range.start))
+            .unwrap_or(usize::MAX);
+        ClientBlocks {
+            client_id,
+            blocks,
+            index,
+            range,
+        }
+    }
+
     /// Returns a collection of globally unique identifiers of sub documents linked within
     /// the structures of this document store.
     fn subdoc_guids(&self) -> SubdocGuids {
@@ -120,6 +331,13 @@ pub trait ReadTxn: Sized {
         store.subdocs()
     }
 
+    /// Returns a collection of information about sub documents linked within the structures of
+    /// this document store: their globally unique identifiers, mount points and load state.
+    fn subdoc_infos(&self) -> SubdocInfos<'_> {
+        let store = self.store();
+        store.subdoc_infos()
+    }
+
     /// Returns a [TextRef] data structure stored under a given `name`. Text structures are used for
     /// collaborative text editing: they expose operations to append and remove chunks of text,
     /// which are free to execute concurrently by multiple peers over remote boundaries.
@@ -287,11 +505,35 @@ fn merge_pending_v2(update: Vec<u8>, store: &Store) -> Vec<u8> {
 #[derive(Debug)]
 pub struct Transaction<'doc> {
     store: AtomicRef<'doc, Store>,
+    cache: Option<ReadCache>,
 }
 
 impl<'doc> Transaction<'doc> {
     pub(crate) fn new(store: AtomicRef<'doc, Store>) -> Self {
-        Transaction { store }
+        Transaction { store, cache: None }
+    }
+
+    /// Enables memoization of materialized values (like [TextRef] string contents) for the
+    /// lifetime of current transaction. Since read-only transactions cannot coexist with
+    /// read-write ones, cached values are guaranteed to stay valid until this transaction is
+    /// dropped.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, GetString, Text, Transact};
+    ///
+    /// let doc = Doc::new();
+    /// let text = doc.get_or_insert_text("text");
+    /// text.push(&mut doc.transact_mut(), "hello");
+    ///
+    /// let txn = doc.transact().with_read_cache();
+    /// assert_eq!(text.get_string(&txn), "hello");
+    /// assert_eq!(text.get_string(&txn), "hello"); // served from cache
+    /// ```
+    pub fn with_read_cache(mut self) -> Self {
+        self.cache = Some(ReadCache::default());
+        self
     }
 }
 
@@ -300,6 +542,33 @@ impl<'doc> ReadTxn for Transaction<'doc> {
     fn store(&self) -> &Store {
         self.store.deref()
     }
+
+    #[inline]
+    fn read_cache(&self) -> Option<&ReadCache> {
+        self.cache.as_ref()
+    }
+}
+
+/// Cache of values materialized from shared types within the scope of a single read-only
+/// [Transaction]. It's discarded together with the transaction that owns it.
+#[derive(Debug, Default)]
+pub struct ReadCache {
+    strings: AtomicRefCell<HashMap<BranchPtr, String>>,
+}
+
+impl ReadCache {
+    /// Returns a string cached for a given `branch`, computing it with `init` if necessary.
+    pub(crate) fn get_or_init_string<F>(&self, branch: BranchPtr, init: F) -> String
+    where
+        F: FnOnce() -> String,
+    {
+        if let Some(s) = self.strings.borrow().get(&branch) {
+            return s.clone();
+        }
+        let s = init();
+        self.strings.borrow_mut().insert(branch, s.clone());
+        s
+    }
 }
 
 /// Read-write transaction. It can be used to modify an underlying state of the corresponding [Doc].
@@ -312,10 +581,13 @@ impl<'doc> ReadTxn for Transaction<'doc> {
 /// triggering necessary event callbacks etc. For performance reasons it's preferred to batch as
 /// many updates as possible using the same transaction.
 ///
-/// In Yrs transactions are always auto-committing all of their changes when dropped. Rollbacks are
-/// not supported (if some operations needs to be undone, this can be achieved using [UndoManager])
+/// In Yrs transactions are always auto-committing all of their changes when dropped. Changes can be
+/// discarded before that happens using [TransactionMut::rollback]. If some already committed
+/// operations need to be undone, this can be achieved using [UndoManager].
 pub struct TransactionMut<'doc> {
-    pub(crate) store: AtomicRefMut<'doc, Store>,
+    /// Exclusive borrow of the document store. It's released manually on drop, so that deferred
+    /// callbacks (see: [TransactionMut::defer]) can open their own follow-up transaction.
+    pub(crate) store: ManuallyDrop<AtomicRefMut<'doc, Store>>,
     /// State vector of a current transaction at the moment of its creation.
     pub(crate) before_state: StateVector,
     /// Current state vector of a transaction, which includes all performed updates.
@@ -333,15 +605,112 @@ pub struct TransactionMut<'doc> {
     pub(crate) changed_parent_types: Vec<BranchPtr>,
     pub(crate) subdocs: Option<Box<Subdocs>>,
     pub(crate) origin: Option<Origin>,
+    /// Callbacks scheduled via [TransactionMut::defer] to run once this transaction is dropped.
+    deferred: Mutex<Vec<Deferred>>,
+    /// Number of follow-up transactions opened to run deferred callbacks, which led to opening
+    /// current transaction.
+    defer_depth: usize,
+    /// Callback registered via [TransactionMut::observe_local_change].
+    local_change: Option<LocalChangeFn<'doc>>,
+    /// Set when current transaction has been opened on a document in [Options::read_only] mode.
+    /// Remote updates can be applied within such transaction, but local changes cannot.
+    read_only: bool,
+    /// Ordered operations applied to maps, which have subscribers registered via
+    /// [MapRef::observe_ops].
+    pub(crate) map_ops: HashMap<BranchPtr, Vec<MapOp>>,
+    /// If set, all compatible blocks of the document will be merged on commit (see: [Doc::compact]).
+    pub(crate) compact: bool,
+    /// Set when blocks which were pending because of missing dependencies have been integrated.
+    pending_resolved: bool,
+    /// Set when an update applied to an empty document has integrated its first blocks.
+    content_loaded: bool,
+    /// State of the document store required to roll back current transaction.
+    checkpoint: Checkpoint,
     doc: Doc,
     committed: bool,
 }
 
+#[cfg(feature = "sync")]
+type Deferred = Box<dyn FnOnce(&mut TransactionMut) + Send + 'static>;
+
+#[cfg(not(feature = "sync"))]
+type Deferred = Box<dyn FnOnce(&mut TransactionMut) + 'static>;
+
+/// Maximum number of nested follow-up transactions, which can be opened by callbacks deferring
+/// further callbacks (see: [TransactionMut::defer]).
+const MAX_DEFER_DEPTH: usize = 16;
+
+#[cfg(feature = "sync")]
+pub(crate) type LocalChangeFn<'doc> = Box<dyn FnMut(&LocalChange) + Send + Sync + 'doc>;
+
+#[cfg(not(feature = "sync"))]
+pub(crate) type LocalChangeFn<'doc> = Box<dyn FnMut(&LocalChange) + 'doc>;
+
+/// Error returned by [TransactionMut::insert_at] when a given path couldn't be resolved.
+/// Each variant carries a position of the path segment, which caused the failure.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum PathError {
+    /// Path has no segments, so there's no place to insert a value at.
+    #[error("path cannot be empty")]
+    Empty,
+    /// Path segment refers to an array element, which doesn't exist.
+    #[error("path segment {0} points to a non-existing array element")]
+    NotFound(usize),
+    /// Path segment doesn't match the type of collection it's applied to, ie. a key segment was
+    /// used on an array or a map entry is not a shared collection.
+    #[error("path segment {0} doesn't match the type of collection it's applied to")]
+    TypeMismatch(usize),
+}
+
+/// Error returned by [TransactionMut::try_apply_update] when an update couldn't be applied.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum UpdateError {
+    /// Update contains blocks authored by a given client ID of a current document, which have
+    /// not been created by a current document itself.
+    #[error("update contains changes of client {0}, which were not authored by current document")]
+    ClientIdCollision(ClientID),
+}
+
+/// A single change performed using the API of a [TransactionMut], reported to a callback
+/// registered with [TransactionMut::observe_local_change] before the transaction is committed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LocalChange {
+    /// A new block of `len` elements starting at `id` has been inserted into a `parent` collection.
+    /// `key` is present if block is an entry of a map-like type.
+    Inserted {
+        parent: BranchPtr,
+        key: Option<Arc<str>>,
+        id: ID,
+        len: u32,
+    },
+    /// A block of `len` elements starting at `id` has been removed from a `parent` collection.
+    /// `key` is present if block is an entry of a map-like type.
+    Removed {
+        parent: BranchPtr,
+        key: Option<Arc<str>>,
+        id: ID,
+        len: u32,
+    },
+}
+
 impl<'doc> ReadTxn for TransactionMut<'doc> {
     #[inline]
     fn store(&self) -> &Store {
         self.store.deref()
     }
+
+    /// Read-write transactions always encode the current state vector, since cached bytes may be
+    /// outdated by uncommitted changes made within the transaction.
+    fn state_vector_bytes_v1(&self) -> Arc<[u8]> {
+        self.state_vector().encode_v1().into()
+    }
+
+    fn encode_state_vector_into(&self, buf: &mut Vec<u8>) {
+        buf.clear();
+        let mut encoder = EncoderV1::with_buffer(std::mem::take(buf));
+        self.state_vector().encode(&mut encoder);
+        *buf = encoder.to_vec();
+    }
 }
 
 impl<'doc> WriteTxn for TransactionMut<'doc> {
@@ -355,17 +724,62 @@ impl<'doc> WriteTxn for TransactionMut<'doc> {
     }
 }
 
+/// Parts of a document store state, which cannot be recovered from the block store alone, captured
+/// before running changes that may need to be rolled back (see: [TransactionMut::rollback]).
+#[derive(Default)]
+pub(crate) struct Checkpoint {
+    /// Weak link dependencies, captured lazily before they are modified for the first time.
+    linked_by: Option<HashMap<ItemPtr, HashSet<BranchPtr>>>,
+    /// Pending blocks and deletions, captured lazily before they are modified for the first time.
+    pending: Option<(Option<EncodedPending>, Option<DeleteSet>)>,
+}
+
+/// Pending update encoded using lib0 v1 encoding together with its missing state.
+type EncodedPending = (Vec<u8>, StateVector);
+
 impl<'doc> Drop for TransactionMut<'doc> {
     fn drop(&mut self) {
-        self.commit()
+        self.commit();
+        let panic = match self.store.events.as_ref() {
+            Some(events) => events.take_unhandled_panic(),
+            None => None,
+        };
+        let deferred = std::mem::take(self.deferred.get_mut().unwrap());
+        // release document store, so that deferred callbacks can open a new transaction
+        unsafe { ManuallyDrop::drop(&mut self.store) };
+        if !deferred.is_empty() {
+            // deferred callbacks are local changes, so they are not run on read-only documents
+            let txn = match self.origin.take() {
+                Some(origin) => self.doc.try_transact_mut_with(origin),
+                None => self.doc.try_transact_mut(),
+            };
+            if let Ok(mut txn) = txn {
+                txn.defer_depth = self.defer_depth + 1;
+                for f in deferred {
+                    f(&mut txn);
+                }
+            }
+        }
+        if let Some(panic) = panic {
+            // panics raised by observers are resumed only once the document has been released
+            if !std::thread::panicking() {
+                std::panic::resume_unwind(panic);
+            }
+        }
     }
 }
 
 impl<'doc> TransactionMut<'doc> {
     pub(crate) fn new(doc: Doc, store: AtomicRefMut<'doc, Store>, origin: Option<Origin>) -> Self {
         let begin_timestamp = store.blocks.get_state_vector();
+        let read_only = store.options.read_only;
         TransactionMut {
-            store,
+            store: ManuallyDrop::new(store),
+            deferred: Mutex::default(),
+            defer_depth: 0,
+            local_change: None,
+            read_only,
+            map_ops: HashMap::new(),
             doc,
             origin,
             before_state: begin_timestamp,
@@ -376,6 +790,10 @@ impl<'doc> TransactionMut<'doc> {
             changed_parent_types: Vec::default(),
             prev_moved: HashMap::default(),
             subdocs: None,
+            compact: false,
+            pending_resolved: false,
+            content_loaded: false,
+            checkpoint: Checkpoint::default(),
             committed: false,
         }
     }
@@ -409,6 +827,300 @@ impl<'doc> TransactionMut<'doc> {
         self.origin.as_ref()
     }
 
+    /// Schedules a callback `f` to be executed in a follow-up read-write transaction, which will
+    /// be opened right after the current one has been committed and dropped. Follow-up
+    /// transaction uses the same [Origin] as the current one.
+    ///
+    /// Document store is exclusively locked for the whole lifetime of a [TransactionMut], including
+    /// the time when the event callbacks are being called during [TransactionMut::commit]. For this
+    /// reason any attempt to open another transaction from within callbacks like
+    /// [Doc::observe_update_v1] will fail. This method can be used instead to safely react to
+    /// changes by mutating the document. All callbacks deferred within the same transaction are
+    /// executed in the order of their scheduling within a single follow-up transaction.
+    ///
+    /// Follow-up transaction is subject to [Options::read_only] mode just like
+    /// [Transact::try_transact_mut]: deferred callbacks are discarded if the document is read-only
+    /// at the moment of opening it.
+    ///
+    /// # Panics
+    ///
+    /// Callbacks may defer further callbacks, each time opening another follow-up transaction.
+    /// This method panics if such chain of follow-up transactions gets deeper than 16 levels,
+    /// ie. when an observer unconditionally defers changes, which trigger it again.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, GetString, Text, Transact};
+    ///
+    /// let doc = Doc::new();
+    /// let text = doc.get_or_insert_text("text");
+    /// let t = text.clone();
+    /// let _sub = doc.observe_update_v1(move |txn, _| {
+    ///     if t.get_string(txn) == "hello" {
+    ///         let t = t.clone();
+    ///         txn.defer(move |txn| t.push(txn, " world"));
+    ///     }
+    /// }).unwrap();
+    ///
+    /// text.push(&mut doc.transact_mut(), "hello");
+    /// assert_eq!(text.get_string(&doc.transact()), "hello world");
+    /// ```
+    #[cfg(feature = "sync")]
+    pub fn defer<F>(&self, f: F)
+    where
+        F: FnOnce(&mut TransactionMut) + Send + 'static,
+    {
+        if self.defer_depth >= MAX_DEFER_DEPTH {
+            panic!(
+                "callbacks deferred recursively by more than {} follow-up transactions",
+                MAX_DEFER_DEPTH
+            );
+        }
+        self.deferred.lock().unwrap().push(Box::new(f));
+    }
+
+    /// Schedules a callback `f` to be executed in a follow-up read-write transaction, which will
+    /// be opened right after the current one has been committed and dropped. Follow-up
+    /// transaction uses the same [Origin] as the current one.
+    ///
+    /// Document store is exclusively locked for the whole lifetime of a [TransactionMut], including
+    /// the time when the event callbacks are being called during [TransactionMut::commit]. For this
+    /// reason any attempt to open another transaction from within callbacks like
+    /// [Doc::observe_update_v1] will fail. This method can be used instead to safely react to
+    /// changes by mutating the document. All callbacks deferred within the same transaction are
+    /// executed in the order of their scheduling within a single follow-up transaction.
+    ///
+    /// Follow-up transaction is subject to [Options::read_only] mode just like
+    /// [Transact::try_transact_mut]: deferred callbacks are discarded if the document is read-only
+    /// at the moment of opening it.
+    ///
+    /// # Panics
+    ///
+    /// Callbacks may defer further callbacks, each time opening another follow-up transaction.
+    /// This method panics if such chain of follow-up transactions gets deeper than 16 levels,
+    /// ie. when an observer unconditionally defers changes, which trigger it again.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, GetString, Text, Transact};
+    ///
+    /// let doc = Doc::new();
+    /// let text = doc.get_or_insert_text("text");
+    /// let t = text.clone();
+    /// let _sub = doc.observe_update_v1(move |txn, _| {
+    ///     if t.get_string(txn) == "hello" {
+    ///         let t = t.clone();
+    ///         txn.defer(move |txn| t.push(txn, " world"));
+    ///     }
+    /// }).unwrap();
+    ///
+    /// text.push(&mut doc.transact_mut(), "hello");
+    /// assert_eq!(text.get_string(&doc.transact()), "hello world");
+    /// ```
+    #[cfg(not(feature = "sync"))]
+    pub fn defer<F>(&self, f: F)
+    where
+        F: FnOnce(&mut TransactionMut) + 'static,
+    {
+        if self.defer_depth >= MAX_DEFER_DEPTH {
+            panic!(
+                "callbacks deferred recursively by more than {} follow-up transactions",
+                MAX_DEFER_DEPTH
+            );
+        }
+        self.deferred.lock().unwrap().push(Box::new(f));
+    }
+
+    /// Registers a callback `f`, which will be called synchronously after every block of data
+    /// inserted or removed using the API of the current transaction, before the transaction is
+    /// committed. This enables reacting to changes while a long-running transaction is still in
+    /// progress. Only one callback can be registered at the time: subsequent calls replace
+    /// previously registered callback.
+    ///
+    /// A single operation (eg. inserting a [MapPrelim] with many entries or removing a range
+    /// spanning over multiple blocks) may trigger the callback several times. Changes applied
+    /// as part of remote updates and rich text formatting markers are not reported.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, LocalChange, Text, Transact};
+    ///
+    /// let doc = Doc::new();
+    /// let text = doc.get_or_insert_text("text");
+    /// let mut changes = 0;
+    /// {
+    ///     let mut txn = doc.transact_mut();
+    ///     txn.observe_local_change(|_: &LocalChange| changes += 1);
+    ///     text.insert(&mut txn, 0, "hello");
+    ///     text.insert(&mut txn, 5, " world");
+    /// }
+    /// assert_eq!(changes, 2);
+    /// ```
+    #[cfg(feature = "sync")]
+    pub fn observe_local_change<F>(&mut self, f: F)
+    where
+        F: FnMut(&LocalChange) + Send + Sync + 'doc,
+    {
+        self.local_change = Some(Box::new(f));
+    }
+
+    /// Registers a callback `f`, which will be called synchronously after every block of data
+    /// inserted or removed using the API of the current transaction, before the transaction is
+    /// committed. This enables reacting to changes while a long-running transaction is still in
+    /// progress. Only one callback can be registered at the time: subsequent calls replace
+    /// previously registered callback.
+    ///
+    /// A single operation (eg. inserting a [MapPrelim] with many entries or removing a range
+    /// spanning over multiple blocks) may trigger the callback several times. Changes applied
+    /// as part of remote updates and rich text formatting markers are not reported.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, LocalChange, Text, Transact};
+    ///
+    /// let doc = Doc::new();
+    /// let text = doc.get_or_insert_text("text");
+    /// let mut changes = 0;
+    /// {
+    ///     let mut txn = doc.transact_mut();
+    ///     txn.observe_local_change(|_: &LocalChange| changes += 1);
+    ///     text.insert(&mut txn, 0, "hello");
+    ///     text.insert(&mut txn, 5, " world");
+    /// }
+    /// assert_eq!(changes, 2);
+    /// ```
+    #[cfg(not(feature = "sync"))]
+    pub fn observe_local_change<F>(&mut self, f: F)
+    where
+        F: FnMut(&LocalChange) + 'doc,
+    {
+        self.local_change = Some(Box::new(f));
+    }
+
+    /// Inserts a `value` at a given `path`, resolved starting from a `root` map. It's a mutating
+    /// counterpart of [Any::get_path]: all segments but the last one are walked through, with
+    /// missing [Map] entries being created as new nested maps along the way. [PathSegment::Index]
+    /// segments must point to existing [Array] elements. The last segment defines where `value`
+    /// will be inserted: either under a map key or at an array index.
+    ///
+    /// Path is fully validated before any change is made, so if an error is returned, document
+    /// is left untouched.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::types::{PathSegment, ToJson};
+    /// use yrs::{Any, Doc, Map, Transact};
+    ///
+    /// let doc = Doc::new();
+    /// let root = doc.get_or_insert_map("root");
+    /// let mut txn = doc.transact_mut();
+    /// let path = [PathSegment::Key("a".into()), PathSegment::Key("b".into())];
+    /// txn.insert_at(&root, &path, "value").unwrap();
+    ///
+    /// let json = root.to_json(&txn);
+    /// assert_eq!(json.get_path(&path), Some(&Any::from("value")));
+    /// ```
+    pub fn insert_at<V: Prelim>(
+        &mut self,
+        root: &MapRef,
+        path: &[PathSegment],
+        value: V,
+    ) -> Result<V::Return, PathError> {
+        let (last, parents) = path.split_last().ok_or(PathError::Empty)?;
+        // resolve existing part of the path first
+        let mut current = Out::YMap(root.clone());
+        let mut resolved = 0;
+        for segment in parents {
+            let next = match (&current, segment) {
+                (Out::YMap(map), PathSegment::Key(key)) => map.get(self, key),
+                (Out::YArray(array), PathSegment::Index(index)) => Some(
+                    array
+                        .get(self, *index)
+                        .ok_or(PathError::NotFound(resolved))?,
+                ),
+                _ => return Err(PathError::TypeMismatch(resolved)),
+            };
+            match next {
+                Some(next) => current = next,
+                None => break,
+            }
+            resolved += 1;
+        }
+        // the rest of the path refers to maps which are yet to be created
+        let missing = &path[resolved..];
+        if let Some(i) = missing
+            .iter()
+            .position(|segment| matches!(segment, PathSegment::Index(_)))
+        {
+            if resolved < parents.len() {
+                return Err(PathError::NotFound(resolved + i));
+            }
+        }
+        match (&current, last) {
+            (Out::YMap(_), PathSegment::Key(_)) => { /* ok */ }
+            (Out::YArray(array), PathSegment::Index(index)) => {
+                if *index > array.len(self) {
+                    return Err(PathError::NotFound(resolved));
+                }
+            }
+            _ => return Err(PathError::TypeMismatch(resolved)),
+        }
+
+        for segment in &path[resolved..parents.len()] {
+            if let (Out::YMap(map), PathSegment::Key(key)) = (&current, segment) {
+                current = Out::YMap(map.insert(self, key.clone(), MapPrelim::default()));
+            }
+        }
+        Ok(match (current, last) {
+            (Out::YMap(map), PathSegment::Key(key)) => map.insert(self, key.clone(), value),
+            (Out::YArray(array), PathSegment::Index(index)) => array.insert(self, *index, value),
+            _ => unreachable!("path has been validated"),
+        })
+    }
+
+    pub(crate) fn notify_local_change(&mut self, item: ItemPtr, inserted: bool) {
+        if let Some(callback) = self.local_change.as_mut() {
+            if let Some(parent) = item.parent.as_branch() {
+                let parent = *parent;
+                let key = item.parent_sub.clone();
+                let id = *item.id();
+                let len = item.len();
+                let change = if inserted {
+                    LocalChange::Inserted {
+                        parent,
+                        key,
+                        id,
+                        len,
+                    }
+                } else {
+                    LocalChange::Removed {
+                        parent,
+                        key,
+                        id,
+                        len,
+                    }
+                };
+                callback(&change)
+            }
+        }
+    }
+
+    /// Records an operation applied to a given map `parent`, if that map has any subscribers
+    /// registered via [MapRef::observe_ops].
+    pub(crate) fn record_map_op<F>(&mut self, parent: BranchPtr, op: F)
+    where
+        F: FnOnce() -> MapOp,
+    {
+        if parent.op_observers.has_subscribers() {
+            self.map_ops.entry(parent).or_default().push(op());
+        }
+    }
+
     /// Returns a list of root level types changed in a scope of the current transaction. This
     /// list is not filled right away, but as a part of [TransactionMut::commit] process.
     pub fn changed_parent_types(&self) -> &[BranchPtr] {
@@ -574,9 +1286,28 @@ impl<'doc> TransactionMut<'doc> {
         }
     }
 
+    /// Panics if current transaction has been opened on a document in [Options::read_only] mode,
+    /// unless it's integrating a remote update at the moment. Called before every local change.
+    pub(crate) fn ensure_writable(&self) {
+        if self.read_only {
+            panic!("cannot change a document in read-only mode");
+        }
+    }
+
     /// Delete item under given pointer.
     /// Returns true if block was successfully deleted, false if it was already deleted in the past.
-    pub(crate) fn delete(&mut self, mut item: ItemPtr) -> bool {
+    pub(crate) fn delete(&mut self, item: ItemPtr) -> bool {
+        self.ensure_writable();
+        let deleted = self.delete_item(item);
+        if deleted {
+            self.notify_local_change(item, false);
+        }
+        deleted
+    }
+
+    /// Delete item under given pointer without notifying [TransactionMut::observe_local_change]
+    /// callback. Used to recursively delete contents of a deleted shared type.
+    fn delete_item(&mut self, mut item: ItemPtr) -> bool {
         let mut recurse = Vec::new();
         let mut result = false;
 
@@ -590,6 +1321,16 @@ impl<'doc> TransactionMut<'doc> {
                 }
             }
 
+            if let (Some(key), TypePtr::Branch(parent)) = (&item.parent_sub, &item.parent) {
+                if parent.map.get(key) == Some(&ptr) {
+                    // only the current value of an entry can be removed, others are overridden
+                    self.record_map_op(*parent, || MapOp::Removed {
+                        key: key.clone(),
+                        old_value: ptr.content.get_last().unwrap_or_default(),
+                    });
+                }
+            }
+
             item.mark_as_deleted();
             self.delete_set.insert(item.id.clone(), item.len());
             if let Some(parent) = item.parent.as_branch() {
@@ -633,7 +1374,7 @@ impl<'doc> TransactionMut<'doc> {
             }
             if item.info.is_linked() {
                 // notify links that current element has been removed
-                if let Some(linked_by) = self.store.linked_by.remove(&item) {
+                if let Some(linked_by) = self.linked_by_mut().remove(&item) {
                     for link in linked_by {
                         self.add_changed_type(link, item.parent_sub.clone());
                         #[cfg(feature = "weak")]
@@ -650,7 +1391,7 @@ impl<'doc> TransactionMut<'doc> {
 
         for &ptr in recurse.iter() {
             let id = *ptr.id();
-            if !self.delete(ptr) {
+            if !self.delete_item(ptr) {
                 // Whis will be gc'd later and we want to merge it if possible
                 // We try to merge all deleted items after each transaction,
                 // but we have no knowledge about that this needs to be merged
@@ -662,6 +1403,72 @@ impl<'doc> TransactionMut<'doc> {
         result
     }
 
+    /// Restores deleted content identified by a given set of `ids` - eg. a [DeleteSet] returned by
+    /// [TransactionMut::delete_set] of a transaction that removed it. Returns `true` if any of the
+    /// content has been restored.
+    ///
+    /// Deletions are permanent from the perspective of other peers, therefore restored content is
+    /// re-inserted as a copy at its original position - the same way [UndoManager] redoes removed
+    /// elements. This way restoration is propagated to remote peers as any other update.
+    ///
+    /// Content can only be restored if it has not been garbage collected yet. In practice this
+    /// requires the document to be created with [Options::skip_gc] enabled, unless the content
+    /// has been deleted within the same transaction. Garbage collected ranges are skipped.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, GetString, Options, Text, Transact};
+    ///
+    /// let doc = Doc::with_options(Options {
+    ///     skip_gc: true,
+    ///     ..Options::default()
+    /// });
+    /// let text = doc.get_or_insert_text("text");
+    /// text.insert(&mut doc.transact_mut(), 0, "hello world");
+    ///
+    /// let deleted = {
+    ///     let mut txn = doc.transact_mut();
+    ///     text.remove_range(&mut txn, 5, 6);
+    ///     txn.delete_set().clone()
+    /// };
+    /// assert_eq!(text.get_string(&doc.transact()), "hello");
+    ///
+    /// let mut txn = doc.transact_mut();
+    /// assert!(txn.undelete(&deleted));
+    /// assert_eq!(text.get_string(&txn), "hello world");
+    /// ```
+    pub fn undelete(&mut self, ids: &DeleteSet) -> bool {
+        let mut to_restore = HashSet::new();
+        let mut deleted = ids.deleted_blocks();
+        while let Some(slice) = deleted.next(self) {
+            if let BlockSlice::Item(slice) = slice {
+                let mut ptr = self.store.materialize(slice);
+                if ptr.redone.is_some() {
+                    // content has been restored before, check its most recent copy
+                    match self.store.follow_redone(ptr.id()) {
+                        Some(slice) => ptr = self.store.materialize(slice),
+                        None => continue,
+                    }
+                }
+                if ptr.is_deleted() && !matches!(ptr.content, ItemContent::Deleted(_)) {
+                    to_restore.insert(ptr);
+                }
+            }
+        }
+
+        let no_deletes = DeleteSet::new();
+        let stack = UndoStack::<()>::default();
+        let mut restored = false;
+        for &ptr in to_restore.iter() {
+            let mut ptr = ptr;
+            restored |= ptr
+                .redo(self, &to_restore, &no_deletes, &stack, &stack)
+                .is_some();
+        }
+        restored
+    }
+
     /// Applies a deserialized [Update] contents into a document owning current transaction. Update
     /// payload can be generated by methods such as [TransactionMut::encode_diff] or passed to
     /// [Doc::observe_update_v1]/[Doc::observe_update_v2] callbacks. Updates are allowed to contain
@@ -673,7 +1480,14 @@ impl<'doc> TransactionMut<'doc> {
     /// predecessors already in place. Out of order updates from the same peer will be stashed
     /// internally and their integration will be postponed until missing blocks arrive first.
     pub fn apply_update(&mut self, update: Update) {
+        self.checkpoint_pending();
+        // remote changes are not reported to local change callback and they are allowed to be
+        // integrated into read-only documents
+        let local_change = self.local_change.take();
+        let read_only = std::mem::replace(&mut self.read_only, false);
+        let was_empty = self.store.blocks.is_empty();
         let (remaining, remaining_ds) = update.integrate(self);
+        self.content_loaded |= was_empty && !self.store.blocks.is_empty();
         let mut retry = false;
         {
             let store = self.store_mut();
@@ -720,10 +1534,210 @@ impl<'doc> TransactionMut<'doc> {
                 let ds = store.pending_ds.take().unwrap_or_default();
                 let mut ds_update = Update::new();
                 ds_update.delete_set = ds;
+                let before = self.store.blocks.get_state_vector();
                 self.apply_update(pending.update);
+                self.pending_resolved |= self.store.blocks.get_state_vector() != before;
                 self.apply_update(ds_update)
             }
         }
+        self.local_change = local_change;
+        self.read_only = read_only;
+    }
+
+    /// Applies a deserialized [Update] just like [TransactionMut::apply_update] does, but first
+    /// checks if it's safe to integrate it.
+    ///
+    /// If an update contains blocks authored by a client ID of a current document, which were not
+    /// created by a current document itself, it means that another peer is using the same client
+    /// ID. Integrating such update would interleave clocks of both peers, corrupting the document
+    /// state, so an [UpdateError::ClientIdCollision] is returned instead and the update is not
+    /// applied.
+    ///
+    /// Blocks are recognized as foreign when their clocks are past the local state, or when they
+    /// describe already known clock range with a different content than the local one. Content
+    /// of shared types, sub-documents and moves is compared only by its kind, while content which
+    /// has already been deleted and garbage collected on either side is not compared at all.
+    ///
+    /// Keep in mind that this also applies to updates produced by a previous instance of
+    /// a document using the same client ID (ie. when restoring a document from persistent
+    /// storage). These should be applied using [TransactionMut::apply_update].
+    pub fn try_apply_update(&mut self, update: Update) -> Result<(), UpdateError> {
+        let client_id = self.store.options.client_id;
+        if let Some(blocks) = update.blocks.get_client(&client_id) {
+            let local_clock = self.store.blocks.get_clock(&client_id);
+            let collision = blocks.iter().any(|block| match block {
+                BlockCarrier::Skip(_) => false,
+                _ if block.last_id().clock >= local_clock => true,
+                BlockCarrier::Item(item) => self.differs_from_local(item),
+                BlockCarrier::GC(_) => false,
+            });
+            if collision {
+                return Err(UpdateError::ClientIdCollision(client_id));
+            }
+        }
+        self.apply_update(update);
+        Ok(())
+    }
+
+    /// Checks if a remote `item`, which clock range is already known to a current document, has
+    /// a content different from the local blocks covering the same range.
+    fn differs_from_local(&self, item: &Item) -> bool {
+        let blocks = match self.store.blocks.get_client(&item.id.client) {
+            Some(blocks) => blocks,
+            None => return false,
+        };
+        let start = item.id.clock;
+        let end = start + item.len;
+        let mut index = match blocks.find_pivot(start) {
+            Some(index) => index,
+            None => return false,
+        };
+        while index < blocks.len() {
+            let (local_start, local_end) = blocks[index].clock_range();
+            if local_start >= end {
+                break;
+            }
+            if let BlockCell::Block(local) = &blocks[index] {
+                let from = local_start.max(start);
+                let len = (local_end + 1).min(end) - from;
+                if !same_content(
+                    &item.content,
+                    from - start,
+                    &local.content,
+                    from - local_start,
+                    len,
+                ) {
+                    return true;
+                }
+            }
+            index += 1;
+        }
+        false
+    }
+
+    /// Applies a deserialized [Update] just like [TransactionMut::apply_update] does, but only
+    /// integrates blocks accepted by a given `predicate` (ie. to reject content inserted by
+    /// a banned client). Rejected blocks are discarded. Deletions are applied as usual.
+    ///
+    /// Keep in mind that skipping blocks can leave other content permanently pending: all
+    /// subsequent blocks of the same client, as well as blocks of other clients referring to
+    /// rejected ones, are missing their predecessors, so they will be stashed as pending
+    /// (see: [TransactionMut::pending]) until rejected blocks are delivered by another update.
+    /// Pending blocks integrated that way are not checked by the `predicate`.
+    pub fn apply_update_filtered<F>(&mut self, mut update: Update, predicate: F)
+    where
+        F: Fn(&BlockInfo) -> bool,
+    {
+        update.skip_rejected(predicate);
+        self.apply_update(update);
+    }
+
+    /// Applies a deserialized [Update] just like [TransactionMut::apply_update] does, then calls
+    /// `f` with current transaction and returns its result. This way, when applying a batch of
+    /// updates within a single transaction, application logic can read an intermediate state of
+    /// the document right after each update has been integrated, while all of them will still be
+    /// committed together.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, GetString, ReadTxn, StateVector, Text, Transact, Update};
+    /// use yrs::updates::decoder::Decode;
+    ///
+    /// let remote = Doc::new();
+    /// let txt = remote.get_or_insert_text("text");
+    /// let mut updates = Vec::new();
+    /// for chunk in ["hello", " world"] {
+    ///     let sv = remote.transact().state_vector();
+    ///     txt.push(&mut remote.transact_mut(), chunk);
+    ///     updates.push(remote.transact().encode_diff_v1(&sv));
+    /// }
+    ///
+    /// let doc = Doc::new();
+    /// let txt = doc.get_or_insert_text("text");
+    /// let mut txn = doc.transact_mut();
+    /// let states: Vec<_> = updates
+    ///     .iter()
+    ///     .map(|u| txn.apply_update_and(Update::decode_v1(u).unwrap(), |txn| txt.get_string(txn)))
+    ///     .collect();
+    /// assert_eq!(states, vec!["hello".to_string(), "hello world".to_string()]);
+    /// ```
+    pub fn apply_update_and<F, R>(&mut self, update: Update, f: F) -> R
+    where
+        F: FnOnce(&TransactionMut) -> R,
+    {
+        self.apply_update(update);
+        f(self)
+    }
+
+    /// Integrates all changes of an `other` document, which are missing in a document owning
+    /// current transaction. This has the same effect as encoding a diff of `other` document
+    /// against the state vector of current one and applying it with [TransactionMut::apply_update],
+    /// but blocks are copied between documents directly without being serialized in between.
+    ///
+    /// This method opens a read-only transaction on `other` document for the duration of the call,
+    /// so it will panic if `other` has a read-write transaction active at the moment. Merging
+    /// a document with itself is a no-op.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, GetString, Text, Transact};
+    ///
+    /// let scratch = Doc::new();
+    /// let txt = scratch.get_or_insert_text("text");
+    /// txt.insert(&mut scratch.transact_mut(), 0, "hello world");
+    ///
+    /// let doc = Doc::new();
+    /// let txt = doc.get_or_insert_text("text");
+    /// let mut txn = doc.transact_mut();
+    /// txn.merge_doc(&scratch);
+    /// assert_eq!(txt.get_string(&txn), "hello world");
+    /// ```
+    pub fn merge_doc(&mut self, other: &Doc) {
+        if Doc::ptr_eq(&self.doc, other) {
+            return;
+        }
+        let update = {
+            let sv = self.store.blocks.get_state_vector();
+            let other_txn = other.transact();
+            other_txn.store().diff_update(&sv)
+        };
+        self.apply_update(update);
+    }
+
+    /// Returns blocks, which have been received as part of the updates applied to this document,
+    /// but couldn't be integrated yet because some of their predecessors are still missing.
+    /// [PendingUpdate::missing] describes the lowest clocks per client, which need to be
+    /// delivered before pending blocks can be integrated.
+    ///
+    /// See: [TransactionMut::try_resolve_pending].
+    pub fn pending(&self) -> Option<&PendingUpdate> {
+        self.store.pending.as_ref()
+    }
+
+    /// Re-attempts to integrate blocks and deletions, which were buffered because of missing
+    /// dependencies (see: [TransactionMut::pending]). Returns `true` if there's nothing left
+    /// pending afterwards.
+    ///
+    /// Pending updates are also retried every time a new update is applied, so this method is
+    /// mostly useful to confirm that dependencies fetched in a separate step were sufficient.
+    pub fn try_resolve_pending(&mut self) -> bool {
+        self.checkpoint_pending();
+        let store = self.store_mut();
+        let pending = store.pending.take();
+        let pending_ds = store.pending_ds.take();
+        if let Some(pending) = pending {
+            let before = self.store.blocks.get_state_vector();
+            self.apply_update(pending.update);
+            self.pending_resolved |= self.store.blocks.get_state_vector() != before;
+        }
+        if let Some(delete_set) = pending_ds {
+            let mut ds_update = Update::new();
+            ds_update.delete_set = delete_set;
+            self.apply_update(ds_update);
+        }
+        self.store.pending.is_none() && self.store.pending_ds.is_none()
     }
 
     pub(crate) fn create_item<T: Prelim>(
@@ -767,6 +1781,7 @@ impl<'doc> TransactionMut<'doc> {
         block_ptr.integrate(self, 0);
 
         self.store_mut().blocks.push_block(block);
+        self.notify_local_change(block_ptr, true);
 
         if let Some(remainder) = remainder {
             remainder.integrate(self, inner_ref.unwrap().into())
@@ -818,6 +1833,20 @@ impl<'doc> TransactionMut<'doc> {
         }
     }
 
+    /// Calls all callbacks of a given shared type `observer`. Panics raised by callbacks are
+    /// handled the same way as panics of document observers (see:
+    /// [Doc::set_observer_panic_handler]).
+    pub(crate) fn trigger_observers<F, E>(&self, observer: &Observer<F>, each: E)
+    where
+        F: 'static,
+        E: FnMut(&F),
+    {
+        match self.store.events.as_ref() {
+            Some(events) => events.trigger(observer, each),
+            None => observer.trigger(each),
+        }
+    }
+
     /// Commits current transaction. This step involves cleaning up and optimizing changes performed
     /// during lifetime of a transaction. Such changes include squashing delete sets data,
     /// squashing blocks that have been appended one after another to preserve memory and triggering
@@ -837,6 +1866,8 @@ impl<'doc> TransactionMut<'doc> {
         // 2. emit 'beforeObserverCalls'
         // 3. for each change observed by the transaction call 'afterTransaction'
         if !self.changed.is_empty() {
+            // shared type observers are guarded against panics by document events
+            self.store_mut().events.get_or_init();
             let mut changed_parents: HashMap<BranchPtr, Vec<usize>> = HashMap::new();
             let mut event_cache = Vec::new();
 
@@ -856,6 +1887,11 @@ impl<'doc> TransactionMut<'doc> {
                 }
             }
 
+            // ordered map operations
+            for (branch, ops) in std::mem::take(&mut self.map_ops) {
+                self.trigger_observers(&branch.op_observers, |fun| fun(self, &ops));
+            }
+
             // deep observe events
             for (&branch, events) in changed_parents.iter() {
                 // sort events by path length so that top-level events are fired first.
@@ -883,8 +1919,23 @@ impl<'doc> TransactionMut<'doc> {
         }
 
         // 4. try GC delete set
-        if !self.store.options.skip_gc {
-            GCCollector::collect(self);
+        match self.store.options.effective_gc_policy() {
+            GcPolicy::Always => GCCollector::collect(self),
+            GcPolicy::Never => { /* do nothing */ }
+            GcPolicy::Threshold { deleted_items } => {
+                let pending = &mut self.store.gc_pending;
+                pending.merge(self.delete_set.clone());
+                pending.squash();
+                let deleted: u32 = pending
+                    .iter()
+                    .flat_map(|(_, range)| range.iter())
+                    .map(|range| range.end - range.start)
+                    .sum();
+                if deleted as usize > deleted_items {
+                    let pending = std::mem::take(&mut self.store.gc_pending);
+                    GCCollector::collect_delete_set(self, &pending);
+                }
+            }
         }
 
         // 5. try merge delete set
@@ -917,13 +1968,38 @@ impl<'doc> TransactionMut<'doc> {
             }
         }
 
+        if self.compact {
+            self.store.blocks.compact();
+        }
+
+        let mut update_v1 = None;
         if let Some(events) = self.store.events.as_ref() {
             // 8. emit 'afterTransactionCleanup'
             events.emit_transaction_cleanup(self);
             // 9. emit 'update'
-            events.emit_update_v1(self);
+            update_v1 = events.emit_update_v1(self);
             // 10. emit 'updateV2'
             events.emit_update_v2(self);
+            if self.pending_resolved {
+                events.emit_pending_resolved(self);
+            }
+            if self.content_loaded {
+                events.emit_content_loaded(self);
+            }
+        }
+
+        if let Some(events) = self.store.events.take() {
+            events.emit_after_transaction_update(self, update_v1.as_ref());
+            self.store.events = Some(events);
+        }
+
+        if !self.delete_set.is_empty() || self.after_state != self.before_state {
+            let update = if self.store.options.update_log_capacity > 0 {
+                Some(self.encode_update_v1())
+            } else {
+                None
+            };
+            self.store.record_commit(update);
         }
 
         // 11. add and remove subdocs
@@ -931,7 +2007,7 @@ impl<'doc> TransactionMut<'doc> {
         if let Some(mut subdocs) = self.subdocs.take() {
             let client_id = store.options.client_id;
             for (guid, subdoc) in subdocs.added.iter_mut() {
-                let mut txn = subdoc.transact_mut();
+                let mut txn = subdoc.transact_mut_unchecked(None);
                 txn.store.options.client_id = client_id;
                 if txn.store.options.collection_id.is_none() {
                     txn.store.options.collection_id = store.options.collection_id.clone();
@@ -946,7 +2022,7 @@ impl<'doc> TransactionMut<'doc> {
             let mut removed = if let Some(events) = store.events.as_ref() {
                 if events.subdocs_events.has_subscribers() {
                     let e = SubdocsEvent::new(subdocs);
-                    events.subdocs_events.trigger(|cb| cb(self, &e));
+                    events.trigger(&events.subdocs_events, |cb| cb(self, &e));
                     e.removed
                 } else {
                     subdocs.removed
@@ -961,6 +2037,219 @@ impl<'doc> TransactionMut<'doc> {
         }
     }
 
+    /// Captures pending blocks and deletions, unless they have been captured already, so that
+    /// they can be restored if current transaction is rolled back.
+    fn checkpoint_pending(&mut self) {
+        if self.checkpoint.pending.is_none() {
+            let pending = self
+                .store
+                .pending
+                .as_ref()
+                .map(|pending| (pending.update.encode_v1(), pending.missing.clone()));
+            self.checkpoint.pending = Some((pending, self.store.pending_ds.clone()));
+        }
+    }
+
+    /// Returns weak link dependencies of a document store for modification, capturing them first
+    /// unless they have been captured already, so that they can be restored if current
+    /// transaction is rolled back.
+    ///
+    /// Links copied over to the parts of split blocks don't need to be captured, since splits are
+    /// not reverted on rollback.
+    pub(crate) fn linked_by_mut(&mut self) -> &mut HashMap<ItemPtr, HashSet<BranchPtr>> {
+        if self.checkpoint.linked_by.is_none() {
+            self.checkpoint.linked_by = Some(self.store.linked_by.clone());
+        }
+        &mut self.store.linked_by
+    }
+
+    /// Discards all changes integrated within the scope of current transaction - no matter if
+    /// they were made locally or came from remote updates - bringing the document back to the
+    /// state it had when the transaction was created. Rolled back transaction is not committed:
+    /// no observers are notified and no deferred callbacks are called.
+    ///
+    /// Blocks created by current transaction are dropped, so any references to shared types
+    /// created within its scope are no longer alive (see: [ReadTxn::is_alive]) and must not be
+    /// used afterwards.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, GetString, Text, Transact};
+    ///
+    /// let doc = Doc::new();
+    /// let text = doc.get_or_insert_text("text");
+    /// text.push(&mut doc.transact_mut(), "hello");
+    ///
+    /// let mut txn = doc.transact_mut();
+    /// text.push(&mut txn, " world");
+    /// txn.rollback();
+    ///
+    /// assert_eq!(text.get_string(&doc.transact()), "hello");
+    /// ```
+    pub fn rollback(mut self) {
+        self.revert();
+    }
+
+    /// Reverts all changes made within current transaction. See: [TransactionMut::rollback].
+    pub(crate) fn revert(&mut self) {
+        let checkpoint = std::mem::take(&mut self.checkpoint);
+        let offset_kind = self.store.options.offset_kind;
+
+        // 1. restore items deleted by current transaction
+        let delete_set = std::mem::take(&mut self.delete_set);
+        let mut restored = Vec::new();
+        for (client, ranges) in delete_set.iter() {
+            let before_clock = self.before_state.get(client);
+            let blocks = match self.store.blocks.get_client(client) {
+                Some(blocks) => blocks,
+                None => continue,
+            };
+            for range in ranges.iter() {
+                let end = range.end.min(before_clock);
+                if range.start >= end {
+                    continue;
+                }
+                let mut index = match blocks.find_pivot(range.start) {
+                    Some(index) => index,
+                    None => continue,
+                };
+                while let Some(item) = blocks.get(index).and_then(|cell| cell.as_item()) {
+                    if item.id.clock >= end {
+                        break;
+                    }
+                    if item.is_deleted() {
+                        restored.push(item);
+                    }
+                    index += 1;
+                }
+            }
+        }
+        let mut restored_moves = Vec::new();
+        for mut item in restored {
+            item.info.clear_deleted();
+            if item.parent_sub.is_none() && item.is_countable() {
+                if let TypePtr::Branch(mut parent) = item.parent {
+                    parent.block_len += item.len();
+                    parent.content_len += item.content_len(offset_kind);
+                }
+            }
+            match &mut item.content {
+                ItemContent::Type(inner) => {
+                    self.store.node_registry.insert(BranchPtr::from(inner));
+                }
+                ItemContent::Move(_) => restored_moves.push(item),
+                _ => { /* nothing to do for other content types */ }
+            }
+        }
+
+        // 2. collect blocks created by current transaction
+        let mut added = Vec::new();
+        for (client, blocks) in self.store.blocks.iter() {
+            let before_clock = self.before_state.get(client);
+            for index in (0..blocks.len()).rev() {
+                let cell = &blocks[index];
+                if cell.clock_start() < before_clock {
+                    break;
+                }
+                if let Some(item) = cell.as_item() {
+                    added.push(item);
+                }
+            }
+        }
+
+        // 3. revert moves: new ones are undone, restored ones are integrated again
+        for &item in added.iter() {
+            if let ItemContent::Move(m) = &item.content {
+                m.rollback(self, item);
+            }
+        }
+        for mut item in restored_moves {
+            let ptr = item;
+            if let ItemContent::Move(m) = &mut item.content {
+                m.integrate_block(self, ptr);
+            }
+        }
+
+        // 4. detach new blocks from their neighbours and parents
+        for &item in added.iter() {
+            if let TypePtr::Branch(mut parent) = item.parent {
+                if !item.is_deleted() && item.parent_sub.is_none() && item.is_countable() {
+                    parent.block_len -= item.len();
+                    parent.content_len -= item.content_len(offset_kind);
+                }
+                if let Some(key) = &item.parent_sub {
+                    if parent.map.get(key) == Some(&item) {
+                        match item.left {
+                            Some(left) => parent.map.insert(key.clone(), left),
+                            None => parent.map.remove(key),
+                        };
+                    }
+                }
+                match item.left {
+                    Some(mut left) => left.right = item.right,
+                    None if item.parent_sub.is_none() && parent.start == Some(item) => {
+                        parent.start = item.right
+                    }
+                    None => { /* first entry of a map key */ }
+                }
+                if let Some(mut right) = item.right {
+                    right.left = item.left;
+                }
+            }
+            match &item.content {
+                ItemContent::Type(inner) => {
+                    self.store.node_registry.remove(&BranchPtr::from(inner));
+                }
+                ItemContent::Doc(_, doc) => {
+                    let mut child_txn = doc.transact_mut_unchecked(None);
+                    child_txn.store.parent = None;
+                }
+                _ => { /* nothing to do for other content types */ }
+            }
+        }
+        let before_state = self.before_state.clone();
+        self.store.blocks.truncate(&before_state);
+
+        // 5. restore the state which cannot be recovered from blocks
+        let store = self.store.deref_mut();
+        if let Some(linked_by) = checkpoint.linked_by {
+            store.linked_by = linked_by;
+        }
+        for item in store.linked_by.keys() {
+            let mut item = *item;
+            item.info.set_linked();
+        }
+        if let Some((pending, pending_ds)) = checkpoint.pending {
+            store.pending = pending.map(|(update, missing)| PendingUpdate {
+                update: Update::decode_v1(&update).unwrap(),
+                missing,
+            });
+            store.pending_ds = pending_ds;
+        }
+        #[cfg(feature = "weak")]
+        {
+            let links: HashSet<BranchPtr> = store.linked_by.values().flatten().cloned().collect();
+            for link in links {
+                if let crate::types::TypeRef::WeakLink(source) = &link.type_ref {
+                    source.first_item.take();
+                    source.materialize(self, link);
+                }
+            }
+        }
+
+        // 6. discard transaction state, so that it won't be committed
+        self.after_state = self.before_state.clone();
+        self.merge_blocks.clear();
+        self.prev_moved.clear();
+        self.changed.clear();
+        self.changed_parent_types.clear();
+        self.map_ops.clear();
+        self.subdocs = None;
+        self.deferred.get_mut().unwrap().clear();
+        self.committed = true;
+    }
+
     pub(crate) fn add_changed_type(&mut self, parent: BranchPtr, parent_sub: Option<Arc<str>>) {
         let trigger = if let Some(ptr) = parent.item {
             (ptr.id().clock < self.before_state.get(&ptr.id().client)) && !ptr.is_deleted()
@@ -1020,13 +2309,13 @@ impl<'doc> TransactionMut<'doc> {
     #[cfg(feature = "weak")]
     fn link(&mut self, mut source: ItemPtr, link: BranchPtr) {
         source.info.set_linked();
-        let links = self.store.linked_by.entry(source).or_default();
+        let links = self.linked_by_mut().entry(source).or_default();
         links.insert(link);
     }
 
     #[cfg(feature = "weak")]
     pub(crate) fn unlink(&mut self, mut source: ItemPtr, link: BranchPtr) {
-        let all_links = &mut self.store.linked_by;
+        let all_links = self.linked_by_mut();
         let prune = if let Some(linked_by) = all_links.get_mut(&source) {
             linked_by.remove(&link) && linked_by.is_empty()
         } else {
@@ -1044,6 +2333,44 @@ impl<'doc> TransactionMut<'doc> {
     }
 }
 
+/// Number of shared type instances of each kind existing in a document, together with their
+/// maximum nesting depth, as returned by [ReadTxn::type_counts].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TypeCounts {
+    /// Number of [TextRef] instances.
+    pub text: usize,
+    /// Number of [ArrayRef] instances.
+    pub array: usize,
+    /// Number of [MapRef] instances.
+    pub map: usize,
+    /// Number of [XmlFragmentRef] instances.
+    pub xml_fragment: usize,
+    /// Number of [XmlElementRef] instances.
+    pub xml_element: usize,
+    /// Number of [XmlTextRef] instances.
+    pub xml_text: usize,
+    /// Other shared types, ie. weak links or root types, whose kind is not yet known.
+    pub other: usize,
+    /// Maximum nesting depth of shared types, with root-level types having depth of 1.
+    /// It's 0 for a document without any shared types.
+    pub max_depth: usize,
+}
+
+impl TypeCounts {
+    fn add(&mut self, type_ref: &TypeRef, depth: usize) {
+        match type_ref {
+            TypeRef::Text => self.text += 1,
+            TypeRef::Array => self.array += 1,
+            TypeRef::Map => self.map += 1,
+            TypeRef::XmlFragment => self.xml_fragment += 1,
+            TypeRef::XmlElement(_) => self.xml_element += 1,
+            TypeRef::XmlText => self.xml_text += 1,
+            _ => self.other += 1,
+        }
+        self.max_depth = self.max_depth.max(depth);
+    }
+}
+
 /// Iterator struct used to traverse over all of the root level types defined in a corresponding [Doc].
 pub struct RootRefs<'doc>(std::collections::hash_map::Iter<'doc, Arc<str>, Arc<Branch>>);
 
@@ -1058,6 +2385,62 @@ impl<'doc> Iterator for RootRefs<'doc> {
     }
 }
 
+/// Compares `len` elements of two item contents, starting at given offsets.
+fn same_content(a: &ItemContent, a_offset: u32, b: &ItemContent, b_offset: u32, len: u32) -> bool {
+    fn same_slice<T: PartialEq>(a: &[T], a_offset: u32, b: &[T], b_offset: u32, len: u32) -> bool {
+        let a = a.iter().skip(a_offset as usize).take(len as usize);
+        let b = b.iter().skip(b_offset as usize).take(len as usize);
+        a.eq(b)
+    }
+    match (a, b) {
+        (ItemContent::Deleted(_), _) | (_, ItemContent::Deleted(_)) => true,
+        (ItemContent::String(a), ItemContent::String(b)) => {
+            let a = a.encode_utf16().skip(a_offset as usize).take(len as usize);
+            let b = b.encode_utf16().skip(b_offset as usize).take(len as usize);
+            a.eq(b)
+        }
+        (ItemContent::Any(a), ItemContent::Any(b)) => same_slice(a, a_offset, b, b_offset, len),
+        (ItemContent::JSON(a), ItemContent::JSON(b)) => same_slice(a, a_offset, b, b_offset, len),
+        (ItemContent::Binary(a), ItemContent::Binary(b)) => a == b,
+        (ItemContent::Embed(a), ItemContent::Embed(b)) => a == b,
+        (ItemContent::Format(k1, v1), ItemContent::Format(k2, v2)) => k1 == k2 && v1 == v2,
+        (a, b) => a.get_ref_number() == b.get_ref_number(),
+    }
+}
+
+/// Iterator over the blocks inserted by a single client within a given clock range, returned by
+/// [ReadTxn::client_blocks].
+pub struct ClientBlocks<'doc> {
+    client_id: ClientID,
+    blocks: Option<&'doc ClientBlockList>,
+    index: usize,
+    range: Range<u32>,
+}
+
+impl<'doc> Iterator for ClientBlocks<'doc> {
+    type Item = BlockInfo;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.range.start >= self.range.end {
+            return None;
+        }
+        let block = self.blocks?.get(self.index)?;
+        let (start, end) = block.clock_range();
+        if start >= self.range.end {
+            return None;
+        }
+        self.index += 1;
+        let clock = start.max(self.range.start);
+        let last = end.min(self.range.end - 1);
+        Some(BlockInfo {
+            id: ID::new(self.client_id, clock),
+            len: last - clock + 1,
+            deleted: block.is_deleted(),
+            gc: matches!(block, BlockCell::GC(_)),
+        })
+    }
+}
+
 #[derive(Default)]
 pub struct Subdocs {
     pub(crate) added: HashMap<DocAddr, Doc>,
@@ -5,13 +5,18 @@ use crate::encoding::serde::from_any;
 use crate::moving::StickyIndex;
 use crate::transaction::TransactionMut;
 use crate::types::{
-    event_change_set, AsPrelim, Branch, BranchPtr, Change, ChangeSet, DefaultPrelim, In, Out, Path,
-    RootRef, SharedRef, ToJson, TypeRef,
+    array_change_set, event_change_set, ArrayChange, AsPrelim, Branch, BranchPtr, Change,
+    ChangeSet, DefaultPrelim, In, Out, Path, RootRef, SharedRef, ToJson, TypeRef,
+};
+use crate::updates::decoder::{Decode, Decoder};
+use crate::updates::encoder::{Encode, Encoder};
+use crate::{
+    Any, Assoc, DeepObservable, IndexedSequence, Map, MapRef, Observable, Origin, ReadTxn, ID,
 };
-use crate::{Any, Assoc, DeepObservable, IndexedSequence, Observable, ReadTxn, ID};
 use serde::de::DeserializeOwned;
 use std::borrow::Borrow;
 use std::cell::UnsafeCell;
+use std::cmp::Ordering;
 use std::collections::HashSet;
 use std::convert::{TryFrom, TryInto};
 use std::iter::FromIterator;
@@ -95,8 +100,9 @@ impl ToJson for ArrayRef {
         let mut buf = vec![Out::default(); len as usize];
         let read = walker.slice(txn, &mut buf);
         if read == len {
-            let res = buf.into_iter().map(|v| v.to_json(txn)).collect();
-            Any::Array(res)
+            let mut res = Any::array_with_capacity(buf.len());
+            res.extend(buf.into_iter().map(|v| v.to_json(txn)));
+            res.build()
         } else {
             panic!(
                 "Defect: Array::to_json didn't read all elements ({}/{})",
@@ -259,6 +265,15 @@ pub trait Array: AsRef<Branch> + Sized {
         }
     }
 
+    /// Clears the contents of current array, effectively removing all of its elements in
+    /// a single pass. Nested shared types are removed recursively.
+    fn clear(&self, txn: &mut TransactionMut) {
+        let len = self.len(txn);
+        if len != 0 {
+            self.remove_range(txn, 0, len);
+        }
+    }
+
     /// Retrieves a value stored at a given `index`. Returns `None` when provided index was out
     /// of the range of a current array.
     fn get<T: ReadTxn>(&self, txn: &T, index: u32) -> Option<Out> {
@@ -270,6 +285,42 @@ pub trait Array: AsRef<Branch> + Sized {
         }
     }
 
+    /// Lends a primitive value stored at a given `index` to a function `f` and returns its result.
+    /// Unlike [Array::get], the value is not cloned, which may be useful when reading many
+    /// elements just to inspect them. Returns `None` when provided index was out of the range of
+    /// a current array or when an element at that index is a shared type or a subdocument - use
+    /// [Array::get] to read these.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Any, Array, Doc, Transact, WriteTxn};
+    ///
+    /// let doc = Doc::new();
+    /// let mut txn = doc.transact_mut();
+    /// let array = txn.get_or_insert_array("array");
+    /// array.insert_range(&mut txn, 0, ["hello", "world"]);
+    ///
+    /// let len = array.with_element(&txn, 1, |value| match value {
+    ///     Any::String(s) => s.len(),
+    ///     _ => 0,
+    /// });
+    /// assert_eq!(len, Some(5));
+    /// assert_eq!(array.with_element(&txn, 2, |_| ()), None);
+    /// ```
+    fn with_element<T, F, R>(&self, txn: &T, index: u32, f: F) -> Option<R>
+    where
+        T: ReadTxn,
+        F: FnOnce(&Any) -> R,
+    {
+        let mut walker = BlockIter::new(BranchPtr::from(self.as_ref()));
+        if walker.try_forward(txn, index) {
+            walker.visit_value(txn, f)
+        } else {
+            None
+        }
+    }
+
     /// Returns a value stored under a given `index` within current map, deserializing it into
     /// expected type if found. If value was not found, the `Any::Null` will be substituted and
     /// deserialized instead (i.e. into instance of `Option` type, if so desired).
@@ -334,6 +385,47 @@ pub trait Array: AsRef<Branch> + Sized {
         from_any(&any)
     }
 
+    /// Reads all elements of current array and converts them into values of type `V`. Fails on
+    /// the first element which cannot be converted (eg. because it's a nested shared type or a
+    /// value of a different type), returning a [ConversionError] with its index.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Array, Doc, Transact};
+    ///
+    /// let doc = Doc::new();
+    /// let array = doc.get_or_insert_array("array");
+    /// let mut txn = doc.transact_mut();
+    /// array.insert_range(&mut txn, 0, [1, 2, 3]);
+    ///
+    /// let values: Vec<i64> = array.collect_as(&txn).unwrap();
+    /// assert_eq!(values, vec![1, 2, 3]);
+    /// ```
+    fn collect_as<T, V>(&self, txn: &T) -> Result<Vec<V>, ConversionError>
+    where
+        T: ReadTxn,
+        V: TryFrom<Any, Error = Any>,
+    {
+        let mut result = Vec::with_capacity(self.len(txn) as usize);
+        for (index, value) in self.iter(txn).enumerate() {
+            let index = index as u32;
+            match value {
+                Out::Any(any) => match V::try_from(any) {
+                    Ok(value) => result.push(value),
+                    Err(any) => {
+                        return Err(ConversionError {
+                            index,
+                            value: Out::Any(any),
+                        })
+                    }
+                },
+                value => return Err(ConversionError { index, value }),
+            }
+        }
+        Ok(result)
+    }
+
     /// Moves element found at `source` index into `target` index position. Both indexes refer to a
     /// current state of the document.
     ///
@@ -362,6 +454,44 @@ pub trait Array: AsRef<Branch> + Sized {
         }
     }
 
+    /// Moves a single element found at `from` index, so that after the operation it's placed at
+    /// `to` index. Unlike [Array::move_to], which treats `target` as a position between elements
+    /// of the array *before* the move, here `to` is the logical index of the moved element *after*
+    /// it has been taken out from `from` position - just like with `Vec::remove` followed by
+    /// `Vec::insert`. Element is moved using move marker, so its identity is preserved.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Array, Doc, Transact};
+    /// let doc = Doc::new();
+    /// let array = doc.get_or_insert_array("array");
+    /// array.insert_range(&mut doc.transact_mut(), 0, [1, 2, 3, 4]);
+    /// // move element 1 so that it ends up at index 2
+    /// array.insert_move(&mut doc.transact_mut(), 0, 2);
+    /// let values: Vec<_> = array.iter(&doc.transact()).collect();
+    /// assert_eq!(values, vec![2.into(), 3.into(), 1.into(), 4.into()]);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This method panics if either `from` or `to` indexes are out of bounds of current array.
+    fn insert_move(&self, txn: &mut TransactionMut, from: u32, to: u32) {
+        let len = self.len(txn);
+        if from >= len || to >= len {
+            panic!(
+                "index out of bounds: cannot move element from {} to {} in an array of length {}",
+                from, to, len
+            );
+        }
+        if from < to {
+            // the gap left by removed element shifts all subsequent indexes to the left
+            self.move_to(txn, from, to + 1);
+        } else {
+            self.move_to(txn, from, to);
+        }
+    }
+
     /// Moves all elements found within `start`..`end` indexes range (both side inclusive) into
     /// new position pointed by `target` index. All elements inserted concurrently by other peers
     /// inside of moved range will be moved as well after synchronization (although it make take
@@ -414,11 +544,276 @@ pub trait Array: AsRef<Branch> + Sized {
         }
     }
 
+    /// Reverses the order of all elements of current array. Elements are rearranged using
+    /// [Array::move_to], so unlike removing and re-inserting them, their identities (and
+    /// therefore any nested shared types, weak links or sticky indexes pointing to them) are
+    /// preserved.
+    ///
+    /// # Concurrency
+    ///
+    /// Every element is repositioned by a separate move operation. If another peer concurrently
+    /// moves the same element, only one of these moves wins, so the resulting order may not be
+    /// a perfect reversal. Elements inserted concurrently by other peers are not reversed: their
+    /// final positions are derived from the layout the array had before reversal. Elements
+    /// removed concurrently are simply gone.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Array, Doc, Transact};
+    /// let doc = Doc::new();
+    /// let array = doc.get_or_insert_array("array");
+    /// array.insert_range(&mut doc.transact_mut(), 0, [1, 2, 3]);
+    /// array.reverse(&mut doc.transact_mut());
+    /// let values: Vec<_> = array.iter(&doc.transact()).collect();
+    /// assert_eq!(values, vec![3.into(), 2.into(), 1.into()]);
+    /// ```
+    fn reverse(&self, txn: &mut TransactionMut) {
+        let len = self.len(txn);
+        for i in 1..len {
+            // move the last element of a range that is yet to be reversed at its beginning
+            self.move_to(txn, len - 1, i - 1);
+        }
+    }
+
+    /// Sorts elements of current array using a `compare` function. Sort is stable: elements
+    /// considered equal keep their relative order. Just like [Array::reverse], elements are
+    /// rearranged using [Array::move_to], so their identities are preserved. Elements which are
+    /// already in their target positions are not moved.
+    ///
+    /// # Concurrency
+    ///
+    /// The target order is computed from the local state of the array and applied as a sequence
+    /// of single element moves. Elements inserted concurrently by other peers are not part of
+    /// that order: they are placed according to regular CRDT rules, relative to their neighbours
+    /// at the time of insertion, so after synchronization the array may no longer be sorted.
+    /// If another peer concurrently moves the same element, only one of these moves wins.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Array, Doc, Out, Transact};
+    /// let doc = Doc::new();
+    /// let array = doc.get_or_insert_array("array");
+    /// array.insert_range(&mut doc.transact_mut(), 0, [3, 1, 2]);
+    /// array.sort_by(&mut doc.transact_mut(), |a, b| match (a, b) {
+    ///     (Out::Any(a), Out::Any(b)) => a.to_string().cmp(&b.to_string()),
+    ///     _ => std::cmp::Ordering::Equal,
+    /// });
+    /// let values: Vec<_> = array.iter(&doc.transact()).collect();
+    /// assert_eq!(values, vec![1.into(), 2.into(), 3.into()]);
+    /// ```
+    fn sort_by<F>(&self, txn: &mut TransactionMut, mut compare: F)
+    where
+        F: FnMut(&Out, &Out) -> Ordering,
+    {
+        let values: Vec<Out> = self.iter(txn).collect();
+        let mut order: Vec<usize> = (0..values.len()).collect();
+        order.sort_by(|&a, &b| compare(&values[a], &values[b]));
+
+        // positions of the original elements as they get rearranged
+        let mut layout: Vec<usize> = (0..values.len()).collect();
+        for (i, &original) in order.iter().enumerate() {
+            let pos = i + layout[i..].iter().position(|&e| e == original).unwrap();
+            if pos != i {
+                self.move_to(txn, pos as u32, i as u32);
+                let e = layout.remove(pos);
+                layout.insert(i, e);
+            }
+        }
+    }
+
+    /// Splits current array in two at a given index `at`: all elements starting from `at` onward
+    /// are moved into a new array, inserted into a `parent` map under a given `key`, while current
+    /// array is truncated to its first `at` elements. Returns a newly created array.
+    ///
+    /// Moving elements across different collections is not supported by the move mechanism
+    /// (see: [Array::move_range_to]), therefore the tail elements are deep-copied into a new array
+    /// and then removed from the current one. This has the following implications:
+    ///
+    /// - Nested shared types are recreated, so they don't preserve their identity: references
+    ///   obtained before the split point to the removed originals.
+    /// - Elements inserted into the tail concurrently by remote peers will stay in the original
+    ///   array, while concurrent updates of the copied elements will be lost.
+    /// - If `parent` already contains an entry under a given `key`, it will be overridden.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Array, Doc, Transact};
+    ///
+    /// let doc = Doc::new();
+    /// let array = doc.get_or_insert_array("array");
+    /// let parent = doc.get_or_insert_map("map");
+    /// let mut txn = doc.transact_mut();
+    /// array.insert_range(&mut txn, 0, [1, 2, 3, 4]);
+    ///
+    /// let tail = array.split_off(&mut txn, &parent, "tail", 1);
+    /// let head: Vec<i64> = array.collect_as(&txn).unwrap();
+    /// let tail: Vec<i64> = tail.collect_as(&txn).unwrap();
+    /// assert_eq!(head, vec![1]);
+    /// assert_eq!(tail, vec![2, 3, 4]);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This method panics if `at` is greater than current array's length.
+    fn split_off(&self, txn: &mut TransactionMut, parent: &MapRef, key: &str, at: u32) -> ArrayRef {
+        let len = self.len(txn);
+        if at > len {
+            panic!("Index {} is outside of the range of an array", at);
+        }
+        let tail: Vec<In> = self
+            .iter(txn)
+            .skip(at as usize)
+            .map(|value| value.as_prelim(txn))
+            .collect();
+        // insert first, so that moved sub-documents are not considered as removed on commit
+        let result = parent.insert(txn, key, ArrayPrelim::from(tail));
+        self.remove_range(txn, at, len - at);
+        result
+    }
+
     /// Returns an iterator, that can be used to lazely traverse over all values stored in a current
     /// array.
     fn iter<'a, T: ReadTxn + 'a>(&self, txn: &'a T) -> ArrayIter<&'a T, T> {
         ArrayIter::from_ref(self.as_ref(), txn)
     }
+
+    /// Returns an iterator, which lazily traverses over current array in chunks of `size`
+    /// consecutive elements. Deleted elements are skipped, and the last chunk may contain less
+    /// than `size` elements.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Array, Doc, Transact};
+    /// let doc = Doc::new();
+    /// let array = doc.get_or_insert_array("array");
+    /// array.insert_range(&mut doc.transact_mut(), 0, [1, 2, 3, 4, 5]);
+    ///
+    /// let txn = doc.transact();
+    /// let chunks: Vec<_> = array.chunks(&txn, 2).collect();
+    /// assert_eq!(chunks, vec![
+    ///     vec![1.into(), 2.into()],
+    ///     vec![3.into(), 4.into()],
+    ///     vec![5.into()],
+    /// ]);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This method panics if `size` is 0.
+    fn chunks<'a, T: ReadTxn + 'a>(&self, txn: &'a T, size: usize) -> ArrayChunks<'a, T> {
+        assert!(size != 0, "chunk size must be greater than 0");
+        ArrayChunks {
+            inner: BlockIter::new(BranchPtr::from(self.as_ref())),
+            txn,
+            size,
+            remaining: self.len(txn) as usize,
+        }
+    }
+
+    /// Returns a page of up to `limit` consecutive elements of current array, starting right after
+    /// a position pointed by `after` cursor (or from the beginning of an array if `after` is not
+    /// provided), together with a cursor that can be used to fetch the next page. If there are no
+    /// more elements after returned page, no cursor is returned.
+    ///
+    /// Unlike offset-based paging, cursors are based on [StickyIndex]es, so that elements inserted
+    /// or removed concurrently before the cursor position between page fetches don't cause
+    /// subsequent pages to skip or repeat elements.
+    ///
+    /// If `after` cursor cannot be resolved in the context of a given transaction (i.e. because it
+    /// refers to elements which have not been integrated yet), an empty page is returned
+    /// together with the same cursor.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Array, Doc, Transact};
+    /// let doc = Doc::new();
+    /// let array = doc.get_or_insert_array("array");
+    /// array.insert_range(&mut doc.transact_mut(), 0, [1, 2, 3, 4, 5]);
+    ///
+    /// let (page, cursor) = array.page(&doc.transact(), None, 2);
+    /// assert_eq!(page, vec![1.into(), 2.into()]);
+    ///
+    /// // element inserted at the beginning doesn't affect the next page
+    /// array.insert(&mut doc.transact_mut(), 0, 0);
+    /// let (page, cursor) = array.page(&doc.transact(), cursor, 2);
+    /// assert_eq!(page, vec![3.into(), 4.into()]);
+    ///
+    /// let (page, cursor) = array.page(&doc.transact(), cursor, 2);
+    /// assert_eq!(page, vec![5.into()]);
+    /// assert!(cursor.is_none());
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This method panics if `after` cursor was created for a different collection.
+    fn page<T: ReadTxn>(
+        &self,
+        txn: &T,
+        after: Option<BranchCursor>,
+        limit: usize,
+    ) -> (Vec<Out>, Option<BranchCursor>) {
+        let this = BranchPtr::from(self.as_ref());
+        let start = match &after {
+            None => 0,
+            Some(cursor) => match cursor.0.get_offset(txn) {
+                Some(offset) => {
+                    assert!(
+                        offset.branch == this,
+                        "cursor doesn't belong to a current array"
+                    );
+                    offset.index
+                }
+                None => return (Vec::new(), after),
+            },
+        };
+        let len = self.len(txn);
+        let end = len.min(start.saturating_add(limit as u32));
+        if start >= end {
+            return (Vec::new(), if start < len { after } else { None });
+        }
+        let mut walker = BlockIter::new(this);
+        let mut page = vec![Out::default(); (end - start) as usize];
+        if !walker.try_forward(txn, start) || walker.slice(txn, &mut page) != end - start {
+            panic!(
+                "Defect: Array::page didn't read all elements within {}..{}",
+                start, end
+            );
+        }
+        let next = if end < len {
+            // cursor sticks to the last element of a current page
+            StickyIndex::at(txn, this, end, Assoc::Before).map(BranchCursor)
+        } else {
+            None
+        };
+        (page, next)
+    }
+
+    /// Checks if current array contains an element equal to a given `value`. Only scalar elements
+    /// are compared: nested shared types never match.
+    fn contains<T: ReadTxn>(&self, _txn: &T, value: &Any) -> bool {
+        let mut current = self.as_ref().start;
+        while let Some(item) = current.as_deref() {
+            if item.is_countable() && !item.is_deleted() {
+                let found = match &item.content {
+                    ItemContent::Any(values) => values.contains(value),
+                    content => content
+                        .get_content()
+                        .iter()
+                        .any(|v| matches!(v, Out::Any(any) if any == value)),
+                };
+                if found {
+                    return true;
+                }
+            }
+            current = item.right;
+        }
+        false
+    }
 }
 
 pub struct ArrayIter<B, T>
@@ -479,14 +874,108 @@ where
     }
 }
 
+/// Iterator returned by [Array::chunks].
+pub struct ArrayChunks<'a, T: ReadTxn> {
+    inner: BlockIter,
+    txn: &'a T,
+    size: usize,
+    remaining: usize,
+}
+
+impl<'a, T: ReadTxn> Iterator for ArrayChunks<'a, T> {
+    type Item = Vec<Out>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // block iterator refuses to read past the end of an array, so the last chunk needs to be
+        // limited to the number of remaining elements
+        let len = self.size.min(self.remaining);
+        if len == 0 || self.inner.finished() {
+            return None;
+        }
+        let mut chunk = vec![Out::default(); len];
+        let read = self.inner.slice(self.txn, &mut chunk) as usize;
+        if read == 0 {
+            None
+        } else {
+            self.remaining -= read;
+            chunk.truncate(read);
+            Some(chunk)
+        }
+    }
+}
+
 impl From<BranchPtr> for ArrayRef {
     fn from(inner: BranchPtr) -> Self {
         ArrayRef(inner)
     }
 }
 
+/// Error returned by [Array::collect_as] when an element couldn't be converted into requested type.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+#[error("element at index {index} couldn't be converted: {value}")]
+pub struct ConversionError {
+    /// Index of the first element, which couldn't be converted.
+    pub index: u32,
+    /// Value of the element, which couldn't be converted.
+    pub value: Out,
+}
+
+/// Pagination cursor returned by [Array::page], pointing to a position right after the last
+/// element of a returned page. Since it's based on a [StickyIndex], it remains valid when elements
+/// are being inserted or removed concurrently. It can be serialized in order to be passed over to
+/// clients requesting subsequent pages.
+#[repr(transparent)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BranchCursor(StickyIndex);
+
+impl BranchCursor {
+    /// Returns a [StickyIndex] this cursor is based upon.
+    pub fn sticky_index(&self) -> &StickyIndex {
+        &self.0
+    }
+}
+
+impl From<BranchCursor> for StickyIndex {
+    #[inline]
+    fn from(cursor: BranchCursor) -> Self {
+        cursor.0
+    }
+}
+
+impl Encode for BranchCursor {
+    #[inline]
+    fn encode<E: Encoder>(&self, encoder: &mut E) {
+        self.0.encode(encoder)
+    }
+}
+
+impl Decode for BranchCursor {
+    #[inline]
+    fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, Error> {
+        Ok(BranchCursor(StickyIndex::decode(decoder)?))
+    }
+}
+
 /// A preliminary array. It can be used to initialize an [ArrayRef], when it's about to be nested
 /// into another Yrs data collection, such as [Map] or another [ArrayRef].
+///
+/// Elements of different kinds - including other nested preliminary types - can be mixed together
+/// by converting them into [In] first:
+///
+/// ```
+/// use std::iter::FromIterator;
+/// use yrs::{Array, ArrayPrelim, Doc, In, MapPrelim, TextPrelim, Transact};
+///
+/// let doc = Doc::new();
+/// let array = doc.get_or_insert_array("array");
+/// let mut txn = doc.transact_mut();
+/// array.push_back(&mut txn, ArrayPrelim::from_iter([
+///     In::from(TextPrelim::new("hello")),
+///     In::from(ArrayPrelim::from([1, 2, 3])),
+///     In::from(MapPrelim::from([("key", "value")])),
+///     In::from(true),
+/// ]));
+/// ```
 #[repr(transparent)]
 #[derive(Debug, Clone, PartialEq, Default)]
 pub struct ArrayPrelim(Vec<In>);
@@ -590,15 +1079,19 @@ pub struct ArrayEvent {
     pub(crate) current_target: BranchPtr,
     target: ArrayRef,
     change_set: UnsafeCell<Option<Box<ChangeSet<Change>>>>,
+    change_set_with_moves: UnsafeCell<Option<Box<ChangeSet<ArrayChange>>>>,
+    origin: Option<Origin>,
 }
 
 impl ArrayEvent {
-    pub(crate) fn new(branch_ref: BranchPtr) -> Self {
+    pub(crate) fn new(branch_ref: BranchPtr, origin: Option<Origin>) -> Self {
         let current_target = branch_ref.clone();
         ArrayEvent {
             target: ArrayRef::from(branch_ref),
             current_target,
             change_set: UnsafeCell::new(None),
+            change_set_with_moves: UnsafeCell::new(None),
+            origin,
         }
     }
 
@@ -607,6 +1100,11 @@ impl ArrayEvent {
         &self.target
     }
 
+    /// Returns an origin of the transaction, which produced this event, if any was defined.
+    pub fn origin(&self) -> Option<&Origin> {
+        self.origin.as_ref()
+    }
+
     /// Returns a path from root type down to [ArrayRef] instance which emitted this event.
     pub fn path(&self) -> Path {
         Branch::path(self.current_target, self.target.0)
@@ -618,6 +1116,17 @@ impl ArrayEvent {
         self.changes(txn).delta.as_slice()
     }
 
+    /// Returns summary of changes made over corresponding [ArrayRef] collection within
+    /// a bounds of current transaction. Unlike [ArrayEvent::delta], elements relocated with
+    /// [Array::move_to] or [Array::move_range_to] are reported as [ArrayChange::Moved] instead of
+    /// being removed from their old position and added at the new one.
+    pub fn delta_with_moves(&self, txn: &TransactionMut) -> &[ArrayChange] {
+        let change_set = unsafe { self.change_set_with_moves.get().as_mut().unwrap() };
+        let change_set = change_set
+            .get_or_insert_with(|| Box::new(array_change_set(txn, self.target.0.start, true)));
+        change_set.delta.as_slice()
+    }
+
     /// Returns a collection of block identifiers that have been added within a bounds of
     /// current transaction.
     pub fn inserts(&self, txn: &TransactionMut) -> &HashSet<ID> {
@@ -630,6 +1139,34 @@ impl ArrayEvent {
         &self.changes(txn).deleted
     }
 
+    /// Returns all values inserted into corresponding [ArrayRef] collection within a bounds of
+    /// current transaction, in order of their appearance. Nested shared types are returned as
+    /// their refs (eg. [Out::YMap]), so they can be observed right away.
+    pub fn inserted_values(&self, txn: &TransactionMut) -> Vec<Out> {
+        let mut values = Vec::new();
+        for change in self.delta(txn) {
+            if let Change::Added(added) = change {
+                values.extend_from_slice(added);
+            }
+        }
+        values
+    }
+
+    /// Returns all values removed from corresponding [ArrayRef] collection within a bounds of
+    /// current transaction, in order in which they used to appear in the collection.
+    pub fn removed_values(&self, txn: &TransactionMut) -> Vec<Out> {
+        let deleted = self.removes(txn);
+        let mut values = Vec::new();
+        let mut current = self.target.0.start;
+        while let Some(item) = current.as_deref() {
+            if deleted.contains(&item.id) {
+                values.append(&mut item.content.get_content());
+            }
+            current = item.right;
+        }
+        values
+    }
+
     fn changes(&self, txn: &TransactionMut) -> &ChangeSet<Change> {
         let change_set = unsafe { self.change_set.get().as_mut().unwrap() };
         change_set.get_or_insert_with(|| Box::new(event_change_set(txn, self.target.0.start)))
@@ -639,11 +1176,15 @@ impl ArrayEvent {
 #[cfg(test)]
 mod test {
     use crate::test_utils::{exchange_updates, run_scenario, RngExt};
+    use crate::types::array::{BranchCursor, ConversionError};
     use crate::types::map::MapPrelim;
-    use crate::types::{Change, DeepObservable, Event, Out, Path, PathSegment, ToJson};
+    use crate::types::{
+        ArrayChange, Change, DeepObservable, Event, Out, Path, PathSegment, ToJson,
+    };
+    use crate::updates::encoder::Encode;
     use crate::{
-        any, Any, Array, ArrayPrelim, Assoc, Doc, Map, MapRef, Observable, SharedRef, StateVector,
-        Transact, Update, WriteTxn, ID,
+        any, Any, Array, ArrayPrelim, ArrayRef, Assoc, Doc, GetString, In, Map, MapRef, Observable,
+        Origin, SharedRef, StateVector, TextPrelim, TextRef, Transact, Update, WriteTxn, ID,
     };
     use std::collections::{HashMap, HashSet};
     use std::iter::FromIterator;
@@ -754,6 +1295,250 @@ mod test {
         assert_eq!(a.len(&txn), 4);
     }
 
+    #[test]
+    fn nested_prelim_from_iter() {
+        let doc = Doc::with_client_id(1);
+        let array = doc.get_or_insert_array("array");
+        let mut txn = doc.transact_mut();
+        let prelim = ArrayPrelim::from_iter([
+            In::from(TextPrelim::new("hello")),
+            In::from(ArrayPrelim::from_iter([
+                In::from(1),
+                In::from(MapPrelim::from_iter([
+                    ("text", In::from(TextPrelim::new("world"))),
+                    ("flag", In::from(None::<bool>)),
+                ])),
+            ])),
+            In::from(MapPrelim::from([("key", "value")])),
+        ]);
+        let nested = array.push_back(&mut txn, prelim);
+
+        let text: TextRef = nested.get(&txn, 0).unwrap().cast().unwrap();
+        assert_eq!(text.get_string(&txn), "hello");
+        let inner: ArrayRef = nested.get(&txn, 1).unwrap().cast().unwrap();
+        let map: MapRef = inner.get(&txn, 1).unwrap().cast().unwrap();
+        let text: TextRef = map.get(&txn, "text").unwrap().cast().unwrap();
+        assert_eq!(text.get_string(&txn), "world");
+        assert_eq!(
+            array.to_json(&txn),
+            any!([["hello", [1, {"text": "world", "flag": null}], {"key": "value"}]])
+        );
+    }
+
+    #[test]
+    fn reverse() {
+        let doc = Doc::with_client_id(1);
+        let array = doc.get_or_insert_array("array");
+        let nested = {
+            let mut txn = doc.transact_mut();
+            array.insert_range(&mut txn, 0, [1, 2, 3, 4]);
+            array.insert(&mut txn, 2, MapPrelim::from([("a", 1)]))
+        };
+
+        array.reverse(&mut doc.transact_mut());
+
+        let txn = doc.transact();
+        assert_eq!(array.to_json(&txn), any!([4, 3, {"a": 1}, 2, 1]));
+        // nested type has been moved, not recreated
+        assert!(txn.is_alive(&nested));
+        assert_eq!(array.get(&txn, 2), Some(Out::YMap(nested)));
+    }
+
+    #[test]
+    fn reverse_concurrent_edit() {
+        let d1 = Doc::with_client_id(1);
+        let a1 = d1.get_or_insert_array("array");
+        let d2 = Doc::with_client_id(2);
+        let a2 = d2.get_or_insert_array("array");
+
+        a1.insert_range(&mut d1.transact_mut(), 0, [1, 2, 3, 4, 5]);
+        exchange_updates(&[&d1, &d2]);
+
+        a1.reverse(&mut d1.transact_mut());
+        {
+            let mut txn = d2.transact_mut();
+            a2.remove(&mut txn, 1);
+            a2.insert(&mut txn, 3, 6);
+        }
+        exchange_updates(&[&d1, &d2]);
+
+        let json = a1.to_json(&d1.transact());
+        assert_eq!(json, a2.to_json(&d2.transact()));
+        assert_eq!(json, any!([5, 4, 3, 1, 6]));
+    }
+
+    #[test]
+    fn sort_by() {
+        let doc = Doc::with_client_id(1);
+        let array = doc.get_or_insert_array("array");
+        let (m1, m2) = {
+            let mut txn = doc.transact_mut();
+            array.insert_range(&mut txn, 0, [5, 3, 8, 1, 9, 2, 7]);
+            let m1 = array.insert(&mut txn, 3, MapPrelim::from([("a", 1)]));
+            let m2 = array.insert(&mut txn, 1, MapPrelim::from([("a", 2)]));
+            (m1, m2)
+        };
+
+        // numbers in ascending order, followed by shared types
+        array.sort_by(&mut doc.transact_mut(), |a, b| match (a, b) {
+            (Out::Any(Any::Number(a)), Out::Any(Any::Number(b))) => a.partial_cmp(b).unwrap(),
+            (Out::Any(_), _) => std::cmp::Ordering::Less,
+            (_, Out::Any(_)) => std::cmp::Ordering::Greater,
+            _ => std::cmp::Ordering::Equal,
+        });
+
+        let txn = doc.transact();
+        assert_eq!(
+            array.to_json(&txn),
+            any!([1, 2, 3, 5, 7, 8, 9, {"a": 2}, {"a": 1}])
+        );
+        // nested types have been moved, not recreated, and kept their relative order
+        assert!(txn.is_alive(&m1));
+        assert!(txn.is_alive(&m2));
+        assert_eq!(array.get(&txn, 7), Some(Out::YMap(m2)));
+        assert_eq!(array.get(&txn, 8), Some(Out::YMap(m1)));
+    }
+
+    #[test]
+    fn sort_by_concurrent_insert() {
+        let d1 = Doc::with_client_id(1);
+        let a1 = d1.get_or_insert_array("array");
+        let d2 = Doc::with_client_id(2);
+        let a2 = d2.get_or_insert_array("array");
+
+        a1.insert_range(&mut d1.transact_mut(), 0, [4, 2, 5, 1, 3]);
+        exchange_updates(&[&d1, &d2]);
+
+        a1.sort_by(&mut d1.transact_mut(), |a, b| {
+            a.to_string().cmp(&b.to_string())
+        });
+        a2.insert(&mut d2.transact_mut(), 2, 6);
+        exchange_updates(&[&d1, &d2]);
+
+        let json = a1.to_json(&d1.transact());
+        assert_eq!(json, a2.to_json(&d2.transact()));
+        // sorted elements keep their order, concurrent insert is placed by CRDT rules
+        let values: Vec<i64> = a1.collect_as(&d1.transact()).unwrap();
+        let sorted: Vec<i64> = values.iter().copied().filter(|&v| v != 6).collect();
+        assert_eq!(sorted, vec![1, 2, 3, 4, 5]);
+        assert!(values.contains(&6));
+    }
+
+    #[test]
+    fn split_off() {
+        let d1 = Doc::with_client_id(1);
+        let a1 = d1.get_or_insert_array("array");
+        let m1 = d1.get_or_insert_map("map");
+        {
+            let mut txn = d1.transact_mut();
+            a1.insert_range(&mut txn, 0, [0, 1, 2, 3, 4, 5, 6, 7, 8]);
+            let nested = a1.push_back(&mut txn, MapPrelim::default());
+            nested.insert(&mut txn, "key", "value");
+        }
+
+        let tail = a1.split_off(&mut d1.transact_mut(), &m1, "tail", 4);
+
+        let d2 = Doc::with_client_id(2);
+        let a2 = d2.get_or_insert_array("array");
+        let m2 = d2.get_or_insert_map("map");
+        exchange_updates(&[&d1, &d2]);
+
+        let expected_head = any!([0, 1, 2, 3]);
+        let expected_tail = any!([4, 5, 6, 7, 8, {"key": "value"}]);
+        let txn = d1.transact();
+        assert_eq!(a1.to_json(&txn), expected_head);
+        assert_eq!(tail.to_json(&txn), expected_tail);
+        let txn = d2.transact();
+        assert_eq!(a2.to_json(&txn), expected_head);
+        assert_eq!(m2.get(&txn, "tail").unwrap().to_json(&txn), expected_tail);
+    }
+
+    #[test]
+    fn contains() {
+        let doc = Doc::with_client_id(1);
+        let array = doc.get_or_insert_array("array");
+        let mut txn = doc.transact_mut();
+        array.insert_range(&mut txn, 0, [1, 2, 3]);
+        array.push_back(&mut txn, "hello");
+        array.push_back(&mut txn, vec![1u8, 2]);
+        array.push_back(&mut txn, MapPrelim::default());
+        array.remove(&mut txn, 1);
+
+        assert!(array.contains(&txn, &Any::from(1)));
+        assert!(array.contains(&txn, &Any::from("hello")));
+        assert!(array.contains(&txn, &Any::from(vec![1u8, 2])));
+        assert!(!array.contains(&txn, &Any::from(2))); // removed
+        assert!(!array.contains(&txn, &Any::from("world")));
+        // nested shared types don't match any scalar value
+        assert!(!array.contains(&txn, &Any::from(HashMap::<String, Any>::new())));
+    }
+
+    #[test]
+    fn collect_as() {
+        let doc = Doc::with_client_id(1);
+        let array = doc.get_or_insert_array("array");
+        let mut txn = doc.transact_mut();
+        array.insert_range(&mut txn, 0, [1, 2, 3, 4]);
+        array.remove(&mut txn, 1);
+
+        let values: Vec<i64> = array.collect_as(&txn).unwrap();
+        assert_eq!(values, vec![1, 3, 4]);
+        let values: Vec<f64> = array.collect_as(&txn).unwrap();
+        assert_eq!(values, vec![1.0, 3.0, 4.0]);
+    }
+
+    #[test]
+    fn collect_as_type_mismatch() {
+        let doc = Doc::with_client_id(1);
+        let array = doc.get_or_insert_array("array");
+        let mut txn = doc.transact_mut();
+        array.insert_range(&mut txn, 0, [1, 2]);
+        array.push_back(&mut txn, "three");
+        array.push_back(&mut txn, 4);
+
+        let err = array.collect_as::<_, i64>(&txn).unwrap_err();
+        assert_eq!(
+            err,
+            ConversionError {
+                index: 2,
+                value: Out::Any(Any::from("three")),
+            }
+        );
+
+        array.remove(&mut txn, 2);
+        array.insert(&mut txn, 1, MapPrelim::default());
+        let err = array.collect_as::<_, i64>(&txn).unwrap_err();
+        assert_eq!(err.index, 1);
+        assert!(matches!(err.value, Out::YMap(_)));
+    }
+
+    #[test]
+    fn clear() {
+        let d1 = Doc::with_client_id(1);
+        let a1 = d1.get_or_insert_array("array");
+        let d2 = Doc::with_client_id(2);
+        let a2 = d2.get_or_insert_array("array");
+        let nested = {
+            let mut t1 = d1.transact_mut();
+            a1.insert_range(&mut t1, 0, [1, 2, 3]);
+            a1.push_back(&mut t1, MapPrelim::from([("key", "value")]))
+        };
+        exchange_updates(&[&d1, &d2]);
+
+        {
+            let mut t1 = d1.transact_mut();
+            a1.clear(&mut t1);
+            assert_eq!(a1.len(&t1), 0);
+            assert_eq!(a1.iter(&t1).count(), 0);
+            assert!(!t1.is_alive(&nested));
+        }
+        exchange_updates(&[&d1, &d2]);
+
+        let t2 = d2.transact();
+        assert_eq!(a2.len(&t2), 0);
+        assert_eq!(a2.to_json(&t2), any!([]));
+    }
+
     #[test]
     fn remove_insert() {
         let d1 = Doc::with_client_id(1);
@@ -1131,6 +1916,54 @@ mod test {
         );
     }
 
+    #[test]
+    fn event_inserted_and_removed_values() {
+        let doc = Doc::with_client_id(1);
+        let array = doc.get_or_insert_array("array");
+        let inserted = Arc::new(Mutex::new(Vec::new()));
+        let removed = Arc::new(Mutex::new(Vec::new()));
+
+        let (inserted_c, removed_c) = (inserted.clone(), removed.clone());
+        let _sub = array.observe(move |txn, e| {
+            for value in e.inserted_values(txn) {
+                match value {
+                    Out::YMap(map) => {
+                        // nested map is already resolved, so it can be read or observed
+                        inserted_c.lock().unwrap().push(map.to_json(txn));
+                    }
+                    Out::Any(any) => inserted_c.lock().unwrap().push(any),
+                    other => panic!("unexpected value: {:?}", other),
+                }
+            }
+            removed_c.lock().unwrap().extend(e.removed_values(txn));
+        });
+
+        {
+            let mut txn = doc.transact_mut();
+            array.insert(&mut txn, 0, 1);
+            array.insert(&mut txn, 1, MapPrelim::from([("key", "value")]));
+        }
+        assert_eq!(
+            inserted.lock().unwrap().drain(..).collect::<Vec<_>>(),
+            vec![any!(1), any!({"key": "value"})]
+        );
+        assert!(removed.lock().unwrap().is_empty());
+
+        {
+            let mut txn = doc.transact_mut();
+            array.insert(&mut txn, 2, "a");
+            array.remove_range(&mut txn, 0, 2);
+        }
+        assert_eq!(
+            inserted.lock().unwrap().drain(..).collect::<Vec<_>>(),
+            vec![any!("a")]
+        );
+        let removed: Vec<_> = removed.lock().unwrap().drain(..).collect();
+        assert_eq!(removed.len(), 2);
+        assert_eq!(removed[0], Out::Any(any!(1)));
+        assert!(matches!(removed[1], Out::YMap(_)));
+    }
+
     #[test]
     fn target_on_local_and_remote() {
         let d1 = Doc::with_client_id(1);
@@ -1337,6 +2170,24 @@ mod test {
         assert_eq!(actual.as_slice(), expected);
     }
 
+    #[test]
+    fn observe_event_origin() {
+        let doc = Doc::with_client_id(1);
+        let array = doc.get_or_insert_array("array");
+
+        let origins = Arc::new(Mutex::new(vec![]));
+        let origins_copy = origins.clone();
+        let _sub = array.observe(move |_txn, e| {
+            origins_copy.lock().unwrap().push(e.origin().cloned());
+        });
+
+        array.push_back(&mut doc.transact_mut_with("remote"), 1);
+        array.push_back(&mut doc.transact_mut(), 2);
+
+        let actual = origins.lock().unwrap();
+        assert_eq!(actual.as_slice(), &[Some(Origin::from("remote")), None]);
+    }
+
     #[test]
     fn move_1() {
         let d1 = Doc::with_client_id(1);
@@ -1452,6 +2303,219 @@ mod test {
         }
     }
 
+    #[test]
+    fn delta_with_moves() {
+        let d1 = Doc::with_client_id(1);
+        let a1 = d1.get_or_insert_array("array");
+        a1.insert_range(&mut d1.transact_mut(), 0, [1, 2, 3, 4, 5]);
+
+        let d2 = Doc::with_client_id(2);
+        let a2 = d2.get_or_insert_array("array");
+        exchange_updates(&[&d1, &d2]);
+
+        let e1 = Arc::new(ArcSwapOption::default());
+        let inner = e1.clone();
+        let _s1 = a1.observe(move |txn, e| {
+            let moves = e.delta_with_moves(txn).to_vec();
+            inner.store(Some(Arc::new((moves, e.inserted_values(txn)))));
+        });
+        let e2 = Arc::new(ArcSwapOption::default());
+        let inner = e2.clone();
+        let _s2 = a2.observe(move |txn, e| {
+            let moves = e.delta_with_moves(txn).to_vec();
+            inner.store(Some(Arc::new((moves, e.inserted_values(txn)))));
+        });
+
+        {
+            let mut txn = d1.transact_mut();
+            a1.move_range_to(&mut txn, 3, Assoc::After, 4, Assoc::Before, 1);
+            a1.insert(&mut txn, 0, 0);
+        }
+        assert_eq!(a1.to_json(&d1.transact()), vec![0, 1, 4, 5, 2, 3].into());
+        let expected = Some(Arc::new((
+            vec![
+                ArrayChange::Added(vec![0.into()]),
+                ArrayChange::Retain(1),
+                ArrayChange::Moved { from: 3, to: 2 },
+                ArrayChange::Moved { from: 4, to: 3 },
+            ],
+            // moved elements are still reported as inserted at their new position
+            vec![0.into(), 4.into(), 5.into()],
+        )));
+        assert_eq!(e1.load_full(), expected);
+
+        // remote peer receives the same move
+        exchange_updates(&[&d1, &d2]);
+        assert_eq!(a2.to_json(&d2.transact()), vec![0, 1, 4, 5, 2, 3].into());
+        assert_eq!(e2.load_full(), expected);
+    }
+
+    #[test]
+    fn with_element() {
+        let doc = Doc::with_client_id(1);
+        let array = doc.get_or_insert_array("array");
+        {
+            let mut txn = doc.transact_mut();
+            array.insert_range(&mut txn, 0, ["a".repeat(1000), "b".repeat(1000)]);
+            array.push_back(&mut txn, vec![1u8, 2, 3]);
+            array.push_back(&mut txn, MapPrelim::from([("key", 1)]));
+            array.insert_range(&mut txn, 4, [1, 2, 3]);
+            array.move_to(&mut txn, 6, 0);
+        }
+
+        let txn = doc.transact();
+        for i in 0..array.len(&txn) {
+            let expected = match array.get(&txn, i) {
+                Some(Out::Any(any)) => Some(any),
+                _ => None, // shared types are not lent
+            };
+            let actual = array.with_element(&txn, i, |value| value.clone());
+            assert_eq!(actual, expected, "element at index {}", i);
+        }
+        assert_eq!(array.with_element(&txn, 4, |_| ()), None);
+        assert_eq!(array.with_element(&txn, array.len(&txn), |_| ()), None);
+
+        // lent values are not cloned
+        let refs = array.with_element(&txn, 1, |value| match value {
+            Any::String(s) => Arc::strong_count(s),
+            _ => 0,
+        });
+        assert_eq!(refs, Some(1));
+        let value = array.get(&txn, 1);
+        let refs = array.with_element(&txn, 1, |value| match value {
+            Any::String(s) => Arc::strong_count(s),
+            _ => 0,
+        });
+        assert_eq!(refs, Some(2));
+        drop(value);
+    }
+
+    #[test]
+    fn page_with_concurrent_changes() {
+        let d1 = Doc::with_client_id(1);
+        let a1 = d1.get_or_insert_array("array");
+        let d2 = Doc::with_client_id(2);
+        let a2 = d2.get_or_insert_array("array");
+        a1.insert_range(&mut d1.transact_mut(), 0, [1, 2, 3, 4, 5, 6, 7, 8]);
+        exchange_updates(&[&d1, &d2]);
+
+        let (page, cursor) = a1.page(&d1.transact(), None, 3);
+        assert_eq!(page, vec![1.into(), 2.into(), 3.into()]);
+
+        // concurrent changes before and right after the cursor position
+        {
+            let mut txn = d2.transact_mut();
+            a2.insert(&mut txn, 0, 0);
+            a2.remove(&mut txn, 2);
+            a2.insert(&mut txn, 3, 35);
+        }
+        exchange_updates(&[&d1, &d2]);
+        assert_eq!(
+            a1.to_json(&d1.transact()),
+            any!([0, 1, 3, 35, 4, 5, 6, 7, 8])
+        );
+
+        let (page, cursor) = a1.page(&d1.transact(), cursor, 3);
+        assert_eq!(page, vec![35.into(), 4.into(), 5.into()]);
+
+        // cursor can be passed over to another replica
+        let cursor = cursor.unwrap();
+        let cursor = BranchCursor::decode_v1(&cursor.encode_v1()).unwrap();
+
+        // last element of the previous page has been removed
+        a2.remove(&mut d2.transact_mut(), 5);
+        let (page, cursor) = a2.page(&d2.transact(), Some(cursor), 3);
+        assert_eq!(page, vec![6.into(), 7.into(), 8.into()]);
+        assert!(cursor.is_none());
+
+        let (page, cursor) = a2.page(&d2.transact(), None, 0);
+        assert!(page.is_empty());
+        assert!(cursor.is_none());
+    }
+
+    #[test]
+    fn chunks() {
+        let doc = Doc::with_client_id(1);
+        let array = doc.get_or_insert_array("array");
+        let empty = doc.get_or_insert_array("empty");
+        array.insert_range(&mut doc.transact_mut(), 0, [0, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
+
+        let txn = doc.transact();
+        let chunks: Vec<Vec<Out>> = array.chunks(&txn, 3).collect();
+        assert_eq!(
+            chunks,
+            vec![
+                vec![0.into(), 1.into(), 2.into()],
+                vec![3.into(), 4.into(), 5.into()],
+                vec![6.into(), 7.into(), 8.into()],
+                vec![9.into()],
+            ]
+        );
+        drop(txn);
+
+        // deleted elements are skipped
+        array.remove_range(&mut doc.transact_mut(), 2, 2);
+        let txn = doc.transact();
+        let chunks: Vec<Vec<Out>> = array.chunks(&txn, 3).collect();
+        assert_eq!(
+            chunks,
+            vec![
+                vec![0.into(), 1.into(), 4.into()],
+                vec![5.into(), 6.into(), 7.into()],
+                vec![8.into(), 9.into()],
+            ]
+        );
+
+        assert_eq!(empty.chunks(&txn, 3).count(), 0);
+    }
+
+    #[test]
+    fn insert_move() {
+        let doc = Doc::with_client_id(1);
+        let array = doc.get_or_insert_array("array");
+        array.insert_range(&mut doc.transact_mut(), 0, [1, 2, 3, 4, 5]);
+
+        // forward
+        array.insert_move(&mut doc.transact_mut(), 1, 3);
+        assert_eq!(array.to_json(&doc.transact()), vec![1, 3, 4, 2, 5].into());
+        array.insert_move(&mut doc.transact_mut(), 0, 4);
+        assert_eq!(array.to_json(&doc.transact()), vec![3, 4, 2, 5, 1].into());
+
+        // backward
+        array.insert_move(&mut doc.transact_mut(), 3, 1);
+        assert_eq!(array.to_json(&doc.transact()), vec![3, 5, 4, 2, 1].into());
+        array.insert_move(&mut doc.transact_mut(), 4, 0);
+        assert_eq!(array.to_json(&doc.transact()), vec![1, 3, 5, 4, 2].into());
+
+        // no-op
+        array.insert_move(&mut doc.transact_mut(), 2, 2);
+        assert_eq!(array.to_json(&doc.transact()), vec![1, 3, 5, 4, 2].into());
+    }
+
+    #[test]
+    fn insert_move_concurrent() {
+        let d1 = Doc::with_client_id(1);
+        let a1 = d1.get_or_insert_array("array");
+        let d2 = Doc::with_client_id(2);
+        let a2 = d2.get_or_insert_array("array");
+
+        a1.insert_range(&mut d1.transact_mut(), 0, [1, 2, 3, 4]);
+        exchange_updates(&[&d1, &d2]);
+
+        // both peers move the same element to different positions
+        a1.insert_move(&mut d1.transact_mut(), 0, 3);
+        assert_eq!(a1.to_json(&d1.transact()), vec![2, 3, 4, 1].into());
+        a2.insert_move(&mut d2.transact_mut(), 0, 1);
+        a2.insert_move(&mut d2.transact_mut(), 3, 0);
+        assert_eq!(a2.to_json(&d2.transact()), vec![4, 2, 1, 3].into());
+
+        exchange_updates(&[&d1, &d2]);
+
+        let expected = a1.to_json(&d1.transact());
+        assert_eq!(a1.len(&d1.transact()), 4);
+        assert_eq!(a2.to_json(&d2.transact()), expected);
+    }
+
     #[test]
     fn move_cycles() {
         let d1 = Doc::with_client_id(1);
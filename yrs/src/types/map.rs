@@ -77,17 +77,40 @@ impl Observable for MapRef {
 impl ToJson for MapRef {
     fn to_json<T: ReadTxn>(&self, txn: &T) -> Any {
         let inner = self.0;
-        let mut res = HashMap::new();
+        // map also contains deleted entries, so its size is an upper bound
+        let mut res = Any::map_with_capacity(inner.map.len());
         for (key, item) in inner.map.iter() {
             if !item.is_deleted() {
                 let last = item.content.get_last().unwrap_or(Out::Any(Any::Null));
                 res.insert(key.to_string(), last.to_json(txn));
             }
         }
-        Any::from(res)
+        res.build()
     }
 }
 
+/// Materializes map entries of a given `branch`, as they existed at the time of a given `snapshot`.
+/// Expects blocks to be split along snapshot boundaries.
+pub(crate) fn map_json_at<T: ReadTxn>(branch: &Branch, txn: &T, snapshot: &Snapshot) -> Any {
+    let mut res = HashMap::new();
+    for (key, item) in branch.map.iter() {
+        // map entries overridden under the same key are linked together via left pointers
+        let mut current = Some(*item);
+        while let Some(item) = current.as_deref() {
+            if snapshot.is_visible(&item.id) {
+                let last = item.content.get_last().unwrap_or(Out::Any(Any::Null));
+                res.insert(
+                    key.to_string(),
+                    crate::types::to_json_at(last, txn, snapshot),
+                );
+                break;
+            }
+            current = item.left;
+        }
+    }
+    Any::from(res)
+}
+
 impl AsRef<Branch> for MapRef {
     fn as_ref(&self) -> &Branch {
         self.0.deref()
@@ -163,6 +186,35 @@ pub trait Map: AsRef<Branch> + Sized {
         len
     }
 
+    /// Converts contents of current map into a JSON-like representation, as it existed at the
+    /// time of a given `snapshot`. Nested maps, arrays and texts are also read at that snapshot.
+    ///
+    /// This method requires the document to be created with [Options::skip_gc] enabled, otherwise
+    /// the contents of deleted blocks could have already been garbage collected.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{any, Doc, Map, Options, ReadTxn, Transact};
+    ///
+    /// let doc = Doc::with_options(Options {
+    ///     skip_gc: true,
+    ///     ..Options::default()
+    /// });
+    /// let map = doc.get_or_insert_map("map");
+    /// let mut txn = doc.transact_mut();
+    /// map.insert(&mut txn, "a", 1);
+    /// let snapshot = txn.snapshot();
+    ///
+    /// map.insert(&mut txn, "a", 2);
+    /// map.insert(&mut txn, "b", 3);
+    /// assert_eq!(map.to_json_at(&mut txn, &snapshot), any!({"a": 1}));
+    /// ```
+    fn to_json_at(&self, txn: &mut TransactionMut, snapshot: &Snapshot) -> Any {
+        txn.split_by_snapshot(snapshot);
+        map_json_at(self.as_ref(), txn, snapshot)
+    }
+
     /// Returns an iterator that enables to traverse over all keys of entries stored within
     /// current map. These keys are not ordered.
     fn keys<'a, T: ReadTxn + 'a>(&'a self, txn: &'a T) -> Keys<'a, &'a T, T> {
@@ -273,6 +325,101 @@ pub trait Map: AsRef<Branch> + Sized {
         self.insert(txn, key, value)
     }
 
+    /// Resolves a nested [MapRef] stored under a given `key` and calls `f` with it. Returns `None`
+    /// if the entry doesn't exist or it's not a map.
+    fn with_map<R, F>(&self, txn: &mut TransactionMut, key: &str, f: F) -> Option<R>
+    where
+        F: FnOnce(&MapRef, &mut TransactionMut) -> R,
+    {
+        let map: MapRef = self.get(txn, key)?.cast().ok()?;
+        Some(f(&map, txn))
+    }
+
+    /// Resolves a nested [ArrayRef] stored under a given `key` and calls `f` with it. Returns
+    /// `None` if the entry doesn't exist or it's not an array.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Array, ArrayPrelim, Doc, Map, MapPrelim, Transact, WriteTxn};
+    ///
+    /// let doc = Doc::new();
+    /// let mut txn = doc.transact_mut();
+    /// let map = txn.get_or_insert_map("map");
+    /// map.insert(&mut txn, "list", ArrayPrelim::from([1, 2]));
+    ///
+    /// let len = map.with_array(&mut txn, "list", |list, txn| {
+    ///     list.push_back(txn, 3);
+    ///     list.len(txn)
+    /// });
+    /// assert_eq!(len, Some(3));
+    ///
+    /// // entry is not an array
+    /// map.insert(&mut txn, "nested", MapPrelim::default());
+    /// assert_eq!(map.with_array(&mut txn, "nested", |list, txn| list.len(txn)), None);
+    /// ```
+    fn with_array<R, F>(&self, txn: &mut TransactionMut, key: &str, f: F) -> Option<R>
+    where
+        F: FnOnce(&ArrayRef, &mut TransactionMut) -> R,
+    {
+        let array: ArrayRef = self.get(txn, key)?.cast().ok()?;
+        Some(f(&array, txn))
+    }
+
+    /// Resolves a nested [TextRef] stored under a given `key` and calls `f` with it. Returns `None`
+    /// if the entry doesn't exist or it's not a text.
+    fn with_text<R, F>(&self, txn: &mut TransactionMut, key: &str, f: F) -> Option<R>
+    where
+        F: FnOnce(&TextRef, &mut TransactionMut) -> R,
+    {
+        let text: TextRef = self.get(txn, key)?.cast().ok()?;
+        Some(f(&text, txn))
+    }
+
+    /// Updates an entry stored under a given `key` using a function `f`, which receives a current
+    /// value of that entry (or `None` if it's absent) and returns a new value to be stored. If `f`
+    /// returns `None`, the entry is removed.
+    ///
+    /// Both read and write are performed within the scope of the same transaction, so no other
+    /// local change can interleave between them. However this is not an atomic operation across
+    /// replicas: concurrent updates of the same `key` made by different peers are resolved like any
+    /// other concurrent inserts (last writer wins), so eg. two concurrent increments of a counter
+    /// will result in only one of them being visible after synchronization.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Any, Doc, Map, Out, Transact, WriteTxn};
+    ///
+    /// let doc = Doc::new();
+    /// let mut txn = doc.transact_mut();
+    /// let map = txn.get_or_insert_map("map");
+    /// let increment = |value: Option<Out>| match value {
+    ///     Some(Out::Any(Any::Number(n))) => Some(Any::Number(n + 1.0)),
+    ///     _ => Some(Any::Number(1.0)),
+    /// };
+    ///
+    /// map.update(&mut txn, "counter", increment);
+    /// map.update(&mut txn, "counter", increment);
+    /// assert_eq!(map.get(&txn, "counter"), Some(Out::Any(Any::Number(2.0))));
+    /// ```
+    fn update<K, F>(&self, txn: &mut TransactionMut, key: K, f: F)
+    where
+        K: Into<Arc<str>>,
+        F: FnOnce(Option<Out>) -> Option<Any>,
+    {
+        let key = key.into();
+        let current = self.get(txn, &key);
+        match f(current) {
+            Some(value) => {
+                self.insert(txn, key, value);
+            }
+            None => {
+                self.remove(txn, &key);
+            }
+        }
+    }
+
     /// Removes a stored within current map under a given `key`. Returns that value or `None` if
     /// no entry with a given `key` was present in current map.
     ///
@@ -287,6 +434,31 @@ pub trait Map: AsRef<Branch> + Sized {
         ptr.remove(txn, key)
     }
 
+    /// Renames an entry stored under `from` key to `to` key, preserving its value. Returns `true`
+    /// if the entry was renamed, `false` if there was no entry under `from` key or an entry under
+    /// `to` key already exists - in that case current map is left unchanged.
+    ///
+    /// ### Renaming nested shared types
+    ///
+    /// Map entries cannot be re-parented under another key without changing their identity.
+    /// Therefore nested shared types (eg. [MapRef], [ArrayRef], [TextRef]) are deep-copied under
+    /// a new key, while the original ones are removed recursively. Any existing references to the
+    /// original type (eg. [StickyIndex] or weak links) will keep pointing to the removed instance.
+    /// Sub-documents are moved under a new key as the same [Doc] instance.
+    fn rename_key(&self, txn: &mut TransactionMut, from: &str, to: &str) -> bool {
+        if self.contains_key(txn, to) {
+            return false;
+        }
+        let value = match self.get(txn, from) {
+            Some(value) => value.as_prelim(txn),
+            None => return false,
+        };
+        // insert first, so that moved sub-documents are not considered as removed on commit
+        self.insert(txn, to, value);
+        self.remove(txn, from);
+        true
+    }
+
     /// Returns [WeakPrelim] to a given `key`, if it exists in a current map.
     #[cfg(feature = "weak")]
     fn link<T: ReadTxn>(&self, _txn: &T, key: &str) -> Option<crate::WeakPrelim<Self>> {
@@ -379,7 +551,46 @@ pub trait Map: AsRef<Branch> + Sized {
         }
     }
 
-    /// Clears the contents of current map, effectively removing all of its entries.
+    /// Checks if any entry of current map holds a value equal to a given `value`. Only scalar
+    /// values are compared: nested shared types never match. See [Map::contains_key] to check
+    /// for the presence of a given key.
+    fn contains_value<T: ReadTxn>(&self, _txn: &T, value: &Any) -> bool {
+        self.as_ref().map.values().any(|item| {
+            !item.is_deleted()
+                && matches!(item.content.get_last(), Some(Out::Any(any)) if &any == value)
+        })
+    }
+
+    /// Returns a snapshot of scalar entries of current map as a plain [HashMap]. Entries holding
+    /// nested shared types are skipped. Use [ToJson::to_json] to include them as well.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::collections::HashMap;
+    /// use yrs::{Any, Doc, Map, MapPrelim, Transact};
+    ///
+    /// let doc = Doc::new();
+    /// let map = doc.get_or_insert_map("map");
+    /// let mut txn = doc.transact_mut();
+    /// map.insert(&mut txn, "a", 1);
+    /// map.insert(&mut txn, "nested", MapPrelim::default());
+    ///
+    /// let scalars = map.to_scalar_map(&txn);
+    /// assert_eq!(scalars, HashMap::from([("a".to_string(), Any::from(1))]));
+    /// ```
+    fn to_scalar_map<T: ReadTxn>(&self, txn: &T) -> HashMap<String, Any> {
+        let mut result = HashMap::new();
+        for (key, value) in self.iter(txn) {
+            if let Out::Any(any) = value {
+                result.insert(key.to_string(), any);
+            }
+        }
+        result
+    }
+
+    /// Clears the contents of current map, effectively removing all of its entries. Nested shared
+    /// types are removed recursively.
     fn clear(&self, txn: &mut TransactionMut) {
         for (_, ptr) in self.as_ref().map.iter() {
             txn.delete(ptr.clone());
@@ -486,8 +697,74 @@ impl From<BranchPtr> for MapRef {
     }
 }
 
+impl MapRef {
+    /// Subscribes a given callback to be triggered whenever current map is changed. Unlike
+    /// [Observable::observe], which reports only the net change of each key, this callback receives
+    /// an ordered list of all operations applied to current map within the scope of a committed
+    /// transaction, including the ones which have been overridden later on.
+    ///
+    /// Operations are recorded only while there's at least one active subscriber.
+    ///
+    /// Returns a [Subscription] which, when dropped, will unsubscribe current callback.
+    #[cfg(feature = "sync")]
+    pub fn observe_ops<F>(&self, f: F) -> Subscription
+    where
+        F: Fn(&TransactionMut, &[MapOp]) + Send + Sync + 'static,
+    {
+        self.0.observe_ops(f)
+    }
+
+    /// Subscribes a given callback to be triggered whenever current map is changed. Unlike
+    /// [Observable::observe], which reports only the net change of each key, this callback receives
+    /// an ordered list of all operations applied to current map within the scope of a committed
+    /// transaction, including the ones which have been overridden later on.
+    ///
+    /// Operations are recorded only while there's at least one active subscriber.
+    ///
+    /// Returns a [Subscription] which, when dropped, will unsubscribe current callback.
+    #[cfg(not(feature = "sync"))]
+    pub fn observe_ops<F>(&self, f: F) -> Subscription
+    where
+        F: Fn(&TransactionMut, &[MapOp]) + 'static,
+    {
+        self.0.observe_ops(f)
+    }
+}
+
+/// A single operation applied to a [MapRef], as reported by [MapRef::observe_ops].
+#[derive(Debug, Clone, PartialEq)]
+pub enum MapOp {
+    /// A new entry has been inserted under a `key`, which didn't exist before.
+    Inserted { key: Arc<str>, value: Out },
+    /// An existing entry under a `key` has been overridden with a new value.
+    Updated {
+        key: Arc<str>,
+        old_value: Out,
+        new_value: Out,
+    },
+    /// An existing entry under a `key` has been removed.
+    Removed { key: Arc<str>, old_value: Out },
+}
+
 /// A preliminary map. It can be used to early initialize the contents of a [MapRef], when it's about
 /// to be inserted into another Yrs collection, such as [ArrayRef] or another [MapRef].
+///
+/// Entries of different kinds - including other nested preliminary types - can be mixed together
+/// by converting them into [In] first:
+///
+/// ```
+/// use std::iter::FromIterator;
+/// use yrs::{ArrayPrelim, Doc, In, Map, MapPrelim, TextPrelim, Transact};
+///
+/// let doc = Doc::new();
+/// let map = doc.get_or_insert_map("map");
+/// let mut txn = doc.transact_mut();
+/// map.insert(&mut txn, "nested", MapPrelim::from_iter([
+///     ("text", In::from(TextPrelim::new("hello"))),
+///     ("list", In::from(ArrayPrelim::from([1, 2, 3]))),
+///     ("value", In::from(1.5)),
+/// ]));
+/// ```
 #[repr(transparent)]
 #[derive(Debug, Clone, PartialEq, Default)]
 pub struct MapPrelim(HashMap<Arc<str>, In>);
@@ -571,15 +848,21 @@ pub struct MapEvent {
     pub(crate) current_target: BranchPtr,
     target: MapRef,
     keys: UnsafeCell<Result<HashMap<Arc<str>, EntryChange>, HashSet<Option<Arc<str>>>>>,
+    origin: Option<Origin>,
 }
 
 impl MapEvent {
-    pub(crate) fn new(branch_ref: BranchPtr, key_changes: HashSet<Option<Arc<str>>>) -> Self {
+    pub(crate) fn new(
+        branch_ref: BranchPtr,
+        key_changes: HashSet<Option<Arc<str>>>,
+        origin: Option<Origin>,
+    ) -> Self {
         let current_target = branch_ref.clone();
         MapEvent {
             target: MapRef::from(branch_ref),
             current_target,
             keys: UnsafeCell::new(Err(key_changes)),
+            origin,
         }
     }
 
@@ -588,6 +871,11 @@ impl MapEvent {
         &self.target
     }
 
+    /// Returns an origin of the transaction, which produced this event, if any was defined.
+    pub fn origin(&self) -> Option<&Origin> {
+        self.origin.as_ref()
+    }
+
     /// Returns a path from root type down to [Map] instance which emitted this event.
     pub fn path(&self) -> Path {
         Branch::path(self.current_target, self.target.0)
@@ -613,20 +901,52 @@ impl MapEvent {
             }
         }
     }
+
+    /// Returns entries which values have been inserted or updated within bounds of current
+    /// transaction, together with their new values. Nested shared types are returned as their
+    /// refs (eg. [Out::YMap]), so they can be observed right away.
+    pub fn inserted_values(&self, txn: &TransactionMut) -> Vec<(&Arc<str>, &Out)> {
+        let mut values = Vec::new();
+        for (key, change) in self.keys(txn) {
+            match change {
+                EntryChange::Inserted(value) | EntryChange::Updated(_, value) => {
+                    values.push((key, value))
+                }
+                EntryChange::Removed(_) => {}
+            }
+        }
+        values
+    }
+
+    /// Returns entries which values have been removed or overridden within bounds of current
+    /// transaction, together with their previous values.
+    pub fn removed_values(&self, txn: &TransactionMut) -> Vec<(&Arc<str>, &Out)> {
+        let mut values = Vec::new();
+        for (key, change) in self.keys(txn) {
+            match change {
+                EntryChange::Removed(value) | EntryChange::Updated(value, _) => {
+                    values.push((key, value))
+                }
+                EntryChange::Inserted(_) => {}
+            }
+        }
+        values
+    }
 }
 
 #[cfg(test)]
 mod test {
     use crate::test_utils::{exchange_updates, run_scenario, RngExt};
     use crate::transaction::ReadTxn;
+    use crate::types::map::MapOp;
     use crate::types::text::TextPrelim;
     use crate::types::{DeepObservable, EntryChange, Event, Out, Path, PathSegment, ToJson};
     use crate::updates::decoder::Decode;
     use crate::updates::encoder::{Encoder, EncoderV1};
     use crate::{
         any, Any, Array, ArrayPrelim, ArrayRef, Doc, GetString, In, Map, MapPrelim, MapRef,
-        Observable, StateVector, Text, TextRef, Transact, Update, WriteTxn, XmlFragment,
-        XmlFragmentRef, XmlTextPrelim, XmlTextRef,
+        Observable, Options, Origin, StateVector, Text, TextRef, Transact, Update, WriteTxn,
+        XmlFragment, XmlFragmentRef, XmlTextPrelim, XmlTextRef,
     };
     use arc_swap::ArcSwapOption;
     use fastrand::Rng;
@@ -690,6 +1010,72 @@ mod test {
         compare_all(&m2, &t2);
     }
 
+    #[test]
+    fn update() {
+        let doc = Doc::with_client_id(1);
+        let map = doc.get_or_insert_map("map");
+        let increment = |value: Option<Out>| match value {
+            Some(Out::Any(Any::Number(n))) => Some(Any::Number(n + 1.0)),
+            _ => Some(Any::Number(1.0)),
+        };
+
+        let mut txn = doc.transact_mut();
+        // absent key initialization
+        map.update(&mut txn, "counter", increment);
+        assert_eq!(map.get(&txn, "counter"), Some(Out::Any(Any::Number(1.0))));
+
+        // increment
+        map.update(&mut txn, "counter", increment);
+        map.update(&mut txn, "counter", increment);
+        assert_eq!(map.get(&txn, "counter"), Some(Out::Any(Any::Number(3.0))));
+
+        // conditional delete
+        let delete_above_2 = |value: Option<Out>| match value {
+            Some(Out::Any(Any::Number(n))) if n > 2.0 => None,
+            Some(Out::Any(any)) => Some(any),
+            _ => None,
+        };
+        map.insert(&mut txn, "low", 1);
+        map.update(&mut txn, "low", delete_above_2);
+        map.update(&mut txn, "counter", delete_above_2);
+        assert_eq!(map.get(&txn, "low"), Some(Out::Any(Any::Number(1.0))));
+        assert_eq!(map.get(&txn, "counter"), None);
+
+        // removing an absent key is a no-op
+        map.update(&mut txn, "missing", |value| {
+            assert_eq!(value, None);
+            None
+        });
+        assert_eq!(map.len(&txn), 1);
+    }
+
+    #[test]
+    fn to_scalar_map() {
+        let doc = Doc::with_client_id(1);
+        let map = doc.get_or_insert_map("map");
+        let mut txn = doc.transact_mut();
+        map.insert(&mut txn, "number", 1);
+        map.insert(&mut txn, "string", "hello");
+        map.insert(&mut txn, "list", any!([1, 2]));
+        map.insert(&mut txn, "null", Any::Null);
+        map.insert(&mut txn, "map", MapPrelim::from([("key", "value")]));
+        map.insert(&mut txn, "array", ArrayPrelim::default());
+        map.insert(&mut txn, "text", TextPrelim::new("text"));
+        map.insert(&mut txn, "removed", true);
+        map.remove(&mut txn, "removed");
+
+        let scalars = map.to_scalar_map(&txn);
+        assert_eq!(
+            scalars,
+            HashMap::from([
+                ("number".to_string(), any!(1)),
+                ("string".to_string(), any!("hello")),
+                ("list".to_string(), any!([1, 2])),
+                ("null".to_string(), Any::Null),
+            ])
+        );
+    }
+
     #[test]
     fn map_get_set() {
         let d1 = Doc::with_client_id(1);
@@ -760,6 +1146,64 @@ mod test {
         assert_eq!(m1.len(&t1), 0);
     }
 
+    #[test]
+    fn rename_key_scalar() {
+        let d1 = Doc::with_client_id(1);
+        let m1 = d1.get_or_insert_map("map");
+        let d2 = Doc::with_client_id(2);
+        let m2 = d2.get_or_insert_map("map");
+        {
+            let mut txn = d1.transact_mut();
+            m1.insert(&mut txn, "firstName", "John");
+            assert!(m1.rename_key(&mut txn, "firstName", "first_name"));
+            assert!(!m1.rename_key(&mut txn, "missing", "other"));
+        }
+        exchange_updates(&[&d1, &d2]);
+
+        let txn = d2.transact();
+        assert_eq!(m2.to_json(&txn), any!({"first_name": "John"}));
+    }
+
+    #[test]
+    fn rename_key_nested() {
+        let d1 = Doc::with_client_id(1);
+        let m1 = d1.get_or_insert_map("map");
+        let d2 = Doc::with_client_id(2);
+        let m2 = d2.get_or_insert_map("map");
+        {
+            let mut txn = d1.transact_mut();
+            let nested = m1.insert(&mut txn, "old", MapPrelim::default());
+            nested.insert(&mut txn, "text", TextPrelim::new("hello"));
+            nested.insert(&mut txn, "value", 1);
+            assert!(m1.rename_key(&mut txn, "old", "new"));
+            assert!(!txn.is_alive(&nested));
+
+            let renamed: MapRef = m1.get(&txn, "new").unwrap().cast().unwrap();
+            let text: TextRef = renamed.get(&txn, "text").unwrap().cast().unwrap();
+            assert_eq!(text.get_string(&txn), "hello");
+        }
+        exchange_updates(&[&d1, &d2]);
+
+        let txn = d2.transact();
+        assert_eq!(
+            m2.to_json(&txn),
+            any!({"new": {"text": "hello", "value": 1}})
+        );
+    }
+
+    #[test]
+    fn rename_key_collision() {
+        let doc = Doc::with_client_id(1);
+        let map = doc.get_or_insert_map("map");
+        let mut txn = doc.transact_mut();
+        map.insert(&mut txn, "a", 1);
+        map.insert(&mut txn, "b", 2);
+
+        assert!(!map.rename_key(&mut txn, "a", "b"));
+        assert!(!map.rename_key(&mut txn, "a", "a"));
+        assert_eq!(map.to_json(&txn), any!({"a": 1, "b": 2}));
+    }
+
     #[test]
     fn map_clear() {
         let d1 = Doc::with_client_id(1);
@@ -1060,6 +1504,68 @@ mod test {
         );
     }
 
+    #[test]
+    fn observe_event_origin() {
+        let doc = Doc::with_client_id(1);
+        let map = doc.get_or_insert_map("map");
+
+        let origins = Arc::new(Mutex::new(Vec::new()));
+        let origins_c = origins.clone();
+        let _sub = map.observe(move |_txn, e| {
+            origins_c.lock().unwrap().push(e.origin().cloned());
+        });
+
+        map.insert(&mut doc.transact_mut_with("remote"), "a", 1);
+        map.insert(&mut doc.transact_mut(), "b", 2);
+
+        assert_eq!(
+            origins.lock().unwrap().drain(..).collect::<Vec<_>>(),
+            vec![Some(Origin::from("remote")), None]
+        );
+    }
+
+    #[test]
+    fn event_inserted_and_removed_values() {
+        let doc = Doc::with_client_id(1);
+        let map = doc.get_or_insert_map("map");
+        let inserted = Arc::new(Mutex::new(Vec::new()));
+        let removed = Arc::new(Mutex::new(Vec::new()));
+
+        let (inserted_c, removed_c) = (inserted.clone(), removed.clone());
+        let _sub = map.observe(move |txn, e| {
+            for (key, value) in e.inserted_values(txn) {
+                if let Out::YMap(nested) = value {
+                    // nested map is already resolved, so it can be read or observed
+                    let json = nested.to_json(txn);
+                    inserted_c.lock().unwrap().push((key.to_string(), json));
+                }
+            }
+            for (key, value) in e.removed_values(txn) {
+                removed_c
+                    .lock()
+                    .unwrap()
+                    .push((key.to_string(), value.clone()));
+            }
+        });
+
+        {
+            let mut txn = doc.transact_mut();
+            map.insert(&mut txn, "a", 1);
+            map.insert(&mut txn, "b", MapPrelim::from([("key", "value")]));
+        }
+        assert_eq!(
+            inserted.lock().unwrap().drain(..).collect::<Vec<_>>(),
+            vec![("b".to_string(), any!({"key": "value"}))]
+        );
+        assert!(removed.lock().unwrap().is_empty());
+
+        map.remove(&mut doc.transact_mut(), "a");
+        assert_eq!(
+            removed.lock().unwrap().drain(..).collect::<Vec<_>>(),
+            vec![("a".to_string(), Out::Any(any!(1)))]
+        );
+    }
+
     fn map_transactions() -> [Box<dyn Fn(&mut Doc, &mut Rng)>; 3] {
         fn set(doc: &mut Doc, rng: &mut Rng) {
             let map = doc.get_or_insert_map("map");
@@ -1191,6 +1697,47 @@ mod test {
         assert_eq!(m.get_string(&txn), "c".to_string());
     }
 
+    #[test]
+    fn with_nested() {
+        let d1 = Doc::with_client_id(1);
+        let map = d1.get_or_insert_map("map");
+        {
+            let mut txn = d1.transact_mut();
+            map.insert(&mut txn, "list", ArrayPrelim::from([1, 2]));
+            map.insert(&mut txn, "text", TextPrelim::new("hello"));
+            map.insert(&mut txn, "value", 1);
+        }
+
+        let d2 = Doc::with_client_id(2);
+        exchange_updates(&[&d1, &d2]);
+
+        {
+            let mut txn = d1.transact_mut();
+            let len = map.with_array(&mut txn, "list", |list, txn| {
+                list.remove(txn, 0);
+                list.push_back(txn, 3);
+                list.len(txn)
+            });
+            assert_eq!(len, Some(2));
+            let appended = map.with_text(&mut txn, "text", |text, txn| {
+                text.push(txn, " world");
+                text.get_string(txn)
+            });
+            assert_eq!(appended.as_deref(), Some("hello world"));
+
+            // missing entries and entries of different type are not resolved
+            assert_eq!(map.with_array(&mut txn, "missing", |_, _| ()), None);
+            assert_eq!(map.with_array(&mut txn, "text", |_, _| ()), None);
+            assert_eq!(map.with_map(&mut txn, "value", |_, _| ()), None);
+        }
+
+        exchange_updates(&[&d1, &d2]);
+        let map = d2.get_or_insert_map("map");
+        let txn = d2.transact();
+        let list: ArrayRef = map.get(&txn, "list").unwrap().cast().unwrap();
+        assert_eq!(list.to_json(&txn), vec![2, 3].into());
+    }
+
     #[test]
     fn try_update() {
         let doc = Doc::new();
@@ -1337,4 +1884,143 @@ mod test {
 
         assert!(value == 1.into() || value == 2.into())
     }
+
+    #[test]
+    fn to_json_at() {
+        let mut options = Options::with_client_id(1);
+        options.skip_gc = true;
+        let doc = Doc::with_options(options);
+        let map = doc.get_or_insert_map("map");
+
+        let snapshot = {
+            let mut txn = doc.transact_mut();
+            map.insert(&mut txn, "a", 1);
+            map.insert(&mut txn, "b", "removed");
+            let text = map.insert(&mut txn, "text", TextPrelim::new("hello"));
+            let nested = map.insert(&mut txn, "nested", MapPrelim::default());
+            nested.insert(&mut txn, "x", true);
+            let snapshot = txn.snapshot();
+
+            map.insert(&mut txn, "a", 2);
+            map.remove(&mut txn, "b");
+            map.insert(&mut txn, "c", 3);
+            text.insert(&mut txn, 5, " world");
+            nested.insert(&mut txn, "x", false);
+            snapshot
+        };
+
+        let mut txn = doc.transact_mut();
+        assert_eq!(
+            map.to_json(&txn),
+            any!({"a": 2, "c": 3, "text": "hello world", "nested": {"x": false}})
+        );
+        assert_eq!(
+            map.to_json_at(&mut txn, &snapshot),
+            any!({"a": 1, "b": "removed", "text": "hello", "nested": {"x": true}})
+        );
+    }
+
+    #[test]
+    fn contains_value() {
+        let doc = Doc::with_client_id(1);
+        let map = doc.get_or_insert_map("map");
+        let mut txn = doc.transact_mut();
+        map.insert(&mut txn, "a", 1);
+        map.insert(&mut txn, "b", "hello");
+        map.insert(&mut txn, "c", 2);
+        map.insert(&mut txn, "nested", MapPrelim::default());
+        map.remove(&mut txn, "c");
+
+        assert!(map.contains_value(&txn, &Any::from(1)));
+        assert!(map.contains_value(&txn, &Any::from("hello")));
+        // removed entries and keys are not matched
+        assert!(!map.contains_value(&txn, &Any::from(2)));
+        assert!(!map.contains_value(&txn, &Any::from("a")));
+        // nested shared types don't match any scalar value
+        assert!(!map.contains_value(&txn, &Any::from(HashMap::<String, Any>::new())));
+    }
+
+    #[test]
+    fn observe_ops() {
+        let d1 = Doc::with_client_id(1);
+        let m1 = d1.get_or_insert_map("map");
+        let ops = Arc::new(Mutex::new(Vec::new()));
+        let _sub = {
+            let ops = ops.clone();
+            m1.observe_ops(move |_, e| ops.lock().unwrap().extend_from_slice(e))
+        };
+        let keys = Arc::new(Mutex::new(None));
+        let _sub_coalesced = {
+            let keys = keys.clone();
+            m1.observe(move |txn, e| {
+                *keys.lock().unwrap() = Some(e.keys(txn).clone());
+            })
+        };
+
+        {
+            let mut txn = d1.transact_mut();
+            m1.insert(&mut txn, "a", 1);
+            m1.insert(&mut txn, "b", 2);
+            m1.insert(&mut txn, "a", 3);
+            m1.remove(&mut txn, "b");
+            m1.insert(&mut txn, "c", 4);
+        }
+
+        let a = Arc::<str>::from("a");
+        let b = Arc::<str>::from("b");
+        let c = Arc::<str>::from("c");
+        assert_eq!(
+            std::mem::take(&mut *ops.lock().unwrap()),
+            vec![
+                MapOp::Inserted {
+                    key: a.clone(),
+                    value: 1.into()
+                },
+                MapOp::Inserted {
+                    key: b.clone(),
+                    value: 2.into()
+                },
+                MapOp::Updated {
+                    key: a.clone(),
+                    old_value: 1.into(),
+                    new_value: 3.into()
+                },
+                MapOp::Removed {
+                    key: b.clone(),
+                    old_value: 2.into()
+                },
+                MapOp::Inserted {
+                    key: c.clone(),
+                    value: 4.into()
+                },
+            ]
+        );
+        // coalesced event still reports only the net changes
+        let keys = keys.lock().unwrap().take().unwrap();
+        assert_eq!(keys.len(), 2);
+        assert_eq!(
+            keys.get(&a),
+            Some(&EntryChange::Inserted(Any::from(3).into()))
+        );
+
+        {
+            let mut txn = d1.transact_mut();
+            m1.remove(&mut txn, "a");
+            m1.insert(&mut txn, "c", 5);
+        }
+        assert_eq!(
+            std::mem::take(&mut *ops.lock().unwrap()),
+            vec![
+                MapOp::Removed {
+                    key: a,
+                    old_value: 3.into()
+                },
+                MapOp::Updated {
+                    key: c,
+                    old_value: 4.into(),
+                    new_value: 5.into()
+                },
+            ]
+        );
+    }
 }
@@ -224,6 +224,24 @@ pub trait Observable: AsRef<Branch> {
         })
     }
 
+    /// Subscribes a given callback to be triggered only once - the next time current y-type is
+    /// changed. After that, callback is automatically unsubscribed. It works the same way as
+    /// [Observable::observe] otherwise.
+    ///
+    /// Returns a [Subscription] which, when dropped before callback has been triggered, will
+    /// unsubscribe current callback.
+    fn observe_once<F>(&self, f: F) -> Subscription
+    where
+        F: Fn(&TransactionMut, &Self::Event) + Send + Sync + 'static,
+        Event: AsRef<Self::Event>,
+    {
+        let mut branch = BranchPtr::from(self.as_ref());
+        branch.observe_once(move |txn, e| {
+            let mapped_event = e.as_ref();
+            f(txn, mapped_event)
+        })
+    }
+
     /// Subscribes a given callback to be triggered whenever current y-type is changed.
     /// A callback is triggered whenever a transaction gets committed. This function does not
     /// trigger if changes have been observed by nested shared collections.
@@ -278,6 +296,24 @@ pub trait Observable: AsRef<Branch> {
         })
     }
 
+    /// Subscribes a given callback to be triggered only once - the next time current y-type is
+    /// changed. After that, callback is automatically unsubscribed. It works the same way as
+    /// [Observable::observe] otherwise.
+    ///
+    /// Returns a [Subscription] which, when dropped before callback has been triggered, will
+    /// unsubscribe current callback.
+    fn observe_once<F>(&self, f: F) -> Subscription
+    where
+        F: Fn(&TransactionMut, &Self::Event) + 'static,
+        Event: AsRef<Self::Event>,
+    {
+        let mut branch = BranchPtr::from(self.as_ref());
+        branch.observe_once(move |txn, e| {
+            let mapped_event = e.as_ref();
+            f(txn, mapped_event)
+        })
+    }
+
     /// Subscribes a given callback to be triggered whenever current y-type is changed.
     /// A callback is triggered whenever a transaction gets committed. This function does not
     /// trigger if changes have been observed by nested shared collections.
@@ -750,6 +786,30 @@ pub enum Change {
     Retain(u32),
 }
 
+/// A single change done over an [Array], as reported by [ArrayEvent::delta_with_moves]. Unlike
+/// [Change], it recognizes existing elements relocated within the same collection, instead of
+/// reporting them as removed from their old position and inserted at the new one.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArrayChange {
+    /// Determines a range of newly inserted elements.
+    Added(Vec<Out>),
+
+    /// Determines a number of consecutive removed elements.
+    Removed(u32),
+
+    /// Determines a number of consecutive unchanged elements.
+    Retain(u32),
+
+    /// Determines an existing element relocated using [Array::move_to] or [Array::move_range_to].
+    /// `from` is an index of the element before the transaction, while `to` is its index after
+    /// the transaction.
+    ///
+    /// Moved elements are not counted by [ArrayChange::Retain] or [ArrayChange::Removed], so
+    /// the delta can be applied by first taking all moved elements out of their `from` positions
+    /// and then placing each one of them back where its [ArrayChange::Moved] entry occurs.
+    Moved { from: u32, to: u32 },
+}
+
 /// A single change done over a map-component of shared data type.
 #[derive(Debug, Clone, PartialEq)]
 pub enum EntryChange {
@@ -868,6 +928,29 @@ pub(crate) fn event_keys(
 }
 
 pub(crate) fn event_change_set(txn: &TransactionMut, start: Option<ItemPtr>) -> ChangeSet<Change> {
+    let change_set = array_change_set(txn, start, false);
+    let delta = change_set
+        .delta
+        .into_iter()
+        .map(|change| match change {
+            ArrayChange::Added(values) => Change::Added(values),
+            ArrayChange::Removed(len) => Change::Removed(len),
+            ArrayChange::Retain(len) => Change::Retain(len),
+            ArrayChange::Moved { .. } => unreachable!("moves are reported only on demand"),
+        })
+        .collect();
+    ChangeSet::new(change_set.added, change_set.deleted, delta)
+}
+
+/// Computes a change set of an array-like collection starting at a given `start` block. When
+/// `with_moves` is set, elements relocated by moves created in current transaction are reported
+/// as [ArrayChange::Moved]. Otherwise they are reported as removed from their old position and
+/// added at the new one.
+pub(crate) fn array_change_set(
+    txn: &TransactionMut,
+    start: Option<ItemPtr>,
+    with_moves: bool,
+) -> ChangeSet<ArrayChange> {
     let mut added = HashSet::new();
     let mut deleted = HashSet::new();
     let mut delta = Vec::new();
@@ -878,6 +961,13 @@ pub(crate) fn event_change_set(txn: &TransactionMut, start: Option<ItemPtr>) ->
     let mut curr_move_is_deleted = false;
     let mut curr_move_end: Option<ItemPtr> = None;
     let mut last_op = None;
+    // indexes of the next element before and after the transaction
+    let mut old_index = 0u32;
+    let mut new_index = 0u32;
+    // old indexes of elements relocated by moves created in this transaction
+    let mut move_sources: HashMap<ItemPtr, u32> = HashMap::new();
+    // positions in delta of moved elements, which sources have not been reached yet
+    let mut move_targets: Vec<(usize, ItemPtr)> = Vec::new();
 
     #[derive(Default)]
     struct MoveStackItem {
@@ -946,15 +1036,23 @@ pub(crate) fn event_change_set(txn: &TransactionMut, start: Option<ItemPtr>) ->
                         && (txn.prev_moved.get(&item).cloned() == curr_move)
                     {
                         match item.moved {
+                            Some(ptr) if txn.has_added(ptr.id()) && with_moves => {
+                                // element has been moved elsewhere: it will be reported
+                                // as ArrayChange::Moved at its new position
+                                move_sources.insert(item, old_index);
+                                old_index += item.content_len(encoding);
+                            }
                             Some(ptr) if txn.has_added(ptr.id()) => {
                                 let len = item.content_len(encoding);
                                 last_op = match last_op.take() {
-                                    Some(Change::Removed(i)) => Some(Change::Removed(i + len)),
+                                    Some(ArrayChange::Removed(i)) => {
+                                        Some(ArrayChange::Removed(i + len))
+                                    }
                                     Some(op) => {
                                         delta.push(op);
-                                        Some(Change::Removed(len))
+                                        Some(ArrayChange::Removed(len))
                                     }
-                                    None => Some(Change::Removed(len)),
+                                    None => Some(ArrayChange::Removed(len)),
                                 };
                             }
                             _ => {}
@@ -968,41 +1066,60 @@ pub(crate) fn event_change_set(txn: &TransactionMut, start: Option<ItemPtr>) ->
                     {
                         let removed = match last_op.take() {
                             None => 0,
-                            Some(Change::Removed(c)) => c,
+                            Some(ArrayChange::Removed(c)) => c,
                             Some(other) => {
                                 delta.push(other);
                                 0
                             }
                         };
-                        last_op = Some(Change::Removed(removed + item.len()));
+                        last_op = Some(ArrayChange::Removed(removed + item.len()));
+                        old_index += item.len();
                         deleted.insert(item.id);
                     } // else nop
                 } else {
-                    if curr_move_is_new
+                    if with_moves && curr_move_is_new && !txn.has_added(&item.id) {
+                        if let Some(op) = last_op.take() {
+                            delta.push(op);
+                        }
+                        let from = move_sources.get(&item).cloned();
+                        if from.is_none() {
+                            move_targets.push((delta.len(), item));
+                        }
+                        for i in 0..item.len() {
+                            delta.push(ArrayChange::Moved {
+                                from: from.unwrap_or_default() + i,
+                                to: new_index + i,
+                            });
+                        }
+                        new_index += item.len();
+                    } else if curr_move_is_new
                         || txn.has_added(&item.id)
                         || txn.prev_moved.contains_key(&item)
                     {
                         let mut inserts = match last_op.take() {
                             None => Vec::with_capacity(item.len() as usize),
-                            Some(Change::Added(values)) => values,
+                            Some(ArrayChange::Added(values)) => values,
                             Some(other) => {
                                 delta.push(other);
                                 Vec::with_capacity(item.len() as usize)
                             }
                         };
                         inserts.append(&mut item.content.get_content());
-                        last_op = Some(Change::Added(inserts));
+                        last_op = Some(ArrayChange::Added(inserts));
+                        new_index += item.len();
                         added.insert(item.id);
                     } else {
                         let retain = match last_op.take() {
                             None => 0,
-                            Some(Change::Retain(c)) => c,
+                            Some(ArrayChange::Retain(c)) => c,
                             Some(other) => {
                                 delta.push(other);
                                 0
                             }
                         };
-                        last_op = Some(Change::Retain(retain + item.len()));
+                        last_op = Some(ArrayChange::Retain(retain + item.len()));
+                        old_index += item.len();
+                        new_index += item.len();
                     }
                 }
             } else {
@@ -1018,10 +1135,29 @@ pub(crate) fn event_change_set(txn: &TransactionMut, start: Option<ItemPtr>) ->
     }
 
     match last_op.take() {
-        None | Some(Change::Retain(_)) => { /* do nothing */ }
+        None | Some(ArrayChange::Retain(_)) => { /* do nothing */ }
         Some(change) => delta.push(change),
     }
 
+    // resolve moved elements, which have been reached before their sources
+    for (pos, item) in move_targets.into_iter().rev() {
+        let len = item.len() as usize;
+        match move_sources.get(&item) {
+            Some(&from) => {
+                for (i, change) in delta[pos..pos + len].iter_mut().enumerate() {
+                    if let ArrayChange::Moved { from: f, .. } = change {
+                        *f = from + i as u32;
+                    }
+                }
+            }
+            None => {
+                // source is not visible in the collection: report it as a regular insertion
+                let values = item.content.get_content();
+                delta.splice(pos..pos + len, std::iter::once(ArrayChange::Added(values)));
+            }
+        }
+    }
+
     ChangeSet::new(added, deleted, delta)
 }
 
@@ -1185,3 +1321,33 @@ pub trait ToJson {
     /// Converts all contents of a current type into a JSON-like representation.
     fn to_json<T: ReadTxn>(&self, txn: &T) -> Any;
 }
+
+/// Converts a given `value` into a JSON-like representation as it existed at the time of a given
+/// `snapshot`. Nested maps, arrays and texts are materialized using their historical contents,
+/// while the remaining shared types fall back to their current state.
+///
+/// This function expects that blocks have already been split along the snapshot boundaries
+/// (see: [TransactionMut::split_by_snapshot]).
+pub(crate) fn to_json_at<T: ReadTxn>(value: Out, txn: &T, snapshot: &Snapshot) -> Any {
+    match value {
+        Out::YText(text) => {
+            let branch: &Branch = text.as_ref();
+            Any::from(text::string_at(branch.start, snapshot))
+        }
+        Out::YMap(map) => map::map_json_at(map.as_ref(), txn, snapshot),
+        Out::YArray(array) => {
+            let mut res = Vec::new();
+            let mut current = array.as_ref().start;
+            while let Some(item) = current.as_deref() {
+                if item.is_countable() && snapshot.is_visible(&item.id) {
+                    for value in item.content.get_content() {
+                        res.push(to_json_at(value, txn, snapshot));
+                    }
+                }
+                current = item.right;
+            }
+            Any::from(res)
+        }
+        other => other.to_json(txn),
+    }
+}
@@ -1,16 +1,20 @@
 use crate::block::{EmbedPrelim, Item, ItemContent, ItemPosition, ItemPtr, Prelim, Unused};
+use crate::encoding::read::Error;
 use crate::transaction::TransactionMut;
 use crate::types::{
     AsPrelim, Attrs, Branch, BranchPtr, DefaultPrelim, Delta, Out, Path, RootRef, SharedRef,
     TypePtr, TypeRef,
 };
+use crate::updates::decoder::{Decode, Decoder};
+use crate::updates::encoder::{Encode, Encoder};
 use crate::utils::OptionExt;
 use crate::*;
+use serde::{Deserialize, Serialize};
 use std::cell::UnsafeCell;
 use std::collections::HashMap;
 use std::convert::{TryFrom, TryInto};
 use std::fmt::Formatter;
-use std::ops::{Deref, DerefMut};
+use std::ops::{Deref, DerefMut, Range};
 
 /// A shared data type used for collaborative text editing. It enables multiple users to add and
 /// remove chunks of text in efficient manner. This type is internally represented as a mutable
@@ -117,7 +121,65 @@ impl GetString for TextRef {
     /// Converts context of this text data structure into a single string value. This method doesn't
     /// render formatting attributes or embedded content. In order to retrieve it, use
     /// [TextRef::diff] method.
-    fn get_string<T: ReadTxn>(&self, _txn: &T) -> String {
+    ///
+    /// If a transaction has a [ReadCache] enabled (see: [Transaction::with_read_cache]), the
+    /// result will be memoized for the lifetime of that transaction.
+    fn get_string<T: ReadTxn>(&self, txn: &T) -> String {
+        match txn.read_cache() {
+            Some(cache) => cache.get_or_init_string(self.0, || self.materialize_string()),
+            None => self.materialize_string(),
+        }
+    }
+}
+
+impl TextRef {
+    /// Converts context of this text data structure into a single string value, together with
+    /// a table of cumulative offsets measured using a given `kind`, regardless of the
+    /// [OffsetKind] configured for the document. Each entry of the table corresponds to a single
+    /// text chunk and contains an offset at which that chunk ends, so the last entry is equal to
+    /// the length of the whole string.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, OffsetKind, Text, Transact};
+    ///
+    /// let doc = Doc::new();
+    /// let text = doc.get_or_insert_text("text");
+    /// let mut txn = doc.transact_mut();
+    /// text.insert(&mut txn, 0, "zażółć");
+    ///
+    /// let (str, offsets) = text.get_string_with_offsets(&txn, OffsetKind::Bytes);
+    /// assert_eq!(str, "zażółć");
+    /// assert_eq!(offsets, vec![10]);
+    /// ```
+    pub fn get_string_with_offsets<T: ReadTxn>(
+        &self,
+        _txn: &T,
+        kind: OffsetKind,
+    ) -> (String, Vec<u32>) {
+        let mut start = self.0.start;
+        let mut s = String::new();
+        let mut offsets = Vec::new();
+        let mut offset = 0;
+        while let Some(item) = start.as_deref() {
+            if !item.is_deleted() {
+                if let ItemContent::String(item_string) = &item.content {
+                    let chunk = item_string.as_str();
+                    offset += match kind {
+                        OffsetKind::Bytes => chunk.len(),
+                        OffsetKind::Utf16 => chunk.encode_utf16().count(),
+                    } as u32;
+                    s.push_str(chunk);
+                    offsets.push(offset);
+                }
+            }
+            start = item.right;
+        }
+        (s, offsets)
+    }
+
+    fn materialize_string(&self) -> String {
         let mut start = self.0.start;
         let mut s = String::new();
         while let Some(item) = start.as_deref() {
@@ -155,12 +217,51 @@ impl TryFrom<Out> for TextRef {
     }
 }
 
+/// Sums up lengths of all non-deleted text chunks of a given `branch`, using `measure` function to
+/// compute length of a string content. Other countable blocks count as 1 per element.
+fn measure_text<F>(branch: &Branch, measure: F) -> u32
+where
+    F: Fn(&str) -> usize,
+{
+    let mut len = 0;
+    let mut current = branch.start;
+    while let Some(item) = current.as_deref() {
+        if !item.is_deleted() && item.is_countable() {
+            len += match &item.content {
+                ItemContent::String(chunk) => measure(chunk.as_str()) as u32,
+                _ => item.len(),
+            };
+        }
+        current = item.right;
+    }
+    len
+}
+
 pub trait Text: AsRef<Branch> + Sized {
     /// Returns a number of characters visible in a current text data structure.
     fn len<T: ReadTxn>(&self, _txn: &T) -> u32 {
         self.as_ref().content_len
     }
 
+    /// Returns a length of a text visible in a current data structure, measured in UTF-8 bytes
+    /// regardless of [OffsetKind] configured for the document. Embedded values count as 1.
+    fn byte_len<T: ReadTxn>(&self, _txn: &T) -> u32 {
+        measure_text(self.as_ref(), str::len)
+    }
+
+    /// Returns a length of a text visible in a current data structure, measured in UTF-16 code
+    /// units regardless of [OffsetKind] configured for the document. Embedded values count as 1.
+    fn utf16_len<T: ReadTxn>(&self, _txn: &T) -> u32 {
+        measure_text(self.as_ref(), |s| s.encode_utf16().count())
+    }
+
+    /// Returns a length of a text visible in a current data structure, measured in unicode
+    /// scalar values (Rust [char]s) regardless of [OffsetKind] configured for the document.
+    /// Embedded values count as 1.
+    fn char_len<T: ReadTxn>(&self, _txn: &T) -> u32 {
+        measure_text(self.as_ref(), |s| s.chars().count())
+    }
+
     /// Inserts a `chunk` of text at a given `index`.
     /// If `index` is `0`, this `chunk` will be inserted at the beginning of a current text.
     /// If `index` is equal to current data structure length, this `chunk` will be appended at
@@ -210,26 +311,112 @@ pub trait Text: AsRef<Branch> + Sized {
     /// ```
     ///
     fn insert(&self, txn: &mut TransactionMut, index: u32, chunk: &str) {
-        if chunk.is_empty() {
-            return;
+        insert_str(BranchPtr::from(self.as_ref()), txn, index, chunk);
+    }
+
+    /// Inserts a `chunk` of text at a given `index` and returns a [StickyIndex] anchored right
+    /// after the last inserted character.
+    ///
+    /// Concurrent inserts made at the same index by other peers may end up on either side of the
+    /// inserted `chunk` once they are merged, so the requested `index` is not a reliable way to
+    /// track the position of a local cursor. Returned anchor always resolves to the offset right
+    /// after the inserted text.
+    ///
+    /// If `chunk` is empty, returned anchor is associated with a character preceding `index`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use yrs::{Doc, GetString, Text, Transact};
+    ///
+    /// let doc = Doc::new();
+    /// let txt = doc.get_or_insert_text("text");
+    /// let mut txn = doc.transact_mut();
+    /// txt.insert(&mut txn, 0, "world");
+    /// let cursor = txt.insert_returning_anchor(&mut txn, 0, "hello");
+    /// txt.insert(&mut txn, 0, ">> ");
+    ///
+    /// let offset = cursor.get_offset(&txn).unwrap();
+    /// assert_eq!(offset.index, 8); // right after ">> hello"
+    /// ```
+    fn insert_returning_anchor(
+        &self,
+        txn: &mut TransactionMut,
+        index: u32,
+        chunk: &str,
+    ) -> StickyIndex {
+        let this = BranchPtr::from(self.as_ref());
+        match insert_str(this, txn, index, chunk) {
+            Some(item) => StickyIndex::from_id(item.last_id(), Assoc::Before),
+            None => StickyIndex::at(txn, this, index, Assoc::Before)
+                .expect("The type or the position doesn't exist!"),
         }
+    }
+
+    /// Inserts a `chunk` of text at the position pointed by a given sticky `anchor`. Anchor is
+    /// resolved and text is inserted within the same transaction, so no other update can shift
+    /// the position in between.
+    ///
+    /// If the element the `anchor` was attached to has been deleted, text will be inserted at the
+    /// nearest surviving position according to anchor's [Assoc].
+    ///
+    /// This method panics if `anchor` cannot be resolved or it points to a different collection
+    /// than the current one.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use yrs::{Assoc, Doc, GetString, IndexedSequence, Text, Transact};
+    ///
+    /// let doc = Doc::new();
+    /// let txt = doc.get_or_insert_text("text");
+    /// let mut txn = doc.transact_mut();
+    /// txt.insert(&mut txn, 0, "hello world");
+    /// let cursor = txt.sticky_index(&mut txn, 5, Assoc::After).unwrap();
+    ///
+    /// txt.insert(&mut txn, 0, ">> ");
+    /// txt.insert_at_sticky(&mut txn, &cursor, ",");
+    /// assert_eq!(txt.get_string(&txn), ">> hello, world");
+    /// ```
+    fn insert_at_sticky(&self, txn: &mut TransactionMut, anchor: &StickyIndex, chunk: &str) {
         let this = BranchPtr::from(self.as_ref());
-        if let Some(mut pos) = find_position(this, txn, index) {
-            let value = crate::block::PrelimString(chunk.into());
-            while let Some(right) = pos.right.as_ref() {
-                if right.is_deleted() {
-                    // skip over deleted blocks, just like Yjs does
-                    pos.forward();
-                } else {
-                    break;
-                }
-            }
-            txn.create_item(&pos, value, None);
-        } else {
-            panic!("The type or the position doesn't exist!");
+        match anchor.get_offset(txn) {
+            Some(offset) if offset.branch == this => self.insert(txn, offset.index, chunk),
+            _ => panic!("The type or the position doesn't exist!"),
         }
     }
 
+    /// Creates a [TextRange] anchored to the characters within `start..end` range. Anchored range
+    /// follows its characters when text is edited, also concurrently by other peers. Text inserted
+    /// right at the start or end boundary of the range is not included in it.
+    ///
+    /// Returns `None` if the range is empty or it doesn't fit within the current text.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use yrs::{Doc, Text, Transact};
+    ///
+    /// let doc = Doc::new();
+    /// let txt = doc.get_or_insert_text("text");
+    /// let mut txn = doc.transact_mut();
+    /// txt.insert(&mut txn, 0, "hello world");
+    /// let range = txt.create_range_anchor(&txn, 6, 11).unwrap(); // "world"
+    ///
+    /// txt.insert(&mut txn, 0, ">> ");
+    /// txt.insert(&mut txn, 14, "!");
+    /// assert_eq!(range.resolve(&txn), Some(9..14));
+    /// ```
+    fn create_range_anchor<T: ReadTxn>(&self, txn: &T, start: u32, end: u32) -> Option<TextRange> {
+        if start >= end || end > self.len(txn) {
+            return None;
+        }
+        let this = BranchPtr::from(self.as_ref());
+        let start = StickyIndex::at(txn, this, start, Assoc::After)?;
+        let end = StickyIndex::at(txn, this, end, Assoc::Before)?;
+        Some(TextRange { start, end })
+    }
+
     fn apply_delta<D, P>(&self, txn: &mut TransactionMut, delta: D)
     where
         D: IntoIterator<Item = Delta<P>>,
@@ -264,6 +451,40 @@ pub trait Text: AsRef<Branch> + Sized {
         }
     }
 
+    /// Applies a sequence of Operational Transform style `ops` to current text. Operations are
+    /// applied one after another from a cursor, which starts at the beginning of the text and
+    /// is moved forward by [OtOp::Retain] and [OtOp::Insert]. Lengths are measured using
+    /// [OffsetKind] configured for the current document. This works just like
+    /// [Text::apply_delta] without formatting attributes.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use yrs::{Doc, GetString, OtOp, Text, Transact};
+    ///
+    /// let doc = Doc::new();
+    /// let txt = doc.get_or_insert_text("text");
+    /// let mut txn = doc.transact_mut();
+    /// txt.insert(&mut txn, 0, "hello world");
+    /// txt.apply_ot_ops(
+    ///     &mut txn,
+    ///     &[
+    ///         OtOp::Retain(6),
+    ///         OtOp::Delete(5),
+    ///         OtOp::Insert("there".into()),
+    ///     ],
+    /// );
+    /// assert_eq!(txt.get_string(&txn), "hello there");
+    /// ```
+    fn apply_ot_ops(&self, txn: &mut TransactionMut, ops: &[OtOp]) {
+        let delta = ops.iter().map(|op| match op {
+            OtOp::Retain(len) => Delta::retain(*len as u32),
+            OtOp::Insert(chunk) => Delta::insert(chunk.as_str()),
+            OtOp::Delete(len) => Delta::delete(*len as u32),
+        });
+        self.apply_delta(txn, delta);
+    }
+
     /// Inserts a `chunk` of text at a given `index`.
     /// If `index` is `0`, this `chunk` will be inserted at the beginning of a current text.
     /// If `index` is equal to current data structure length, this `chunk` will be appended at
@@ -378,6 +599,30 @@ pub trait Text: AsRef<Branch> + Sized {
         }
     }
 
+    /// Removes formatting attributes with given `keys` from a range of text described by
+    /// `index`-`len` parameters. Other formatting attributes applied over that range are left
+    /// intact.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, Text, Transact};
+    /// use yrs::types::Attrs;
+    ///
+    /// let doc = Doc::new();
+    /// let text = doc.get_or_insert_text("text");
+    /// let mut txn = doc.transact_mut();
+    /// let attrs = Attrs::from([("bold".into(), true.into()), ("italic".into(), true.into())]);
+    /// text.insert_with_attributes(&mut txn, 0, "hello", attrs);
+    ///
+    /// // remove bold from "ell", keep it italic
+    /// text.remove_format(&mut txn, 1, 3, &["bold"]);
+    /// ```
+    fn remove_format(&self, txn: &mut TransactionMut, index: u32, len: u32, keys: &[&str]) {
+        let attributes: Attrs = keys.iter().map(|key| ((*key).into(), Any::Null)).collect();
+        self.format(txn, index, len, attributes)
+    }
+
     /// Returns an ordered sequence of formatted chunks, current [Text] corresponds of. These chunks
     /// may contain inserted pieces of text or more complex elements like embedded binaries of
     /// shared objects. Chunks are organized by type of inserted value and formatting attributes
@@ -427,6 +672,41 @@ pub trait Text: AsRef<Branch> + Sized {
         asm.finish()
     }
 
+    /// Returns the [Diff] representation of this YText type, just like [Text::diff] does, but
+    /// passes every embedded value through a given `resolver` first. Resolver decides if an embed
+    /// should be kept as is, replaced with another value or skipped from the produced delta.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Any, Doc, Out, Text, Transact};
+    /// use yrs::types::text::{Diff, EmbedOutput};
+    ///
+    /// let doc = Doc::new();
+    /// let text = doc.get_or_insert_text("article");
+    /// let mut txn = doc.transact_mut();
+    /// text.insert(&mut txn, 0, "hello ");
+    /// text.insert_embed(&mut txn, 6, Any::from("mention:1"));
+    ///
+    /// let chunks = text.diff_with(&txn, |embed| match embed {
+    ///     Any::String(id) if id.as_ref() == "mention:1" => EmbedOutput::Replace(Out::from("@Alice")),
+    ///     _ => EmbedOutput::Unchanged,
+    /// });
+    /// assert_eq!(chunks, vec![
+    ///     Diff::new("hello ".into(), None),
+    ///     Diff::new("@Alice".into(), None),
+    /// ]);
+    /// ```
+    fn diff_with<T, F>(&self, _txn: &T, resolver: F) -> Vec<Diff<YChange>>
+    where
+        T: ReadTxn,
+        F: Fn(&Any) -> EmbedOutput,
+    {
+        let mut asm = DiffAssembler::new(YChange::identity).with_embed_resolver(&resolver);
+        asm.process(self.as_ref().start, None, None, None, None);
+        asm.finish()
+    }
+
     /// Returns the Delta representation of this YText type.
     fn diff_range<D, F>(
         &self,
@@ -449,6 +729,113 @@ pub trait Text: AsRef<Branch> + Sized {
         asm.process(self.as_ref().start, hi, lo, None, None);
         asm.finish()
     }
+
+    /// Returns a string content of this text as it existed at the time of a given `snapshot`.
+    /// Just like [GetString::get_string], formatting attributes and embedded content are skipped.
+    ///
+    /// This method requires the document to be created with [Options::skip_gc] enabled, otherwise
+    /// the contents of deleted blocks could have already been garbage collected.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, GetString, Options, ReadTxn, Text, Transact};
+    ///
+    /// let doc = Doc::with_options(Options {
+    ///     skip_gc: true,
+    ///     ..Options::default()
+    /// });
+    /// let text = doc.get_or_insert_text("text");
+    /// let mut txn = doc.transact_mut();
+    /// text.insert(&mut txn, 0, "hello world");
+    /// let snapshot = txn.snapshot();
+    ///
+    /// text.remove_range(&mut txn, 5, 6);
+    /// assert_eq!(text.get_string(&txn), "hello");
+    /// assert_eq!(text.get_string_at(&mut txn, &snapshot), "hello world");
+    /// ```
+    fn get_string_at(&self, txn: &mut TransactionMut, snapshot: &Snapshot) -> String {
+        txn.split_by_snapshot(snapshot);
+        string_at(self.as_ref().start, snapshot)
+    }
+}
+
+/// Materializes a string from a block list starting at `start`, as it existed at the time of
+/// a given `snapshot`. Expects blocks to be split along snapshot boundaries.
+pub(crate) fn string_at(mut start: Option<ItemPtr>, snapshot: &Snapshot) -> String {
+    let mut s = String::new();
+    while let Some(item) = start.as_deref() {
+        if snapshot.is_visible(&item.id) {
+            if let ItemContent::String(item_string) = &item.content {
+                s.push_str(item_string);
+            }
+        }
+        start = item.right;
+    }
+    s
+}
+
+/// A range of text anchored to the characters it was created with (see:
+/// [Text::create_range_anchor]). It's based on a pair of [StickyIndex]es, so it remains valid when
+/// text is being edited concurrently, which makes it useful eg. for attaching comments to a text.
+/// It can be serialized and shared with other peers.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TextRange {
+    start: StickyIndex,
+    end: StickyIndex,
+}
+
+impl TextRange {
+    /// Returns a [StickyIndex] pointing to the first character of the range.
+    pub fn start(&self) -> &StickyIndex {
+        &self.start
+    }
+
+    /// Returns a [StickyIndex] pointing right after the last character of the range.
+    pub fn end(&self) -> &StickyIndex {
+        &self.end
+    }
+
+    /// Resolves current offsets of the range within its text. Returns `None` if all characters of
+    /// the range have been deleted or the text itself doesn't exist.
+    pub fn resolve<T: ReadTxn>(&self, txn: &T) -> Option<Range<u32>> {
+        let start = self.start.get_offset(txn)?;
+        let end = self.end.get_offset(txn)?;
+        if start.branch != end.branch || start.index >= end.index {
+            None
+        } else {
+            Some(start.index..end.index)
+        }
+    }
+}
+
+/// A single operation of Operational Transform style text edit, which can be applied using
+/// [Text::apply_ot_ops]. When serialized, it's represented as `{"retain": 1}`, `{"insert": "a"}`
+/// or `{"delete": 1}` object.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OtOp {
+    /// Moves the cursor forward by a given number of characters, leaving them unchanged.
+    Retain(usize),
+    /// Inserts a string at the cursor position and moves the cursor after it.
+    Insert(String),
+    /// Deletes a given number of characters following the cursor.
+    Delete(usize),
+}
+
+impl Encode for TextRange {
+    fn encode<E: Encoder>(&self, encoder: &mut E) {
+        self.start.encode(encoder);
+        self.end.encode(encoder);
+    }
+}
+
+impl Decode for TextRange {
+    fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, Error> {
+        let start = StickyIndex::decode(decoder)?;
+        let end = StickyIndex::decode(decoder)?;
+        Ok(TextRange { start, end })
+    }
 }
 
 impl From<BranchPtr> for TextRef {
@@ -516,7 +903,7 @@ where
     }
 }
 
-struct DiffAssembler<D, F>
+struct DiffAssembler<'a, D, F>
 where
     F: Fn(YChange) -> D,
 {
@@ -525,9 +912,10 @@ where
     curr_attrs: Attrs,
     curr_ychange: Option<YChange>,
     compute_ychange: F,
+    resolve_embed: Option<&'a dyn Fn(&Any) -> EmbedOutput>,
 }
 
-impl<T, F> DiffAssembler<T, F>
+impl<'a, T, F> DiffAssembler<'a, T, F>
 where
     F: Fn(YChange) -> T,
 {
@@ -538,8 +926,14 @@ where
             curr_attrs: HashMap::new(),
             curr_ychange: None,
             compute_ychange,
+            resolve_embed: None,
         }
     }
+
+    fn with_embed_resolver(mut self, resolve_embed: &'a dyn Fn(&Any) -> EmbedOutput) -> Self {
+        self.resolve_embed = Some(resolve_embed);
+        self
+    }
     fn pack_str(&mut self) {
         if !self.buf.is_empty() {
             let attrs = self.attrs_boxed();
@@ -659,6 +1053,19 @@ where
                             }
                         }
                     }
+                    ItemContent::Embed(embed) if self.resolve_embed.is_some() => {
+                        let resolve_embed = self.resolve_embed.unwrap();
+                        let value = match resolve_embed(embed) {
+                            EmbedOutput::Unchanged => Some(Out::Any(embed.clone())),
+                            EmbedOutput::Replace(value) => Some(value),
+                            EmbedOutput::Skip => None,
+                        };
+                        if let Some(value) = value {
+                            self.pack_str();
+                            let attrs = self.attrs_boxed();
+                            self.ops.push(Diff::new(value, attrs));
+                        }
+                    }
                     ItemContent::Type(_) | ItemContent::Embed(_) => {
                         self.pack_str();
                         if let Some(value) = item.content.get_first() {
@@ -731,6 +1138,31 @@ pub(crate) fn update_current_attributes(attrs: &mut Attrs, key: &str, value: &An
     }
 }
 
+fn insert_str(
+    this: BranchPtr,
+    txn: &mut TransactionMut,
+    index: u32,
+    chunk: &str,
+) -> Option<ItemPtr> {
+    if chunk.is_empty() {
+        return None;
+    }
+    if let Some(mut pos) = find_position(this, txn, index) {
+        let value = crate::block::PrelimString(chunk.into());
+        while let Some(right) = pos.right.as_ref() {
+            if right.is_deleted() {
+                // skip over deleted blocks, just like Yjs does
+                pos.forward();
+            } else {
+                break;
+            }
+        }
+        txn.create_item(&pos, value, None)
+    } else {
+        panic!("The type or the position doesn't exist!");
+    }
+}
+
 fn find_position(this: BranchPtr, txn: &mut TransactionMut, index: u32) -> Option<ItemPosition> {
     let mut pos = {
         ItemPosition {
@@ -1126,6 +1558,17 @@ impl<T> Diff<T> {
     }
 }
 
+/// Result of resolving an embedded value by the callback passed to [Text::diff_with].
+#[derive(Debug, Clone, PartialEq)]
+pub enum EmbedOutput {
+    /// Embedded value is included in a produced delta as is.
+    Unchanged,
+    /// Embedded value is replaced with a given value in a produced delta.
+    Replace(Out),
+    /// Embedded value is not included in a produced delta.
+    Skip,
+}
+
 impl<T> From<Diff<T>> for Delta {
     #[inline]
     fn from(value: Diff<T>) -> Self {
@@ -1506,21 +1949,131 @@ mod test {
     use crate::doc::{OffsetKind, Options};
     use crate::test_utils::{exchange_updates, run_scenario, RngExt};
     use crate::transaction::ReadTxn;
-    use crate::types::text::{Attrs, ChangeKind, Delta, Diff, YChange};
+    use crate::types::text::{Attrs, ChangeKind, Delta, Diff, EmbedOutput, YChange};
     use crate::types::Out;
     use crate::updates::decoder::Decode;
     use crate::updates::encoder::{Encode, Encoder, EncoderV1};
     use crate::{
-        any, Any, ArrayPrelim, Doc, GetString, Map, MapPrelim, MapRef, Observable, StateVector,
-        Text, Transact, Update, WriteTxn, ID,
+        any, Any, ArrayPrelim, Assoc, Doc, GetString, IndexedSequence, Map, MapPrelim, MapRef,
+        Observable, OtOp, StateVector, Text, TextRange, Transact, Update, WriteTxn, ID,
     };
     use arc_swap::ArcSwapOption;
     use fastrand::Rng;
     use std::collections::HashMap;
     use std::sync::atomic::{AtomicBool, Ordering};
-    use std::sync::Arc;
+    use std::sync::{Arc, Mutex};
     use std::time::Duration;
 
+    #[test]
+    fn text_range_anchor() {
+        let d1 = Doc::with_client_id(1);
+        let txt1 = d1.get_or_insert_text("text");
+        txt1.insert(&mut d1.transact_mut(), 0, "hello world");
+        let range = txt1.create_range_anchor(&d1.transact(), 6, 11).unwrap(); // "world"
+        assert_eq!(range.resolve(&d1.transact()), Some(6..11));
+
+        // empty and out of bounds ranges cannot be anchored
+        assert!(txt1.create_range_anchor(&d1.transact(), 3, 3).is_none());
+        assert!(txt1.create_range_anchor(&d1.transact(), 6, 12).is_none());
+
+        // edits before the range
+        txt1.insert(&mut d1.transact_mut(), 0, ">> ");
+        txt1.remove_range(&mut d1.transact_mut(), 3, 2);
+        assert_eq!(range.resolve(&d1.transact()), Some(7..12));
+
+        // edits at the boundaries are not included
+        txt1.insert(&mut d1.transact_mut(), 7, "[");
+        txt1.insert(&mut d1.transact_mut(), 13, "]");
+        assert_eq!(txt1.get_string(&d1.transact()), ">> llo [world]");
+        assert_eq!(range.resolve(&d1.transact()), Some(8..13));
+
+        // edits inside of the range, including removal of its boundary characters
+        txt1.insert(&mut d1.transact_mut(), 10, "--");
+        txt1.remove_range(&mut d1.transact_mut(), 8, 1);
+        txt1.remove_range(&mut d1.transact_mut(), 13, 1);
+        let r = range.resolve(&d1.transact()).unwrap();
+        let s = txt1.get_string(&d1.transact());
+        assert_eq!(&s[r.start as usize..r.end as usize], "o--rl");
+
+        // range is shared with a remote peer
+        let d2 = Doc::with_client_id(2);
+        let txt2 = d2.get_or_insert_text("text");
+        exchange_updates(&[&d1, &d2]);
+        let range2 = TextRange::decode_v1(&range.encode_v1()).unwrap();
+        assert_eq!(range2.resolve(&d2.transact()), Some(r.clone()));
+        txt2.insert(&mut d2.transact_mut(), 0, "!");
+        assert_eq!(range2.resolve(&d2.transact()), Some(r.start + 1..r.end + 1));
+
+        // edit spanning over the whole range
+        txt1.remove_range(&mut d1.transact_mut(), r.start - 1, r.end - r.start + 2);
+        assert_eq!(txt1.get_string(&d1.transact()), ">> llo ");
+        assert_eq!(range.resolve(&d1.transact()), None);
+        exchange_updates(&[&d1, &d2]);
+        assert_eq!(range2.resolve(&d2.transact()), None);
+    }
+
+    #[test]
+    fn insert_at_sticky() {
+        let d1 = Doc::with_client_id(1);
+        let txt1 = d1.get_or_insert_text("text");
+        let d2 = Doc::with_client_id(2);
+        let txt2 = d2.get_or_insert_text("text");
+
+        let cursor = {
+            let mut txn = d1.transact_mut();
+            txt1.insert(&mut txn, 0, "hello world");
+            txt1.sticky_index(&mut txn, 5, Assoc::After).unwrap()
+        };
+        exchange_updates(&[&d1, &d2]);
+
+        // concurrent remote edit shifts the cursor position
+        txt2.insert(&mut d2.transact_mut(), 0, "oh, ");
+        exchange_updates(&[&d1, &d2]);
+
+        txt1.insert_at_sticky(&mut d1.transact_mut(), &cursor, "!");
+        assert_eq!(txt1.get_string(&d1.transact()), "oh, hello! world");
+
+        // anchored character got deleted: fallback to the nearest surviving position
+        txt1.remove_range(&mut d1.transact_mut(), 10, 3);
+        assert_eq!(txt1.get_string(&d1.transact()), "oh, hello!rld");
+        txt1.insert_at_sticky(&mut d1.transact_mut(), &cursor, "_");
+        assert_eq!(txt1.get_string(&d1.transact()), "oh, hello!_rld");
+
+        exchange_updates(&[&d1, &d2]);
+        assert_eq!(txt2.get_string(&d2.transact()), "oh, hello!_rld");
+    }
+
+    #[test]
+    fn insert_returning_anchor() {
+        let d1 = Doc::with_client_id(2);
+        let txt1 = d1.get_or_insert_text("text");
+        let d2 = Doc::with_client_id(1);
+        let txt2 = d2.get_or_insert_text("text");
+
+        txt1.insert(&mut d1.transact_mut(), 0, "abcd");
+        exchange_updates(&[&d1, &d2]);
+
+        // both peers insert at the same offset concurrently
+        let anchor = txt1.insert_returning_anchor(&mut d1.transact_mut(), 2, "XY");
+        txt2.insert(&mut d2.transact_mut(), 2, "123");
+        txt2.insert(&mut d2.transact_mut(), 0, ">> ");
+        exchange_updates(&[&d1, &d2]);
+
+        let txn = d1.transact();
+        let merged = txt1.get_string(&txn);
+        assert_eq!(merged, txt2.get_string(&d2.transact()));
+        let expected = merged.find("XY").unwrap() as u32 + 2;
+        let offset = anchor.get_offset(&txn).unwrap();
+        assert_eq!(offset.index, expected);
+
+        // empty chunk is anchored to a preceding character
+        drop(txn);
+        let mut txn = d1.transact_mut();
+        let anchor = txt1.insert_returning_anchor(&mut txn, 3, "");
+        txt1.insert(&mut txn, 3, "_");
+        assert_eq!(anchor.get_offset(&txn).unwrap().index, 3);
+    }
+
     #[test]
     fn insert_empty_string() {
         let doc = Doc::new();
@@ -1537,6 +2090,24 @@ mod test {
         assert_eq!(txt.get_string(&txn).as_str(), "abc");
     }
 
+    #[test]
+    fn get_string_read_cache() {
+        let doc = Doc::new();
+        let txt = doc.get_or_insert_text("test");
+        txt.push(&mut doc.transact_mut(), "hello");
+
+        {
+            let txn = doc.transact().with_read_cache();
+            assert_eq!(txt.get_string(&txn), "hello");
+            assert_eq!(txt.get_string(&txn), "hello");
+        }
+
+        // cache must not outlive the transaction
+        txt.push(&mut doc.transact_mut(), " world");
+        let txn = doc.transact().with_read_cache();
+        assert_eq!(txt.get_string(&txn), "hello world");
+    }
+
     #[test]
     fn append_single_character_blocks() {
         let doc = Doc::new();
@@ -1906,6 +2477,24 @@ mod test {
         assert_eq!(delta.swap(None), None);
     }
 
+    #[test]
+    fn observe_once() {
+        let doc = Doc::with_client_id(1);
+        let txt = doc.get_or_insert_text("text");
+        let deltas = Arc::new(Mutex::new(Vec::new()));
+        let deltas_c = deltas.clone();
+        let _sub = txt.observe_once(move |txn, e| {
+            deltas_c.lock().unwrap().push(e.delta(txn).to_vec());
+        });
+
+        txt.insert(&mut doc.transact_mut(), 0, "abc");
+        txt.insert(&mut doc.transact_mut(), 3, "def");
+        assert_eq!(
+            *deltas.lock().unwrap(),
+            vec![vec![Delta::Inserted("abc".into(), None)]]
+        );
+    }
+
     #[test]
     fn insert_and_remove_event_changes() {
         let d1 = Doc::with_client_id(1);
@@ -2001,6 +2590,34 @@ mod test {
         fuzzy(3)
     }
 
+    #[test]
+    fn remove_format() {
+        let d1 = Doc::with_client_id(1);
+        let txt1 = d1.get_or_insert_text("text");
+        let d2 = Doc::with_client_id(2);
+        let txt2 = d2.get_or_insert_text("text");
+
+        let bold: Attrs = HashMap::from([("bold".into(), Any::Bool(true))]);
+        let italic: Attrs = HashMap::from([("italic".into(), Any::Bool(true))]);
+        {
+            let mut txn = d1.transact_mut();
+            txt1.insert(&mut txn, 0, "abcdef");
+            txt1.format(&mut txn, 0, 4, bold.clone());
+            txt1.format(&mut txn, 2, 4, italic.clone());
+        }
+        txt1.remove_format(&mut d1.transact_mut(), 1, 4, &["bold"]);
+
+        let expected = vec![
+            Diff::new("a".into(), Some(Box::new(bold.clone()))),
+            Diff::new("b".into(), None),
+            Diff::new("cdef".into(), Some(Box::new(italic.clone()))),
+        ];
+        assert_eq!(txt1.diff(&d1.transact(), YChange::identity), expected);
+
+        exchange_updates(&[&d1, &d2]);
+        assert_eq!(txt2.diff(&d2.transact(), YChange::identity), expected);
+    }
+
     #[test]
     fn basic_format() {
         let d1 = Doc::with_client_id(1);
@@ -2527,6 +3144,71 @@ mod test {
         assert_eq!(txt.get_string(&txn).as_str(), "abc");
     }
 
+    #[test]
+    fn explicit_length_units() {
+        for offset_kind in [OffsetKind::Bytes, OffsetKind::Utf16] {
+            let doc = Doc::with_options(Options {
+                offset_kind,
+                ..Options::with_client_id(1)
+            });
+            let text = doc.get_or_insert_text("text");
+            {
+                let mut txn = doc.transact_mut();
+                text.push(&mut txn, "a😀b");
+                text.push(&mut txn, "éx");
+                text.insert_embed(&mut txn, 0, Any::Bool(true));
+            }
+            // remove "x" so that tombstones are present
+            let len = text.len(&doc.transact());
+            text.remove_range(&mut doc.transact_mut(), len - 1, 1);
+
+            let txn = doc.transact();
+            assert_eq!(text.byte_len(&txn), 1 + 1 + 4 + 1 + 2);
+            assert_eq!(text.utf16_len(&txn), 1 + 1 + 2 + 1 + 1);
+            assert_eq!(text.char_len(&txn), 1 + 1 + 1 + 1 + 1);
+        }
+    }
+
+    #[test]
+    fn get_string_with_offsets() {
+        let doc = Doc::with_client_id(1);
+        let text = doc.get_or_insert_text("text");
+        text.insert(&mut doc.transact_mut(), 0, "a😀b");
+        text.insert(&mut doc.transact_mut(), 0, "é");
+        let txn = doc.transact();
+
+        let (bytes_str, bytes) = text.get_string_with_offsets(&txn, OffsetKind::Bytes);
+        let (utf16_str, utf16) = text.get_string_with_offsets(&txn, OffsetKind::Utf16);
+        assert_eq!(bytes_str, "éa😀b");
+        assert_eq!(utf16_str, "éa😀b");
+        assert_eq!(bytes, vec![2, 8]);
+        assert_eq!(utf16, vec![1, 5]);
+    }
+
+    #[test]
+    fn get_string_at() {
+        let mut options = Options::with_client_id(1);
+        options.skip_gc = true;
+        let doc = Doc::with_options(options);
+        let text = doc.get_or_insert_text("text");
+
+        text.insert(&mut doc.transact_mut(), 0, "hello world");
+        let snapshot = doc.transact_mut().snapshot();
+
+        {
+            let mut txn = doc.transact_mut();
+            text.remove_range(&mut txn, 0, 6);
+            text.insert(&mut txn, 5, "!");
+        }
+        text.insert(&mut doc.transact_mut(), 0, "big ");
+
+        let mut txn = doc.transact_mut();
+        assert_eq!(text.get_string(&txn), "big world!");
+        assert_eq!(text.get_string_at(&mut txn, &snapshot), "hello world");
+        // reading at a snapshot doesn't change the current state
+        assert_eq!(text.get_string(&txn), "big world!");
+    }
+
     #[test]
     fn snapshots() {
         let doc = Doc::with_client_id(1);
@@ -2834,4 +3516,69 @@ mod test {
         txn.apply_update(Update::decode_v1(bin.as_slice()).unwrap());
         assert_eq!(txt.get_string(&txn), "ab");
     }
+
+    #[test]
+    fn apply_ot_ops() {
+        let doc = Doc::with_client_id(1);
+        let txt = doc.get_or_insert_text("text");
+        let mut txn = doc.transact_mut();
+        txt.insert(&mut txn, 0, "The quick brown fox");
+
+        let ops: Vec<OtOp> = serde_json::from_str(
+            r#"[{"retain":4},{"delete":5},{"insert":"slow"},{"retain":6},{"insert":" cat"},{"delete":4}]"#,
+        )
+        .unwrap();
+        assert_eq!(ops[0], OtOp::Retain(4));
+        assert_eq!(ops[2], OtOp::Insert("slow".into()));
+
+        txt.apply_ot_ops(&mut txn, &ops);
+        assert_eq!(txt.get_string(&txn), "The slow brown cat");
+
+        // trailing text, which is not retained explicitly, stays untouched
+        txt.apply_ot_ops(&mut txn, &[OtOp::Insert(">> ".into()), OtOp::Delete(4)]);
+        assert_eq!(txt.get_string(&txn), ">> slow brown cat");
+    }
+
+    #[test]
+    fn diff_with_embed_resolver() {
+        let doc = Doc::with_client_id(1);
+        let txt = doc.get_or_insert_text("text");
+        let mut txn = doc.transact_mut();
+        let bold = Attrs::from([("b".into(), true.into())]);
+        txt.insert(&mut txn, 0, "see ");
+        txt.insert_embed(&mut txn, 4, any!({"mention": "bob"}));
+        txt.insert(&mut txn, 5, " here");
+        txt.insert_embed_with_attributes(
+            &mut txn,
+            10,
+            any!({"image": "http://old.host/cat.png"}),
+            bold.clone(),
+        );
+
+        let diff = txt.diff_with(&txn, |embed| match embed {
+            Any::Map(map) => match map.get("image") {
+                Some(Any::String(url)) => {
+                    let url = url.replace("http://old.host/", "https://cdn.host/");
+                    EmbedOutput::Replace(Out::Any(any!({ "image": url })))
+                }
+                _ => EmbedOutput::Skip,
+            },
+            _ => EmbedOutput::Unchanged,
+        });
+        assert_eq!(
+            diff,
+            vec![
+                // skipped embed doesn't split surrounding text
+                Diff::new("see  here".into(), None),
+                Diff::new(
+                    Out::Any(any!({"image": "https://cdn.host/cat.png"})),
+                    Some(Box::new(bold))
+                ),
+            ]
+        );
+
+        // keeping all embeds unchanged produces the same delta as a regular diff
+        let diff = txt.diff_with(&txn, |_| EmbedOutput::Unchanged);
+        assert_eq!(diff, txt.diff(&txn, YChange::identity));
+    }
 }
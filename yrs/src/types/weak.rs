@@ -571,7 +571,7 @@ impl LinkSource {
             if let Some(mut last) = Some(curr).to_iter().last() {
                 self.first_item.swap(last);
                 last.info.set_linked();
-                let linked_by = txn.store.linked_by.entry(last).or_default();
+                let linked_by = txn.linked_by_mut().entry(last).or_default();
                 linked_by.insert(inner_ref);
             }
         } else {
@@ -590,7 +590,7 @@ impl LinkSource {
                     first = false;
                 }
                 item.info.set_linked();
-                let linked_by = txn.store.linked_by.entry(item).or_default();
+                let linked_by = txn.linked_by_mut().entry(item).or_default();
                 linked_by.insert(inner_ref);
             }
         }
@@ -619,11 +619,12 @@ impl LinkSource {
         result
     }
 
-    pub fn to_xml_string<T: ReadTxn>(&self, _txn: &T) -> String {
+    pub fn to_xml_string<T: ReadTxn>(&self, txn: &T) -> String {
         let curr = self.first_item.get_owned();
         if let Some(item) = curr.as_deref() {
             if let Some(branch) = item.parent.as_branch() {
                 return XmlTextRef::get_string_fragment(
+                    txn,
                     branch.start,
                     Some(&self.quote_start),
                     Some(&self.quote_end),
@@ -800,7 +801,7 @@ pub(crate) fn join_linked_range(mut block: ItemPtr, txn: &mut TransactionMut) {
     // we checked if left and right exists before this method call
     let left = item.left.unwrap();
     let right = item.right.unwrap();
-    let all_links = &mut txn.store.linked_by;
+    let all_links = txn.linked_by_mut();
     let left_links = all_links.get(&left);
     let right_links = all_links.get(&right);
     let mut common = HashSet::new();
@@ -875,10 +876,11 @@ mod test {
     use crate::types::text::YChange;
     use crate::types::weak::{WeakPrelim, WeakRef};
     use crate::types::{Attrs, EntryChange, Event, Out, ToJson};
+    use crate::updates::decoder::Decode;
     use crate::Assoc::{After, Before};
     use crate::{
-        Array, ArrayRef, DeepObservable, Doc, GetString, Map, MapPrelim, MapRef, Observable,
-        Quotable, Text, TextRef, Transact, XmlTextRef,
+        Array, ArrayPrelim, ArrayRef, DeepObservable, Doc, GetString, Map, MapPrelim, MapRef,
+        Observable, Quotable, ReadTxn, StateVector, Text, TextRef, Transact, Update, XmlTextRef,
     };
 
     #[test]
@@ -904,6 +906,45 @@ mod test {
         assert_eq!(actual, expected);
     }
 
+    #[test]
+    fn shared_type_link_roundtrip() {
+        let d1 = Doc::with_client_id(1);
+        let m1 = d1.get_or_insert_map("map");
+        let nested = {
+            let mut txn = d1.transact_mut();
+            let nested = m1.insert(&mut txn, "nested", ArrayPrelim::default());
+            nested.insert_range(&mut txn, 0, [1, 2]);
+            let link = m1.link(&txn, "nested").unwrap();
+            m1.insert(&mut txn, "link", link);
+            nested
+        };
+
+        // decode the document from scratch
+        let d2 = Doc::with_client_id(2);
+        let m2 = d2.get_or_insert_map("map");
+        let update = d1
+            .transact()
+            .encode_state_as_update_v1(&StateVector::default());
+        d2.transact_mut()
+            .apply_update(Update::decode_v1(&update).unwrap());
+
+        // edit the linked shared type after the link has been created
+        nested.push_back(&mut d1.transact_mut(), 3);
+        nested.remove(&mut d1.transact_mut(), 0);
+        exchange_updates(&[&d1, &d2]);
+
+        for (doc, map) in [(&d1, &m1), (&d2, &m2)] {
+            let txn = doc.transact();
+            let link = map
+                .get(&txn, "link")
+                .unwrap()
+                .cast::<WeakRef<MapRef>>()
+                .unwrap();
+            let target: ArrayRef = link.try_deref(&txn).unwrap();
+            assert_eq!(target.to_json(&txn), vec![2, 3].into());
+        }
+    }
+
     #[test]
     fn basic_array_link() {
         let d1 = Doc::with_client_id(1);
@@ -469,8 +469,9 @@ impl From<XmlElementPrelim> for In {
 /// using [XmlTextRef].
 ///
 /// Keep in mind that [XmlTextRef::get_string] method returns a raw string, while rendering
-/// formatting attrbitues as XML tags in-text. However it doesn't include embedded elements.
-/// If there's a need to include them, use [XmlTextRef::diff] method instead.
+/// formatting attrbitues as XML tags in-text. Inline XML nodes embedded with [Text::insert_embed]
+/// (eg. [XmlElementPrelim]) are rendered in place, however other embedded elements are not
+/// included. If there's a need to include them, use [XmlTextRef::diff] method instead.
 ///
 /// Another note worth reminding is that human-readable numeric indexes are not good for maintaining
 /// cursor positions in rich text documents with real-time collaborative capabilities. In such cases
@@ -483,7 +484,7 @@ impl From<XmlElementPrelim> for In {
 /// # Example
 ///
 /// ```rust
-/// use yrs::{Any, Array, ArrayPrelim, Doc, GetString, Text, Transact, WriteTxn, XmlFragment, XmlTextPrelim};
+/// use yrs::{Any, Array, ArrayPrelim, Doc, GetString, Text, Transact, WriteTxn, XmlElementPrelim, XmlFragment, XmlTextPrelim};
 /// use yrs::types::Attrs;
 ///
 /// let doc = Doc::new();
@@ -514,13 +515,20 @@ impl From<XmlElementPrelim> for In {
 /// let table = text.insert_embed(&mut txn, 5, ArrayPrelim::default());
 /// let header = table.insert(&mut txn, 0, ArrayPrelim::from(["Book title", "Author"]));
 /// let row = table.insert(&mut txn, 1, ArrayPrelim::from(["\"Moby-Dick\"", "Herman Melville"]));
+///
+/// // insert inline xml element
+/// let text = f.insert(&mut txn, 1, XmlTextPrelim::new("hello world"));
+/// let link = text.insert_embed(&mut txn, 6, XmlElementPrelim::empty("a"));
+/// link.push_back(&mut txn, XmlTextPrelim::new("dear "));
+/// assert_eq!(text.get_string(&txn), "hello <a>dear </a>world");
 /// ```
 #[repr(transparent)]
 #[derive(Debug, Clone)]
 pub struct XmlTextRef(BranchPtr);
 
 impl XmlTextRef {
-    pub(crate) fn get_string_fragment(
+    pub(crate) fn get_string_fragment<T: ReadTxn>(
+        txn: &T,
         head: Option<ItemPtr>,
         start: Option<&StickyIndex>,
         end: Option<&StickyIndex>,
@@ -546,9 +554,13 @@ impl XmlTextRef {
                 buf.push('>');
             }
 
-            // write string content of delta
-            if let Out::Any(any) = d.insert {
-                write!(buf, "{}", any).unwrap();
+            // write string content of delta, including inline embedded xml nodes
+            match d.insert {
+                Out::Any(any) => write!(buf, "{}", any).unwrap(),
+                node @ Out::YXmlElement(_)
+                | node @ Out::YXmlText(_)
+                | node @ Out::YXmlFragment(_) => buf.push_str(&node.to_string(txn)),
+                _ => {}
             }
 
             // write attributes as xml closing tags
@@ -581,8 +593,8 @@ impl Observable for XmlTextRef {
 }
 
 impl GetString for XmlTextRef {
-    fn get_string<T: ReadTxn>(&self, _txn: &T) -> String {
-        XmlTextRef::get_string_fragment(self.0.start, None, None)
+    fn get_string<T: ReadTxn>(&self, txn: &T) -> String {
+        XmlTextRef::get_string_fragment(txn, self.0.start, None, None)
     }
 }
 
@@ -1164,6 +1176,381 @@ pub trait XmlFragment: AsRef<Branch> {
     fn successors<'a, T: ReadTxn>(&'a self, txn: &'a T) -> TreeWalker<'a, &'a T, T> {
         TreeWalker::new(self.as_ref(), txn)
     }
+
+    /// Merges every run of adjacent [XmlTextRef] children of a current XML node into a single
+    /// text node, similar to DOM `Node.normalize()`. Nested XML elements and fragments are
+    /// normalized recursively.
+    ///
+    /// Contents of each run - including their formatting attributes and embeds - are appended to
+    /// the first text node of that run, while the remaining nodes are removed. XML attributes of
+    /// removed nodes are copied over, unless the first node already defines them.
+    ///
+    /// Keep in mind that only the first text node of each run preserves its identity: all other
+    /// merged [XmlTextRef]s are deleted and their contents are re-inserted as new blocks.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, GetString, Transact, XmlFragment, XmlTextPrelim};
+    ///
+    /// let doc = Doc::new();
+    /// let xml = doc.get_or_insert_xml_fragment("xml");
+    /// let mut txn = doc.transact_mut();
+    /// xml.push_back(&mut txn, XmlTextPrelim::new("hello"));
+    /// xml.push_back(&mut txn, XmlTextPrelim::new(" world"));
+    ///
+    /// xml.normalize(&mut txn);
+    /// assert_eq!(xml.len(&txn), 1);
+    /// assert_eq!(xml.get_string(&txn), "hello world");
+    /// ```
+    fn normalize(&self, txn: &mut TransactionMut) {
+        let children: Vec<XmlOut> = self.children(txn).collect();
+        let mut index = 0;
+        let mut head: Option<XmlTextRef> = None;
+        for child in children {
+            match child {
+                XmlOut::Text(text) => {
+                    if let Some(head) = head.as_ref() {
+                        let mut delta = vec![Delta::Retain(head.len(txn), None)];
+                        for diff in text.diff(txn, YChange::identity) {
+                            let value = diff.insert.as_prelim(txn);
+                            delta.push(Delta::Inserted(value, diff.attributes));
+                        }
+                        let attrs: Vec<(Arc<str>, String)> = text
+                            .attributes(txn)
+                            .filter(|(key, _)| head.get_attribute(txn, key).is_none())
+                            .map(|(key, value)| (Arc::from(key), value))
+                            .collect();
+                        head.apply_delta(txn, delta);
+                        for (key, value) in attrs {
+                            head.insert_attribute(txn, key, value);
+                        }
+                        self.remove(txn, index);
+                        continue;
+                    } else {
+                        head = Some(text);
+                    }
+                }
+                XmlOut::Element(elem) => {
+                    elem.normalize(txn);
+                    head = None;
+                }
+                XmlOut::Fragment(fragment) => {
+                    fragment.normalize(txn);
+                    head = None;
+                }
+            }
+            index += 1;
+        }
+    }
+
+    /// Parses a given `html` string and inserts the resulting XML nodes at a given `index`.
+    /// Elements are converted into [XmlElementRef]s (together with their attributes) and text
+    /// content into [XmlTextRef]s.
+    ///
+    /// The parser is intentionally minimal and accepts only a well-formed HTML/XML subset:
+    ///
+    /// - Every element must be closed (`<p></p>` or `<p/>`), except for HTML void elements
+    ///   like `<br>` or `<img>`. Closing tags must match their opening tags.
+    /// - Attribute values can be quoted with either `"` or `'`. Attributes without values are
+    ///   stored with an empty string value.
+    /// - Named entities `&amp;`, `&lt;`, `&gt;`, `&quot;`, `&apos;` and `&nbsp;`, as well as
+    ///   numeric character references (`&#38;` or `&#x26;`) are decoded both in text content and
+    ///   in attribute values. Any other named entity (eg. `&copy;`) is rejected with
+    ///   [ParseError::UnknownEntity].
+    /// - Elements can be nested up to [MAX_HTML_DEPTH] levels deep, deeper structures are rejected
+    ///   with [ParseError::TooDeep].
+    /// - Comments (`<!-- ... -->`) are skipped. Other markup declarations, processing instructions
+    ///   or CDATA sections are not supported.
+    ///
+    /// Whitespace is preserved as is. Nothing is inserted if parsing fails.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use yrs::{Doc, GetString, Transact, XmlFragment};
+    ///
+    /// let doc = Doc::new();
+    /// let xml = doc.get_or_insert_xml_fragment("xml");
+    /// let mut txn = doc.transact_mut();
+    /// xml.insert_html(&mut txn, 0, "<p class=\"title\">Tom &amp; Jerry<br></p>").unwrap();
+    /// assert_eq!(xml.get_string(&txn), "<p class=\"title\">Tom & Jerry<br></br></p>");
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// This method panics if `index` is greater than current node's length.
+    fn insert_html(
+        &self,
+        txn: &mut TransactionMut,
+        index: u32,
+        html: &str,
+    ) -> Result<(), ParseError> {
+        let nodes = HtmlParser::new(html).parse()?;
+        for (i, node) in nodes.into_iter().enumerate() {
+            self.insert(txn, index + i as u32, node);
+        }
+        Ok(())
+    }
+}
+
+/// Error returned by [XmlFragment::insert_html] when a given HTML string couldn't be parsed.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ParseError {
+    #[error("unexpected end of input")]
+    UnexpectedEnd,
+    #[error("unexpected character '{1}' at byte offset {0}")]
+    UnexpectedChar(usize, char),
+    #[error(
+        "closing tag </{found}> doesn't match opening tag <{expected}> at byte offset {offset}"
+    )]
+    MismatchedTag {
+        offset: usize,
+        expected: String,
+        found: String,
+    },
+    #[error("closing tag </{1}> at byte offset {0} has no matching opening tag")]
+    UnexpectedClosingTag(usize, String),
+    #[error("unknown entity '&{1};' at byte offset {0}")]
+    UnknownEntity(usize, String),
+    #[error("element at byte offset {0} exceeds maximum nesting depth")]
+    TooDeep(usize),
+}
+
+/// Maximum nesting depth of elements accepted by [XmlFragment::insert_html].
+pub const MAX_HTML_DEPTH: usize = 128;
+
+/// HTML elements, which don't need to be closed.
+const VOID_ELEMENTS: [&str; 13] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// Byte offset and name of a closing tag.
+type ClosingTag = (usize, String);
+
+/// Minimal parser of well-formed HTML/XML subset used by [XmlFragment::insert_html].
+struct HtmlParser<'a> {
+    input: &'a str,
+    pos: usize,
+    depth: usize,
+}
+
+impl<'a> HtmlParser<'a> {
+    fn new(input: &'a str) -> Self {
+        HtmlParser {
+            input,
+            pos: 0,
+            depth: 0,
+        }
+    }
+
+    fn parse(mut self) -> Result<Vec<XmlIn>, ParseError> {
+        let (nodes, closing) = self.parse_nodes()?;
+        match closing {
+            None => Ok(nodes),
+            Some((offset, tag)) => Err(ParseError::UnexpectedClosingTag(offset, tag)),
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn next_char(&mut self) -> Result<char, ParseError> {
+        let c = self.peek().ok_or(ParseError::UnexpectedEnd)?;
+        self.pos += c.len_utf8();
+        Ok(c)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), ParseError> {
+        let offset = self.pos;
+        match self.next_char()? {
+            c if c == expected => Ok(()),
+            c => Err(ParseError::UnexpectedChar(offset, c)),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            if !c.is_whitespace() {
+                break;
+            }
+            self.pos += c.len_utf8();
+        }
+    }
+
+    /// Parses a sequence of sibling nodes until the end of input or a closing tag, which offset
+    /// and name are returned alongside.
+    fn parse_nodes(&mut self) -> Result<(Vec<XmlIn>, Option<ClosingTag>), ParseError> {
+        let mut nodes = Vec::new();
+        let mut text = String::new();
+        loop {
+            let rest = self.rest();
+            if rest.is_empty() {
+                break;
+            } else if let Some(comment) = rest.strip_prefix("<!--") {
+                let end = comment.find("-->").ok_or(ParseError::UnexpectedEnd)?;
+                self.pos += end + 7;
+            } else if rest.starts_with("</") {
+                let offset = self.pos;
+                self.pos += 2;
+                let tag = self.parse_name()?;
+                self.skip_whitespace();
+                self.expect('>')?;
+                Self::flush_text(&mut text, &mut nodes);
+                return Ok((nodes, Some((offset, tag))));
+            } else if rest.starts_with('<') {
+                Self::flush_text(&mut text, &mut nodes);
+                nodes.push(XmlIn::Element(self.parse_element()?));
+            } else if rest.starts_with('&') {
+                text.push(self.parse_entity()?);
+            } else {
+                let c = self.next_char()?;
+                text.push(c);
+            }
+        }
+        Self::flush_text(&mut text, &mut nodes);
+        Ok((nodes, None))
+    }
+
+    fn flush_text(text: &mut String, nodes: &mut Vec<XmlIn>) {
+        if !text.is_empty() {
+            let chunk = std::mem::take(text);
+            nodes.push(XmlIn::from(XmlTextPrelim::new(chunk)));
+        }
+    }
+
+    fn parse_element(&mut self) -> Result<XmlElementPrelim, ParseError> {
+        if self.depth == MAX_HTML_DEPTH {
+            return Err(ParseError::TooDeep(self.pos));
+        }
+        self.expect('<')?;
+        let tag = self.parse_name()?;
+        let mut elem = XmlElementPrelim::empty(tag.as_str());
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some('>') => {
+                    self.pos += 1;
+                    break;
+                }
+                Some('/') => {
+                    self.pos += 1;
+                    self.expect('>')?;
+                    return Ok(elem);
+                }
+                Some(_) => {
+                    let (key, value) = self.parse_attribute()?;
+                    elem.attributes.insert(key.into(), value);
+                }
+                None => return Err(ParseError::UnexpectedEnd),
+            }
+        }
+        if VOID_ELEMENTS.contains(&tag.to_lowercase().as_str()) {
+            return Ok(elem);
+        }
+        self.depth += 1;
+        let (children, closing) = self.parse_nodes()?;
+        self.depth -= 1;
+        match closing {
+            Some((_, found)) if found == tag => {
+                elem.children = children;
+                Ok(elem)
+            }
+            Some((offset, found)) => Err(ParseError::MismatchedTag {
+                offset,
+                expected: tag,
+                found,
+            }),
+            None => Err(ParseError::UnexpectedEnd),
+        }
+    }
+
+    fn parse_name(&mut self) -> Result<String, ParseError> {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if c.is_alphanumeric() || c == '-' || c == '_' || c == ':' || c == '.' {
+                self.pos += c.len_utf8();
+            } else {
+                break;
+            }
+        }
+        if start == self.pos {
+            let c = self.next_char()?;
+            Err(ParseError::UnexpectedChar(start, c))
+        } else {
+            Ok(self.input[start..self.pos].to_string())
+        }
+    }
+
+    fn parse_attribute(&mut self) -> Result<(String, String), ParseError> {
+        let key = self.parse_name()?;
+        self.skip_whitespace();
+        if self.peek() != Some('=') {
+            return Ok((key, String::new()));
+        }
+        self.pos += 1;
+        self.skip_whitespace();
+        let offset = self.pos;
+        let quote = self.next_char()?;
+        if quote != '"' && quote != '\'' {
+            return Err(ParseError::UnexpectedChar(offset, quote));
+        }
+        let mut value = String::new();
+        loop {
+            match self.peek() {
+                Some(c) if c == quote => {
+                    self.pos += 1;
+                    return Ok((key, value));
+                }
+                Some('&') => value.push(self.parse_entity()?),
+                Some(c) => {
+                    self.pos += c.len_utf8();
+                    value.push(c);
+                }
+                None => return Err(ParseError::UnexpectedEnd),
+            }
+        }
+    }
+
+    fn parse_entity(&mut self) -> Result<char, ParseError> {
+        let offset = self.pos;
+        let rest = self.rest();
+        let end = rest.find(';').ok_or(ParseError::UnexpectedEnd)?;
+        let name = &rest[1..end];
+        let c = match name {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some('\u{a0}'),
+            _ => {
+                let code = if let Some(hex) =
+                    name.strip_prefix("#x").or_else(|| name.strip_prefix("#X"))
+                {
+                    u32::from_str_radix(hex, 16).ok()
+                } else if let Some(dec) = name.strip_prefix('#') {
+                    dec.parse::<u32>().ok()
+                } else {
+                    None
+                };
+                code.and_then(std::char::from_u32)
+            }
+        };
+        match c {
+            Some(c) => {
+                self.pos += end + 1;
+                Ok(c)
+            }
+            None => Err(ParseError::UnknownEntity(offset, name.to_string())),
+        }
+    }
 }
 
 /// Iterator over the attributes (key-value pairs represented as a strings) of an [XmlElement].
@@ -1510,7 +1897,7 @@ mod test {
 
     use crate::test_utils::exchange_updates;
     use crate::transaction::ReadTxn;
-    use crate::types::xml::{Xml, XmlFragment, XmlOut};
+    use crate::types::xml::{ParseError, Xml, XmlFragment, XmlOut, MAX_HTML_DEPTH};
     use crate::types::{Attrs, Change, EntryChange, Out};
     use crate::updates::decoder::Decode;
     use crate::updates::encoder::{Encoder, EncoderV1};
@@ -1519,6 +1906,95 @@ mod test {
         XmlElementPrelim, XmlTextPrelim, XmlTextRef,
     };
 
+    #[test]
+    fn insert_html_nested() {
+        let doc = Doc::with_client_id(1);
+        let xml = doc.get_or_insert_xml_fragment("xml");
+        let mut txn = doc.transact_mut();
+        xml.push_back(&mut txn, XmlTextPrelim::new("end"));
+        xml.insert_html(
+            &mut txn,
+            0,
+            "<div id='main'><p>Hello <b>world</b></p><!-- comment --><img src=\"a.png\"/></div>",
+        )
+        .unwrap();
+
+        assert_eq!(xml.len(&txn), 2);
+        let div = xml.get(&txn, 0).unwrap().into_xml_element().unwrap();
+        assert_eq!(div.tag().as_ref(), "div");
+        assert_eq!(div.get_attribute(&txn, "id"), Some("main".to_string()));
+        assert_eq!(div.len(&txn), 2);
+        let p = div.get(&txn, 0).unwrap().into_xml_element().unwrap();
+        assert_eq!(p.get_string(&txn), "<p>Hello <b>world</b></p>");
+        let img = div.get(&txn, 1).unwrap().into_xml_element().unwrap();
+        assert_eq!(img.get_attribute(&txn, "src"), Some("a.png".to_string()));
+        assert_eq!(img.len(&txn), 0);
+        let end = xml.get(&txn, 1).unwrap().into_xml_text().unwrap();
+        assert_eq!(end.get_string(&txn), "end");
+    }
+
+    #[test]
+    fn insert_html_entities() {
+        let doc = Doc::with_client_id(1);
+        let xml = doc.get_or_insert_xml_fragment("xml");
+        let mut txn = doc.transact_mut();
+        xml.insert_html(
+            &mut txn,
+            0,
+            "<a title=\"&quot;Tom&quot; &amp; Jerry\" data-empty>1 &lt; 2 &#x26; 3&#62;2<br>ok</a>",
+        )
+        .unwrap();
+
+        let a = xml.get(&txn, 0).unwrap().into_xml_element().unwrap();
+        assert_eq!(
+            a.get_attribute(&txn, "title"),
+            Some("\"Tom\" & Jerry".to_string())
+        );
+        assert_eq!(a.get_attribute(&txn, "data-empty"), Some(String::new()));
+        let text = a.get(&txn, 0).unwrap().into_xml_text().unwrap();
+        assert_eq!(text.get_string(&txn), "1 < 2 & 3>2");
+        let br = a.get(&txn, 1).unwrap().into_xml_element().unwrap();
+        assert_eq!(br.tag().as_ref(), "br");
+        assert_eq!(a.len(&txn), 3);
+    }
+
+    #[test]
+    fn insert_html_errors() {
+        let doc = Doc::with_client_id(1);
+        let xml = doc.get_or_insert_xml_fragment("xml");
+        let mut txn = doc.transact_mut();
+        assert_eq!(
+            xml.insert_html(&mut txn, 0, "<p><b>text</p></b>"),
+            Err(ParseError::MismatchedTag {
+                offset: 10,
+                expected: "b".to_string(),
+                found: "p".to_string(),
+            })
+        );
+        assert_eq!(
+            xml.insert_html(&mut txn, 0, "<p>a &copy; b</p>"),
+            Err(ParseError::UnknownEntity(5, "copy".to_string()))
+        );
+        assert_eq!(
+            xml.insert_html(&mut txn, 0, "<p>unclosed"),
+            Err(ParseError::UnexpectedEnd)
+        );
+        assert_eq!(
+            xml.insert_html(&mut txn, 0, "text</p>"),
+            Err(ParseError::UnexpectedClosingTag(4, "p".to_string()))
+        );
+        let too_deep = "<b>".repeat(MAX_HTML_DEPTH + 1);
+        assert_eq!(
+            xml.insert_html(&mut txn, 0, &too_deep),
+            Err(ParseError::TooDeep(MAX_HTML_DEPTH * 3))
+        );
+        assert_eq!(xml.len(&txn), 0);
+
+        let deepest = "<b>".repeat(MAX_HTML_DEPTH) + &"</b>".repeat(MAX_HTML_DEPTH);
+        xml.insert_html(&mut txn, 0, &deepest).unwrap();
+        assert_eq!(xml.get_string(&txn), deepest);
+    }
+
     #[test]
     fn insert_attribute() {
         let d1 = Doc::with_client_id(1);
@@ -1806,6 +2282,39 @@ mod test {
         );
     }
 
+    #[test]
+    fn normalize_adjacent_text_nodes() {
+        let d1 = Doc::with_client_id(1);
+        let f1 = d1.get_or_insert_xml_fragment("xml");
+        let mut t1 = d1.transact_mut();
+        let p = f1.push_back(&mut t1, XmlElementPrelim::empty("p"));
+        let a = f1.push_back(&mut t1, XmlTextPrelim::new("hello"));
+        let b = f1.push_back(&mut t1, XmlTextPrelim::new(" world"));
+        let c = f1.push_back(&mut t1, XmlTextPrelim::new("!"));
+        let bold = Attrs::from([("b".into(), true.into())]);
+        b.format(&mut t1, 1, 5, bold);
+        c.insert_attribute(&mut t1, "lang", "en");
+        p.push_back(&mut t1, XmlTextPrelim::new("a"));
+        p.push_back(&mut t1, XmlTextPrelim::new("b"));
+
+        f1.normalize(&mut t1);
+
+        assert_eq!(f1.len(&t1), 2);
+        assert_eq!(p.len(&t1), 1);
+        assert_eq!(p.get_string(&t1), "<p>ab</p>");
+        let text = f1.get(&t1, 1).unwrap().into_xml_text().unwrap();
+        assert_eq!(text, a);
+        assert_eq!(text.get_string(&t1), "hello <b>world</b>!");
+        assert_eq!(text.get_attribute(&t1, "lang"), Some("en".to_string()));
+        drop(t1);
+
+        let d2 = Doc::with_client_id(2);
+        let f2 = d2.get_or_insert_xml_fragment("xml");
+        exchange_updates(&[&d1, &d2]);
+        let t2 = d2.transact();
+        assert_eq!(f2.get_string(&t2), "<p>ab</p>hello <b>world</b>!");
+    }
+
     #[test]
     fn xml_to_string() {
         let doc = Doc::new();
@@ -1854,6 +2363,38 @@ mod test {
         assert_eq!(xml.get_string(&txn), "<b>hello</b> world");
     }
 
+    #[test]
+    fn xml_text_inline_element_embed() {
+        let d1 = Doc::with_client_id(1);
+        let f1 = d1.get_or_insert_xml_fragment("xml");
+        let mut t1 = d1.transact_mut();
+        let text = f1.push_back(&mut t1, XmlTextPrelim::new("hello world"));
+        let link = text.insert_embed(&mut t1, 6, XmlElementPrelim::empty("a"));
+        link.insert_attribute(&mut t1, "href", "http://domain.org");
+        link.push_back(&mut t1, XmlTextPrelim::new("dear "));
+        let bold = Attrs::from([("b".into(), true.into())]);
+        text.format(&mut t1, 0, 5, bold);
+
+        let expected = "<b>hello</b> <a href=\"http://domain.org\">dear </a>world";
+        assert_eq!(text.get_string(&t1), expected);
+        assert_eq!(f1.get_string(&t1), expected);
+        drop(t1);
+
+        let d2 = Doc::with_client_id(2);
+        let f2 = d2.get_or_insert_xml_fragment("xml");
+        exchange_updates(&[&d1, &d2]);
+
+        // concurrent edits around and inside of embedded element
+        let t2 = f2.get(&d2.transact(), 0).unwrap().into_xml_text().unwrap();
+        t2.push(&mut d2.transact_mut(), "!");
+        link.push_back(&mut d1.transact_mut(), XmlTextPrelim::new("old "));
+        exchange_updates(&[&d1, &d2]);
+
+        let expected = "<b>hello</b> <a href=\"http://domain.org\">dear old </a>world!";
+        assert_eq!(text.get_string(&d1.transact()), expected);
+        assert_eq!(t2.get_string(&d2.transact()), expected);
+    }
+
     #[test]
     fn format_attributes_decode_compatibility_v1() {
         let data = &[
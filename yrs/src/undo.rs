@@ -30,10 +30,12 @@ use crate::{DeleteSet, Doc, Observer, Transact, TransactionMut, ID};
 /// Users can also subscribe to change notifications observed by undo manager:
 /// - [UndoManager::observe_item_added], which is fired every time a new [StackItem] is created.
 /// - [UndoManager::observe_item_updated], which is fired every time when an existing [StackItem]
-///    had been extended due to new document changes arriving before capture timeout for that stack
-///    item finished.
+///   had been extended due to new document changes arriving before capture timeout for that stack
+///   item finished.
 /// - [UndoManager::observe_item_popped], which is fired whenever [StackItem] is being from undo
-///    manager as a result of calling either [UndoManager::undo] or [UndoManager::redo] method.
+///   manager as a result of calling either [UndoManager::undo] or [UndoManager::redo] method.
+/// - [UndoManager::observe_stack_cleared], which is fired whenever undo and/or redo stacks have
+///   been cleared as a result of calling [UndoManager::clear] method.
 #[repr(transparent)]
 pub struct UndoManager<M>(Arc<Inner<M>>);
 
@@ -43,6 +45,12 @@ type UndoFn<M> = Box<dyn Fn(&TransactionMut, &mut Event<M>) + Send + Sync + 'sta
 #[cfg(not(feature = "sync"))]
 type UndoFn<M> = Box<dyn Fn(&TransactionMut, &mut Event<M>) + 'static>;
 
+#[cfg(feature = "sync")]
+type ClearFn = Box<dyn Fn(&TransactionMut, &StackClearedEvent) + Send + Sync + 'static>;
+
+#[cfg(not(feature = "sync"))]
+type ClearFn = Box<dyn Fn(&TransactionMut, &StackClearedEvent) + 'static>;
+
 #[cfg(feature = "sync")]
 pub trait Meta: Default + Send + Sync {}
 #[cfg(feature = "sync")]
@@ -65,6 +73,7 @@ struct Inner<M> {
     observer_added: Observer<UndoFn<M>>,
     observer_updated: Observer<UndoFn<M>>,
     observer_popped: Observer<UndoFn<M>>,
+    observer_cleared: Observer<ClearFn>,
 }
 
 impl<M> UndoManager<M>
@@ -109,6 +118,7 @@ where
             observer_added: Observer::new(),
             observer_updated: Observer::new(),
             observer_popped: Observer::new(),
+            observer_cleared: Observer::new(),
         });
         let origin = Origin::from(Arc::as_ptr(&inner) as usize);
         let inner_mut = Arc::get_mut(&mut inner).unwrap();
@@ -454,6 +464,69 @@ where
         self.0.observer_popped.unsubscribe(&key.into())
     }
 
+    /// Registers a callback function to be called every time undo and/or redo stacks have been
+    /// cleared as a result of [UndoManager::clear] method.
+    ///
+    /// Returns a subscription object which - when dropped - will unregister provided callback.
+    #[cfg(feature = "sync")]
+    pub fn observe_stack_cleared<F>(&self, f: F) -> crate::Subscription
+    where
+        F: Fn(&TransactionMut, &StackClearedEvent) + Send + Sync + 'static,
+    {
+        self.0.observer_cleared.subscribe(Box::new(f))
+    }
+
+    /// Registers a callback function to be called every time undo and/or redo stacks have been
+    /// cleared as a result of [UndoManager::clear] method.
+    ///
+    /// Returns a subscription object which - when dropped - will unregister provided callback.
+    #[cfg(not(feature = "sync"))]
+    pub fn observe_stack_cleared<F>(&self, f: F) -> crate::Subscription
+    where
+        F: Fn(&TransactionMut, &StackClearedEvent) + 'static,
+    {
+        self.0.observer_cleared.subscribe(Box::new(f))
+    }
+
+    /// Registers a callback function to be called every time undo and/or redo stacks have been
+    /// cleared as a result of [UndoManager::clear] method.
+    ///
+    /// Provided `key` is used to identify the origin of the callback. It can be used to unregister
+    /// the callback later on.
+    #[cfg(feature = "sync")]
+    pub fn observe_stack_cleared_with<K, F>(&self, key: K, f: F)
+    where
+        K: Into<Origin>,
+        F: Fn(&TransactionMut, &StackClearedEvent) + Send + Sync + 'static,
+    {
+        self.0
+            .observer_cleared
+            .subscribe_with(key.into(), Box::new(f))
+    }
+
+    /// Registers a callback function to be called every time undo and/or redo stacks have been
+    /// cleared as a result of [UndoManager::clear] method.
+    ///
+    /// Provided `key` is used to identify the origin of the callback. It can be used to unregister
+    /// the callback later on.
+    #[cfg(not(feature = "sync"))]
+    pub fn observe_stack_cleared_with<K, F>(&self, key: K, f: F)
+    where
+        K: Into<Origin>,
+        F: Fn(&TransactionMut, &StackClearedEvent) + 'static,
+    {
+        self.0
+            .observer_cleared
+            .subscribe_with(key.into(), Box::new(f))
+    }
+
+    pub fn unobserve_stack_cleared<K>(&self, key: K) -> bool
+    where
+        K: Into<Origin>,
+    {
+        self.0.observer_cleared.unsubscribe(&key.into())
+    }
+
     /// Extends a list of shared types tracked by current undo manager by a given `scope`.
     pub fn expand_scope<T>(&mut self, scope: &T)
     where
@@ -493,11 +566,21 @@ where
         for item in inner.undo_stack.drain(0..len) {
             Self::clear_item(&inner.scope, &mut txn, item);
         }
+        let undo_stack_cleared = len != 0;
 
         let len = inner.redo_stack.len();
         for item in inner.redo_stack.drain(0..len) {
             Self::clear_item(&inner.scope, &mut txn, item);
         }
+        let redo_stack_cleared = len != 0;
+
+        if (undo_stack_cleared || redo_stack_cleared) && inner.observer_cleared.has_subscribers() {
+            let e = StackClearedEvent {
+                undo_stack_cleared,
+                redo_stack_cleared,
+            };
+            inner.observer_cleared.trigger(|fun| fun(&txn, &e));
+        }
 
         Ok(())
     }
@@ -549,6 +632,47 @@ where
         inner.last_change = 0;
     }
 
+    /// Runs a given function `f` within the scope of a new read-write transaction, which origin
+    /// is tracked by current undo manager (see: [UndoManager::as_origin]). All changes made by `f`
+    /// over the shared types in the scope of current undo manager are grouped into a single
+    /// [StackItem], regardless of [Options::capture_timeout_millis]: they are neither merged with
+    /// changes made before, nor with the ones that will follow.
+    ///
+    /// Example:
+    /// ```rust
+    /// use yrs::{Doc, GetString, Text, Transact, UndoManager};
+    /// let doc = Doc::new();
+    /// let txt = doc.get_or_insert_text("text");
+    /// let mut mgr = UndoManager::new(&doc, &txt);
+    /// txt.insert(&mut doc.transact_mut(), 0, "a");
+    /// mgr.group(|txn| {
+    ///     txt.insert(txn, 1, "b");
+    ///     txt.insert(txn, 2, "c");
+    /// })
+    /// .unwrap();
+    /// mgr.undo().unwrap();
+    /// assert_eq!(txt.get_string(&doc.transact()), "a");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This method requires an exclusive access to underlying document store. This means that
+    /// no other transaction on that same document can be active while calling this method.
+    /// Otherwise an error will be returned.
+    pub fn group<F, R>(&mut self, f: F) -> Result<R, TransactionAcqError>
+    where
+        F: FnOnce(&mut TransactionMut) -> R,
+    {
+        let origin = self.as_origin();
+        let doc = self.0.doc.clone();
+        let mut txn = doc.try_transact_mut_with(origin)?;
+        self.reset();
+        let result = f(&mut txn);
+        drop(txn);
+        self.reset();
+        Ok(result)
+    }
+
     /// Are there any undo steps available?
     pub fn can_undo(&self) -> bool {
         !self.0.undo_stack.is_empty()
@@ -907,6 +1031,16 @@ impl<M> Event<M> {
     }
 }
 
+/// Event triggered by [UndoManager::clear] and passed to [UndoManager::observe_stack_cleared]
+/// callbacks.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct StackClearedEvent {
+    /// True if there were any [StackItem]s removed from the undo stack.
+    pub undo_stack_cleared: bool,
+    /// True if there were any [StackItem]s removed from the redo stack.
+    pub redo_stack_cleared: bool,
+}
+
 /// Enum which informs if correlated [Event] was produced as a result of either undo or redo
 /// operation over [UndoManager].
 #[repr(u8)]
@@ -1027,6 +1161,61 @@ mod test {
         assert_eq!(txt.get_string(&doc.transact()), "12321");
     }
 
+    #[test]
+    fn undo_group() {
+        let doc = Doc::with_client_id(1);
+        let txt = doc.get_or_insert_text("text");
+        let map = doc.get_or_insert_map("map");
+        let mut mgr = UndoManager::with_options(
+            &doc,
+            Options {
+                capture_timeout_millis: 0,
+                ..Options::default()
+            },
+        );
+        mgr.expand_scope(&txt);
+        mgr.expand_scope(&map);
+
+        txt.insert(&mut doc.transact_mut(), 0, "a");
+        mgr.group(|txn| {
+            txt.insert(txn, 1, "bc");
+            txt.remove_range(txn, 0, 1);
+            map.insert(txn, "template", "applied");
+            map.insert(txn, "nested", MapPrelim::from([("x", 1)]));
+        })
+        .unwrap();
+        txt.insert(&mut doc.transact_mut(), 2, "d");
+        assert_eq!(mgr.undo_stack().len(), 3);
+
+        mgr.undo().unwrap();
+        assert_eq!(txt.get_string(&doc.transact()), "bc");
+
+        // whole group is undone in a single call
+        mgr.undo().unwrap();
+        assert_eq!(txt.get_string(&doc.transact()), "a");
+        assert_eq!(map.to_json(&doc.transact()), any!({}));
+
+        mgr.redo().unwrap();
+        assert_eq!(txt.get_string(&doc.transact()), "bc");
+        assert_eq!(
+            map.to_json(&doc.transact()),
+            any!({"template": "applied", "nested": {"x": 1}})
+        );
+    }
+
+    #[test]
+    fn undo_group_ignores_capture_timeout() {
+        let doc = Doc::with_client_id(1);
+        let txt = doc.get_or_insert_text("text");
+        // default capture timeout would merge all of these changes together
+        let mut mgr = UndoManager::new(&doc, &txt);
+
+        txt.insert(&mut doc.transact_mut(), 0, "a");
+        mgr.group(|txn| txt.insert(txn, 1, "b")).unwrap();
+        txt.insert(&mut doc.transact_mut(), 2, "c");
+        assert_eq!(mgr.undo_stack().len(), 3);
+    }
+
     #[test]
     fn undo_map() {
         let d1 = Doc::with_client_id(1);
@@ -1265,6 +1454,40 @@ mod test {
         assert_eq!(result.load(Ordering::SeqCst), 2);
     }
 
+    #[test]
+    fn undo_stack_cleared_event() {
+        use crate::undo::StackClearedEvent;
+
+        let doc = Doc::with_client_id(1);
+        let txt = doc.get_or_insert_text("test");
+        let mut mgr = UndoManager::new(&doc, &txt);
+
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let events_c = events.clone();
+        let _sub = mgr.observe_stack_cleared(move |_, e| {
+            events_c.lock().unwrap().push(*e);
+        });
+
+        mgr.clear().unwrap(); // nothing to clear
+        assert!(events.lock().unwrap().is_empty());
+
+        txt.insert(&mut doc.transact_mut(), 0, "a");
+        mgr.reset();
+        txt.insert(&mut doc.transact_mut(), 1, "b");
+        mgr.undo().unwrap();
+        mgr.clear().unwrap();
+
+        assert_eq!(
+            events.lock().unwrap().as_slice(),
+            &[StackClearedEvent {
+                undo_stack_cleared: true,
+                redo_stack_cleared: true,
+            }]
+        );
+        assert!(!mgr.can_undo());
+        assert!(!mgr.can_redo());
+    }
+
     #[test]
     fn undo_until_change_performed() {
         let d1 = Doc::with_client_id(1);
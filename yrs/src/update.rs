@@ -1,24 +1,32 @@
 use std::cmp::Ordering;
 use std::collections::hash_map::Entry;
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::hash::BuildHasherDefault;
 use std::sync::Arc;
 
 use crate::block::{
-    BlockRange, ClientID, Item, ItemContent, ItemPtr, BLOCK_GC_REF_NUMBER, BLOCK_SKIP_REF_NUMBER,
-    HAS_ORIGIN, HAS_PARENT_SUB, HAS_RIGHT_ORIGIN,
+    BlockCell, BlockInfo, BlockRange, ClientID, Item, ItemContent, ItemPtr, BLOCK_GC_REF_NUMBER,
+    BLOCK_SKIP_REF_NUMBER, HAS_ORIGIN, HAS_PARENT_SUB, HAS_RIGHT_ORIGIN,
 };
+#[cfg(feature = "weak")]
+use crate::branch::Branch;
+use crate::doc::GcPolicy;
 use crate::encoding::read::Error;
 use crate::id_set::DeleteSet;
+use crate::moving::Move;
 use crate::slice::ItemSlice;
 #[cfg(test)]
 use crate::store::Store;
 use crate::transaction::TransactionMut;
+#[cfg(feature = "weak")]
+use crate::types::weak::LinkSource;
 use crate::types::TypePtr;
+#[cfg(feature = "weak")]
+use crate::types::TypeRef;
 use crate::updates::decoder::{Decode, Decoder};
-use crate::updates::encoder::{Encode, Encoder};
+use crate::updates::encoder::{Encode, Encoder, EncoderV1};
 use crate::utils::client_hasher::ClientHasher;
-use crate::{OffsetKind, StateVector, ID};
+use crate::{Doc, OffsetKind, Options, StateVector, ID};
 
 #[derive(Debug, Default, PartialEq)]
 pub(crate) struct UpdateBlocks {
@@ -39,6 +47,11 @@ impl UpdateBlocks {
         self.clients.is_empty()
     }
 
+    /// Returns blocks authored by a given `client`, ordered by their clocks.
+    pub(crate) fn get_client(&self, client: &ClientID) -> Option<&VecDeque<BlockCarrier>> {
+        self.clients.get(client)
+    }
+
     /// Returns an iterator that allows a traversal of all of the blocks
     /// which consist into this [Update].
     pub(crate) fn blocks(&self) -> Blocks<'_> {
@@ -98,10 +111,25 @@ impl Update {
         Self::default()
     }
 
-    pub(crate) fn is_empty(&self) -> bool {
+    /// Checks if current update doesn't contain any changes: neither inserted blocks nor
+    /// deleted ranges. Applying an empty update is a no-op.
+    ///
+    /// Keep in mind that a diff computed with [crate::ReadTxn::encode_diff] always carries a whole delete
+    /// set of a document, so it's not empty if any content has ever been deleted. Use
+    /// [Update::structs_len] to check if it contains any new blocks.
+    pub fn is_empty(&self) -> bool {
         self.blocks.is_empty() && self.delete_set.is_empty()
     }
 
+    /// Returns a total number of structs (blocks, GC and skip ranges) stored in current update.
+    pub fn structs_len(&self) -> usize {
+        self.blocks
+            .clients
+            .values()
+            .map(|blocks| blocks.len())
+            .sum()
+    }
+
     /// Returns a state vector representing an upper bound of client clocks included by blocks
     /// stored in current update.
     pub fn state_vector(&self) -> StateVector {
@@ -113,6 +141,62 @@ impl Update {
         sv
     }
 
+    /// Returns a set of clients, which authored blocks stored in current update.
+    ///
+    /// Deletions are not included: a delete set only identifies removed blocks, not the clients
+    /// who removed them.
+    pub fn author_clients(&self) -> HashSet<ClientID> {
+        self.blocks
+            .clients
+            .iter()
+            .filter(|(_, blocks)| blocks.iter().any(|block| !block.is_skip()))
+            .map(|(&client, _)| client)
+            .collect()
+    }
+
+    /// Returns names of the root types modified by blocks stored in current update.
+    ///
+    /// This is a best-effort analysis: a block's root type can only be resolved if its parent is
+    /// a root type or its parent (or neighbour, for blocks that don't define their parent
+    /// explicitly) can be found within the same update. Deletions are not included, as
+    /// a delete set doesn't carry information about the types it refers to.
+    pub fn affected_roots(&self) -> HashSet<String> {
+        let mut roots = HashSet::new();
+        for block in self.blocks.blocks() {
+            if let BlockCarrier::Item(item) = block {
+                if let Some(name) = self.root_of(item) {
+                    if !roots.contains(name) {
+                        roots.insert(name.to_string());
+                    }
+                }
+            }
+        }
+        roots
+    }
+
+    fn root_of<'a>(&'a self, mut item: &'a Item) -> Option<&'a str> {
+        // bound number of steps in case of malformed updates with cyclic references
+        for _ in 0..self.structs_len() {
+            let next = match &item.parent {
+                TypePtr::Named(name) => return Some(name),
+                TypePtr::ID(id) => id,
+                TypePtr::Unknown => item.origin.as_ref().or(item.right_origin.as_ref())?,
+                TypePtr::Branch(_) => return None,
+            };
+            item = self.find_item(next)?;
+        }
+        None
+    }
+
+    fn find_item(&self, id: &ID) -> Option<&Item> {
+        let blocks = self.blocks.clients.get(&id.client)?;
+        let i = blocks.partition_point(|block| block.last_id().clock < id.clock);
+        match blocks.get(i)? {
+            BlockCarrier::Item(item) if item.id.clock <= id.clock => Some(item),
+            _ => None,
+        }
+    }
+
     /// Merges another update into current one. Their blocks are deduplicated and reordered.
     pub fn merge(&mut self, other: Self) {
         for (client, other_blocks) in other.blocks.clients {
@@ -161,6 +245,64 @@ impl Update {
         }
     }
 
+    /// Returns a canonical form of current update: all adjacent blocks of the same client, which
+    /// could have been created as a single block, are squashed together and delete set ranges are
+    /// merged. This way logically equal updates - ie. produced by merging the same updates in
+    /// different order - have the same structure.
+    ///
+    /// Canonical update is logically equivalent to the original one: it can be applied, merged or
+    /// diffed like any other update, producing the same document state. Use
+    /// [Update::encode_canonical_v1] to obtain its deterministic binary representation.
+    pub fn canonicalize(&self) -> Update {
+        // blocks are not cloneable, so a deep copy is obtained by decoding an encoded update
+        let mut update = Update::decode_v1(&self.encode_v1())
+            .expect("Defect: encoded update couldn't be decoded");
+        for blocks in update.blocks.clients.values_mut() {
+            let mut squashed: VecDeque<BlockCarrier> = VecDeque::with_capacity(blocks.len());
+            for block in blocks.drain(..) {
+                let merged = match squashed.back_mut() {
+                    Some(last) => last.try_squash_adjacent(&block),
+                    None => false,
+                };
+                if !merged {
+                    squashed.push_back(block);
+                }
+            }
+            *blocks = squashed;
+        }
+        update.delete_set.squash();
+        update
+    }
+
+    /// Encodes a canonical form of current update (see: [Update::canonicalize]) using lib0 v1
+    /// encoding. Unlike [Encode::encode_v1], logically equal updates are guaranteed to produce
+    /// identical binary output, which makes it suitable for ie. content-addressed storage.
+    pub fn encode_canonical_v1(&self) -> Vec<u8> {
+        let update = self.canonicalize();
+        let mut encoder = EncoderV1::new();
+        update.encode_blocks(&StateVector::default(), &mut encoder);
+        update.delete_set.encode_sorted(&mut encoder);
+        encoder.to_vec()
+    }
+
+    /// Replaces all blocks of current update, which were rejected by a given `predicate`, with
+    /// skip ranges, so that they won't be integrated.
+    pub(crate) fn skip_rejected<F>(&mut self, predicate: F)
+    where
+        F: Fn(&BlockInfo) -> bool,
+    {
+        for blocks in self.blocks.clients.values_mut() {
+            for block in blocks.iter_mut() {
+                if !block.is_skip() {
+                    let info = block.info();
+                    if !predicate(&info) {
+                        *block = BlockCarrier::Skip(BlockRange::new(info.id, info.len));
+                    }
+                }
+            }
+        }
+    }
+
     /// Integrates current update into a block store referenced by a given transaction.
     /// If entire integration process was successful a `None` value is returned. Otherwise a
     /// pending update object is returned which contains blocks that couldn't be integrated, most
@@ -488,6 +630,11 @@ impl Update {
     }
 
     pub(crate) fn encode_diff<E: Encoder>(&self, remote_sv: &StateVector, encoder: &mut E) {
+        self.encode_blocks(remote_sv, encoder);
+        self.delete_set.encode(encoder)
+    }
+
+    fn encode_blocks<E: Encoder>(&self, remote_sv: &StateVector, encoder: &mut E) {
         let mut clients = HashMap::new();
         for (client, blocks) in self.blocks.clients.iter() {
             let remote_clock = remote_sv.get(client);
@@ -531,7 +678,6 @@ impl Update {
                 block.encode_with_offset(encoder, 0);
             }
         }
-        self.delete_set.encode(encoder)
     }
 
     pub fn merge_updates<T>(block_stores: T) -> Update
@@ -838,6 +984,29 @@ impl BlockCarrier {
         }
     }
 
+    pub(crate) fn info(&self) -> BlockInfo {
+        match self {
+            BlockCarrier::Item(x) => BlockInfo {
+                id: x.id,
+                len: x.len,
+                deleted: x.is_deleted(),
+                gc: false,
+            },
+            BlockCarrier::GC(x) => BlockInfo {
+                id: x.id,
+                len: x.len,
+                deleted: true,
+                gc: true,
+            },
+            BlockCarrier::Skip(x) => BlockInfo {
+                id: x.id,
+                len: x.len,
+                deleted: false,
+                gc: false,
+            },
+        }
+    }
+
     pub(crate) fn last_id(&self) -> ID {
         match self {
             BlockCarrier::Item(x) => x.last_id(),
@@ -859,6 +1028,33 @@ impl BlockCarrier {
         }
     }
 
+    /// Tries to squash `other` block into current one, if it's directly adjacent and both of
+    /// them could have been created as a single block. Unlike [BlockCarrier::try_squash], this
+    /// works on blocks which have not been integrated yet.
+    fn try_squash_adjacent(&mut self, other: &BlockCarrier) -> bool {
+        let last_id = self.last_id();
+        if last_id.client != other.id().client || last_id.clock + 1 != other.id().clock {
+            return false;
+        }
+        match (self, other) {
+            (BlockCarrier::Item(a), BlockCarrier::Item(b)) => {
+                let squashed = b.origin == Some(last_id)
+                    && a.right_origin == b.right_origin
+                    && a.content.try_squash(&b.content);
+                if squashed {
+                    a.len = a.content.len(OffsetKind::Utf16);
+                }
+                squashed
+            }
+            (BlockCarrier::GC(a), BlockCarrier::GC(b))
+            | (BlockCarrier::Skip(a), BlockCarrier::Skip(b)) => {
+                a.merge(b);
+                true
+            }
+            _ => false,
+        }
+    }
+
     pub fn as_item_ptr(&mut self) -> Option<ItemPtr> {
         if let BlockCarrier::Item(block) = self {
             Some(ItemPtr::from(block))
@@ -909,6 +1105,77 @@ impl BlockCarrier {
     }
 }
 
+impl BlockCarrier {
+    /// Creates a detached copy of a given block, just like it would look like after being encoded
+    /// and decoded back. Copied block can be integrated into another document.
+    pub(crate) fn copy_of(client: ClientID, cell: &BlockCell) -> Self {
+        match cell {
+            BlockCell::GC(gc) => {
+                BlockCarrier::GC(BlockRange::new(ID::new(client, gc.start), gc.len()))
+            }
+            BlockCell::Block(item) => {
+                let parent = match &item.parent {
+                    TypePtr::Branch(branch) => {
+                        if let Some(block) = branch.item {
+                            TypePtr::ID(*block.id())
+                        } else if let Some(name) = branch.name.as_ref() {
+                            TypePtr::Named(name.clone())
+                        } else {
+                            unreachable!("Could not get parent branch info for item")
+                        }
+                    }
+                    other => other.clone(),
+                };
+                let content = match &item.content {
+                    ItemContent::Doc(_, doc) => {
+                        // only options, which are part of encoded sub-document are copied
+                        let src = doc.options();
+                        let options = Options {
+                            guid: src.guid.clone(),
+                            collection_id: src.collection_id.clone(),
+                            offset_kind: src.offset_kind,
+                            skip_gc: src.effective_gc_policy() == GcPolicy::Never,
+                            auto_load: src.auto_load,
+                            should_load: src.auto_load,
+                            ..Options::default()
+                        };
+                        ItemContent::Doc(None, Doc::with_options(options))
+                    }
+                    ItemContent::Move(m) => {
+                        // overrides refer to blocks of the source document store
+                        let m = Move::new(m.start.clone(), m.end.clone(), m.priority);
+                        ItemContent::Move(Box::new(m))
+                    }
+                    #[cfg(feature = "weak")]
+                    ItemContent::Type(branch) => match &branch.type_ref {
+                        TypeRef::WeakLink(source) => {
+                            // link source caches a pointer to a block of the source document
+                            let source = LinkSource::new(
+                                source.quote_start.clone(),
+                                source.quote_end.clone(),
+                            );
+                            ItemContent::Type(Branch::new(TypeRef::WeakLink(Arc::new(source))))
+                        }
+                        _ => ItemContent::Type(Branch::new(branch.type_ref.clone())),
+                    },
+                    other => other.clone(),
+                };
+                let copy = Item::new(
+                    item.id,
+                    None,
+                    item.origin,
+                    None,
+                    item.right_origin,
+                    parent,
+                    item.parent_sub.clone(),
+                    content,
+                );
+                BlockCarrier::Item(copy.expect("copied block should not be empty"))
+            }
+        }
+    }
+}
+
 impl From<Box<Item>> for BlockCarrier {
     fn from(block: Box<Item>) -> Self {
         BlockCarrier::Item(block)
@@ -1068,6 +1335,7 @@ impl Iterator for IntoBlocks {
 
 #[cfg(test)]
 mod test {
+    use std::collections::HashSet;
     use std::sync::{Arc, Mutex};
 
     use crate::block::{Item, ItemContent};
@@ -1075,10 +1343,74 @@ mod test {
     use crate::types::{Delta, TypePtr};
     use crate::update::{BlockCarrier, Update};
     use crate::updates::decoder::{Decode, DecoderV1};
+    use crate::updates::encoder::Encode;
     use crate::{
-        Doc, GetString, Options, ReadTxn, StateVector, Text, Transact, XmlFragment, XmlOut, ID,
+        Array, ArrayPrelim, Doc, GetString, Map, Options, ReadTxn, StateVector, Text, Transact,
+        XmlFragment, XmlOut, ID,
     };
 
+    #[test]
+    fn update_authors_and_roots() {
+        let doc = Doc::with_client_id(1);
+        let text = doc.get_or_insert_text("text");
+        let map = doc.get_or_insert_map("map");
+        {
+            let mut txn = doc.transact_mut();
+            text.insert(&mut txn, 0, "hello");
+            let nested = map.insert(&mut txn, "list", ArrayPrelim::default());
+            nested.push_back(&mut txn, 1);
+        }
+        // blocks with an origin don't store their parent
+        text.insert(&mut doc.transact_mut(), 5, " world");
+
+        let update = Update::decode_v1(
+            &doc.transact()
+                .encode_state_as_update_v1(&StateVector::default()),
+        )
+        .unwrap();
+        assert_eq!(update.author_clients(), HashSet::from([1]));
+        assert_eq!(
+            update.affected_roots(),
+            HashSet::from(["text".to_string(), "map".to_string()])
+        );
+
+        // deletions are not attributed to any client or type
+        let sv = doc.transact().state_vector();
+        text.remove_range(&mut doc.transact_mut(), 0, 5);
+        let update = Update::decode_v1(&doc.transact().encode_state_as_update_v1(&sv)).unwrap();
+        assert!(update.author_clients().is_empty());
+        assert!(update.affected_roots().is_empty());
+    }
+
+    #[test]
+    fn update_is_empty() {
+        assert!(Update::new().is_empty());
+        assert_eq!(Update::new().structs_len(), 0);
+
+        let doc = Doc::with_client_id(1);
+        let text = doc.get_or_insert_text("text");
+        text.insert(&mut doc.transact_mut(), 0, "hello");
+        text.insert(&mut doc.transact_mut(), 0, "world ");
+
+        let txn = doc.transact();
+        let update = Update::decode_v1(&txn.encode_diff_v1(&StateVector::default())).unwrap();
+        assert!(!update.is_empty());
+        assert_eq!(update.structs_len(), 2);
+
+        // diff against up-to-date state vector
+        let update = Update::decode_v1(&txn.encode_diff_v1(&txn.state_vector())).unwrap();
+        assert!(update.is_empty());
+        assert_eq!(update.structs_len(), 0);
+        drop(txn);
+
+        // deletions are not structs, but they still make an update non-empty
+        text.remove_range(&mut doc.transact_mut(), 0, 2);
+        let txn = doc.transact();
+        let update = Update::decode_v1(&txn.encode_diff_v1(&txn.state_vector())).unwrap();
+        assert!(!update.is_empty());
+        assert_eq!(update.structs_len(), 0);
+    }
+
     #[test]
     fn update_decode() {
         /* Generated with:
@@ -1332,6 +1664,58 @@ mod test {
         assert_eq!(str, "nenor");
     }
 
+    #[test]
+    fn canonicalize() {
+        let mut updates = Vec::new();
+        for client_id in 1..=5 {
+            let doc = Doc::with_client_id(client_id);
+            let txt = doc.get_or_insert_text("text");
+            for chunk in ["a", "b", "c"] {
+                let sv = doc.transact().state_vector();
+                let len = txt.len(&doc.transact());
+                txt.insert(&mut doc.transact_mut(), len, chunk);
+                updates.push(doc.transact().encode_diff_v1(&sv));
+            }
+            let sv = doc.transact().state_vector();
+            txt.remove_range(&mut doc.transact_mut(), 2, 1);
+            updates.push(doc.transact().encode_diff_v1(&sv));
+        }
+
+        // the same updates merged in different order
+        let u1 = Update::merge_updates(updates.iter().map(|u| Update::decode_v1(u).unwrap()));
+        let u2 = Update::merge_updates(updates.iter().rev().map(|u| Update::decode_v1(u).unwrap()));
+        // document state, where consecutive insertions have been squashed into single blocks
+        let doc = Doc::with_options(Options {
+            skip_gc: true,
+            ..Options::default()
+        });
+        {
+            let mut txn = doc.transact_mut();
+            for u in updates.iter() {
+                txn.apply_update(Update::decode_v1(u).unwrap());
+            }
+        }
+        let u3 = Update::decode_v1(
+            &doc.transact()
+                .encode_state_as_update_v1(&StateVector::default()),
+        )
+        .unwrap();
+        assert_ne!(u1.encode_v1(), u3.encode_v1());
+
+        let canonical = u1.encode_canonical_v1();
+        assert_eq!(u2.encode_canonical_v1(), canonical);
+        assert_eq!(u3.encode_canonical_v1(), canonical);
+        assert_eq!(u1.canonicalize().encode_canonical_v1(), canonical);
+
+        // canonical update integrates the same way as the original ones
+        let expected = doc.get_or_insert_text("text").get_string(&doc.transact());
+        let doc = Doc::new();
+        let txt = doc.get_or_insert_text("text");
+        doc.transact_mut()
+            .apply_update(Update::decode_v1(&canonical).unwrap());
+        assert_eq!(txt.get_string(&doc.transact()), expected);
+    }
+
     fn decode_update(bin: &[u8]) -> Update {
         Update::decode(&mut DecoderV1::new(Cursor::new(bin))).unwrap()
     }
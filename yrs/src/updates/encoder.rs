@@ -88,6 +88,12 @@ impl EncoderV1 {
         }
     }
 
+    /// Creates a new encoder, which appends its output to a given `buf`, reusing its allocated
+    /// capacity.
+    pub fn with_buffer(buf: Vec<u8>) -> Self {
+        EncoderV1 { buf }
+    }
+
     fn write_id(&mut self, id: &ID) {
         self.write_var(id.client);
         self.write_var(id.clock)
@@ -147,6 +153,112 @@ impl Encoder for EncoderV1 {
         self.write_u8(info)
     }
 
+    #[inline]
+    fn write_parent_info(&mut self, is_y_key: bool) {
+        self.write_var(if is_y_key { 1u32 } else { 0u32 })
+    }
+
+    #[inline]
+    fn write_type_ref(&mut self, info: u8) {
+        self.write_u8(info)
+    }
+
+    #[inline]
+    fn write_len(&mut self, len: u32) {
+        self.write_var(len)
+    }
+
+    #[inline]
+    fn write_any(&mut self, any: &Any) {
+        any.encode(self)
+    }
+
+    fn write_json(&mut self, any: &Any) {
+        let mut buf = String::new();
+        any.to_json(&mut buf);
+        self.write_string(buf.as_str())
+    }
+
+    #[inline]
+    fn write_key(&mut self, key: &str) {
+        self.write_string(key)
+    }
+}
+
+/// Encoder which - instead of producing lib0 v1 encoded bytes - only counts their number.
+/// Used to compute the exact size of an encoded payload without allocating a buffer for it.
+#[derive(Debug, Default)]
+pub(crate) struct EncodedLenV1 {
+    len: usize,
+}
+
+impl EncodedLenV1 {
+    /// Returns a number of bytes that would have been written so far by [EncoderV1].
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    fn write_id(&mut self, id: &ID) {
+        self.write_var(id.client);
+        self.write_var(id.clock)
+    }
+}
+
+impl Write for EncodedLenV1 {
+    #[inline]
+    fn write_all(&mut self, buf: &[u8]) {
+        self.len += buf.len()
+    }
+
+    #[inline]
+    fn write_u8(&mut self, _value: u8) {
+        self.len += 1
+    }
+}
+
+impl Encoder for EncodedLenV1 {
+    /// Length counter doesn't retain any of the written bytes, so this always returns an empty
+    /// vector. Use [EncodedLenV1::len] instead.
+    #[inline]
+    fn to_vec(self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    #[inline]
+    fn reset_ds_cur_val(&mut self) {
+        /* no op */
+    }
+
+    #[inline]
+    fn write_ds_clock(&mut self, clock: u32) {
+        self.write_var(clock)
+    }
+
+    #[inline]
+    fn write_ds_len(&mut self, len: u32) {
+        self.write_var(len)
+    }
+
+    #[inline]
+    fn write_left_id(&mut self, id: &ID) {
+        self.write_id(id)
+    }
+
+    #[inline]
+    fn write_right_id(&mut self, id: &ID) {
+        self.write_id(id)
+    }
+
+    #[inline]
+    fn write_client(&mut self, client: ClientID) {
+        self.write_var(client)
+    }
+
+    #[inline]
+    fn write_info(&mut self, info: u8) {
+        self.write_u8(info)
+    }
+
     #[inline]
     fn write_parent_info(&mut self, is_y_key: bool) {
         self.write_var(if is_y_key { 1 as u32 } else { 0 as u32 })
@@ -65,6 +65,7 @@ impl Js {
             Any::Bool(value) => Js(JsValue::from_bool(*value)),
             Any::Number(value) => Js(JsValue::from_f64(*value)),
             Any::BigInt(value) => Js(js_sys::BigInt::from(*value).into()),
+            Any::BigUInt(value) => Js(js_sys::BigInt::from(*value).into()),
             Any::String(str) => Js(JsValue::from_str(&*str)),
             Any::Buffer(binary) => Js(Uint8Array::from(binary.as_ref()).into()),
             Any::Array(array) => {